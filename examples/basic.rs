@@ -33,19 +33,17 @@ fn setup_scene(
                 }
             },
         ))
-        .add(coroutine(
-            |mut s: Scope, transform: Rd<Transform>| async move {
-                let mut i = 0;
-                let original_x = transform.get(&s).translation.x;
-                loop {
-                    s.duration(Duration::from_secs(1)).await;
-                    i += 1;
-                    println!(
-                        "After {} seconds, we moved {} to the right",
-                        i,
-                        transform.get(&s).translation.x - original_x
-                    );
-                }
-            },
-        ));
+        .add(co_owned!(|s, transform: Rd<Transform>| {
+            let mut i = 0;
+            let original_x = transform.get(&s).translation.x;
+            loop {
+                s.duration(Duration::from_secs(1)).await;
+                i += 1;
+                println!(
+                    "After {} seconds, we moved {} to the right",
+                    i,
+                    transform.get(&s).translation.x - original_x
+                );
+            }
+        }));
 }
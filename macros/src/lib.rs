@@ -0,0 +1,140 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, Type, Visibility};
+
+/// Derive `Serialize` and `Deserialize` for a coroutine state machine, so that it can be saved
+/// and restored with the executor, without depending on serde directly. Each field must itself
+/// implement them, and `#[serde(...)]` attributes are supported as with serde's own derives.
+#[proc_macro_derive(CoroutineState, attributes(serde))]
+pub fn derive_coroutine_state(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    if let Data::Union(_) = input.data {
+        return syn::Error::new_spanned(name, "CoroutineState cannot be derived for unions")
+            .to_compile_error()
+            .into();
+    }
+
+    // Serde derives its impls on a copy of the type, through which the original one is
+    // (de)serialized, see https://serde.rs/remote-derive.html
+    let mut remote = input.clone();
+    remote.ident = format_ident!("__{}Remote", name);
+    remote.vis = Visibility::Inherited;
+    remote.attrs.retain(is_serde_attr);
+    match &mut remote.data {
+        Data::Struct(data) => retain_serde_attrs(&mut data.fields),
+        Data::Enum(data) => {
+            for variant in &mut data.variants {
+                variant.attrs.retain(is_serde_attr);
+                retain_serde_attrs(&mut variant.fields);
+            }
+        }
+        Data::Union(_) => unreachable!(),
+    }
+    let remote_name = &remote.ident;
+
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let remote_path = quote!(#name #ty_generics).to_string();
+    let serde = quote! { ::corentin::state::serde };
+
+    let mut ser_generics = input.generics.clone();
+    for param in ser_generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#serde::Serialize));
+    }
+    let (ser_impl_generics, _, _) = ser_generics.split_for_impl();
+
+    let mut de_generics = input.generics.clone();
+    for param in de_generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#serde::Deserialize<'de>));
+    }
+    de_generics.params.insert(0, parse_quote!('de));
+    let (de_impl_generics, _, _) = de_generics.split_for_impl();
+
+    quote! {
+        const _: () = {
+            #[derive(#serde::Serialize, #serde::Deserialize)]
+            #[serde(crate = "::corentin::state::serde", remote = #remote_path)]
+            #[allow(dead_code)]
+            #remote
+
+            impl #ser_impl_generics #serde::Serialize for #name #ty_generics #where_clause {
+                fn serialize<S: #serde::Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> ::std::result::Result<S::Ok, S::Error> {
+                    #remote_name::serialize(self, serializer)
+                }
+            }
+
+            impl #de_impl_generics #serde::Deserialize<'de> for #name #ty_generics #where_clause {
+                fn deserialize<D: #serde::Deserializer<'de>>(
+                    deserializer: D,
+                ) -> ::std::result::Result<Self, D::Error> {
+                    #remote_name::deserialize(deserializer)
+                }
+            }
+        };
+    }
+    .into()
+}
+
+/// Derive a coroutine function for a struct implementing `CoroutineStateMachine`, so that it can
+/// be started like an `async` function. Each `#[param(...)]` attribute declares a parameter, in
+/// order, such as `#[param(Rd<Health>)]`, received by `init` and `update` as a tuple.
+#[proc_macro_derive(EntityCoroutine, attributes(param))]
+pub fn derive_entity_coroutine(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut params = Vec::new();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("param"))
+    {
+        match attr.parse_args::<Type>() {
+            Ok(param) => params.push(param),
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+
+    let state_machine = quote! { ::corentin::function_coroutine::state_machine };
+    quote! {
+        impl #impl_generics #state_machine::StateMachineParams for #name #ty_generics
+        #where_clause
+        {
+            type Params = (#(#params,)*);
+        }
+
+        impl #impl_generics ::corentin::function_coroutine::CoroutineParamFunction<
+            #state_machine::StateMachineMarker,
+            (),
+        > for #name #ty_generics #where_clause
+        {
+            type Future = #state_machine::StateMachineFuture<Self>;
+            type Params = (#(#params,)*);
+
+            fn init(
+                self,
+                scope: ::corentin::function_coroutine::scope::Scope,
+                params: Self::Params,
+            ) -> Self::Future {
+                #state_machine::StateMachineFuture::new(self, scope, params)
+            }
+        }
+    }
+    .into()
+}
+
+fn is_serde_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("serde")
+}
+
+/// Strip the attributes of the fields which are not meant for serde, such as the helpers of
+/// other derives.
+fn retain_serde_attrs(fields: &mut Fields) {
+    for field in fields.iter_mut() {
+        field.attrs.retain(is_serde_attr);
+    }
+}
@@ -1,10 +1,12 @@
 use bevy::ecs::system::{Command, EntityCommand};
+use bevy::utils::synccell::SyncCell;
 use std::marker::PhantomData;
 
-use bevy::prelude::{Entity, World};
+use bevy::prelude::{Component, Entity, ReflectComponent, World};
+use bevy::reflect::Reflect;
 
 use super::executor::Executor;
-use super::function_coroutine::CoroutineParamFunction;
+use super::function_coroutine::{behavior::BehaviorHandle, CoroutineParamFunction};
 
 pub struct AddRootCoroutine<Marker, T, C> {
     coroutine: C,
@@ -26,7 +28,32 @@ where
 {
     fn apply(self, owner: Entity, world: &mut World) {
         world.resource_scope::<Executor, ()>(|world, mut executor| {
-            executor.add_function_coroutine(Some(owner), world, self.coroutine);
+            if let Err(invalid) =
+                executor.add_function_coroutine_with(Some(owner), world, None, self.coroutine)
+            {
+                invalid.debug_assert_no_conflict::<C>();
+            }
+        });
+    }
+}
+
+pub struct AddCoroutineInto<Marker, T, C> {
+    coroutine: C,
+    _phantom1: PhantomData<Marker>,
+    _phantom2: PhantomData<T>,
+}
+
+impl<Marker, C, T> EntityCommand for AddCoroutineInto<Marker, T, C>
+where
+    C: CoroutineParamFunction<Marker, T>,
+    T: Component,
+    Marker: 'static + Send,
+{
+    fn apply(self, owner: Entity, world: &mut World) {
+        world.resource_scope::<Executor, ()>(|world, mut executor| {
+            if let Err(invalid) = executor.add_coroutine_into(owner, world, self.coroutine) {
+                invalid.debug_assert_no_conflict::<C>();
+            }
         });
     }
 }
@@ -39,11 +66,18 @@ where
 {
     fn apply(self, world: &mut World) {
         world.resource_scope::<Executor, ()>(|w, mut executor| {
-            executor.add_function_coroutine(None, w, self.coroutine);
+            if let Err(invalid) =
+                executor.add_function_coroutine_with(None, w, None, self.coroutine)
+            {
+                invalid.debug_assert_no_conflict::<C>();
+            }
         });
     }
 }
 
+/// Returns a [`Command`] adding the `coroutine` to the [`Executor`], without owner. Nothing is
+/// added if its parameters are invalid, but in debug builds, conflicting parameters panic
+/// instead, since the coroutine could never run.
 pub fn root_coroutine<M, C, T>(coroutine: C) -> AddRootCoroutine<M, T, C> {
     AddRootCoroutine {
         coroutine,
@@ -52,6 +86,8 @@ pub fn root_coroutine<M, C, T>(coroutine: C) -> AddRootCoroutine<M, T, C> {
     }
 }
 
+/// Returns an [`EntityCommand`] binding the `coroutine` to the entity. Invalid parameters are
+/// handled like with [`root_coroutine`].
 pub fn coroutine<M, C, T>(coroutine: C) -> AddCoroutineTo<M, T, C> {
     AddCoroutineTo {
         coroutine,
@@ -59,3 +95,178 @@ pub fn coroutine<M, C, T>(coroutine: C) -> AddCoroutineTo<M, T, C> {
         _phantom2: PhantomData,
     }
 }
+
+/// Returns an [`EntityCommand`] binding the `coroutine` to the entity, and inserting its result
+/// on it once it finishes.
+pub fn coroutine_into<M, C, T>(coroutine: C) -> AddCoroutineInto<M, T, C> {
+    AddCoroutineInto {
+        coroutine,
+        _phantom1: PhantomData,
+        _phantom2: PhantomData,
+    }
+}
+
+pub struct StartBehavior<Marker, M, C> {
+    coroutine: C,
+    _phantom1: PhantomData<Marker>,
+    _phantom2: PhantomData<fn() -> M>,
+}
+
+impl<Marker, M, C> EntityCommand for StartBehavior<Marker, M, C>
+where
+    C: CoroutineParamFunction<Marker, ()>,
+    M: Send + 'static,
+    Marker: 'static + Send,
+{
+    fn apply(self, owner: Entity, world: &mut World) {
+        world.resource_scope::<Executor, ()>(|world, mut executor| {
+            match executor.add_behavior::<Marker, M, C>(Some(owner), world, self.coroutine) {
+                Ok(handle) => {
+                    // Dropping the handle right away cancels the behavior of a despawned owner
+                    if let Some(mut owner) = world.get_entity_mut(owner) {
+                        owner.insert(Behavior(handle));
+                    }
+                }
+                Err(invalid) => invalid.debug_assert_no_conflict::<C>(),
+            }
+        });
+    }
+}
+
+/// Returns an [`EntityCommand`] binding the behavior `coroutine` to the entity, as
+/// [`Scope::start_behavior`](crate::prelude::Scope::start_behavior) does from within a
+/// coroutine. Its handle is inserted on the entity in a [`Behavior<M>`] component, replacing and
+/// cancelling the previous behavior with messages of type `M`, if any. Invalid parameters are
+/// handled like with [`root_coroutine`].
+pub fn start_behavior<Marker, M, C>(coroutine: C) -> StartBehavior<Marker, M, C> {
+    StartBehavior {
+        coroutine,
+        _phantom1: PhantomData,
+        _phantom2: PhantomData,
+    }
+}
+
+/// A [`Component`] holding the handle to the behavior started on its entity with
+/// [`start_behavior`], which receives messages of type `M`. Removing it cancels the behavior.
+#[derive(Component)]
+pub struct Behavior<M: Send + 'static>(BehaviorHandle<M>);
+
+impl<M: Send + 'static> Behavior<M> {
+    /// Returns the handle to the behavior, to send it messages or to pause it.
+    pub fn handle(&self) -> &BehaviorHandle<M> {
+        &self.0
+    }
+}
+
+/// A coroutine waiting to be bound to an [`Entity`], with its type erased.
+type CoroutineFactory = SyncCell<Box<dyn FnOnce(Entity, &World, &mut Executor) + Send>>;
+
+/// A [`Component`] holding coroutines that should be bound to the entity it is attached to. It
+/// makes it possible to declare coroutines as part of a [`Bundle`](bevy::prelude::Bundle), the
+/// [`CorentinPlugin`](crate::plugin::CorentinPlugin) registers them to the [`Executor`] at the
+/// beginning of the next [`Update`](bevy::prelude::Update).
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct EntityCoroutines {
+    #[reflect(ignore)]
+    pending: Vec<CoroutineFactory>,
+}
+
+impl EntityCoroutines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a `coroutine` to be bound to the owning entity.
+    pub fn add<Marker, T, C>(&mut self, coroutine: C) -> &mut Self
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+        Marker: 'static + Send,
+    {
+        self.pending.push(SyncCell::new(Box::new(
+            move |owner: Entity, world: &World, executor: &mut Executor| {
+                if let Err(invalid) =
+                    executor.add_function_coroutine_with(Some(owner), world, None, coroutine)
+                {
+                    invalid.debug_assert_no_conflict::<C>();
+                }
+            },
+        )));
+        self
+    }
+
+    /// Same as [`EntityCoroutines::add`], but by value, to be used when building a bundle.
+    pub fn with<Marker, T, C>(mut self, coroutine: C) -> Self
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+        Marker: 'static + Send,
+    {
+        self.add(coroutine);
+        self
+    }
+
+    /// Returns true if no coroutines are waiting to be registered.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Register all the pending coroutines to the `executor`, binding them to `owner`.
+    pub(crate) fn register(
+        pending: Vec<CoroutineFactory>,
+        owner: Entity,
+        world: &World,
+        executor: &mut Executor,
+    ) {
+        for factory in pending {
+            SyncCell::to_inner(factory)(owner, world, executor);
+        }
+    }
+
+    pub(crate) fn take_pending(&mut self) -> Vec<CoroutineFactory> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Queue a coroutine in the [`EntityCoroutines`] of an entity, inserting the component if needed.
+pub struct AddEntityCoroutine<Marker, T, C> {
+    entity: Entity,
+    coroutine: C,
+    _phantom1: PhantomData<Marker>,
+    _phantom2: PhantomData<T>,
+}
+
+impl<Marker, C, T> Command for AddEntityCoroutine<Marker, T, C>
+where
+    C: CoroutineParamFunction<Marker, T>,
+    T: Sync + Send + 'static,
+    Marker: 'static + Send,
+{
+    fn apply(self, world: &mut World) {
+        let Some(mut entity) = world.get_entity_mut(self.entity) else {
+            return;
+        };
+
+        match entity.get_mut::<EntityCoroutines>() {
+            Some(mut coroutines) => {
+                coroutines.add(self.coroutine);
+            }
+            None => {
+                entity.insert(EntityCoroutines::new().with(self.coroutine));
+            }
+        }
+    }
+}
+
+/// Returns a [`Command`] queueing the `coroutine` to be bound to `entity`. It is registered
+/// to the [`Executor`] the next time the [`EntityCoroutines`] are processed. If the entity does
+/// not exist when the command is applied, the coroutine is dropped.
+pub fn entity_coroutine<M, C, T>(entity: Entity, coroutine: C) -> AddEntityCoroutine<M, T, C> {
+    AddEntityCoroutine {
+        entity,
+        coroutine,
+        _phantom1: PhantomData,
+        _phantom2: PhantomData,
+    }
+}
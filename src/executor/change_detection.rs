@@ -0,0 +1,173 @@
+use std::any::TypeId;
+
+use bevy::{
+    ecs::{
+        component::Tick,
+        query::ReadOnlyWorldQuery,
+        system::{ReadOnlySystem, System},
+    },
+    prelude::{
+        Component, DetectChanges, Entity, IntoSystem, Query, QueryState, Ref, RemovedComponents,
+        World,
+    },
+    utils::synccell::SyncCell,
+};
+use smallvec::SmallVec;
+
+use crate::function_coroutine::once_channel::OnceSender;
+
+/// The entities whose component changed, as returned by
+/// [`Scope::on_any_change`](crate::function_coroutine::scope::Scope::on_any_change).
+pub type ChangedEntities = SmallVec<[Entity; 8]>;
+
+/// A query looking for the entities whose component changed since a given tick, shared by all
+/// the coroutines waiting on the same component and filter.
+pub trait ChangeDetector: Send + Sync {
+    /// Returns all the matching entities changed after `since`, alongside the tick at which they
+    /// changed.
+    fn detect(&mut self, world: &World, since: Tick, this_run: Tick) -> Vec<(Entity, Tick)>;
+
+    /// Returns false if the ticks returned by [`ChangeDetector::detect`] are not the ones at
+    /// which the entities changed. The detection then runs once for each distinct `since`,
+    /// rather than once from the oldest one.
+    fn reports_ticks(&self) -> bool {
+        true
+    }
+}
+
+struct QueryChangeDetector<T: Component, F: ReadOnlyWorldQuery + 'static> {
+    state: QueryState<(Entity, Ref<'static, T>), F>,
+}
+
+impl<T: Component, F: ReadOnlyWorldQuery + 'static> ChangeDetector for QueryChangeDetector<T, F> {
+    fn detect(&mut self, world: &World, since: Tick, this_run: Tick) -> Vec<(Entity, Tick)> {
+        // Inserting a component also marks it as changed, spawned entities are therefore
+        // detected as well.
+        self.state
+            .iter(world)
+            .filter_map(|(entity, value)| {
+                let changed = value.last_changed();
+                changed
+                    .is_newer_than(since, this_run)
+                    .then_some((entity, changed))
+            })
+            .collect()
+    }
+}
+
+/// A system listing the entities matching a query whose filter may contain change filters,
+/// such as `Changed<T>`. The filters are evaluated from the tick given as the last run of the
+/// system, the tick at which the entities changed is unknown.
+struct FilterChangeDetector {
+    system: Box<dyn ReadOnlySystem<In = (), Out = Vec<Entity>>>,
+}
+
+fn matching_entities<Q: ReadOnlyWorldQuery, F: ReadOnlyWorldQuery>(
+    query: Query<(Entity, Q), F>,
+) -> Vec<Entity> {
+    query.iter().map(|(entity, _)| entity).collect()
+}
+
+impl ChangeDetector for FilterChangeDetector {
+    fn detect(&mut self, world: &World, since: Tick, this_run: Tick) -> Vec<(Entity, Tick)> {
+        self.system.set_last_run(since);
+        self.system
+            .run_readonly((), world)
+            .into_iter()
+            .map(|entity| (entity, this_run))
+            .collect()
+    }
+
+    fn reports_ticks(&self) -> bool {
+        false
+    }
+}
+
+/// A system listing the entities whose component was removed since it last ran, including the
+/// despawned ones, as told by [`RemovedComponents`]. The tick at which they were removed is
+/// unknown, they are all reported at the tick of the detection.
+struct RemovalDetector {
+    system: Box<dyn ReadOnlySystem<In = (), Out = Vec<Entity>>>,
+}
+
+fn removed_entities<T: Component>(mut removed: RemovedComponents<T>) -> Vec<Entity> {
+    removed.iter().collect()
+}
+
+impl ChangeDetector for RemovalDetector {
+    // Reporting the tick of the detection keeps a single run per tick for all the waiters,
+    // which matters since the system consumes the removals it reads
+    fn detect(&mut self, world: &World, _since: Tick, this_run: Tick) -> Vec<(Entity, Tick)> {
+        self.system
+            .run_readonly((), world)
+            .into_iter()
+            .map(|entity| (entity, this_run))
+            .collect()
+    }
+}
+
+/// A coroutine waiting for any entity with the component `T` and matching the filter `F` to
+/// change.
+pub struct AnyChangeWait {
+    /// Identifies the component and filter, waits with the same key share their detection query
+    pub key: TypeId,
+    /// Only changes strictly newer than this tick are reported
+    pub since: Tick,
+    pub new_detector: fn(&mut World) -> Box<dyn ChangeDetector>,
+    pub sender: SyncCell<OnceSender<ChangedEntities>>,
+}
+
+impl AnyChangeWait {
+    pub fn new<T: Component, F: ReadOnlyWorldQuery + 'static>(
+        since: Tick,
+        sender: OnceSender<ChangedEntities>,
+    ) -> Self {
+        Self {
+            key: TypeId::of::<(T, F)>(),
+            since,
+            new_detector: |world| {
+                Box::new(QueryChangeDetector::<T, F> {
+                    state: QueryState::new(world),
+                })
+            },
+            sender: SyncCell::new(sender),
+        }
+    }
+
+    /// A wait for the component `T` to be removed from any entity after `since`, or for any
+    /// entity with it to be despawned.
+    pub fn removed<T: Component>(since: Tick, sender: OnceSender<ChangedEntities>) -> Self {
+        Self {
+            key: TypeId::of::<RemovedComponents<'static, 'static, T>>(),
+            since,
+            new_detector: |world| {
+                let mut system = IntoSystem::into_system(removed_entities::<T>);
+                system.initialize(world);
+                Box::new(RemovalDetector {
+                    system: Box::new(system),
+                })
+            },
+            sender: SyncCell::new(sender),
+        }
+    }
+
+    /// A wait for any entity matching the query `Q` and the filter `F` changed after `since`,
+    /// as told by the change filters of `F`.
+    pub fn query<Q: ReadOnlyWorldQuery + 'static, F: ReadOnlyWorldQuery + 'static>(
+        since: Tick,
+        sender: OnceSender<ChangedEntities>,
+    ) -> Self {
+        Self {
+            key: TypeId::of::<Query<'static, 'static, (Entity, Q), F>>(),
+            since,
+            new_detector: |world| {
+                let mut system = IntoSystem::into_system(matching_entities::<Q, F>);
+                system.initialize(world);
+                Box::new(FilterChangeDetector {
+                    system: Box::new(system),
+                })
+            },
+            sender: SyncCell::new(sender),
+        }
+    }
+}
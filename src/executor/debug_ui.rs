@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::{Component, Entity, ReflectComponent, World},
+    reflect::Reflect,
+};
+
+use super::{CoroInfo, Executor};
+
+/// A coroutine as shown by inspectors, see [`CoroutineDebugInfo`].
+#[derive(Reflect, Clone, Debug, Default, PartialEq)]
+pub struct CoroutineInfo {
+    pub name: String,
+    /// What the coroutine waits on, see [`WaitReason`](super::WaitReason)
+    pub wait: String,
+    /// The time elapsed since the coroutine was added to the executor
+    pub age: Duration,
+}
+
+impl From<CoroInfo> for CoroutineInfo {
+    fn from(info: CoroInfo) -> Self {
+        Self {
+            name: info.name.to_string(),
+            wait: format!("{:?}", info.wait),
+            age: info.lifetime,
+        }
+    }
+}
+
+/// The coroutines bound to an entity, for inspectors such as `bevy-inspector-egui`. Updated at
+/// the end of each tick of the [`Executor`], only on the entities it was inserted on.
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component)]
+pub struct CoroutineDebugInfo(pub Vec<CoroutineInfo>);
+
+impl Executor {
+    pub(crate) fn sync_debug_info(&self, world: &mut World) {
+        let mut query = world.query::<(Entity, &mut CoroutineDebugInfo)>();
+        for (entity, mut info) in query.iter_mut(world) {
+            info.0 = self
+                .coroutines_of(entity)
+                .map(CoroutineInfo::from)
+                .collect();
+        }
+    }
+}
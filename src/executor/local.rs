@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::{Resource, World},
+    time::Time,
+    utils::Instant,
+};
+
+use crate::function_coroutine::{
+    cleanup::CleanupRegistry, handle::CoroHandle, CoroutineParamFunction,
+};
+
+use super::{Executor, TickCount};
+
+/// An [`Executor`] borrowing the world for a limited region, typically within an exclusive
+/// system, to run a few coroutines to completion without involving the executor of the world.
+///
+/// While it runs, it gets its own [`Time`], [`TickCount`] and bookkeeping resources, swapped
+/// with the ones of the world, so that neither executor sees the coroutines of the other. Its
+/// clock only advances with [`LocalExecutor::tick_with_delta`].
+///
+/// When dropped, its remaining coroutines are cancelled, their cleanups run, their local
+/// entities are despawned, and the commands they queued are applied rather than discarded.
+pub struct LocalExecutor<'w> {
+    world: &'w mut World,
+    executor: Executor,
+    resources: LocalResources,
+}
+
+/// The resources of the world swapped with the ones of a [`LocalExecutor`] while it runs.
+struct LocalResources {
+    time: Option<Time>,
+    tick_count: Option<TickCount>,
+    cleanups: Option<CleanupRegistry>,
+}
+
+impl LocalResources {
+    fn swap(&mut self, world: &mut World) {
+        swap_resource(world, &mut self.time);
+        swap_resource(world, &mut self.tick_count);
+        swap_resource(world, &mut self.cleanups);
+    }
+}
+
+/// Swap the resource `T` of the `world` with the one in `slot`, removing it from the world if
+/// there is none.
+fn swap_resource<T: Resource>(world: &mut World, slot: &mut Option<T>) {
+    let previous = world.remove_resource::<T>();
+    if let Some(value) = slot.take() {
+        world.insert_resource(value);
+    }
+    *slot = previous;
+}
+
+impl<'w> LocalExecutor<'w> {
+    pub fn new(world: &'w mut World) -> Self {
+        LocalExecutor {
+            world,
+            executor: Executor::default(),
+            resources: LocalResources {
+                time: Some(Time::new(Instant::now())),
+                tick_count: None,
+                cleanups: None,
+            },
+        }
+    }
+
+    /// Run `f` with the resources of this executor in the world.
+    fn isolated<R>(&mut self, f: impl FnOnce(&mut Executor, &mut World) -> R) -> R {
+        self.resources.swap(self.world);
+        let result = f(&mut self.executor, self.world);
+        self.resources.swap(self.world);
+        result
+    }
+
+    /// Add a coroutine, and returns a [`CoroHandle`] to it, see [`Executor::start`].
+    pub fn start<Marker: 'static, T, C>(&mut self, coroutine: C) -> CoroHandle<T>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.isolated(|executor, world| executor.start(world, coroutine))
+    }
+
+    /// Advance the clock of this executor by `delta`, and tick it once.
+    pub fn tick_with_delta(&mut self, delta: Duration) {
+        self.isolated(|executor, world| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap_or(time.startup());
+            time.update_with_instant(last_update + delta);
+            executor.tick(world);
+        });
+    }
+
+    /// Tick this executor with `delta` until the coroutine of `handle` finishes, at most
+    /// `max_ticks` times, see [`Executor::block_on`].
+    pub fn block_on<T>(
+        &mut self,
+        handle: &mut CoroHandle<T>,
+        delta: Duration,
+        max_ticks: usize,
+    ) -> Option<T> {
+        for _ in 0..max_ticks {
+            if let Some(result) = handle.try_take() {
+                return Some(result);
+            }
+            handle.id()?;
+            self.tick_with_delta(delta);
+        }
+        handle.try_take()
+    }
+
+    /// Returns true if none of its coroutines are still running.
+    pub fn is_empty(&self) -> bool {
+        self.executor.is_empty()
+    }
+
+    /// Returns the borrowed world, to be modified in between two ticks.
+    pub fn world(&mut self) -> &mut World {
+        self.world
+    }
+}
+
+impl Drop for LocalExecutor<'_> {
+    fn drop(&mut self) {
+        // The last tick runs the cleanups, despawns the local entities and applies the commands
+        // of the cancelled coroutines, without resuming any
+        self.isolated(|executor, world| {
+            executor.cancel_all();
+            executor.tick(world);
+        });
+    }
+}
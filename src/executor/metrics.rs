@@ -0,0 +1,30 @@
+use bevy::{prelude::Resource, utils::HashMap};
+
+use super::TickSummary;
+
+/// The value of each counter incremented with
+/// [`Scope::metrics_counter`](crate::function_coroutine::scope::Scope::metrics_counter).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot(pub HashMap<&'static str, i64>);
+
+impl MetricsSnapshot {
+    /// Returns the value of the counter `name`, 0 if it was never incremented.
+    pub fn get(&self, name: &str) -> i64 {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn add(&mut self, name: &'static str, delta: i64) {
+        *self.0.entry(name).or_default() += delta;
+    }
+
+    pub(crate) fn add_summary(&mut self, summary: &TickSummary) {
+        self.add("corentin.resumed", summary.resumed as i64);
+        self.add("corentin.completed", summary.completed as i64);
+        self.add("corentin.cancelled", summary.cancelled as i64);
+    }
+}
+
+/// The increments queued by coroutines during a tick, merged into the executor counters once
+/// the commands are applied.
+#[derive(Resource, Default)]
+pub(crate) struct PendingMetrics(pub Vec<(&'static str, i64)>);
@@ -1,192 +1,1668 @@
-use bevy::{prelude::Entity, time::Time, utils::synccell::SyncCell};
-use std::{collections::VecDeque, ops::Index};
+use bevy::{
+    ecs::{component::Tick, event::Events, system::CommandQueue, world::WorldId},
+    prelude::{Component, Entity, ReflectComponent},
+    reflect::Reflect,
+    time::Time,
+    utils::{synccell::SyncCell, Instant},
+};
+use std::{
+    any::{Any, TypeId},
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    ops::Index,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::ThreadId,
+    time::Duration,
+};
 
 use bevy::{
+    hierarchy::Parent,
     prelude::{Resource, World},
-    time::Timer,
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
+use smallvec::SmallVec;
 use tinyset::{SetU64, SetUsize};
 
 use crate::{
-    function_coroutine::ResumeParam,
+    function_coroutine::{
+        cleanup::{CleanupFn, CleanupRegistry, CleanupScope},
+        ResumeParam,
+    },
     global_channel::{Channel, CommandChannel},
 };
 
-use self::msg::{CoroStatus, EmitMsg, NewCoroutine, SignalId, YieldMsg};
+use self::change_detection::{AnyChangeWait, ChangeDetector, ChangedEntities};
+#[cfg(any(test, feature = "testing"))]
+use self::msg::CoroStatusKind;
+use self::msg::{
+    CancelMsg, CancelReason, CleanupReason, CoroStatus, EmitMsg, Latch, LocalEntityMsg,
+    NewCoroutine, SignalId, SignalType, Wakeups, YieldMsg,
+};
+use self::phase::Phase;
+use self::quota::{CoroutineCancelled, Limits, Quota};
+use self::record::{CoroRecord, WaitState};
+use self::watch::{Watch, WatchToken};
 
 use super::{
-    function_coroutine::{resume::Resume, scope::Scope, CoroutineParamFunction, FunctionCoroutine},
+    function_coroutine::{
+        await_has_all::QueryStates,
+        await_marked::MarkedCoroutines,
+        behavior::{BehaviorHandle, MessageQueue},
+        defer::DeferredCommands,
+        handle::CoroHandle,
+        insert_result,
+        once_channel::sync_once_channel,
+        resource_lock::ResourceLocks,
+        resume::Resume,
+        scope::Scope,
+        scope_resource::ScopeResources,
+        CoroutineParamFunction, FunctionCoroutine, InvalidParams, ResultSender,
+    },
     id_alloc::{Id, Ids},
-    Coroutine, HeapCoro,
+    CoroAccess, CoroMeta, Coroutine, HeapCoro,
 };
 
+pub mod change_detection;
+#[cfg(feature = "debug-ui")]
+pub mod debug_ui;
+pub mod local;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod msg;
+pub mod phase;
+pub mod quota;
+mod record;
+mod save;
+pub mod watch;
 
+pub use local::LocalExecutor;
+pub use save::DeserializeError;
+
+/// Runs all the coroutines of a [`World`]. Each [`App`](bevy::prelude::App) or
+/// [`SubApp`](bevy::app::SubApp) running coroutines owns its own executor, which is never cloned
+/// or extracted from one world to another (see
+/// [`CorentinPlugin::for_sub_app`](crate::plugin::CorentinPlugin::for_sub_app)).
 #[derive(Resource, Default)]
 pub struct Executor {
     ids: Ids,
-    coroutines: HashMap<Id, HeapCoro>,
+    /// Each coroutine, with what it waits on and how it is linked to the others
+    records: HashMap<Id, CoroRecord>,
+    /// The coroutines waiting on the next tick. It is drained each tick, skipping the ones which
+    /// were cancelled meanwhile.
     waiting_on_tick: VecDeque<Id>,
-    waiting_on_time: HashMap<Id, Timer>,
-    waiting_on_all: HashMap<Id, SetU64>,
-    waiting_on_first: HashMap<Id, SetU64>,
-    waiting_on_signal: HashMap<SignalId, SetU64>,
-    scope_ownership: HashMap<Id, SetU64>,
-    is_awaited_by: HashMap<Id, Id>,
+    /// The coroutines waiting on a tick number, the earliest first. Like the tick queue, the
+    /// entries of the coroutines cancelled meanwhile are skipped once popped.
+    waiting_on_exact_tick: BinaryHeap<(Reverse<u64>, Id)>,
+    /// The coroutines waiting on each phase, drained when the executor is ticked for it
+    waiting_on_phase: HashMap<Phase, VecDeque<Id>>,
+    /// The coroutines waiting on a duration, or on the deadline of `all_within`
+    timers: SetU64,
+    /// The coroutines waiting on each signal, or on any signal
+    signal_waiters: SignalWaiters,
+    /// The coroutines counting the emissions of a signal, until they are resumed
+    signal_counters: SetU64,
+    waiting_on_any_change: SetU64,
+    /// The coroutines waiting on a component changed by someone else, see
+    /// [`Scope::await_changed_by_other`]
+    waiting_on_other_change: SetU64,
+    /// The latched signals, received right away by the coroutines awaiting them
+    latches: HashMap<SignalId, Latch>,
+    /// The watch functions evaluated at the start of each tick, with their signal
+    watches: Vec<(SignalId, Watch)>,
+    /// The coroutines started with limits, whose commands are counted each tick
+    limited: SetU64,
+    /// The coroutines cancelled for crossing their limits, reported at the end of the tick
+    quota_exceeded: Vec<Id>,
+    /// The coroutines awaited before they are registered, linked to their record once they are
+    pending_awaits: HashMap<Id, Id>,
+    /// Detection queries, kept around to avoid rebuilding them each tick
+    change_detectors: HashMap<TypeId, Box<dyn ChangeDetector>>,
+    /// The coroutines bound to each entity
+    entity_coroutines: HashMap<Entity, SetU64>,
+    /// The depth of the entities with coroutines in their hierarchy, when they got their first
+    /// one. Their coroutines are cancelled from the deepest to the root once despawned.
+    owner_depths: HashMap<Entity, usize>,
+    /// The entities whose [`HasCoroutines`] marker must be updated, if markers are enabled
+    dirty_markers: Option<HashSet<Entity>>,
+    /// The total time elapsed, as seen by the executor
+    elapsed: Duration,
+    /// The number of times the executor was ticked, mirrored in the [`TickCount`] resource
+    tick_count: u64,
+    /// Local entities whose coroutine ended, despawned at the end of the tick
+    to_despawn: Vec<Entity>,
+    /// When the commands queued by the coroutines are applied
+    command_flush: CommandFlushPoint,
+    /// The order in which the coroutines waiting on the next tick are resumed
+    tick_order: TickOrder,
+    /// If true, the commands are applied after each coroutine is resumed
+    apply_deferred_after_each: bool,
+    /// The closures registered with `Scope::on_cleanup`, shared with the coroutines
+    cleanup_registry: CleanupRegistry,
+    /// The cleanup closures of the coroutines which ended, run at the end of the tick
+    pending_cleanups: Vec<(Id, SyncCell<Vec<CleanupFn>>)>,
+    /// The values the coroutines keep in between two resumes
+    storage: ScopeStorage,
+    /// Set while polling a single coroutine, the others made ready meanwhile wait on the next
+    /// tick instead
+    poll_only: Option<Id>,
+    /// The world in which the coroutines run, set once the executor is first used
+    world_id: Option<WorldId>,
     new_coro_channel: Channel<NewCoroutine>,
+    local_entity_channel: Channel<LocalEntityMsg>,
+    cancel_channel: Channel<CancelMsg>,
     signal_channel: Channel<EmitMsg>,
     commands_channel: CommandChannel,
     yield_channel: Channel<YieldMsg>,
+    /// The thread on which the executor was first ticked, used to detect misuses
+    tick_thread: Option<ThreadId>,
+    cleanup_hooks: Vec<CleanupHook>,
+    hooks: ExecutorHooks,
+    /// Restore the state machines from their serialized state, by name
+    state_loaders: HashMap<&'static str, save::StateLoader>,
+    /// The summary of the tick being run
+    summary: TickSummary,
+    /// The summary of the last completed tick
+    last_summary: TickSummary,
+    #[cfg(any(test, feature = "testing"))]
+    last_yields: Vec<(Id, CoroStatusKind)>,
+    #[cfg(feature = "metrics")]
+    metrics: metrics::MetricsSnapshot,
+}
+
+/// The number of coroutines waiting on each kind of event, see [`Executor::counts`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WaitCounts {
+    pub on_tick: usize,
+    pub on_time: usize,
+    pub on_first: usize,
+    pub on_all: usize,
+    pub on_signal: usize,
+    /// The coroutines whose parent waits on them with `first` or `all`
+    pub awaited: usize,
+}
+
+/// What a coroutine is currently waiting on, see [`Executor::coroutines_of`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitReason {
+    Tick,
+    Phase,
+    Time,
+    First,
+    All,
+    Signal,
+    AnySignal,
+    AnyChange,
+    Paused,
+    /// Not waiting on anything yet, or waiting to be resumed
+    Other,
+}
+
+/// The result of [`Executor::poll_coroutine`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PollOutcome {
+    /// The coroutine was resumed, and now waits on this
+    Waiting(WaitReason),
+    /// The coroutine was resumed, and finished or was cancelled
+    Finished,
+    /// The coroutine was not resumed, since it waits on other coroutines or on signals, or is
+    /// paused
+    Blocked(WaitReason),
+    /// There is no coroutine with this id
+    NotFound,
+}
+
+/// Information about a coroutine, see [`Executor::coroutines_of`].
+#[derive(Clone, Copy, Debug)]
+pub struct CoroInfo {
+    pub id: Id,
+    pub name: &'static str,
+    pub wait: WaitReason,
+    /// The time elapsed since the coroutine was added to the executor
+    pub lifetime: Duration,
+}
+
+/// The coroutines bound to an entity, kept in sync by the executor once enabled with
+/// [`Executor::enable_coroutine_markers`]. Allows UI tools to query them from the ECS directly.
+#[derive(Component, Reflect, Default, Clone)]
+#[reflect(Component)]
+pub struct HasCoroutines(pub SmallVec<[Id; 4]>);
+
+/// Inserted on the entities spawned with [`Scope::spawn_empty_scoped`], to find the ones
+/// belonging to a coroutine from the ECS. Those whose coroutine no longer exists are despawned
+/// by [`despawn_orphaned_entities`](crate::plugin::despawn_orphaned_entities).
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CoroutineSpawned {
+    /// The coroutine which spawned the entity
+    pub by: Id,
+}
+
+/// The coroutines waiting on signals, which coroutines can read to skip emitting the signals
+/// nobody waits on, see [`Scope::has_waiters`].
+#[derive(Default)]
+pub struct SignalWaiters {
+    by_signal: HashMap<SignalId, SetU64>,
+    /// The coroutines waiting on any signal
+    any: SetU64,
+}
+
+impl SignalWaiters {
+    /// Returns true if a coroutine currently waits on the signal `id`, or on any signal.
+    pub fn has_waiters(&self, id: SignalId) -> bool {
+        !self.any.is_empty() || self.by_signal.contains_key(&id)
+    }
+}
+
+/// The values the coroutines keep in the executor, reached from their [`Scope`] while they are
+/// resumed.
+#[derive(Default)]
+pub struct ScopeStorage {
+    /// The values stored with [`Scope::scope_resource`]
+    pub(crate) resources: ScopeResources,
+    /// The coroutines registered with [`Scope::register_as`]
+    pub(crate) marked: MarkedCoroutines,
+    /// The queries built by [`Scope::entity_has_all`]
+    pub(crate) queries: QueryStates,
+    /// The name and the access of each coroutine, as of its last resume, checked by
+    /// [`Scope::map_world`]
+    pub(crate) accesses: HashMap<Id, (&'static str, CoroAccess)>,
+    /// The coroutines woken by the primitives they wait on, such as a mutex
+    pub(crate) wakeups: Wakeups,
 }
 
-// SAFETY: The [`Executor`] can only be accessed througth an exclusive
-// reference, therefore it never has to be synced.
-unsafe impl Sync for Executor {}
+/// The node of the signals emitted by the executor itself, which no coroutine descends from.
+const EXECUTOR_NODE: usize = usize::MAX;
+
+/// When the commands queued by coroutines are applied, see
+/// [`Executor::set_command_flush_point`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommandFlushPoint {
+    /// At the end of each tick of the executor. They are therefore applied before the commands
+    /// of the systems which ran earlier in the same schedule, but not yet applied.
+    #[default]
+    InsideTick,
+    /// Kept until [`apply_coroutine_commands`](crate::plugin::apply_coroutine_commands) runs,
+    /// added right after a sync point following the executor by
+    /// [`CommandFlushPlugin`](crate::plugin::CommandFlushPlugin). They are therefore applied
+    /// after the commands of the systems which ran before the executor. The local entities of
+    /// the coroutines which ended are despawned at this point as well.
+    NextApplyDeferred,
+}
+
+/// The order in which the coroutines waiting on the next tick are resumed, see
+/// [`Executor::set_tick_order`]. A coroutine resumed later in a tick observes the writes of the
+/// ones resumed before it, so this order can favor some coroutines over others.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TickOrder {
+    /// In the order they yielded during the previous tick. The cheapest, but a coroutine
+    /// resumed last keeps being resumed last, and adding unrelated coroutines changes the order.
+    #[default]
+    SpawnOrder,
+    /// Same as [`TickOrder::Sorted`], but the starting point moves by one coroutine each tick,
+    /// so no coroutine is always resumed first or last.
+    Rotating,
+    /// By increasing [`Id`], which is stable whatever the order in which they yielded.
+    Sorted,
+}
+
+/// The commands of the coroutines waiting to be applied, with the
+/// [`CommandFlushPoint::NextApplyDeferred`] flush point.
+#[derive(Resource, Default)]
+pub(crate) struct PendingCommands(Vec<CommandQueue>);
+
+impl PendingCommands {
+    /// Apply the pending commands, in the order they were queued.
+    pub(crate) fn apply(world: &mut World) {
+        let Some(mut pending) = world.get_resource_mut::<PendingCommands>() else {
+            return;
+        };
+        for mut queue in std::mem::take(&mut pending.0) {
+            queue.apply(world);
+        }
+        DeferredCommands::apply(world);
+    }
+}
+
+/// The number of times the [`Executor`] was ticked so far, including the current tick. The
+/// ticks for a [`Phase`] are not counted. The count is owned by the executor, and this resource
+/// only mirrors it for the coroutines, so overwriting it has no effect on the executor.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TickCount(pub u64);
+
+/// A callback notified each time a coroutine is cleaned up.
+type CleanupHook = Box<dyn Fn(Id, CleanupReason) + Send + Sync>;
+
+/// A run of a [`ChangeDetector`]: its key, and the tick it runs from, unless it reports the tick
+/// of each change.
+type DetectorRun = (TypeId, Option<u32>);
+
+/// Callbacks notified by the executor while it ticks, see [`Executor::set_hooks`]. They only
+/// receive read-only information, and cannot access the executor itself.
+#[derive(Default)]
+pub struct ExecutorHooks {
+    /// Called at the start of each tick, before any coroutine is resumed
+    pub on_tick_start: Option<TickStartHook>,
+    /// Called at the end of each tick, once the commands are applied
+    pub on_tick_end: Option<TickEndHook>,
+    /// Called right before a coroutine is resumed
+    pub before_resume: Option<BeforeResumeHook>,
+    /// Called right after a coroutine is resumed, with the status it yielded
+    pub after_resume: Option<AfterResumeHook>,
+}
+
+pub type TickStartHook = Box<dyn Fn(&World) + Send + Sync>;
+pub type TickEndHook = Box<dyn Fn(&World, TickSummary) + Send + Sync>;
+pub type BeforeResumeHook = Box<dyn Fn(&CoroInfo) + Send + Sync>;
+pub type AfterResumeHook = Box<dyn Fn(&CoroInfo, &CoroStatus) + Send + Sync>;
+
+/// What happened during a tick of the executor, see [`ExecutorHooks::on_tick_end`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickSummary {
+    /// The number of times a coroutine was resumed
+    pub resumed: usize,
+    pub completed: usize,
+    pub cancelled: usize,
+    /// The wall-clock time spent in the tick
+    pub duration: Duration,
+}
 
 impl Executor {
     pub fn add_coroutine(&mut self, id: Id, coroutine: HeapCoro) {
-        let prev = self.coroutines.insert(id, coroutine);
-        self.waiting_on_tick.push_back(id);
+        let prev = self.insert_coroutine(id, coroutine, None, None);
+        self.wait_on(id, WaitState::Tick);
         debug_assert!(prev.is_none());
     }
 
-    fn cancel(&mut self, coro_id: Id) {
-        self.ids.free(coro_id);
-        self.coroutines.remove(&coro_id);
+    fn insert_coroutine(
+        &mut self,
+        id: Id,
+        mut coroutine: HeapCoro,
+        owned_by: Option<Id>,
+        started_by: Option<Id>,
+    ) -> Option<CoroRecord> {
+        let saved_state = coroutine.get().save();
+        let meta = coroutine.get().meta();
+        let (name, owner) = (meta.name, meta.owner);
+        self.storage
+            .accesses
+            .insert(id, (name, meta.access.clone()));
+        let mut record = CoroRecord::new(coroutine, name, self.elapsed);
+        record.owner = owner;
+        record.saved_state = saved_state;
+        record.awaited_by = self.pending_awaits.remove(&id);
+
+        if let Some(parent) = owned_by.and_then(|p| self.records.get_mut(&p)) {
+            parent.owned.insert(id.to_bits());
+            record.owned_by = owned_by;
+        }
+
+        // Whether it belongs to the scope it was started from or not, both could run in parallel
+        if let Some(scope) = started_by.and_then(|s| self.records.get_mut(&s)) {
+            let mut access = scope.coroutine.get().meta().access.clone();
+            record.conflicts_with_scope = !access.union_with(&record.coroutine.get().meta().access);
+        }
+
+        if let Some(owner) = owner {
+            self.entity_coroutines
+                .entry(owner)
+                .or_default()
+                .insert(id.to_bits());
+            if let Some(dirty) = &mut self.dirty_markers {
+                dirty.insert(owner);
+            }
+        }
+        self.records.insert(id, record)
+    }
+
+    /// Remove the coroutine, and unlink it from the secondary indices and from its scope. Its
+    /// local entities are queued to be despawned. Returns its record if it was still there.
+    fn remove_coroutine(&mut self, id: Id) -> Option<CoroRecord> {
+        let mut record = self.records.remove(&id)?;
+        self.unindex(id, &record.wait);
+        if record.counter.is_some() {
+            self.signal_counters.remove(id.to_bits());
+        }
+        if record.quota.is_some() {
+            self.limited.remove(id.to_bits());
+        }
+        if let Some(parent) = record.owned_by.and_then(|p| self.records.get_mut(&p)) {
+            parent.owned.remove(id.to_bits());
+        }
+        self.to_despawn.append(&mut record.local_entities);
+        self.storage.accesses.remove(&id);
+        if self.storage.marked.unregister(id) {
+            self.signal_channel.send(EmitMsg {
+                id: SignalId::completed(id),
+                by: EXECUTOR_NODE,
+                latch: None,
+            });
+        }
+        let cleanups = self.cleanup_registry.take(id);
+        if !cleanups.is_empty() {
+            self.pending_cleanups.push((id, SyncCell::new(cleanups)));
+        }
+
+        if let Some(owner) = record.owner {
+            if let Some(owned) = self.entity_coroutines.get_mut(&owner) {
+                owned.remove(id.to_bits());
+                if owned.is_empty() {
+                    self.entity_coroutines.remove(&owner);
+                    self.owner_depths.remove(&owner);
+                }
+            }
+            if let Some(dirty) = &mut self.dirty_markers {
+                dirty.insert(owner);
+            }
+        }
+
+        Some(record)
+    }
+
+    /// Make the coroutine wait on `wait`, indexing it so that it can be found again.
+    fn wait_on(&mut self, coro_id: Id, wait: WaitState) {
+        if !self.records.contains_key(&coro_id) {
+            return;
+        }
+
+        for signal_id in wait.signals() {
+            self.signal_waiters
+                .by_signal
+                .entry(*signal_id)
+                .or_default()
+                .insert(coro_id.to_bits());
+        }
+
+        match &wait {
+            WaitState::Tick => self.waiting_on_tick.push_back(coro_id),
+            WaitState::ExactTick(tick) => {
+                self.waiting_on_exact_tick.push((Reverse(*tick), coro_id))
+            }
+            WaitState::Phase(phase) => self
+                .waiting_on_phase
+                .entry(phase.clone())
+                .or_default()
+                .push_back(coro_id),
+            WaitState::AnySignal(_) => {
+                self.signal_waiters.any.insert(coro_id.to_bits());
+            }
+            WaitState::AnyChange(_) => {
+                self.waiting_on_any_change.insert(coro_id.to_bits());
+            }
+            WaitState::ChangedByOther { .. } => {
+                self.waiting_on_other_change.insert(coro_id.to_bits());
+            }
+            _ => {}
+        }
+        if wait.has_timer() {
+            self.timers.insert(coro_id.to_bits());
+        }
+
+        // Each handle is unique, so each coroutine is awaited at most once
+        for handle in wait.handles().into_iter().flat_map(|h| h.iter()) {
+            let handle = Id::from_bits(handle);
+            match self.records.get_mut(&handle) {
+                Some(record) => record.awaited_by = Some(coro_id),
+                // Started during this tick, and not registered yet
+                None => {
+                    self.pending_awaits.insert(handle, coro_id);
+                }
+            }
+        }
+
+        self.records.get_mut(&coro_id).unwrap().wait = wait;
+    }
+
+    /// Stop waiting, and returns what the coroutine was waiting on.
+    fn stop_waiting(&mut self, coro_id: Id) -> WaitState {
+        let Some(record) = self.records.get_mut(&coro_id) else {
+            return WaitState::Ready;
+        };
+        let wait = std::mem::replace(&mut record.wait, WaitState::Ready);
+        self.unindex(coro_id, &wait);
+        wait
+    }
+
+    /// Remove the coroutine from the secondary index of what it waits on. The tick queue is
+    /// drained each tick instead.
+    fn unindex(&mut self, coro_id: Id, wait: &WaitState) {
+        for signal_id in wait.signals() {
+            if let Some(waiting) = self.signal_waiters.by_signal.get_mut(signal_id) {
+                waiting.remove(coro_id.to_bits());
+                if waiting.is_empty() {
+                    self.signal_waiters.by_signal.remove(signal_id);
+                }
+            }
+        }
+
+        match wait {
+            WaitState::AnySignal(_) => {
+                self.signal_waiters.any.remove(coro_id.to_bits());
+            }
+            WaitState::AnyChange(_) => {
+                self.waiting_on_any_change.remove(coro_id.to_bits());
+            }
+            WaitState::ChangedByOther { .. } => {
+                self.waiting_on_other_change.remove(coro_id.to_bits());
+            }
+            // Unlike the tick queue, a phase may never be ticked again
+            WaitState::Phase(phase) => {
+                if let Some(waiting) = self.waiting_on_phase.get_mut(phase) {
+                    waiting.retain(|id| *id != coro_id);
+                    if waiting.is_empty() {
+                        self.waiting_on_phase.remove(phase);
+                    }
+                }
+            }
+            _ => {}
+        }
+        if wait.has_timer() {
+            self.timers.remove(coro_id.to_bits());
+        }
+    }
+
+    /// Unlink the coroutine `id` from the one awaiting it.
+    fn clear_awaited_by(&mut self, id: Id) {
+        self.pending_awaits.remove(&id);
+        if let Some(record) = self.records.get_mut(&id) {
+            record.awaited_by = None;
+        }
+    }
+
+    /// Returns information about each coroutine bound to `entity`.
+    pub fn coroutines_of(&self, entity: Entity) -> impl Iterator<Item = CoroInfo> + '_ {
+        self.entity_coroutines
+            .get(&entity)
+            .into_iter()
+            .flat_map(|ids| ids.iter())
+            .map(|id| self.coro_info(Id::from_bits(id)))
+    }
+
+    fn coro_info(&self, id: Id) -> CoroInfo {
+        let record = &self.records[&id];
+        CoroInfo {
+            id,
+            name: record.name,
+            wait: record.wait_reason(),
+            lifetime: self.elapsed - record.spawned_at,
+        }
+    }
+
+    /// The number of ids held by the secondary indices, to check that none is left behind.
+    #[cfg(test)]
+    pub(crate) fn indexed_len(&self) -> usize {
+        self.timers.len()
+            + self
+                .signal_waiters
+                .by_signal
+                .values()
+                .map(|w| w.len())
+                .sum::<usize>()
+            + self.signal_counters.len()
+            + self.signal_waiters.any.len()
+            + self.waiting_on_any_change.len()
+            + self.waiting_on_other_change.len()
+            + self
+                .waiting_on_phase
+                .values()
+                .map(|w| w.len())
+                .sum::<usize>()
+            + self.pending_awaits.len()
+    }
+
+    /// Keep a [`HasCoroutines`] component in sync on each entity with coroutines bound to it.
+    /// It is updated at the end of each tick.
+    pub fn enable_coroutine_markers(&mut self) {
+        if self.dirty_markers.is_none() {
+            self.dirty_markers = Some(self.entity_coroutines.keys().copied().collect());
+        }
+    }
+
+    fn sync_markers(&mut self, world: &mut World) {
+        let Some(dirty) = &mut self.dirty_markers else {
+            return;
+        };
+
+        for entity in dirty.drain() {
+            let Some(mut entity_mut) = world.get_entity_mut(entity) else {
+                continue;
+            };
+
+            match self.entity_coroutines.get(&entity) {
+                Some(ids) => {
+                    entity_mut.insert(HasCoroutines(ids.iter().map(Id::from_bits).collect()));
+                }
+                None => {
+                    entity_mut.remove::<HasCoroutines>();
+                }
+            }
+        }
+    }
+
+    /// Returns true if the coroutine with the given `id` is currently handled by this executor.
+    pub fn contains(&self, id: Id) -> bool {
+        self.records.contains_key(&id)
+    }
+
+    /// Returns true if the coroutine `id` declared an access conflicting with the one of the
+    /// scope it was started from, see [`CoroAccess::union_with`](crate::CoroAccess::union_with).
+    /// Both are always resumed one after the other for now, but could not run in parallel.
+    pub fn conflicts_with_scope(&self, id: Id) -> bool {
+        self.records
+            .get(&id)
+            .is_some_and(|record| record.conflicts_with_scope)
+    }
+
+    /// Returns the number of coroutines handled by this executor.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the number of times this executor was ticked so far, see [`TickCount`].
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// Returns true if there are no coroutines, and no pending messages that could spawn or
+    /// resume one (a coroutine started this tick might not be registered yet for instance).
+    pub fn is_idle(&mut self) -> bool {
+        self.records.is_empty()
+            && self.new_coro_channel.is_empty()
+            && self.yield_channel.is_empty()
+            && self.signal_channel.is_empty()
+            && self.local_entity_channel.is_empty()
+            && self.cancel_channel.is_empty()
+    }
+
+    /// Returns a copy of the counters incremented by coroutines so far. The executor also counts
+    /// its own activity, summed over the [`TickSummary`] of each tick, in `corentin.resumed`,
+    /// `corentin.completed` and `corentin.cancelled`.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_snapshot(&self) -> metrics::MetricsSnapshot {
+        self.metrics.clone()
+    }
+
+    /// Set the counter `name` back to 0.
+    #[cfg(feature = "metrics")]
+    pub fn reset_metric(&mut self, name: &str) {
+        self.metrics.0.remove(name);
+    }
+
+    /// Returns the number of coroutines waiting on each kind of event.
+    pub fn counts(&self) -> WaitCounts {
+        let mut counts = WaitCounts {
+            awaited: self.pending_awaits.len(),
+            ..Default::default()
+        };
+
+        for record in self.records.values() {
+            match record.wait {
+                WaitState::Tick | WaitState::ExactTick(_) => counts.on_tick += 1,
+                WaitState::Time(_) => counts.on_time += 1,
+                WaitState::First(_) | WaitState::FirstOrCancel(_) => counts.on_first += 1,
+                WaitState::All { .. } | WaitState::Quorum { .. } => counts.on_all += 1,
+                WaitState::Signal { .. } | WaitState::Signals { .. } => counts.on_signal += 1,
+                _ => {}
+            }
+            if record.awaited_by.is_some() {
+                counts.awaited += 1;
+            }
+        }
+
+        counts
+    }
+
+    /// Prevent the coroutine from being resumed until [`Executor::resume_coroutine`] is called.
+    /// It keeps waiting on what it was waiting on, but its timers are frozen meanwhile.
+    pub fn pause_coroutine(&mut self, id: Id) {
+        if let Some(record) = self.records.get_mut(&id) {
+            record.paused = true;
+        }
+    }
+
+    /// Allow a coroutine paused with [`Executor::pause_coroutine`] to be resumed again.
+    pub fn resume_coroutine(&mut self, id: Id) {
+        if let Some(record) = self.records.get_mut(&id) {
+            record.paused = false;
+        }
+    }
+
+    /// Register a `hook` called each time a coroutine is cleaned up, either because it
+    /// completed or because it was cancelled. Hooks are called in registration order.
+    pub fn register_cleanup_hook(
+        &mut self,
+        hook: impl Fn(Id, CleanupReason) + Send + Sync + 'static,
+    ) {
+        self.cleanup_hooks.push(Box::new(hook));
+    }
 
-        if let Some(owned) = self.scope_ownership.remove(&coro_id) {
-            for c in owned {
-                self.cancel(Id::from_bits(c))
+    /// Remove all the hooks registered with [`Executor::register_cleanup_hook`].
+    pub fn clear_cleanup_hooks(&mut self) {
+        self.cleanup_hooks.clear();
+    }
+
+    /// Choose when the commands queued by the coroutines are applied, see [`CommandFlushPoint`].
+    pub fn set_command_flush_point(&mut self, point: CommandFlushPoint) {
+        self.command_flush = point;
+    }
+
+    /// Choose the order in which the coroutines waiting on the next tick are resumed, see
+    /// [`TickOrder`].
+    pub fn set_tick_order(&mut self, order: TickOrder) {
+        self.tick_order = order;
+    }
+
+    /// If true, the commands queued by each coroutine are applied right after it is resumed,
+    /// with [`Executor::apply_deferred`], so that the coroutines resumed after it in the same
+    /// tick see their effects. The [`CommandFlushPoint`] is then ignored.
+    pub fn set_apply_deferred_after_each(&mut self, enabled: bool) {
+        self.apply_deferred_after_each = enabled;
+    }
+
+    /// Apply the commands queued by the coroutines so far, right away, instead of waiting for
+    /// the [`CommandFlushPoint`].
+    pub fn apply_deferred(&mut self, world: &mut World) {
+        self.commands_channel.apply(world);
+        DeferredCommands::apply(world);
+    }
+
+    /// Replace the hooks notified while ticking, see [`ExecutorHooks`].
+    pub fn set_hooks(&mut self, hooks: ExecutorHooks) {
+        self.hooks = hooks;
+    }
+
+    /// Returns the summary of the last tick.
+    pub fn last_tick_summary(&self) -> TickSummary {
+        self.last_summary
+    }
+
+    /// Returns the status each coroutine yielded during the last tick, in the order they were
+    /// resumed.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn debug_last_yields(&self) -> Vec<(Id, CoroStatusKind)> {
+        self.last_yields.clone()
+    }
+
+    fn run_cleanup_hooks(&self, coro_id: Id, reason: CleanupReason) {
+        for hook in &self.cleanup_hooks {
+            hook(coro_id, reason);
+        }
+    }
+
+    /// Cancel all the coroutines, so that the executor can be used with another world. Their
+    /// cleanup hooks run, but the entities they spawned locally are not despawned.
+    pub fn migrate_world(&mut self) {
+        let ids: Vec<Id> = self.records.keys().copied().collect();
+        for id in ids {
+            self.cancel(id, CancelReason::External);
+        }
+        self.to_despawn.clear();
+        self.pending_cleanups.clear();
+        // Their queries are bound to the previous world
+        self.change_detectors.clear();
+        self.storage.queries = QueryStates::default();
+        self.world_id = None;
+    }
+
+    /// Panics in debug builds if `world` is not the one the coroutines were added to. In release
+    /// builds, they are all cancelled instead, as with [`Executor::migrate_world`], since their
+    /// parameters and the queries of the executor refer to the other world.
+    fn check_world(&mut self, world: &World) {
+        if self.world_id.is_some_and(|id| id != world.id()) {
+            #[cfg(debug_assertions)]
+            panic!(
+                "The executor is used with another world than its coroutines, call \
+                `Executor::migrate_world` first to move it intentionally"
+            );
+            #[cfg(not(debug_assertions))]
+            self.migrate_world();
+        }
+        self.world_id = Some(world.id());
+    }
+
+    /// Cancel the coroutine `id`, and the ones depending on it, as if it was cancelled during a
+    /// tick. Returns false if it already finished or was cancelled. This includes the coroutines
+    /// started with [`Scope::start_forget`](crate::prelude::Scope::start_forget).
+    pub fn cancel_coroutine(&mut self, id: Id) -> bool {
+        if !self.records.contains_key(&id) {
+            return false;
+        }
+        self.cancel(id, CancelReason::External);
+        true
+    }
+
+    /// Cancel all the coroutines, as with [`Executor::cancel_coroutine`].
+    pub fn cancel_all(&mut self) {
+        let ids: Vec<Id> = self.records.keys().copied().collect();
+        for id in ids {
+            // Skipping the ones already cancelled with their scope
+            if self.records.contains_key(&id) {
+                self.cancel(id, CancelReason::External);
+            }
+        }
+    }
+
+    fn cancel(&mut self, coro_id: Id, reason: CancelReason) {
+        // A coroutine can be reached several times while cascading, for instance when it is
+        // both owned by a scope and awaited by it, only the first cancellation does anything.
+        if !self.ids.free(coro_id) {
+            return;
+        }
+
+        let Some(record) = self.remove_coroutine(coro_id) else {
+            // Cancelled before being registered, while already awaited
+            if let Some(parent) = self.pending_awaits.remove(&coro_id) {
+                self.child_cancelled(parent, coro_id);
             }
+            return;
+        };
+        self.summary.cancelled += 1;
+        self.run_cleanup_hooks(coro_id, CleanupReason::Cancelled(reason));
+        if reason == CancelReason::QuotaExceeded {
+            self.quota_exceeded.push(coro_id);
+        }
+
+        for c in record.owned {
+            self.cancel(Id::from_bits(c), CancelReason::ParentCancelled)
+        }
+
+        if let Some(parent) = record.awaited_by {
+            self.child_cancelled(parent, coro_id);
+        }
+
+        for o in record.wait.into_handles().into_iter().flatten() {
+            self.cancel(Id::from_bits(o), CancelReason::ParentCancelled);
+        }
+    }
+
+    /// Cancel `parent` since the coroutine `child` it awaits was cancelled, unless it still
+    /// waits on the first of other coroutines with `first_or_cancel`.
+    fn child_cancelled(&mut self, parent: Id, child: Id) {
+        if let Some(WaitState::FirstOrCancel(waits_on)) =
+            self.records.get_mut(&parent).map(|record| &mut record.wait)
+        {
+            waits_on.remove(child.to_bits());
+            if !waits_on.is_empty() {
+                return;
+            }
+        }
+        self.cancel(parent, CancelReason::ChildCancelled);
+    }
+
+    /// Register a value computed by `f`, evaluated at the start of each tick. The coroutines
+    /// waiting on it with [`Scope::await_watch`] are resumed during the tick it changes.
+    pub fn add_watch<T>(&mut self, f: impl Fn(&World) -> T + Send + Sync + 'static) -> WatchToken<T>
+    where
+        T: Clone + PartialEq + Send + Sync + 'static,
+    {
+        let signal_id = SignalId {
+            signal_type: SignalType::Watch(self.watches.len()),
+            owner: None,
+        };
+        let (token, watch) = WatchToken::new(signal_id, f);
+        self.watches.push((signal_id, watch));
+        token
+    }
+
+    /// Forget the latched signal `signal_id`, see
+    /// [`Scope::emit_latched`](crate::function_coroutine::scope::Scope::emit_latched). Returns
+    /// false if it was not latched.
+    pub fn clear_latch(&mut self, signal_id: SignalId) -> bool {
+        self.latches.remove(&signal_id).is_some()
+    }
+
+    pub fn tick_until_empty(&mut self, world: &mut World) {
+        while !self.records.is_empty() {
+            self.tick(world);
+        }
+    }
+
+    pub fn tick(&mut self, world: &mut World) {
+        let start = self.begin_tick(world);
+        self.tick_count += 1;
+        let tick_count = self.tick_count;
+        world.insert_resource(TickCount(tick_count));
+
+        // The latches of despawned entities are no longer relevant
+        self.latches.retain(|signal_id, _| {
+            signal_id
+                .owner
+                .is_none_or(|owner| world.get_entity(owner).is_some())
+        });
+
+        let mut root_coros = VecDeque::<Id>::new();
+
+        // Skipping the coroutines cancelled since, or already woken by a second entry
+        for coro_id in std::mem::take(&mut self.waiting_on_tick) {
+            if let Some(record) = self.records.get_mut(&coro_id) {
+                if matches!(record.wait, WaitState::Tick) {
+                    record.wait = WaitState::Ready;
+                    root_coros.push_back(coro_id);
+                }
+            }
+        }
+        while let Some((Reverse(tick), coro_id)) = self.waiting_on_exact_tick.peek().copied() {
+            if tick > tick_count {
+                break;
+            }
+            self.waiting_on_exact_tick.pop();
+            if let Some(record) = self.records.get_mut(&coro_id) {
+                if matches!(record.wait, WaitState::ExactTick(t) if t == tick) {
+                    record.wait = WaitState::Ready;
+                    root_coros.push_back(coro_id);
+                }
+            }
+        }
+        match self.tick_order {
+            TickOrder::SpawnOrder => {}
+            // From a stable order, since the order they yielded in already carries the
+            // rotation of the previous tick
+            TickOrder::Rotating => {
+                root_coros.make_contiguous().sort_unstable();
+                if !root_coros.is_empty() {
+                    let offset = (tick_count - 1) % root_coros.len() as u64;
+                    root_coros.rotate_left(offset as usize);
+                }
+            }
+            TickOrder::Sorted => root_coros.make_contiguous().sort_unstable(),
+        }
+
+        let delta_time = world.resource::<Time>().delta();
+        self.elapsed += delta_time;
+
+        for bits in self.limited.iter() {
+            if let Some(quota) = &mut self.records.get_mut(&Id::from_bits(bits)).unwrap().quota {
+                quota.commands = 0;
+            }
+        }
+
+        // Tick all the timers, except the ones of paused coroutines
+        let mut expired = Vec::new();
+        for bits in self.timers.clone() {
+            let coro_id = Id::from_bits(bits);
+            let record = self.records.get_mut(&coro_id).unwrap();
+            if record.paused {
+                continue;
+            }
+            match &mut record.wait {
+                WaitState::Time(timer) => {
+                    let remaining = timer.remaining();
+                    timer.tick(delta_time);
+                    if timer.just_finished() {
+                        let overshoot = delta_time.saturating_sub(remaining);
+                        record.coroutine.get().as_mut().set_overshoot(overshoot);
+                        self.stop_waiting(coro_id);
+                        root_coros.push_back(coro_id);
+                    }
+                }
+                WaitState::All {
+                    deadline: Some(timer),
+                    ..
+                } => {
+                    timer.tick(delta_time);
+                    // The finished deadline is kept, to be told apart from a new one
+                    if timer.finished() {
+                        self.timers.remove(bits);
+                        expired.push(coro_id);
+                    }
+                }
+                _ => unreachable!("Only the coroutines with a timer are indexed"),
+            }
+        }
+
+        self.wake_invalid_graceful(world, &mut root_coros);
+        self.detect_changes(world, &mut root_coros);
+        self.detect_changes_by_systems(world, &mut root_coros);
+        self.check_watches(world, &mut root_coros);
+        self.check_wakeups(world, &mut root_coros);
+
+        self.run_ready(world, root_coros, expired, start);
+    }
+
+    /// Resume only the coroutine `id`, as if it was woken by a tick, and returns what it waits
+    /// on afterward. The coroutines it starts are registered, but only run on the next tick,
+    /// like the ones it wakes. Its commands are applied as at the end of a tick, but time does
+    /// not advance and no change is detected. This lets an external tool step through a single
+    /// coroutine.
+    ///
+    /// A coroutine waiting on a tick, a phase or a duration is resumed right away. The ones
+    /// waiting on other coroutines or on signals, or paused, are left untouched.
+    pub fn poll_coroutine(&mut self, id: Id, world: &mut World) -> PollOutcome {
+        let Some(record) = self.records.get(&id) else {
+            return PollOutcome::NotFound;
+        };
+        let forced = matches!(
+            record.wait,
+            WaitState::Ready
+                | WaitState::Tick
+                | WaitState::ExactTick(_)
+                | WaitState::Phase(_)
+                | WaitState::Time(_)
+        );
+        if record.paused || !forced {
+            return PollOutcome::Blocked(record.wait_reason());
+        }
+
+        let start = self.begin_tick(world);
+        // The tick queue is skipped if it is not waiting on it anymore once drained
+        self.stop_waiting(id);
+
+        self.poll_only = Some(id);
+        self.run_ready(world, VecDeque::from([id]), Vec::new(), start);
+        self.poll_only = None;
+
+        match self.records.get(&id) {
+            Some(record) => PollOutcome::Waiting(record.wait_reason()),
+            None => PollOutcome::Finished,
+        }
+    }
+
+    /// Tick the executor for `phase`, resuming only the coroutines waiting on it, see
+    /// [`Scope::after_set`]. The others are left untouched: time does not advance, and no
+    /// change is detected. The coroutines started or woken by signals meanwhile still run right
+    /// away, as during a regular tick.
+    pub fn tick_phase(&mut self, world: &mut World, phase: &Phase) {
+        let start = self.begin_tick(world);
+
+        // The paused coroutines keep waiting on the phase, the others are always woken
+        let waiting = self.waiting_on_phase.remove(phase).unwrap_or_default();
+        let (paused, root_coros): (VecDeque<Id>, VecDeque<Id>) = waiting
+            .into_iter()
+            .partition(|coro_id| self.records[coro_id].paused);
+        for coro_id in &root_coros {
+            self.records.get_mut(coro_id).unwrap().wait = WaitState::Ready;
+        }
+        if !paused.is_empty() {
+            self.waiting_on_phase.insert(phase.clone(), paused);
+        }
+
+        self.run_ready(world, root_coros, Vec::new(), start);
+    }
+
+    /// Everything done before the coroutines of a tick are resumed. Returns when it started.
+    fn begin_tick(&mut self, world: &mut World) -> Instant {
+        self.check_tick_thread();
+        self.check_world(world);
+
+        let start = Instant::now();
+        self.summary = TickSummary::default();
+        #[cfg(any(test, feature = "testing"))]
+        self.last_yields.clear();
+        world.init_resource::<ResourceLocks>();
+        world.init_resource::<DeferredCommands>();
+        self.cleanup_registry = world
+            .get_resource_or_insert_with(CleanupRegistry::default)
+            .clone();
+        if let Some(hook) = &self.hooks.on_tick_start {
+            hook(world);
+        }
+        self.cancel_despawned_owners(world);
+        start
+    }
+
+    /// Cancel the coroutines of the entities despawned since the last tick, children first, so
+    /// that a whole subtree despawned with `despawn_recursive` is cleaned up in the same tick.
+    /// Graceful coroutines are left to be resumed one last time, as for any invalid parameter.
+    fn cancel_despawned_owners(&mut self, world: &World) {
+        let mut despawned: Vec<Entity> = self
+            .entity_coroutines
+            .keys()
+            .copied()
+            .filter(|owner| world.get_entity(*owner).is_none())
+            .collect();
+        despawned.sort_by_key(|owner| Reverse(self.owner_depths.get(owner).copied()));
+
+        for owner in despawned {
+            let Some(ids) = self.entity_coroutines.get(&owner) else {
+                continue;
+            };
+            let ids: Vec<Id> = ids.iter().map(Id::from_bits).collect();
+            for id in ids {
+                let graceful = self
+                    .records
+                    .get_mut(&id)
+                    .map(|record| record.coroutine.get().meta().graceful);
+                if graceful == Some(false) {
+                    self.cancel(id, CancelReason::OwnerDespawned);
+                }
+            }
+        }
+    }
+
+    /// Wake the graceful coroutines which became invalid while waiting, so that they get their
+    /// last resume even if what they wait on never happens. They are doomed once resumed, as for
+    /// any invalid coroutine. The ones waiting on something they expect to receive when resumed
+    /// are cancelled right away instead.
+    fn wake_invalid_graceful(&mut self, world: &World, root_coros: &mut VecDeque<Id>) {
+        let mut invalid = Vec::new();
+        for (coro_id, record) in self.records.iter_mut() {
+            if matches!(record.wait, WaitState::Ready) || record.doomed.is_some() {
+                continue;
+            }
+            let coro = record.coroutine.get();
+            if coro.meta().graceful && !coro.is_valid(world) {
+                invalid.push((*coro_id, invalidation_reason(coro.meta(), world)));
+            }
+        }
+
+        // In a stable order, the records are not
+        invalid.sort_unstable_by_key(|(coro_id, _)| *coro_id);
+        for (coro_id, reason) in invalid {
+            if self.records[&coro_id].wait.resumable_early() {
+                self.stop_waiting(coro_id);
+                root_coros.push_back(coro_id);
+            } else {
+                self.cancel(coro_id, reason);
+            }
+        }
+    }
+
+    /// Record the depth in their hierarchy of the entities which got their first coroutine.
+    fn update_owner_depths(&mut self, world: &World) {
+        for owner in self.entity_coroutines.keys() {
+            if self.owner_depths.contains_key(owner) {
+                continue;
+            }
+            let mut depth = 0;
+            let mut entity = *owner;
+            while let Some(parent) = world.get::<Parent>(entity) {
+                depth += 1;
+                entity = parent.get();
+            }
+            self.owner_depths.insert(*owner, depth);
+        }
+    }
+
+    /// Resume the `root_coros`, and the ones they wake, until none is ready. The coroutines
+    /// whose `all_within` deadline `expired` get their stragglers cancelled meanwhile. The
+    /// commands are then applied, and the tick is reported.
+    fn run_ready(
+        &mut self,
+        world: &mut World,
+        root_coros: VecDeque<Id>,
+        mut expired: Vec<Id>,
+        start: Instant,
+    ) {
+        let mut parents = ParentTable::new();
+        let mut signals = HashMap::new();
+
+        // The ready coroutines are popped from the back, the first root must be the last one
+        let mut ready_coro: Vec<(Id, usize)> = root_coros
+            .into_iter()
+            .rev()
+            .map(|c_id| (c_id, parents.add_root(c_id)))
+            .collect();
+
+        while !ready_coro.is_empty()
+            || self.resume_expired(&mut expired, &mut ready_coro, &mut parents)
+        {
+            while let Some((coro_id, node)) = ready_coro.pop() {
+                if !self.ids.contains(coro_id) {
+                    continue;
+                }
+
+                // Only the polled coroutine runs, see `Executor::poll_coroutine`
+                if self.poll_only.is_some_and(|only| only != coro_id) {
+                    self.wait_on(coro_id, WaitState::Tick);
+                    continue;
+                }
+
+                let record = self.records.get_mut(&coro_id).unwrap();
+                let coro = record.coroutine.get();
+
+                if !coro.is_valid(world) {
+                    let reason = invalidation_reason(coro.meta(), world);
+
+                    // Graceful coroutines are resumed once more, and cancelled right after
+                    if !coro.meta().graceful || record.doomed.is_some() {
+                        self.cancel(coro_id, reason);
+                        continue;
+                    }
+
+                    coro.as_mut().invalidate(reason);
+                    record.doomed = Some(reason);
+                    record.paused = false;
+                }
+
+                // A paused coroutine keeps being postponed to the next tick
+                if record.paused {
+                    self.wait_on(coro_id, WaitState::Tick);
+                    continue;
+                }
+
+                let info = (self.hooks.before_resume.is_some()
+                    || self.hooks.after_resume.is_some())
+                .then(|| self.coro_info(coro_id));
+                if let (Some(hook), Some(info)) = (&self.hooks.before_resume, &info) {
+                    hook(info);
+                }
+
+                let issued = self.commands_channel.issued();
+                let record = self.records.get_mut(&coro_id).unwrap();
+                if record.counter.take().is_some() {
+                    self.signal_counters.remove(coro_id.to_bits());
+                }
+
+                let coro = record.coroutine.get();
+                let status = Coroutine::resume(
+                    coro.as_mut(),
+                    world,
+                    &self.ids,
+                    node,
+                    &self.signal_channel,
+                    &self.new_coro_channel,
+                    &self.local_entity_channel,
+                    &self.cancel_channel,
+                    &self.commands_channel,
+                    &self.signal_waiters,
+                    &mut self.storage,
+                );
+
+                if let (Some(state), Some(saved)) = (coro.save(), record.saved_state.as_mut()) {
+                    *saved = state;
+                }
+                // Its parameters may have been reborrowed, or new accesses registered
+                self.storage
+                    .accesses
+                    .insert(coro_id, (record.name, coro.meta().access.clone()));
+                let doomed = record.doomed;
+
+                self.summary.resumed += 1;
+                if let (Some(hook), Some(info)) = (&self.hooks.after_resume, &info) {
+                    hook(info, &status);
+                }
+
+                // Must be done before the coroutine gets cleaned up
+                self.receive_local_entities();
+                if self.apply_deferred_after_each {
+                    self.apply_deferred(world);
+                }
+
+                let commands = self.commands_channel.issued() - issued;
+                if self.exceeds_quota(coro_id, commands) {
+                    self.cancel(coro_id, CancelReason::QuotaExceeded);
+                    continue;
+                }
+
+                if let Some(reason) = doomed {
+                    if !matches!(status, CoroStatus::Done) {
+                        self.cancel(coro_id, reason);
+                        continue;
+                    }
+                }
+
+                // TODO remove copy paste
+                // Note to self: When running on a single thread, it's faster to process each
+                // status immediatly, rather than accumulating them and processing them afterward.
+                // (however for the other queue, it seems to be faster to process them at the end
+                // (based on way too simple stress test))
+                #[cfg(any(test, feature = "testing"))]
+                self.last_yields
+                    .push((coro_id, CoroStatusKind::from(&status)));
+
+                match status {
+                    CoroStatus::Done => {
+                        self.mark_as_done(coro_id, node, &mut ready_coro, &mut parents)
+                    }
+                    // The ready coroutines are popped from the back, this one runs last
+                    CoroStatus::Cooperative => ready_coro.insert(0, (coro_id, node)),
+                    // Coroutines are resumed one at a time, and with the world, it runs next
+                    CoroStatus::Exclusive => ready_coro.push((coro_id, node)),
+                    CoroStatus::Cancel => {
+                        self.cancel(coro_id, CancelReason::ExplicitCancel);
+                    }
+                    CoroStatus::WrongAwait => {
+                        self.cancel(coro_id, CancelReason::WrongAwait);
+                    }
+                    CoroStatus::Signal(signal_id) => self.wait_on_signal(
+                        world,
+                        (coro_id, node),
+                        WaitState::Signal {
+                            signal_id,
+                            predicate: None,
+                        },
+                        &signals,
+                        &mut parents,
+                        &mut ready_coro,
+                    ),
+                    CoroStatus::SignalWhen(signal_id, predicate) => self.wait_on_signal(
+                        world,
+                        (coro_id, node),
+                        WaitState::Signal {
+                            signal_id,
+                            predicate: Some(predicate),
+                        },
+                        &signals,
+                        &mut parents,
+                        &mut ready_coro,
+                    ),
+                    CoroStatus::SignalCount(signal_id, counter) => {
+                        self.count_signal(coro_id, signal_id, counter);
+                        self.wait_on_signal(
+                            world,
+                            (coro_id, node),
+                            WaitState::Signal {
+                                signal_id,
+                                predicate: None,
+                            },
+                            &signals,
+                            &mut parents,
+                            &mut ready_coro,
+                        )
+                    }
+                    status @ (CoroStatus::Signals { .. } | CoroStatus::ChangedByOther { .. }) => {
+                        self.wait_on_signal(
+                            world,
+                            (coro_id, node),
+                            WaitState::from_status(status),
+                            &signals,
+                            &mut parents,
+                            &mut ready_coro,
+                        )
+                    }
+                    status => self.wait_on(coro_id, WaitState::from_status(status)),
+                };
+            }
+
+            self.process_channels(world, &mut ready_coro, &mut parents, &mut signals);
+        }
+
+        self.ids.flush();
+        // Their commands are applied along the ones of the coroutines
+        for (id, cleanups) in std::mem::take(&mut self.pending_cleanups) {
+            let mut scope = CleanupScope::new(id, world, &self.commands_channel);
+            for f in SyncCell::to_inner(cleanups) {
+                f(&mut scope);
+            }
+        }
+        match self.command_flush {
+            CommandFlushPoint::InsideTick => {
+                self.commands_channel.apply(world);
+                DeferredCommands::apply(world);
+            }
+            CommandFlushPoint::NextApplyDeferred => self.defer_commands(world),
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(mut pending) = world.get_resource_mut::<metrics::PendingMetrics>() {
+            for (name, delta) in pending.0.drain(..) {
+                self.metrics.add(name, delta);
+            }
+        }
+
+        // Done after the commands, which may still refer to local entities. They may already be
+        // gone with their owner, when a whole hierarchy is despawned.
+        for entity in self.to_despawn.drain(..) {
+            if let Some(entity) = world.get_entity_mut(entity) {
+                entity.despawn();
+            }
+        }
+
+        let records = &self.records;
+        self.storage
+            .resources
+            .retain(|id| records.contains_key(&id));
+
+        let exceeded = std::mem::take(&mut self.quota_exceeded);
+        if let Some(mut events) = world.get_resource_mut::<Events<CoroutineCancelled>>() {
+            for id in exceeded {
+                events.send(CoroutineCancelled {
+                    id,
+                    reason: CancelReason::QuotaExceeded,
+                });
+            }
+        }
+
+        self.update_owner_depths(world);
+        self.sync_markers(world);
+        #[cfg(feature = "debug-ui")]
+        self.sync_debug_info(world);
+
+        self.summary.duration = start.elapsed();
+        self.last_summary = self.summary;
+        #[cfg(feature = "metrics")]
+        self.metrics.add_summary(&self.summary);
+        if let Some(hook) = &self.hooks.on_tick_end {
+            hook(world, self.summary);
+        }
+    }
+
+    /// Move the commands queued during this tick to [`PendingCommands`], followed by the
+    /// despawning of the local entities which may still be referred to by them.
+    fn defer_commands(&mut self, world: &mut World) {
+        let mut queues: Vec<CommandQueue> = self.commands_channel.take().collect();
+        let mut despawn = CommandQueue::default();
+        for entity in self.to_despawn.drain(..) {
+            despawn.push(move |world: &mut World| {
+                if let Some(entity) = world.get_entity_mut(entity) {
+                    entity.despawn();
+                }
+            });
         }
+        queues.push(despawn);
+        world
+            .get_resource_or_insert_with(PendingCommands::default)
+            .0
+            .append(&mut queues);
+    }
 
-        if let Some(parent) = self.is_awaited_by.remove(&coro_id) {
-            self.cancel(parent);
+    /// Coroutines are not `Sync`, the executor must therefore always be ticked from the same
+    /// thread, the one owning the world the coroutines are running in.
+    fn check_tick_thread(&mut self) {
+        if cfg!(debug_assertions) {
+            let current = std::thread::current().id();
+            let owner = *self.tick_thread.get_or_insert(current);
+            debug_assert_eq!(
+                owner, current,
+                "The executor was ticked from a different thread than the one owning its world"
+            );
         }
+    }
 
-        if let Some(others) = self.waiting_on_first.remove(&coro_id) {
-            for o in others {
-                self.cancel(Id::from_bits(o));
-            }
-        }
+    /// Evaluate the watches, and resume the coroutines waiting on the ones which changed.
+    fn check_watches(&mut self, world: &World, root_coros: &mut VecDeque<Id>) {
+        let changed: Vec<SignalId> = self
+            .watches
+            .iter_mut()
+            .filter_map(|(signal_id, watch)| watch(world).then_some(*signal_id))
+            .collect();
 
-        if let Some(others) = self.waiting_on_all.remove(&coro_id) {
-            for o in others {
-                self.cancel(Id::from_bits(o));
+        for signal_id in changed {
+            let waiting = self.signal_waiters.by_signal.get(&signal_id).cloned();
+            for bits in waiting.into_iter().flatten() {
+                let coro_id = Id::from_bits(bits);
+                if self.receive_signal(world, coro_id, signal_id) {
+                    self.wake_on_signal(coro_id);
+                    root_coros.push_back(coro_id);
+                }
             }
         }
     }
 
-    pub fn tick_until_empty(&mut self, world: &mut World) {
-        while !self.coroutines.is_empty() {
-            self.tick(world);
-        }
+    /// Resume the coroutines woken since the last tick, see [`Wakeups`].
+    fn check_wakeups(&mut self, world: &World, root_coros: &mut VecDeque<Id>) {
+        root_coros.extend(self.take_wakeups(world));
     }
 
-    pub fn tick(&mut self, world: &mut World) {
-        let mut root_coros = VecDeque::<Id>::new();
+    /// Stop the coroutines woken since the last call from waiting, and returns them. Unlike
+    /// other signals, their emission is not kept in the signal table, since only the woken
+    /// coroutine waits on it and it may wait on it again during the same tick.
+    fn take_wakeups(&mut self, world: &World) -> Vec<Id> {
+        let mut woken = Vec::new();
+        for coro_id in self.storage.wakeups.take() {
+            let signal_id = SignalId::wake(coro_id);
+            // Only this coroutine waits on its signal, if it still does
+            if self.signal_waiters.by_signal.contains_key(&signal_id)
+                && self.receive_signal(world, coro_id, signal_id)
+            {
+                self.wake_on_signal(coro_id);
+                woken.push(coro_id);
+            }
+        }
+        woken
+    }
 
-        root_coros.append(&mut self.waiting_on_tick);
+    /// Resume the coroutines waiting for some entities to change, with the entities which
+    /// changed since they started waiting. Each detection query runs at most once per tick, no
+    /// matter how many coroutines are waiting on it.
+    fn detect_changes(&mut self, world: &mut World, root_coros: &mut VecDeque<Id>) {
+        if self.waiting_on_any_change.is_empty() {
+            return;
+        }
 
-        let delta_time = world.resource::<Time>().delta();
+        let this_run = world.change_tick();
 
-        // Tick all coroutines waiting on duration
-        self.waiting_on_time.retain(|coro, timer| {
-            timer.tick(delta_time);
-            if timer.just_finished() {
-                root_coros.push_back(*coro);
-                false
-            } else {
-                true
+        // A single run from the oldest cursor covers all the waiters of a query, unless the
+        // query cannot tell when the entities changed, then it runs once per cursor
+        let cursor = |detector: &dyn ChangeDetector, wait: &AnyChangeWait| {
+            (
+                wait.key,
+                (!detector.reports_ticks()).then_some(wait.since.get()),
+            )
+        };
+        let mut oldest: HashMap<DetectorRun, Tick> = HashMap::new();
+        let waits = self.waiting_on_any_change.iter().filter_map(|bits| {
+            match &self.records[&Id::from_bits(bits)].wait {
+                WaitState::AnyChange(wait) => Some(wait),
+                _ => None,
             }
         });
+        for wait in waits {
+            let detector = self
+                .change_detectors
+                .entry(wait.key)
+                .or_insert_with(|| (wait.new_detector)(world));
+            oldest
+                .entry(cursor(detector.as_ref(), wait))
+                .and_modify(|since| {
+                    if since.is_newer_than(wait.since, this_run) {
+                        *since = wait.since;
+                    }
+                })
+                .or_insert(wait.since);
+        }
 
-        let mut parents = ParentTable::new();
-        let mut signals = HashMap::new();
-
-        let mut ready_coro: Vec<(Id, usize)> = root_coros
+        let changes: HashMap<DetectorRun, Vec<(Entity, Tick)>> = oldest
             .into_iter()
-            .map(|c_id| (c_id, parents.add_root(c_id)))
+            .map(|(key, since)| {
+                let detector = self.change_detectors.get_mut(&key.0).unwrap();
+                (key, detector.detect(world, since, this_run))
+            })
             .collect();
 
-        while !ready_coro.is_empty() {
-            while let Some((coro_id, node)) = ready_coro.pop() {
-                if !self.ids.contains(coro_id) {
-                    continue;
-                }
-
-                let coro = self.coroutines.get_mut(&coro_id).unwrap().get();
+        let mut woken = Vec::new();
+        for bits in self.waiting_on_any_change.iter() {
+            let coro_id = Id::from_bits(bits);
+            let WaitState::AnyChange(wait) = &self.records[&coro_id].wait else {
+                continue;
+            };
+            let detector = self.change_detectors[&wait.key].as_ref();
+            let exact = detector.reports_ticks();
+            let changed: ChangedEntities = changes[&cursor(detector, wait)]
+                .iter()
+                .filter(|(_, tick)| !exact || tick.is_newer_than(wait.since, this_run))
+                .map(|(entity, _)| *entity)
+                .collect();
 
-                if !coro.is_valid(world) {
-                    self.cancel(coro_id);
-                    continue;
-                }
+            if !changed.is_empty() {
+                woken.push((coro_id, changed));
+            }
+        }
 
-                let status = Coroutine::resume(
-                    coro.as_mut(),
-                    world,
-                    &self.ids,
-                    node,
-                    &self.signal_channel,
-                    &self.new_coro_channel,
-                    &self.commands_channel,
-                );
+        for (coro_id, changed) in woken {
+            let WaitState::AnyChange(wait) = self.stop_waiting(coro_id) else {
+                unreachable!();
+            };
+            SyncCell::to_inner(wait.sender).send(changed);
+            root_coros.push_back(coro_id);
+        }
+    }
 
-                // TODO remove copy paste
-                // Note to self: When running on a single thread, it's faster to process each
-                // status immediatly, rather than accumulating them and processing them afterward.
-                // (however for the other queue, it seems to be faster to process them at the end
-                // (based on way too simple stress test))
-                match status {
-                    CoroStatus::Done => {
-                        self.mark_as_done(coro_id, node, &mut ready_coro, &mut parents)
-                    }
-                    CoroStatus::Tick => self.waiting_on_tick.push_back(coro_id),
-                    CoroStatus::Duration(d) => {
-                        self.waiting_on_time.insert(coro_id, d);
-                    }
-                    CoroStatus::First(handlers) => {
-                        self.waiting_on_first.insert(coro_id, handlers.clone());
+    /// Resume the coroutines waiting on a component which changed since they started waiting,
+    /// or whose entity was despawned. The changes made by other coroutines during the tick are
+    /// received as signals instead, see [`ParentTable::saw_emission`].
+    fn detect_changes_by_systems(&mut self, world: &World, root_coros: &mut VecDeque<Id>) {
+        let this_run = world.read_change_tick();
+        let changed: Vec<Id> = self
+            .waiting_on_other_change
+            .iter()
+            .map(Id::from_bits)
+            .filter(|coro_id| {
+                let WaitState::ChangedByOther { signal_id, since } = &self.records[coro_id].wait
+                else {
+                    return false;
+                };
+                let (SignalType::Component(component_id), Some(entity)) =
+                    (signal_id.signal_type, signal_id.owner)
+                else {
+                    return false;
+                };
+                let Some(entity) = world.get_entity(entity) else {
+                    return true;
+                };
+                entity
+                    .get_change_ticks_by_id(component_id)
+                    .is_none_or(|ticks| ticks.last_changed_tick().is_newer_than(*since, this_run))
+            })
+            .collect();
 
-                        for handler in handlers.iter() {
-                            self.is_awaited_by.insert(Id::from_bits(handler), coro_id);
-                        }
-                    }
-                    CoroStatus::All(handlers) => {
-                        let waits_on = handlers.clone();
+        for coro_id in changed {
+            self.stop_waiting(coro_id);
+            root_coros.push_back(coro_id);
+        }
+    }
 
-                        for handler in handlers.iter() {
-                            self.is_awaited_by.insert(Id::from_bits(handler), coro_id);
-                        }
+    /// Cancel the coroutines which did not finish before the deadline of the coroutine awaiting
+    /// them, and resume it. Returns true if any coroutine was resumed.
+    fn resume_expired(
+        &mut self,
+        expired: &mut Vec<Id>,
+        ready_coro: &mut Vec<(Id, usize)>,
+        parents: &mut ParentTable,
+    ) -> bool {
+        for coro_id in expired.drain(..) {
+            // Either it was resumed since, or it already waits on something else
+            let Some(record) = self.records.get_mut(&coro_id) else {
+                continue;
+            };
+            let WaitState::All {
+                deadline: Some(deadline),
+                ..
+            } = &record.wait
+            else {
+                continue;
+            };
+            if !deadline.finished() {
+                continue;
+            }
 
-                        self.waiting_on_all.insert(coro_id, waits_on);
-                    }
-                    CoroStatus::Cancel => {
-                        self.cancel(coro_id);
-                    }
-                    CoroStatus::Signal(signal_id) => {
-                        if let Some(writer) = signals.get(&signal_id) {
-                            if !parents.is_parent(*writer, node) {
-                                let node = parents.add_child(*writer, coro_id);
-                                ready_coro.push((coro_id, node));
-                                continue;
-                            }
-                        }
-
-                        self.waiting_on_signal
-                            .entry(signal_id)
-                            .or_default()
-                            .insert(coro_id.to_bits());
-                    }
-                };
+            let stragglers = self.stop_waiting(coro_id).into_handles().unwrap();
+            for s in stragglers {
+                let id = Id::from_bits(s);
+                self.clear_awaited_by(id);
+                self.cancel(id, CancelReason::ParentCancelled);
             }
 
-            self.process_channels(&mut ready_coro, &mut parents, &mut signals);
+            ready_coro.push((coro_id, parents.add_root(coro_id)));
         }
 
-        self.ids.flush();
-        self.commands_channel.apply(world);
+        !ready_coro.is_empty()
+    }
+
+    fn receive_local_entities(&mut self) {
+        let received: Vec<LocalEntityMsg> = self.local_entity_channel.receive().collect();
+        for LocalEntityMsg { owner, entity } in received {
+            match self.records.get_mut(&owner) {
+                Some(record) => record.local_entities.push(entity),
+                // Its coroutine already ended
+                None => self.to_despawn.push(entity),
+            }
+        }
     }
 
     /// Mark a coroutine as done, and properly handles cleanup.
@@ -197,172 +1673,578 @@ impl Executor {
         ready_coro: &mut Vec<(Id, usize)>,
         parents: &mut ParentTable,
     ) {
-        self.coroutines.remove(&coro_id);
+        let Some(record) = self.remove_coroutine(coro_id) else {
+            return;
+        };
+        self.summary.completed += 1;
+        self.run_cleanup_hooks(coro_id, CleanupReason::Completed);
 
-        if let Some(owned) = self.scope_ownership.remove(&coro_id) {
-            for c in owned {
-                self.cancel(Id::from_bits(c))
-            }
+        for c in record.owned {
+            self.cancel(Id::from_bits(c), CancelReason::ParentCancelled)
         }
 
-        if let Some(parent) = self.is_awaited_by.remove(&coro_id) {
-            if let Some(mut others) = self.waiting_on_first.remove(&parent) {
-                others.remove(coro_id.to_bits());
-                // coro is the "winner", all the others are cancelled
-                for o in others {
-                    let id = Id::from_bits(o);
-                    self.is_awaited_by.remove(&id);
-                    self.cancel(id);
-                }
-
-                let node = parents.add_child(coro_node, parent);
-                ready_coro.push((parent, node));
-            }
-
-            if let Some(others) = self.waiting_on_all.get_mut(&parent) {
-                others.remove(coro_id.to_bits());
+        let Some(parent) = record.awaited_by else {
+            return;
+        };
+        let Some(parent_record) = self.records.get_mut(&parent) else {
+            return;
+        };
 
+        let resumed = match &mut parent_record.wait {
+            // coro is the "winner", all the others are cancelled
+            WaitState::First(_) | WaitState::FirstOrCancel(_) => true,
+            WaitState::All { waits_on, .. } => {
+                waits_on.remove(coro_id.to_bits());
                 let node = parents.add_child(coro_node, parent);
-
-                if others.is_empty() {
+                if waits_on.is_empty() {
+                    self.stop_waiting(parent);
                     ready_coro.push((parent, node));
-                    self.waiting_on_all.remove(&parent);
                 }
+                return;
+            }
+            WaitState::Quorum { need, waits_on } => {
+                waits_on.remove(coro_id.to_bits());
+                *need -= 1;
+                // Once the quorum is reached, the stragglers are cancelled
+                *need == 0
+            }
+            _ => false,
+        };
+
+        if resumed {
+            let mut others = self.stop_waiting(parent).into_handles().unwrap();
+            others.remove(coro_id.to_bits());
+            for o in others {
+                let id = Id::from_bits(o);
+                self.clear_awaited_by(id);
+                self.cancel(id, CancelReason::ParentCancelled);
             }
+
+            let node = parents.add_child(coro_node, parent);
+            ready_coro.push((parent, node));
         }
     }
 
+    /// Add a coroutine, optionally bound to `owner`. Returns its id, or [`None`] if its
+    /// parameters are invalid, because they conflict or `owner` lacks a component for instance.
     pub fn add_function_coroutine<Marker: 'static, T, C>(
         &mut self,
         owner: Option<Entity>,
         world: &World,
         coroutine: C,
+    ) -> Option<Id>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.add_function_coroutine_with(owner, world, None, coroutine)
+            .ok()
+    }
+
+    /// Add a coroutine bound to `owner`, whose result is inserted on `owner` once it finishes.
+    /// Nothing is added if its parameters are invalid.
+    pub fn add_function_coroutine_into<Marker: 'static, T, C>(
+        &mut self,
+        owner: Entity,
+        world: &World,
+        coroutine: C,
     ) where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Component,
+    {
+        self.add_coroutine_into(owner, world, coroutine).ok();
+    }
+
+    /// Same as [`Executor::add_function_coroutine_into`], but returns why the coroutine was not
+    /// added.
+    pub(crate) fn add_coroutine_into<Marker: 'static, T, C>(
+        &mut self,
+        owner: Entity,
+        world: &World,
+        coroutine: C,
+    ) -> Result<Id, InvalidParams>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Component,
+    {
+        let result_sender = ResultSender::Insert(owner, insert_result::<T>);
+        self.add_function_coroutine_with(Some(owner), world, Some(result_sender), coroutine)
+    }
+
+    /// Add a coroutine, and returns a [`CoroHandle`] to it, as [`Scope::start`] does from within
+    /// a coroutine. When the handle is dropped, the coroutine is cancelled. Its result can be
+    /// taken with [`CoroHandle::try_take`], or with [`Executor::block_on`].
+    ///
+    /// # Panics
+    /// If the parameters of the `coroutine` are invalid, like [`Scope::start`].
+    pub fn start<Marker: 'static, T, C>(&mut self, world: &World, coroutine: C) -> CoroHandle<T>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.start_handle(None, world, coroutine)
+    }
+
+    /// Same as [`Executor::start`], but the coroutine is bound to `owner`.
+    pub fn start_on<Marker: 'static, T, C>(
+        &mut self,
+        owner: Entity,
+        world: &World,
+        coroutine: C,
+    ) -> CoroHandle<T>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.start_handle(Some(owner), world, coroutine)
+    }
+
+    fn start_handle<Marker: 'static, T, C>(
+        &mut self,
+        owner: Option<Entity>,
+        world: &World,
+        coroutine: C,
+    ) -> CoroHandle<T>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        let (result_sender, receiver) = sync_once_channel();
+        let result_sender = Some(ResultSender::Handle(result_sender));
+        let id = self
+            .add_function_coroutine_with(owner, world, result_sender, coroutine)
+            .unwrap_or_else(|invalid| invalid.panic::<C>());
+        CoroHandle::Waiting { id, receiver }
+    }
+
+    /// Tick the executor until the coroutine of `handle` finishes, at most `max_ticks` times.
+    /// Returns its result, or [`None`] if it did not finish in time or was cancelled, in which
+    /// case the handle can still be awaited later on.
+    pub fn block_on<T>(
+        &mut self,
+        handle: &mut CoroHandle<T>,
+        world: &mut World,
+        max_ticks: usize,
+    ) -> Option<T> {
+        for _ in 0..max_ticks {
+            if let Some(result) = handle.try_take() {
+                return Some(result);
+            }
+            handle.id()?;
+            self.tick(world);
+        }
+        handle.try_take()
+    }
+
+    pub(crate) fn add_function_coroutine_with<Marker: 'static, T, C>(
+        &mut self,
+        owner: Option<Entity>,
+        world: &World,
+        result_sender: Option<ResultSender<T>>,
+        coroutine: C,
+    ) -> Result<Id, InvalidParams>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.add_coroutine_with_mailbox(owner, world, result_sender, None, coroutine)
+    }
+
+    /// Add a behavior, as [`Scope::start_behavior`] does from within a coroutine, and returns
+    /// the handle to it, or why it was not added.
+    pub(crate) fn add_behavior<Marker: 'static, M, C>(
+        &mut self,
+        owner: Option<Entity>,
+        world: &World,
+        coroutine: C,
+    ) -> Result<BehaviorHandle<M>, InvalidParams>
+    where
+        C: CoroutineParamFunction<Marker, ()>,
+        M: Send + 'static,
+    {
+        let (sender, receiver) = sync_once_channel();
+        let queue = Arc::new(MessageQueue::<M>::default());
+        let id = self.add_coroutine_with_mailbox(
+            owner,
+            world,
+            Some(ResultSender::Handle(sender)),
+            Some(queue.clone()),
+            coroutine,
+        )?;
+        Ok(BehaviorHandle::new(id, queue, receiver))
+    }
+
+    fn add_coroutine_with_mailbox<Marker: 'static, T, C>(
+        &mut self,
+        owner: Option<Entity>,
+        world: &World,
+        result_sender: Option<ResultSender<T>>,
+        mailbox: Option<Arc<dyn Any + Send + Sync>>,
+        coroutine: C,
+    ) -> Result<Id, InvalidParams>
+    where
         C: CoroutineParamFunction<Marker, T>,
         T: Sync + Send + 'static,
     {
+        self.check_world(world);
         let resume_param = Resume::new(ResumeParam::new());
 
         let id = self.ids.allocate_id();
 
-        let new_scope = Scope::new(id, owner, resume_param.clone());
+        let new_scope = Scope::new(
+            id,
+            owner,
+            resume_param.clone(),
+            mailbox,
+            #[cfg(feature = "rand")]
+            crate::function_coroutine::rng::LazyRng::root(
+                world.as_unsafe_world_cell_readonly(),
+                id,
+            ),
+        );
 
-        if let Some(c) = FunctionCoroutine::new(
+        let c = FunctionCoroutine::new(
             new_scope,
             world.as_unsafe_world_cell_readonly(),
+            self.commands_channel.commands(world.entities()),
             resume_param,
             id,
-            None,
+            result_sender,
             coroutine,
-        ) {
-            self.add_coroutine(id, SyncCell::new(Box::pin(c)));
+        )?;
+        self.add_coroutine(id, SyncCell::new(Box::pin(c)));
+        Ok(id)
+    }
+
+    /// Make the coroutine wait on one or several signals. If they were already emitted by a
+    /// coroutine which could not be observed so far, or if they are latched, they are received
+    /// right away, and it may be resumed right away as well.
+    fn wait_on_signal(
+        &mut self,
+        world: &World,
+        (coro_id, node): (Id, usize),
+        wait: WaitState,
+        signal_table: &HashMap<SignalId, usize>,
+        parents: &mut ParentTable,
+        ready_coro: &mut Vec<(Id, usize)>,
+    ) {
+        let signals = wait.signals().to_vec();
+        let from_others = matches!(wait, WaitState::ChangedByOther { .. });
+        self.wait_on(coro_id, wait);
+
+        for signal_id in &signals {
+            let Some(latch) = self.latches.get(signal_id).copied() else {
+                continue;
+            };
+            if self.receive_signal(world, coro_id, *signal_id) {
+                // Only consumed once it resumes a coroutine
+                if latch == Latch::Oneshot {
+                    self.latches.remove(signal_id);
+                }
+                self.wake_on_signal(coro_id);
+                if let Some((_, counter)) = &self.records[&coro_id].counter {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+                ready_coro.push((coro_id, node));
+                return;
+            }
+        }
+
+        for signal_id in signals {
+            let Some(writer) = signal_table.get(&signal_id) else {
+                continue;
+            };
+            let seen = if from_others {
+                parents.saw_emission(*writer, coro_id)
+            } else {
+                parents.is_parent(*writer, node)
+            };
+            if !seen && self.receive_signal(world, coro_id, signal_id) {
+                self.wake_on_signal(coro_id);
+                // The emission it reacts to was not counted yet
+                if let Some((_, counter)) = &self.records[&coro_id].counter {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+                let node = parents.add_child(*writer, coro_id);
+                ready_coro.push((coro_id, node));
+                return;
+            }
+        }
+    }
+
+    /// Attach the `limits` of the coroutine `id`, shared with what remains of the ones of the
+    /// coroutine which started it, if any. Returns true if the latter just crossed its limit of
+    /// children.
+    fn start_with_limits(&mut self, id: Id, started_by: Id, limits: Option<Limits>) -> bool {
+        let (limits, exceeded) = match self.records.get_mut(&started_by) {
+            Some(CoroRecord {
+                quota: Some(quota),
+                local_entities,
+                ..
+            }) => {
+                quota.children += 1;
+                let inherited = quota.remaining(local_entities.len());
+                let limits = limits.map_or(inherited, |limits| limits.min(inherited));
+                (Some(limits), quota.exceeded(local_entities.len()))
+            }
+            _ => (limits, false),
         };
+
+        if let Some(limits) = limits {
+            self.records.get_mut(&id).unwrap().quota = Some(Quota::new(limits));
+            self.limited.insert(id.to_bits());
+        }
+        exceeded
+    }
+
+    /// Count the `commands` queued by the coroutine while it was resumed, and returns true if it
+    /// crossed one of its limits.
+    fn exceeds_quota(&mut self, coro_id: Id, commands: usize) -> bool {
+        let Some(record) = self.records.get_mut(&coro_id) else {
+            return false;
+        };
+        let local_entities = record.local_entities.len();
+        let Some(quota) = &mut record.quota else {
+            return false;
+        };
+        quota.commands += commands;
+        quota.exceeded(local_entities)
+    }
+
+    /// Count the emissions of `signal_id` for the coroutine, until it is resumed.
+    fn count_signal(&mut self, coro_id: Id, signal_id: SignalId, counter: Arc<AtomicUsize>) {
+        if let Some(record) = self.records.get_mut(&coro_id) {
+            record.counter = Some((signal_id, counter));
+            self.signal_counters.insert(coro_id.to_bits());
+        }
+    }
+
+    /// Notify the coroutine that `signal_id` was emitted. Returns true if it should be resumed:
+    /// if it has no predicate or if its predicate holds, and once all the signals it waits on
+    /// were received, if it waits on all of them.
+    fn receive_signal(&mut self, world: &World, coro_id: Id, signal_id: SignalId) -> bool {
+        match self.records.get_mut(&coro_id).map(|r| &mut r.wait) {
+            Some(WaitState::Signal {
+                predicate: Some(predicate),
+                ..
+            }) => (predicate.get())(world),
+            Some(WaitState::Signals {
+                signals,
+                all,
+                received,
+                order,
+                ..
+            }) => {
+                let mut is_new = false;
+                for (i, _) in signals.iter().enumerate().filter(|(_, s)| **s == signal_id) {
+                    is_new |= *received & (1 << i) == 0;
+                    *received |= 1 << i;
+                }
+                if is_new {
+                    order.push(signal_id);
+                }
+                !*all || *received == u64::MAX >> (64 - signals.len())
+            }
+            _ => true,
+        }
+    }
+
+    /// Stop waiting on signals, sending back the ones which were received if needed.
+    fn wake_on_signal(&mut self, coro_id: Id) {
+        if let WaitState::Signals { order, sender, .. } = self.stop_waiting(coro_id) {
+            SyncCell::to_inner(sender).send(order);
+        }
     }
 
     fn process_channels(
         &mut self,
+        world: &World,
         ready_coro: &mut Vec<(Id, usize)>,
         parents: &mut ParentTable,
         signal_table: &mut HashMap<SignalId, usize>,
     ) {
+        self.receive_local_entities();
+
+        let new_coros: Vec<NewCoroutine> = self.new_coro_channel.receive().collect();
         for NewCoroutine {
             id,
             ran_after,
             coroutine,
             is_owned_by,
+            started_by,
+            limits,
             should_start_now,
-        } in self.new_coro_channel.receive()
+        } in new_coros
         {
-            self.coroutines.insert(id, coroutine);
+            // Its scope ended during this tick, before it could be registered
+            let orphan = is_owned_by.is_some_and(|parent| !self.records.contains_key(&parent));
+            self.insert_coroutine(id, coroutine, is_owned_by, Some(started_by));
+            if orphan {
+                self.cancel(id, CancelReason::ParentCancelled);
+                continue;
+            }
 
-            if let Some(parent) = is_owned_by {
-                self.scope_ownership
-                    .entry(parent)
-                    .or_default()
-                    .insert(id.to_bits());
+            if self.start_with_limits(id, started_by, limits) {
+                self.cancel(id, CancelReason::ParentCancelled);
+                self.cancel(started_by, CancelReason::QuotaExceeded);
+                continue;
             }
 
             if should_start_now {
                 let next_node = parents.add_child(ran_after, id);
                 ready_coro.push((id, next_node));
+            } else {
+                // Started on the next tick, once the commands of this one are applied
+                self.wait_on(id, WaitState::Tick);
             }
         }
 
         let mut just_done: Vec<(Id, usize)> = Vec::new();
-        let mut just_canceled: Vec<Id> = Vec::new();
+        let mut just_canceled: Vec<(Id, CancelReason)> = Vec::new();
+        let mut just_waiting: Vec<(Id, usize, WaitState)> = Vec::new();
+
+        let yields: Vec<YieldMsg> = self.yield_channel.receive().collect();
+        for YieldMsg { id, node, status } in yields {
+            #[cfg(any(test, feature = "testing"))]
+            self.last_yields.push((id, CoroStatusKind::from(&status)));
 
-        for YieldMsg { id, node, status } in self.yield_channel.receive() {
             match status {
                 CoroStatus::Done => {
                     just_done.push((id, node));
                 }
-                CoroStatus::Tick => self.waiting_on_tick.push_back(id),
-                CoroStatus::Duration(d) => {
-                    self.waiting_on_time.insert(id, d);
+                CoroStatus::Cooperative => ready_coro.insert(0, (id, node)),
+                CoroStatus::Exclusive => ready_coro.push((id, node)),
+                CoroStatus::Cancel => {
+                    just_canceled.push((id, CancelReason::ExplicitCancel));
                 }
-                CoroStatus::First(handlers) => {
-                    self.waiting_on_first.insert(id, handlers.clone());
-
-                    for handler in handlers.iter() {
-                        self.is_awaited_by.insert(Id::from_bits(handler), id);
-                    }
+                CoroStatus::WrongAwait => {
+                    just_canceled.push((id, CancelReason::WrongAwait));
                 }
-                CoroStatus::All(handlers) => {
-                    let waits_on = handlers.clone();
-
-                    for handler in handlers.iter() {
-                        self.is_awaited_by.insert(Id::from_bits(handler), id);
-                    }
-
-                    self.waiting_on_all.insert(id, waits_on);
+                CoroStatus::Signal(signal_id) => {
+                    let wait = WaitState::Signal {
+                        signal_id,
+                        predicate: None,
+                    };
+                    just_waiting.push((id, node, wait));
                 }
-                CoroStatus::Cancel => {
-                    just_canceled.push(id);
+                CoroStatus::SignalWhen(signal_id, predicate) => {
+                    let wait = WaitState::Signal {
+                        signal_id,
+                        predicate: Some(predicate),
+                    };
+                    just_waiting.push((id, node, wait));
                 }
-                CoroStatus::Signal(signal_id) => {
-                    if let Some(writer) = signal_table.get(&signal_id) {
-                        if !parents.is_parent(*writer, node) {
-                            let node = parents.add_child(*writer, id);
-                            ready_coro.push((id, node));
-                            continue;
-                        }
-                    }
-
-                    self.waiting_on_signal
-                        .entry(signal_id)
-                        .or_default()
-                        .insert(id.to_bits());
+                CoroStatus::SignalCount(signal_id, counter) => {
+                    self.count_signal(id, signal_id, counter);
+                    let wait = WaitState::Signal {
+                        signal_id,
+                        predicate: None,
+                    };
+                    just_waiting.push((id, node, wait));
                 }
+                status @ (CoroStatus::Signals { .. } | CoroStatus::ChangedByOther { .. }) => {
+                    just_waiting.push((id, node, WaitState::from_status(status)));
+                }
+                status => self.wait_on(id, WaitState::from_status(status)),
             };
         }
 
+        for (id, node, wait) in just_waiting {
+            self.wait_on_signal(world, (id, node), wait, signal_table, parents, ready_coro);
+        }
+
         for (id, node) in just_done {
             self.mark_as_done(id, node, ready_coro, parents);
         }
 
-        for id in just_canceled {
-            self.cancel(id);
+        for (id, reason) in just_canceled {
+            self.cancel(id, reason);
+        }
+
+        // Woken by the coroutines which just ran, after the yields above so that none is missed
+        for coro_id in self.take_wakeups(world) {
+            ready_coro.push((coro_id, parents.add_root(coro_id)));
         }
 
-        for EmitMsg { id, by } in self.signal_channel.receive() {
+        let emitted: Vec<EmitMsg> = self.signal_channel.receive().collect();
+        for EmitMsg { id, by, latch } in emitted {
             signal_table.insert(id, by);
-            if let Some(children) = self.waiting_on_signal.remove(&id) {
-                for c in children {
-                    let id = Id::from_bits(c);
-                    let node = parents.add_child(by, id);
-                    ready_coro.push((id, node));
+
+            for bits in self.signal_counters.iter() {
+                let (signal_id, counter) =
+                    self.records[&Id::from_bits(bits)].counter.as_ref().unwrap();
+                if *signal_id == id {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+
+            for bits in self.signal_waiters.any.clone() {
+                let coro_id = Id::from_bits(bits);
+                let WaitState::AnySignal(sender) = self.stop_waiting(coro_id) else {
+                    unreachable!();
+                };
+                SyncCell::to_inner(sender).send(id);
+                let node = parents.add_child(by, coro_id);
+                ready_coro.push((coro_id, node));
+            }
+
+            let mut received = false;
+            let waiting = self.signal_waiters.by_signal.get(&id).cloned();
+            for c in waiting.into_iter().flatten() {
+                let coro_id = Id::from_bits(c);
+                let from_others = matches!(
+                    self.records.get(&coro_id).map(|record| &record.wait),
+                    Some(WaitState::ChangedByOther { .. })
+                );
+                if from_others && parents.saw_emission(by, coro_id) {
+                    continue;
                 }
+                if !self.receive_signal(world, coro_id, id) {
+                    continue;
+                }
+                received = true;
+                self.wake_on_signal(coro_id);
+                let node = parents.add_child(by, coro_id);
+                ready_coro.push((coro_id, node));
+            }
+
+            match latch {
+                Some(Latch::Sticky) => {
+                    self.latches.insert(id, Latch::Sticky);
+                }
+                Some(Latch::Oneshot) if !received => {
+                    self.latches.insert(id, Latch::Oneshot);
+                }
+                _ => {}
+            }
+        }
+
+        // The coroutines which already ended are skipped
+        let cancelled: Vec<CancelMsg> = self.cancel_channel.receive().collect();
+        for CancelMsg { id } in cancelled {
+            if self.records.contains_key(&id) {
+                self.cancel(id, CancelReason::External);
             }
         }
     }
 }
 
+/// Why a coroutine which is no longer valid gets cancelled.
+fn invalidation_reason(meta: &CoroMeta, world: &World) -> CancelReason {
+    match meta.owner {
+        Some(owner) if world.get_entity(owner).is_none() => CancelReason::OwnerDespawned,
+        _ => CancelReason::InvalidParams,
+    }
+}
+
 /// Keep track of who ran after who, to make sure coroutines can react to even they could have
 /// seen, and do not react to events they could not see.
 #[derive(Default)]
 struct ParentTable {
     table: Vec<SetUsize>,
     node_map: HashMap<Id, usize>,
+    /// The coroutine of each node
+    coroutines: Vec<Id>,
 }
 
 impl ParentTable {
@@ -379,18 +2261,23 @@ impl ParentTable {
             parents = self.table.get(*node).unwrap().clone();
         }
 
-        parents.extend(self.table.index(parent).clone());
-        parents.insert(parent);
+        // The executor itself is no coroutine, nothing to inherit from it
+        if parent != EXECUTOR_NODE {
+            parents.extend(self.table.index(parent).clone());
+            parents.insert(parent);
+        }
         let node = self.table.len();
 
         self.node_map.insert(child, node);
         self.table.push(parents);
+        self.coroutines.push(child);
 
         node
     }
 
     fn add_root(&mut self, c: Id) -> usize {
         self.table.push(SetUsize::new());
+        self.coroutines.push(c);
         let node = self.table.len() - 1;
         self.node_map.insert(c, node);
         node
@@ -403,4 +2290,17 @@ impl ParentTable {
     fn is_parent(&self, parent: usize, child: usize) -> bool {
         self.table.get(child).unwrap().contains(parent)
     }
+
+    /// Return true if the emission made at the node `by` was made by the coroutine `c` itself,
+    /// or by one which ran before it, in which case `c` already saw it.
+    fn saw_emission(&self, by: usize, c: Id) -> bool {
+        if by == EXECUTOR_NODE {
+            return false;
+        }
+        self.coroutines[by] == c
+            || self
+                .node_map
+                .get(&c)
+                .is_some_and(|node| self.is_parent(by, *node))
+    }
 }
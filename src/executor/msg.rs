@@ -1,8 +1,19 @@
-use bevy::prelude::Entity;
-use bevy::{ecs::component::ComponentId, time::Timer};
+use std::{
+    any::TypeId,
+    sync::{atomic::AtomicUsize, Arc, Mutex},
+};
+
+use bevy::prelude::{Entity, World};
+use bevy::utils::synccell::SyncCell;
+use bevy::{
+    ecs::component::{ComponentId, Tick},
+    time::Timer,
+};
 use tinyset::SetU64;
 
-use crate::{id_alloc::Id, HeapCoro};
+use crate::{function_coroutine::once_channel::OnceSender, id_alloc::Id, HeapCoro};
+
+use super::{change_detection::AnyChangeWait, phase::Phase, quota::Limits};
 
 /// A newly spawned [`Coroutine`] and how it should be handled by the [`Executor`](executor).
 pub struct NewCoroutine {
@@ -10,9 +21,42 @@ pub struct NewCoroutine {
     pub ran_after: usize,
     pub coroutine: HeapCoro,
     pub is_owned_by: Option<Id>,
+    /// The coroutine which started it
+    pub started_by: Id,
+    /// Set if it was started with limits, which it shares with its own children
+    pub limits: Option<Limits>,
     pub should_start_now: bool,
 }
 
+/// The msg notifying that an entity was spawned by a [`Coroutine`], and must be despawned once
+/// the coroutine ends.
+pub struct LocalEntityMsg {
+    pub owner: Id,
+    pub entity: Entity,
+}
+
+/// The coroutines to wake with their [`SignalId::wake`] signal, such as the ones waiting on a
+/// [`CoroMutex`](crate::function_coroutine::mutex::CoroMutex) released by another one. Unlike
+/// the other channels, it can be written to from anywhere, including from outside a tick.
+#[derive(Clone, Default)]
+pub(crate) struct Wakeups(Arc<Mutex<Vec<Id>>>);
+
+impl Wakeups {
+    pub fn wake(&self, id: Id) {
+        self.0.lock().unwrap().push(id);
+    }
+
+    pub fn take(&self) -> Vec<Id> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+/// The msg asking to cancel a [`Coroutine`] by its id, sent with
+/// [`Scope::try_cancel`](crate::function_coroutine::scope::Scope::try_cancel).
+pub struct CancelMsg {
+    pub id: Id,
+}
+
 /// The msg yield by a [`Coroutine`].
 pub struct YieldMsg {
     pub id: Id,
@@ -24,32 +68,245 @@ pub struct YieldMsg {
 pub enum CoroStatus {
     /// Get resumed after one tick
     Tick,
+    /// Get resumed during the tick where the [`TickCount`](super::TickCount) reaches this number
+    ExactTick(u64),
+    /// Get resumed during the same tick, once the other ready coroutines got a chance to run
+    Cooperative,
     /// Get resumed once the duration is reached
     Duration(Timer),
     /// Get resumed once any of the coroutine has terminate
     First(SetU64),
+    /// Same as [`CoroStatus::First`], but the cancelled coroutines are removed from the set,
+    /// it is only cancelled once all of them are
+    FirstOrCancel(SetU64),
     /// Get resumed once all coroutines have terminate
     All(SetU64),
+    /// Get resumed once all coroutines have terminate, or once the timer finishes. In which case
+    /// the remaining coroutines are cancelled
+    AllWithin(SetU64, Timer),
+    /// Get resumed once `need` of the coroutines have terminate. In which case the remaining
+    /// ones are cancelled
+    Quorum { need: usize, set: SetU64 },
     /// Get resumed once the signal is triggered
     Signal(SignalId),
+    /// Get resumed once the signal is triggered and the predicate holds
+    SignalWhen(SignalId, SignalPredicate),
+    /// Get resumed once the signal is triggered, counting each emission until then
+    SignalCount(SignalId, Arc<AtomicUsize>),
+    /// Get resumed once any signal is triggered, which is sent back
+    AnySignal(OnceSender<SignalId>),
+    /// Get resumed once any, or all, of the signals are triggered. The ones which were received
+    /// are sent back, in order
+    Signals {
+        signals: Vec<SignalId>,
+        all: bool,
+        sender: OnceSender<Vec<SignalId>>,
+    },
+    /// Get resumed once any entity matching a query has its component changed
+    AnyChange(AnyChangeWait),
+    /// Get resumed once the component of a [`SignalId::component`] signal changes, either by a
+    /// system since the tick, or by a coroutine which did not run before this one
+    ChangedByOther { signal_id: SignalId, since: Tick },
+    /// Get resumed the next time the executor is ticked for this phase
+    Phase(Phase),
+    /// Get resumed right away, while the executor holds the world exclusively
+    Exclusive,
     /// Has finished execution
     Done,
     /// Never get resumed, and gets cleanup instead
     Cancel,
+    /// Yielded without telling why, by awaiting a future which is not part of this library. Never
+    /// get resumed, and gets cleanup instead
+    WrongAwait,
+}
+
+/// A [`CoroStatus`] without the data which cannot be compared, to inspect what the coroutines
+/// yielded in tests, see [`Executor::debug_last_yields`](super::Executor::debug_last_yields).
+#[cfg(any(test, feature = "testing"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoroStatusKind {
+    Tick,
+    /// The tick number
+    ExactTick(u64),
+    Cooperative,
+    /// The duration of the timer
+    Duration(std::time::Duration),
+    First,
+    All,
+    AllWithin,
+    Quorum,
+    Signal(SignalId),
+    SignalWhen(SignalId),
+    SignalCount(SignalId),
+    AnySignal,
+    /// Whether all the signals are awaited, or any of them
+    Signals {
+        all: bool,
+    },
+    AnyChange,
+    ChangedByOther(SignalId),
+    Phase,
+    Exclusive,
+    Done,
+    Cancel,
+    WrongAwait,
 }
 
+#[cfg(any(test, feature = "testing"))]
+impl From<&CoroStatus> for CoroStatusKind {
+    fn from(status: &CoroStatus) -> Self {
+        match status {
+            CoroStatus::Tick => CoroStatusKind::Tick,
+            CoroStatus::ExactTick(tick) => CoroStatusKind::ExactTick(*tick),
+            CoroStatus::Cooperative => CoroStatusKind::Cooperative,
+            CoroStatus::Duration(timer) => CoroStatusKind::Duration(timer.duration()),
+            CoroStatus::First(_) | CoroStatus::FirstOrCancel(_) => CoroStatusKind::First,
+            CoroStatus::All(_) => CoroStatusKind::All,
+            CoroStatus::AllWithin(_, _) => CoroStatusKind::AllWithin,
+            CoroStatus::Quorum { .. } => CoroStatusKind::Quorum,
+            CoroStatus::Signal(id) => CoroStatusKind::Signal(*id),
+            CoroStatus::SignalWhen(id, _) => CoroStatusKind::SignalWhen(*id),
+            CoroStatus::SignalCount(id, _) => CoroStatusKind::SignalCount(*id),
+            CoroStatus::AnySignal(_) => CoroStatusKind::AnySignal,
+            CoroStatus::Signals { all, .. } => CoroStatusKind::Signals { all: *all },
+            CoroStatus::AnyChange(_) => CoroStatusKind::AnyChange,
+            CoroStatus::ChangedByOther { signal_id, .. } => {
+                CoroStatusKind::ChangedByOther(*signal_id)
+            }
+            CoroStatus::Phase(_) => CoroStatusKind::Phase,
+            CoroStatus::Exclusive => CoroStatusKind::Exclusive,
+            CoroStatus::Done => CoroStatusKind::Done,
+            CoroStatus::Cancel => CoroStatusKind::Cancel,
+            CoroStatus::WrongAwait => CoroStatusKind::WrongAwait,
+        }
+    }
+}
+
+/// A predicate evaluated by the executor when a signal is emitted, to decide if a coroutine
+/// waiting on it should be resumed.
+pub type SignalPredicate = SyncCell<Box<dyn Fn(&World) -> bool + Send>>;
+
 /// The msg notifying that a [`Signal`] was emitted.
 #[derive(Clone, Copy)]
 pub struct EmitMsg {
     pub id: SignalId,
     pub by: usize,
+    /// Set if the emission must be remembered by the executor, see [`Latch`]
+    pub latch: Option<Latch>,
+}
+
+/// How a latched signal, emitted with
+/// [`Scope::emit_latched`](crate::function_coroutine::scope::Scope::emit_latched), is remembered
+/// by the executor for the coroutines awaiting it later on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Latch {
+    /// Every coroutine awaiting the signal afterward receives it right away
+    Sticky,
+    /// Only the first coroutine awaiting the signal receives it. If a coroutine was already
+    /// waiting on it when emitted, it is not remembered at all.
+    Oneshot,
 }
 
-/// The Id of a signal is the concatenation of the component id
-/// of the `Signal<S, T>` and the [`Entity`] on which it is defined.
-/// Note that signals can also be global, hence have no `owner`.
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+/// The Id of a signal is the concatenation of its type and the [`Entity`] on which it is
+/// defined. Note that signals can also be global, hence have no `owner`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct SignalId {
-    pub signal_type: ComponentId,
+    pub signal_type: SignalType,
     pub owner: Option<Entity>,
 }
+
+impl SignalId {
+    /// The signal emitted when the component `id` of `owner` is mutated, see
+    /// [`ChangeTracker`](crate::function_coroutine::coro_param::on_change::ChangeTracker).
+    pub fn component(id: ComponentId, owner: Option<Entity>) -> Self {
+        Self {
+            signal_type: SignalType::Component(id),
+            owner,
+        }
+    }
+
+    /// A user signal identified by the type `S`, which does not need to be a component.
+    pub fn custom<S: 'static>(owner: Option<Entity>) -> Self {
+        Self {
+            signal_type: SignalType::Custom(TypeId::of::<S>()),
+            owner,
+        }
+    }
+
+    /// A user signal identified by its `name`.
+    pub fn named(name: &'static str, owner: Option<Entity>) -> Self {
+        Self {
+            signal_type: SignalType::Named(name),
+            owner,
+        }
+    }
+
+    /// The signal emitted by the executor when the coroutine `id`, registered under a marker,
+    /// ends.
+    pub fn completed(id: Id) -> Self {
+        Self {
+            signal_type: SignalType::Completed(id),
+            owner: None,
+        }
+    }
+
+    /// The signal emitted by the executor to resume the coroutine `id`, once what it waits on
+    /// may be ready, see [`Wakeups`].
+    pub(crate) fn wake(id: Id) -> Self {
+        Self {
+            signal_type: SignalType::Wake(id),
+            owner: None,
+        }
+    }
+}
+
+/// What a signal is about, see [`SignalId`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SignalType {
+    /// A component was mutated
+    Component(ComponentId),
+    /// A signal identified by a type
+    Custom(TypeId),
+    /// A signal identified by a name, two signals with the same name are the same signal
+    Named(&'static str),
+    /// The value of a [`WatchToken`](super::watch::WatchToken) changed, identified by the order
+    /// in which it was added
+    Watch(usize),
+    /// A coroutine registered under a marker ended, see
+    /// [`Scope::register_as`](crate::function_coroutine::scope::Scope::register_as)
+    Completed(Id),
+    /// A coroutine may proceed, such as one waiting on a
+    /// [`CoroMutex`](crate::function_coroutine::mutex::CoroMutex) which was released
+    Wake(Id),
+}
+
+/// Why a [`Coroutine`](crate::Coroutine) was cleaned up by the executor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CleanupReason {
+    /// It has finished execution
+    Completed,
+    /// It was cancelled before finishing
+    Cancelled(CancelReason),
+}
+
+/// Why a [`Coroutine`](crate::Coroutine) was cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CancelReason {
+    /// The entity owning it was despawned
+    OwnerDespawned,
+    /// The scope owning it (or awaiting it) has ended or was cancelled
+    ParentCancelled,
+    /// A coroutine it was awaiting was cancelled
+    ChildCancelled,
+    /// It cancelled itself
+    ExplicitCancel,
+    /// One of its parameters is no longer valid, or its handle was dropped
+    InvalidParams,
+    /// It was cancelled from outside, with [`Executor::cancel_coroutine`](super::Executor::cancel_coroutine)
+    /// or by id with [`Scope::try_cancel`](crate::function_coroutine::scope::Scope::try_cancel)
+    External,
+    /// It awaited a future which is not part of this library, see [`CoroStatus::WrongAwait`]
+    WrongAwait,
+    /// It crossed one of its [`Limits`]
+    QuotaExceeded,
+}
@@ -0,0 +1,35 @@
+use std::hash::{Hash, Hasher};
+
+use bevy::ecs::schedule::{BoxedSystemSet, SystemSet};
+
+/// A secondary point of the frame at which the [`Executor`](super::Executor) can be ticked, right
+/// after a [`SystemSet`], see [`Executor::tick_phase`](super::Executor::tick_phase). Only the
+/// coroutines waiting on this phase, with [`Scope::after_set`](crate::prelude::Scope::after_set),
+/// are resumed then.
+#[derive(Clone, Debug)]
+pub struct Phase(BoxedSystemSet);
+
+impl PartialEq for Phase {
+    fn eq(&self, other: &Self) -> bool {
+        *self.0 == *other.0
+    }
+}
+
+impl Eq for Phase {}
+
+impl Hash for Phase {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self.0).hash(state);
+    }
+}
+
+impl Phase {
+    /// The phase running after the system set `set`.
+    pub fn after(set: impl SystemSet) -> Self {
+        Phase(Box::new(set))
+    }
+
+    pub(crate) fn from_boxed(set: BoxedSystemSet) -> Self {
+        Phase(set)
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::ecs::event::Event;
+
+use crate::id_alloc::Id;
+
+use super::msg::CancelReason;
+
+/// Limits on what a coroutine can do, to contain untrusted scripts, see
+/// [`Scope::start_with`](crate::function_coroutine::scope::Scope::start_with). Crossing any of
+/// them cancels the coroutine, with [`CancelReason::QuotaExceeded`]. The default has no limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The number of coroutines it can start, over its whole lifetime
+    pub max_children: usize,
+    /// The number of times it can queue commands during a single tick, each call to
+    /// [`Scope::commands`](crate::function_coroutine::scope::Scope::commands) counting as one
+    pub max_commands_per_tick: usize,
+    /// The number of entities it can spawn with
+    /// [`Scope::spawn_local`](crate::function_coroutine::scope::Scope::spawn_local)
+    pub max_local_entities: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_children: usize::MAX,
+            max_commands_per_tick: usize::MAX,
+            max_local_entities: usize::MAX,
+        }
+    }
+}
+
+impl Limits {
+    /// The strictest of both limits.
+    pub fn min(self, other: Limits) -> Limits {
+        Limits {
+            max_children: self.max_children.min(other.max_children),
+            max_commands_per_tick: self.max_commands_per_tick.min(other.max_commands_per_tick),
+            max_local_entities: self.max_local_entities.min(other.max_local_entities),
+        }
+    }
+}
+
+/// The event sent when a coroutine is cancelled because it crossed one of its [`Limits`]. Only
+/// sent if the [`Events`](bevy::ecs::event::Events) resource exists, which
+/// [`CorentinPlugin`](crate::plugin::CorentinPlugin) adds.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CoroutineCancelled {
+    pub id: Id,
+    pub reason: CancelReason,
+}
+
+/// What a coroutine with [`Limits`] used so far.
+pub(super) struct Quota {
+    pub limits: Limits,
+    pub children: usize,
+    /// The commands queued during the current tick
+    pub commands: usize,
+}
+
+impl Quota {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            children: 0,
+            commands: 0,
+        }
+    }
+
+    /// Returns true if it crossed one of its limits.
+    pub fn exceeded(&self, local_entities: usize) -> bool {
+        self.children > self.limits.max_children
+            || self.commands > self.limits.max_commands_per_tick
+            || local_entities > self.limits.max_local_entities
+    }
+
+    /// The limits of a child started now, what remains of this budget.
+    pub fn remaining(&self, local_entities: usize) -> Limits {
+        Limits {
+            max_children: self.limits.max_children.saturating_sub(self.children),
+            max_commands_per_tick: self.limits.max_commands_per_tick,
+            max_local_entities: self
+                .limits
+                .max_local_entities
+                .saturating_sub(local_entities),
+        }
+    }
+}
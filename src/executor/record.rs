@@ -0,0 +1,250 @@
+use std::{
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
+
+use bevy::{ecs::component::Tick, prelude::Entity, time::Timer, utils::synccell::SyncCell};
+use tinyset::SetU64;
+
+use crate::{function_coroutine::once_channel::OnceSender, id_alloc::Id, HeapCoro};
+
+use super::{
+    change_detection::AnyChangeWait,
+    msg::{CancelReason, CoroStatus, SignalId, SignalPredicate},
+    phase::Phase,
+    quota::Quota,
+    WaitReason,
+};
+
+/// A coroutine handled by the [`Executor`](super::Executor), alongside everything which lives
+/// exactly as long as it does. The secondary indices of the executor only refer to it by id.
+pub(super) struct CoroRecord {
+    pub coroutine: HeapCoro,
+    pub name: &'static str,
+    /// The time elapsed when it was added, as seen by the executor
+    pub spawned_at: Duration,
+    pub owner: Option<Entity>,
+    pub wait: WaitState,
+    /// The scope this coroutine was started from, which cancels it when it ends
+    pub owned_by: Option<Id>,
+    /// The coroutines started from this one, cancelled when it ends
+    pub owned: SetU64,
+    /// Set if its access conflicts with the one of its scope, which it must never run in
+    /// parallel with
+    pub conflicts_with_scope: bool,
+    /// The coroutine waiting on this one with `first`, `all` or `quorum`
+    pub awaited_by: Option<Id>,
+    pub paused: bool,
+    /// Set when it is invalid, and resumed one last time before being cancelled
+    pub doomed: Option<CancelReason>,
+    /// The emissions of a signal counted for it, until it is resumed
+    pub counter: Option<(SignalId, Arc<AtomicUsize>)>,
+    /// The entities spawned with `spawn_local`, despawned once it ends
+    pub local_entities: Vec<Entity>,
+    /// The last serialized state, for the coroutines which can be saved
+    pub saved_state: Option<serde_json::Value>,
+    /// What it used so far, if it was started with limits
+    pub quota: Option<Quota>,
+}
+
+impl CoroRecord {
+    pub fn new(coroutine: HeapCoro, name: &'static str, spawned_at: Duration) -> Self {
+        Self {
+            coroutine,
+            name,
+            spawned_at,
+            owner: None,
+            wait: WaitState::Ready,
+            owned_by: None,
+            owned: SetU64::new(),
+            conflicts_with_scope: false,
+            awaited_by: None,
+            paused: false,
+            doomed: None,
+            counter: None,
+            local_entities: Vec::new(),
+            saved_state: None,
+            quota: None,
+        }
+    }
+
+    pub fn wait_reason(&self) -> WaitReason {
+        if self.paused {
+            return WaitReason::Paused;
+        }
+        match self.wait {
+            WaitState::Ready => WaitReason::Other,
+            WaitState::Tick | WaitState::ExactTick(_) => WaitReason::Tick,
+            WaitState::Phase(_) => WaitReason::Phase,
+            WaitState::Time(_) => WaitReason::Time,
+            WaitState::First(_) | WaitState::FirstOrCancel(_) => WaitReason::First,
+            WaitState::All { .. } | WaitState::Quorum { .. } => WaitReason::All,
+            WaitState::Signal { .. }
+            | WaitState::Signals { .. }
+            | WaitState::ChangedByOther { .. } => WaitReason::Signal,
+            WaitState::AnySignal(_) => WaitReason::AnySignal,
+            WaitState::AnyChange(_) => WaitReason::AnyChange,
+        }
+    }
+}
+
+/// What a coroutine waits on before it can be resumed again.
+pub(super) enum WaitState {
+    /// Not started yet, or about to be resumed
+    Ready,
+    Tick,
+    /// The tick during which the [`TickCount`](super::TickCount) reaches this number
+    ExactTick(u64),
+    /// The next tick of the executor for this phase
+    Phase(Phase),
+    Time(Timer),
+    /// The handles are all still running: a finished coroutine sends its result before being
+    /// removed, which [`AwaitFirst`](crate::function_coroutine::await_first::AwaitFirst) fetches
+    /// without yielding.
+    First(SetU64),
+    /// Same as [`WaitState::First`], the cancelled coroutines are removed from the set
+    FirstOrCancel(SetU64),
+    All {
+        waits_on: SetU64,
+        /// The deadline of `all_within`, which is kept once finished, until the stragglers are
+        /// cancelled
+        deadline: Option<Timer>,
+    },
+    Quorum {
+        /// The number of coroutines which still need to finish
+        need: usize,
+        waits_on: SetU64,
+    },
+    Signal {
+        signal_id: SignalId,
+        predicate: Option<SignalPredicate>,
+    },
+    AnySignal(SyncCell<OnceSender<SignalId>>),
+    Signals {
+        signals: Vec<SignalId>,
+        all: bool,
+        /// A bit for each of `signals` which was received, kept across ticks
+        received: u64,
+        /// The signals received so far, in order
+        order: Vec<SignalId>,
+        sender: SyncCell<OnceSender<Vec<SignalId>>>,
+    },
+    AnyChange(AnyChangeWait),
+    /// The signal of a component, which is only received from the coroutines which did not run
+    /// before this one, and the tick since which the changes made by systems count
+    ChangedByOther {
+        signal_id: SignalId,
+        since: Tick,
+    },
+}
+
+impl WaitState {
+    /// What a coroutine yielding `status` waits on. The statuses which do not make it wait, and
+    /// the single signals which need more context, are handled by the executor itself.
+    pub fn from_status(status: CoroStatus) -> Self {
+        match status {
+            CoroStatus::Tick => WaitState::Tick,
+            CoroStatus::ExactTick(tick) => WaitState::ExactTick(tick),
+            CoroStatus::Phase(phase) => WaitState::Phase(phase),
+            CoroStatus::Duration(timer) => WaitState::Time(timer),
+            CoroStatus::First(waits_on) => WaitState::First(waits_on),
+            CoroStatus::FirstOrCancel(waits_on) => WaitState::FirstOrCancel(waits_on),
+            CoroStatus::All(waits_on) => WaitState::All {
+                waits_on,
+                deadline: None,
+            },
+            CoroStatus::AllWithin(waits_on, deadline) => WaitState::All {
+                waits_on,
+                deadline: Some(deadline),
+            },
+            CoroStatus::Quorum { need, set } => WaitState::Quorum {
+                need,
+                waits_on: set,
+            },
+            CoroStatus::AnySignal(sender) => WaitState::AnySignal(SyncCell::new(sender)),
+            CoroStatus::AnyChange(wait) => WaitState::AnyChange(wait),
+            CoroStatus::ChangedByOther { signal_id, since } => {
+                WaitState::ChangedByOther { signal_id, since }
+            }
+            CoroStatus::Signals {
+                signals,
+                all,
+                sender,
+            } => WaitState::Signals {
+                signals,
+                all,
+                received: 0,
+                order: Vec::new(),
+                sender: SyncCell::new(sender),
+            },
+            CoroStatus::Done
+            | CoroStatus::Cooperative
+            | CoroStatus::Exclusive
+            | CoroStatus::Cancel
+            | CoroStatus::WrongAwait
+            | CoroStatus::Signal(_)
+            | CoroStatus::SignalWhen(..)
+            | CoroStatus::SignalCount(..) => {
+                unreachable!("This status does not translate to a wait state")
+            }
+        }
+    }
+
+    /// The coroutines this one waits on to finish, if any.
+    pub fn handles(&self) -> Option<&SetU64> {
+        match self {
+            WaitState::First(waits_on)
+            | WaitState::FirstOrCancel(waits_on)
+            | WaitState::All { waits_on, .. }
+            | WaitState::Quorum { waits_on, .. } => Some(waits_on),
+            _ => None,
+        }
+    }
+
+    pub fn into_handles(self) -> Option<SetU64> {
+        match self {
+            WaitState::First(waits_on)
+            | WaitState::FirstOrCancel(waits_on)
+            | WaitState::All { waits_on, .. }
+            | WaitState::Quorum { waits_on, .. } => Some(waits_on),
+            _ => None,
+        }
+    }
+
+    /// The signals it waits on, under which it is indexed by the executor.
+    pub fn signals(&self) -> &[SignalId] {
+        match self {
+            WaitState::Signal { signal_id, .. } | WaitState::ChangedByOther { signal_id, .. } => {
+                std::slice::from_ref(signal_id)
+            }
+            WaitState::Signals { signals, .. } => signals,
+            _ => &[],
+        }
+    }
+
+    /// Returns true if the future which yielded it can be resumed before what it waits on
+    /// happens. The others expect a value to have been sent to them by then.
+    pub fn resumable_early(&self) -> bool {
+        matches!(
+            self,
+            WaitState::Tick
+                | WaitState::ExactTick(_)
+                | WaitState::Phase(_)
+                | WaitState::Time(_)
+                | WaitState::Signal { .. }
+                | WaitState::ChangedByOther { .. }
+        )
+    }
+
+    /// Returns true if it is indexed in the timers of the executor.
+    pub fn has_timer(&self) -> bool {
+        matches!(
+            self,
+            WaitState::Time(_)
+                | WaitState::All {
+                    deadline: Some(_),
+                    ..
+                }
+        )
+    }
+}
@@ -0,0 +1,177 @@
+use std::{error::Error, fmt::Display, time::Duration};
+
+use bevy::{
+    prelude::{Entity, World},
+    time::{Timer, TimerMode},
+    utils::synccell::SyncCell,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    id_alloc::Id,
+    state::{CoroutineState, StateCoroutine},
+    HeapCoro,
+};
+
+use super::{msg::SignalId, msg::SignalType, record::WaitState, Executor};
+
+/// Deserializes the state of a coroutine, see [`Executor::register_state`]. Returns [`None`] if
+/// it is invalid, otherwise what builds the coroutine once it gets an id.
+pub(super) type StateLoader = fn(Value) -> Option<CoroutineBuilder>;
+
+pub(super) type CoroutineBuilder = Box<dyn FnOnce(Id, Option<Entity>) -> HeapCoro>;
+
+/// Why [`Executor::deserialize`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeserializeError {
+    /// The data is truncated or corrupted
+    Invalid,
+    /// No state machine with this name was registered, see [`Executor::register_state`]
+    UnknownState(String),
+    /// A coroutine is bound to an entity which does not exist
+    MissingOwner(Entity),
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeserializeError::Invalid => write!(f, "the saved coroutines are corrupted"),
+            DeserializeError::UnknownState(name) => {
+                write!(f, "the state machine `{name}` is not registered")
+            }
+            DeserializeError::MissingOwner(entity) => {
+                write!(f, "the entity {entity:?} owning a coroutine does not exist")
+            }
+        }
+    }
+}
+
+impl Error for DeserializeError {}
+
+/// A coroutine saved by [`Executor::serialize`].
+#[derive(Serialize, Deserialize)]
+struct SavedCoroutine {
+    name: String,
+    owner: Option<Entity>,
+    wait: SavedWait,
+    state: Value,
+}
+
+/// What a saved coroutine waits on.
+#[derive(Serialize, Deserialize)]
+enum SavedWait {
+    Tick,
+    Duration(Duration),
+    Signal(String, Option<Entity>),
+}
+
+impl Executor {
+    /// Add a coroutine written as a state machine, optionally bound to `owner`. Unlike other
+    /// coroutines, it is saved by [`Executor::serialize`].
+    pub fn add_state_coroutine<S: CoroutineState>(&mut self, owner: Option<Entity>, state: S) {
+        self.register_state::<S>();
+        let id = self.ids.allocate_id();
+        let coroutine = StateCoroutine::new(id, owner, state);
+        self.add_coroutine(id, SyncCell::new(Box::pin(coroutine)));
+    }
+
+    /// Allow the state machine `S` to be restored by [`Executor::deserialize`]. This is done
+    /// automatically when adding a coroutine with [`Executor::add_state_coroutine`].
+    pub fn register_state<S: CoroutineState>(&mut self) {
+        self.state_loaders.insert(S::name(), |state| {
+            let state: S = serde_json::from_value(state).ok()?;
+            Some(Box::new(move |id, owner| {
+                SyncCell::new(Box::pin(StateCoroutine::new(id, owner, state)))
+            }))
+        });
+    }
+
+    /// Serialize all the coroutines which can be saved, the state machines added with
+    /// [`Executor::add_state_coroutine`], with what they are waiting on, as JSON. Other
+    /// coroutines are ignored. Entities are saved as is, and must keep the same ids once
+    /// restored.
+    pub fn serialize(&self) -> Vec<u8> {
+        let saved: Vec<_> = self
+            .records
+            .values()
+            .filter_map(|record| {
+                Some(SavedCoroutine {
+                    name: record.name.to_string(),
+                    owner: record.owner,
+                    wait: saved_wait(&record.wait),
+                    state: record.saved_state.clone()?,
+                })
+            })
+            .collect();
+        serde_json::to_vec(&saved).expect("The saved coroutines can always be serialized")
+    }
+
+    /// Restore the coroutines saved with [`Executor::serialize`], on top of the ones already
+    /// running. Their state machines must be registered with [`Executor::register_state`].
+    /// Nothing is restored if an error is returned.
+    pub fn deserialize(&mut self, data: &[u8], world: &mut World) -> Result<(), DeserializeError> {
+        let saved: Vec<SavedCoroutine> =
+            serde_json::from_slice(data).map_err(|_| DeserializeError::Invalid)?;
+
+        // Everything is validated first, so that no coroutine nor id is added on failure
+        let mut restored = Vec::new();
+        for SavedCoroutine {
+            name,
+            owner,
+            wait,
+            state,
+        } in saved
+        {
+            let loader = *self
+                .state_loaders
+                .get(name.as_str())
+                .ok_or(DeserializeError::UnknownState(name))?;
+            if let Some(owner) = owner {
+                if world.get_entity(owner).is_none() {
+                    return Err(DeserializeError::MissingOwner(owner));
+                }
+            }
+            let builder = loader(state).ok_or(DeserializeError::Invalid)?;
+            restored.push((builder, owner, wait));
+        }
+
+        for (builder, owner, wait) in restored {
+            let id = self.ids.allocate_id();
+            self.insert_coroutine(id, builder(id, owner), None, None);
+            let wait = match wait {
+                SavedWait::Tick => WaitState::Tick,
+                SavedWait::Duration(remaining) => {
+                    WaitState::Time(Timer::new(remaining, TimerMode::Once))
+                }
+                SavedWait::Signal(name, owner) => {
+                    // Signal names are static, the few restored ones are leaked
+                    let name: &'static str = Box::leak(name.into_boxed_str());
+                    WaitState::Signal {
+                        signal_id: SignalId::named(name, owner),
+                        predicate: None,
+                    }
+                }
+            };
+            self.wait_on(id, wait);
+        }
+
+        Ok(())
+    }
+}
+
+fn saved_wait(wait: &WaitState) -> SavedWait {
+    match wait {
+        WaitState::Time(timer) => SavedWait::Duration(timer.remaining()),
+        WaitState::Signal {
+            signal_id:
+                SignalId {
+                    signal_type: SignalType::Named(name),
+                    owner,
+                },
+            ..
+        } => SavedWait::Signal(name.to_string(), *owner),
+        // Either waiting on the next tick, or not started yet
+        _ => SavedWait::Tick,
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::World;
+
+use super::msg::SignalId;
+
+/// A watch function registered with [`Executor::add_watch`](super::Executor::add_watch),
+/// returning true if its value changed since the last time it was evaluated.
+pub(crate) type Watch = Box<dyn FnMut(&World) -> bool + Send + Sync>;
+
+/// A value computed from the [`World`] by the executor at the start of each tick, created with
+/// [`Executor::add_watch`](super::Executor::add_watch). Coroutines can wait for it to change with
+/// [`Scope::await_watch`](crate::function_coroutine::scope::Scope::await_watch).
+pub struct WatchToken<T> {
+    signal_id: SignalId,
+    value: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> Clone for WatchToken<T> {
+    fn clone(&self) -> Self {
+        Self {
+            signal_id: self.signal_id,
+            value: Arc::clone(&self.value),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Send + Sync + 'static> WatchToken<T> {
+    /// Returns a watch computing its value with `f`, and the function to evaluate it.
+    pub(crate) fn new(
+        signal_id: SignalId,
+        f: impl Fn(&World) -> T + Send + Sync + 'static,
+    ) -> (Self, Watch) {
+        let value = Arc::new(Mutex::new(None));
+        let last = Arc::clone(&value);
+        let watch = move |world: &World| {
+            let new = f(world);
+            let mut last = last.lock().unwrap();
+            // The first evaluation only records the initial value
+            let changed = last.as_ref().is_some_and(|last| *last != new);
+            if last.as_ref() != Some(&new) {
+                *last = Some(new);
+            }
+            changed
+        };
+
+        (Self { signal_id, value }, Box::new(watch))
+    }
+
+    /// Returns the last value computed, or [`None`] if it was not evaluated yet.
+    pub fn get(&self) -> Option<T> {
+        self.value.lock().unwrap().clone()
+    }
+
+    /// The signal received by the coroutines waiting on this watch when its value changes.
+    pub fn signal_id(&self) -> SignalId {
+        self.signal_id
+    }
+}
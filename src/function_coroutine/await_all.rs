@@ -7,10 +7,13 @@ use std::{
 use pin_project::pin_project;
 
 use super::{
-    handle::{HandleTuple, Status},
+    handle::{CoroHandle, HandleTuple, Status},
     CoroState, CoroStatus, Scope,
 };
 
+/// The future returned by [`Scope::parallel_map`], resolving with the results in input order.
+pub type ParallelMapFuture<'a, R> = AwaitAll<'a, Vec<CoroHandle<R>>>;
+
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 #[pin_project]
 pub struct AwaitAll<'a, H: HandleTuple> {
@@ -0,0 +1,67 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bevy::time::{Timer, TimerMode};
+use pin_project::pin_project;
+
+use super::{
+    handle::{HandleTuple, Status},
+    CoroState, CoroStatus, Scope,
+};
+
+/// A future resolving once all the coroutines finished, or once the duration elapsed. Only the
+/// results of the finished coroutines are returned, the others are cancelled.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct AwaitAllWithin<'a, H: HandleTuple> {
+    scope: &'a mut Scope,
+    handlers: H,
+    duration: Duration,
+    state: CoroState,
+}
+
+impl<'a, H: HandleTuple> AwaitAllWithin<'a, H> {
+    pub(crate) fn new(scope: &'a mut Scope, handlers: H, duration: Duration) -> Self {
+        AwaitAllWithin {
+            scope,
+            handlers,
+            duration,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl<H: HandleTuple> Future for AwaitAllWithin<'_, H> {
+    type Output = H::Partial;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.state {
+            // We assume the executor will only poll it once all the coroutines have finish
+            // executing, or once the stragglers have been cancelled
+            CoroState::Halted => {
+                *this.state = CoroState::Running;
+                Poll::Ready(this.handlers.take_partial())
+            }
+            CoroState::Running => {
+                *this.state = CoroState::Halted;
+                match this.handlers.update_status() {
+                    Status::Done => Poll::Ready(this.handlers.take_partial()),
+                    Status::StillWaiting(ids) => {
+                        let timer = Timer::new(*this.duration, TimerMode::Once);
+                        this.scope.yield_(CoroStatus::AllWithin(ids, timer));
+                        Poll::Pending
+                    }
+                    _ => {
+                        this.scope.yield_(CoroStatus::Cancel);
+                        Poll::Pending
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::{
+    ecs::{component::Tick, query::ReadOnlyWorldQuery},
+    prelude::Component,
+};
+
+use crate::{
+    executor::{
+        change_detection::{AnyChangeWait, ChangedEntities},
+        msg::CoroStatus,
+    },
+    SourceId,
+};
+
+use super::{
+    once_channel::{sync_once_channel, OnceRec, OnceSender},
+    scope::Scope,
+};
+
+type NewWait = fn(Tick, OnceSender<ChangedEntities>) -> AnyChangeWait;
+
+/// A future resolving with the entities whose component changed, once at least one did.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AnyChangeFuture<'a> {
+    scope: &'a mut Scope,
+    /// Taken on the first poll, to create the wait from the tick at which the coroutine suspends
+    wait: Option<(NewWait, OnceSender<ChangedEntities>)>,
+    receiver: OnceRec<ChangedEntities>,
+    register_read: fn(&mut Scope),
+}
+
+impl<'a> AnyChangeFuture<'a> {
+    pub fn new<T: Component, F: ReadOnlyWorldQuery + 'static>(scope: &'a mut Scope) -> Self {
+        let (sender, receiver) = sync_once_channel();
+
+        Self {
+            scope,
+            wait: Some((AnyChangeWait::new::<T, F>, sender)),
+            receiver,
+            register_read: |scope| {
+                let Some(component_id) = scope.world_cell().components().component_id::<T>() else {
+                    // No entity has it yet, it is only ever read once spawned with it
+                    return;
+                };
+                // SAFETY: The metadata are only set while the coroutine is polled, and are not
+                // borrowed by anything else meanwhile
+                let meta = unsafe { &mut *scope.meta_ptr() };
+                // The query only runs in the executor, in between two resumes, so a write of the
+                // coroutine itself on one of the entities is not an actual conflict
+                if !meta.access.can_read(SourceId::AllEntities, component_id) {
+                    meta.access.add_read(SourceId::AllEntities, component_id);
+                }
+            },
+        }
+    }
+}
+
+impl<'a> Future for AnyChangeFuture<'a> {
+    type Output = ChangedEntities;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some((new_wait, sender)) = this.wait.take() {
+            (this.register_read)(this.scope);
+            // Like when a system runs, the changes made from now on get a newer tick
+            let since = this.scope.world_cell().increment_change_tick();
+            this.scope
+                .yield_(CoroStatus::AnyChange(new_wait(since, sender)));
+            return Poll::Pending;
+        }
+
+        // We assume the executor will only poll it once the changed entities are sent
+        Poll::Ready(
+            self.receiver
+                .try_recv()
+                .expect("The coroutine was resumed before any entity changed"),
+        )
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::executor::msg::{CoroStatus, SignalId};
+
+use super::{
+    once_channel::{sync_once_channel, OnceRec},
+    scope::Scope,
+};
+
+/// A future resolving with the id of the next signal emitted, whichever it is.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AnySignalFuture<'a> {
+    scope: &'a mut Scope,
+    receiver: Option<OnceRec<SignalId>>,
+}
+
+impl<'a> AnySignalFuture<'a> {
+    pub fn new(scope: &'a mut Scope) -> Self {
+        Self {
+            scope,
+            receiver: None,
+        }
+    }
+}
+
+impl<'a> Future for AnySignalFuture<'a> {
+    type Output = SignalId;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &self.receiver {
+            // We assume the executor will only poll it once a signal is emitted
+            Some(receiver) => Poll::Ready(
+                receiver
+                    .try_recv()
+                    .expect("The coroutine was resumed before any signal was emitted"),
+            ),
+            None => {
+                let (sender, receiver) = sync_once_channel();
+                self.receiver = Some(receiver);
+                self.scope.yield_(CoroStatus::AnySignal(sender));
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bevy::asset::{Asset, Assets, Handle};
+
+use crate::{executor::msg::CoroStatus, SourceId};
+
+use super::scope::Scope;
+
+/// A future resolving once the asset of a [`Handle`] is loaded, with a reference to it. Created
+/// with [`Scope::await_asset_loaded`]. The handle is kept while waiting, so the asset cannot be
+/// unloaded meanwhile.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AssetLoadedFuture<'a, A: Asset> {
+    scope: &'a mut Scope,
+    handle: Handle<A>,
+}
+
+impl<'a, A: Asset> AssetLoadedFuture<'a, A> {
+    pub(crate) fn new(scope: &'a mut Scope, handle: Handle<A>) -> Self {
+        Self { scope, handle }
+    }
+
+    /// Returns the asset if it is loaded.
+    fn asset(&self) -> Option<&'a A> {
+        // SAFETY: The returned reference borrows the scope for `'a`, meaning the coroutine
+        // cannot yield while holding it, and the assets cannot be mutated in the meantime.
+        let scope: &'a Scope = unsafe { &*(self.scope as *const Scope) };
+        let assets = unsafe { scope.world_cell().get_resource::<Assets<A>>() };
+        assets.and_then(|assets| assets.get(&self.handle))
+    }
+
+    /// Adds a read of the [`Assets`] resource to the access of the coroutine.
+    fn register_read(&mut self) {
+        let Some(assets_id) = self
+            .scope
+            .world_cell()
+            .components()
+            .resource_id::<Assets<A>>()
+        else {
+            return;
+        };
+        // SAFETY: The metadata are only set while the coroutine is polled, and are not borrowed
+        // by anything else meanwhile
+        let meta = unsafe { &mut *self.scope.meta_ptr() };
+        if !meta.access.can_read(SourceId::World, assets_id) {
+            meta.access.add_read(SourceId::World, assets_id);
+        }
+    }
+}
+
+impl<'a, A: Asset> Future for AssetLoadedFuture<'a, A> {
+    type Output = &'a A;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.register_read();
+        if let Some(asset) = this.asset() {
+            return Poll::Ready(asset);
+        }
+
+        // Checked again on each tick, like the changes of resources
+        this.scope.yield_(CoroStatus::Tick);
+        Poll::Pending
+    }
+}
+
+/// Same as [`AssetLoadedFuture`], but resolving with [`None`] once the duration elapsed. Created
+/// with [`Scope::await_asset_or_timeout`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AssetOrTimeoutFuture<'a, A: Asset> {
+    loaded: AssetLoadedFuture<'a, A>,
+    /// The total elapsed time after which it resolves
+    deadline: Duration,
+}
+
+impl<'a, A: Asset> AssetOrTimeoutFuture<'a, A> {
+    pub(crate) fn new(scope: &'a mut Scope, handle: Handle<A>, duration: Duration) -> Self {
+        let deadline = scope.elapsed_time() + duration;
+        Self {
+            loaded: AssetLoadedFuture::new(scope, handle),
+            deadline,
+        }
+    }
+}
+
+impl<'a, A: Asset> Future for AssetOrTimeoutFuture<'a, A> {
+    type Output = Option<&'a A>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.loaded.register_read();
+        // Checked before the deadline, an asset loaded during the last tick still counts
+        if let Some(asset) = this.loaded.asset() {
+            return Poll::Ready(Some(asset));
+        }
+        if this.loaded.scope.elapsed_time() >= this.deadline {
+            return Poll::Ready(None);
+        }
+
+        this.loaded.scope.yield_(CoroStatus::Tick);
+        Poll::Pending
+    }
+}
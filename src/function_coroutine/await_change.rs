@@ -4,7 +4,7 @@ use std::{
     task::{Context, Poll},
 };
 
-use crate::executor::msg::{CoroStatus, SignalId};
+use crate::executor::msg::{CoroStatus, SignalId, SignalPredicate};
 
 use super::{scope::Scope, CoroState};
 
@@ -41,3 +41,42 @@ impl<'a> Future for AwaitChange<'a> {
         }
     }
 }
+
+/// Same as [`AwaitChange`], but only resolves once the predicate holds after a change.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AwaitChangeWhen<'a> {
+    scope: &'a mut Scope,
+    id: SignalId,
+    predicate: Option<SignalPredicate>,
+    state: CoroState,
+}
+
+impl<'a> AwaitChangeWhen<'a> {
+    pub fn new(scope: &'a mut Scope, id: SignalId, predicate: SignalPredicate) -> Self {
+        Self {
+            scope,
+            id,
+            predicate: Some(predicate),
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl<'a> Future for AwaitChangeWhen<'a> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.state {
+            // The executor only resumes us once the predicate holds
+            CoroState::Halted => Poll::Ready(()),
+            CoroState::Running => {
+                self.state = CoroState::Halted;
+
+                let id = self.id;
+                let predicate = self.predicate.take().unwrap();
+                self.scope.yield_(CoroStatus::SignalWhen(id, predicate));
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::{ecs::component::ComponentId, prelude::Entity};
+
+use crate::executor::msg::{CoroStatus, SignalId};
+
+use super::{scope::Scope, CoroState};
+
+/// A future resolving once a component was changed by anything else than the coroutine awaiting
+/// it, or by the coroutines which ran before it during the same tick.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ChangedByOtherFuture<'a> {
+    scope: &'a mut Scope,
+    entity: Entity,
+    component_id: ComponentId,
+    state: CoroState,
+}
+
+impl<'a> ChangedByOtherFuture<'a> {
+    pub fn new(scope: &'a mut Scope, entity: Entity, component_id: ComponentId) -> Self {
+        Self {
+            scope,
+            entity,
+            component_id,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl<'a> Future for ChangedByOtherFuture<'a> {
+    type Output = bool;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.state {
+            // We assume the executor will only poll it once the component changed, or once its
+            // entity was despawned
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                let world = self.scope.world_cell();
+                let exists = world
+                    .get_entity(self.entity)
+                    .is_some_and(|entity| entity.contains_id(self.component_id));
+                Poll::Ready(exists)
+            }
+            CoroState::Running => {
+                self.state = CoroState::Halted;
+                // The changes made by the coroutines during this tick share its change tick,
+                // the executor tells them apart with the order they ran in instead
+                let since = self.scope.world_cell().change_tick();
+                let signal_id = SignalId::component(self.component_id, Some(self.entity));
+                self.scope
+                    .yield_(CoroStatus::ChangedByOther { signal_id, since });
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::ecs::event::{Event, Events, ManualEventReader};
+
+use crate::{executor::msg::CoroStatus, SourceId};
+
+use super::scope::Scope;
+
+/// A future reading every event `E` sent after its creation, until the predicate returns `true`.
+/// Created with [`Scope::react_to_event`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReactToEventFuture<'a, E: Event> {
+    scope: &'a mut Scope,
+    reader: ManualEventReader<E>,
+    f: Box<dyn FnMut(&E) -> bool + Send + 'a>,
+}
+
+impl<'a, E: Event> ReactToEventFuture<'a, E> {
+    /// # Panics
+    /// If the resource [`Events<E>`] does not exist.
+    pub fn new(scope: &'a mut Scope, f: impl FnMut(&E) -> bool + Send + 'a) -> Self {
+        let reader = events::<E>(scope).get_reader_current();
+
+        let events_id = scope
+            .world_cell()
+            .components()
+            .resource_id::<Events<E>>()
+            .unwrap();
+        // SAFETY: The metadata are only set while the coroutine is polled, and are not borrowed
+        // by anything else meanwhile
+        let meta = unsafe { &mut *scope.meta_ptr() };
+        if !meta.access.can_read(SourceId::World, events_id) {
+            meta.access.add_read(SourceId::World, events_id);
+        }
+
+        Self {
+            scope,
+            reader,
+            f: Box::new(f),
+        }
+    }
+}
+
+fn events<E: Event>(scope: &Scope) -> &Events<E> {
+    // SAFETY: Events are only read while the coroutine is running
+    unsafe {
+        scope
+            .world_cell()
+            .get_resource::<Events<E>>()
+            .expect("Cannot react to events which were not added to the world")
+    }
+}
+
+// The reader and the boxed predicate are never pinned
+impl<E: Event> Unpin for ReactToEventFuture<'_, E> {}
+
+impl<E: Event> Future for ReactToEventFuture<'_, E> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let events = events::<E>(this.scope);
+
+        // The remaining events are left unread once the predicate holds
+        for event in this.reader.iter(events) {
+            if (this.f)(event) {
+                return Poll::Ready(());
+            }
+        }
+
+        this.scope.yield_(CoroStatus::Tick);
+        Poll::Pending
+    }
+}
@@ -35,43 +35,118 @@ impl<const N: usize, T: Send + Sync + 'static> Future for AwaitFirst<'_, N, T> {
 
     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
         let this = self.project();
-        match this.state {
-            // We assume the executor will only poll it once any of the coroutines have finish executing
-            CoroState::Halted => {
-                *this.state = CoroState::Running;
-                for h in this.handles {
-                    if let Status::Done = h.update_status() {
-                        return Poll::Ready(h.try_fetch().unwrap());
-                    }
+        let resumed = matches!(this.state, CoroState::Halted);
+        *this.state = CoroState::Halted;
+
+        let mut set = SetU64::new();
+        let mut canceled = false;
+        for h in this.handles.iter_mut() {
+            match h.update_status() {
+                // An already finished coroutine wins, even if others were cancelled
+                Status::Done => {
+                    *this.state = CoroState::Running;
+                    return Poll::Ready(h.try_fetch().unwrap());
                 }
-                panic!("The executor resumed a coroutine at the wrong time, this is a bug");
-            }
-            CoroState::Running => {
-                *this.state = CoroState::Halted;
-                let mut set = SetU64::new();
-                let mut done = None;
-                for h in this.handles {
-                    match h.update_status() {
-                        Status::Done => {
-                            if done.is_none() {
-                                done = Some(h.try_fetch().unwrap());
-                            }
-                        }
-                        Status::StillWaiting(id) => {
-                            set.extend(id);
-                        }
-                        _ => {
-                            this.scope.yield_(CoroStatus::Cancel);
-                            return Poll::Pending;
-                        }
-                    }
+                Status::StillWaiting(id) => {
+                    set.extend(id);
                 }
-                if let Some(value) = done {
-                    return Poll::Ready(value);
+                _ => canceled = true,
+            }
+        }
+
+        if canceled {
+            this.scope.yield_(CoroStatus::Cancel);
+            return Poll::Pending;
+        }
+
+        // We assume the executor will only resume it once any of the coroutines have finish executing
+        if resumed {
+            panic!("The executor resumed a coroutine at the wrong time, this is a bug");
+        }
+
+        this.scope.yield_(CoroStatus::First(set));
+        Poll::Pending
+    }
+}
+
+/// A future resolving with the index and the result of the first coroutine to finish, among
+/// the ones which were not cancelled. Created with [`Scope::first_or_cancel`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct FirstOrCancelFuture<'a, const N: usize, T> {
+    scope: &'a mut Scope,
+    handles: [CoroHandle<T>; N],
+    state: CoroState,
+}
+
+impl<'a, const N: usize, T> FirstOrCancelFuture<'a, N, T> {
+    pub(crate) fn new(scope: &'a mut Scope, handles: [CoroHandle<T>; N]) -> Self {
+        Self {
+            scope,
+            handles,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl<const N: usize, T: Send + Sync + 'static> Future for FirstOrCancelFuture<'_, N, T> {
+    type Output = (usize, T);
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let resumed = matches!(this.state, CoroState::Halted);
+        *this.state = CoroState::Halted;
+
+        // The cancelled coroutines are out of the race
+        let mut set = SetU64::new();
+        for (i, h) in this.handles.iter_mut().enumerate() {
+            match h.update_status() {
+                Status::Done => {
+                    *this.state = CoroState::Running;
+                    return Poll::Ready((i, h.try_fetch().unwrap()));
                 }
-                this.scope.yield_(CoroStatus::First(set));
-                Poll::Pending
+                Status::StillWaiting(id) => set.extend(id),
+                _ => {}
             }
         }
+
+        if set.is_empty() {
+            this.scope.yield_(CoroStatus::Cancel);
+            return Poll::Pending;
+        }
+
+        // We assume the executor will only resume it once any of the coroutines have finish executing
+        if resumed {
+            panic!("The executor resumed a coroutine at the wrong time, this is a bug");
+        }
+
+        this.scope.yield_(CoroStatus::FirstOrCancel(set));
+        Poll::Pending
+    }
+}
+
+/// A [`CoroHandle`] turned into a [`Future`] resolving with its result, created with
+/// [`Scope::into_future`]. It borrows the scope mutably, several of them cannot be polled
+/// together: use [`Scope::all`] or [`Scope::first`] to await several handles.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct ScopedHandleFuture<'a, T> {
+    #[pin]
+    inner: AwaitFirst<'a, 1, T>,
+}
+
+impl<'a, T> ScopedHandleFuture<'a, T> {
+    pub(crate) fn new(scope: &'a mut Scope, handle: CoroHandle<T>) -> Self {
+        Self {
+            inner: AwaitFirst::new(scope, [handle]),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> Future for ScopedHandleFuture<'_, T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.project().inner.poll(cx)
     }
 }
@@ -0,0 +1,93 @@
+use std::{
+    any::{Any, TypeId},
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::{
+    ecs::query::{QueryState, ReadOnlyWorldQuery},
+    prelude::Entity,
+    utils::HashMap,
+};
+
+use super::{scope::Scope, CoroState, CoroStatus};
+
+/// The query states built by [`Scope::entity_has_all`], by query type, kept in the
+/// [`ScopeStorage`](crate::executor::ScopeStorage) of the executor so that the components of
+/// each query are only registered once.
+#[derive(Default)]
+pub(crate) struct QueryStates(HashMap<TypeId, Box<dyn Any + Send + Sync>>);
+
+impl QueryStates {
+    /// Take the state of the query `Q` out of the cache, if it was built already.
+    pub fn take<Q: ReadOnlyWorldQuery + 'static>(&mut self) -> Option<Box<QueryState<Q>>> {
+        self.0
+            .remove(&TypeId::of::<QueryState<Q>>())
+            .map(|state| state.downcast().unwrap())
+    }
+
+    /// Put back the state of the query `Q`.
+    pub fn insert<Q: ReadOnlyWorldQuery + 'static>(&mut self, state: Box<QueryState<Q>>) {
+        self.0.insert(TypeId::of::<QueryState<Q>>(), state);
+    }
+}
+
+/// A future resolving with true if an entity matches the query `Q`, created with
+/// [`Scope::entity_has_all`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct HasAllFuture<'a, Q> {
+    scope: &'a mut Scope,
+    entity: Entity,
+    state: CoroState,
+    _phantom: PhantomData<fn() -> Q>,
+}
+
+impl<'a, Q> HasAllFuture<'a, Q> {
+    pub(crate) fn new(scope: &'a mut Scope, entity: Entity) -> Self {
+        Self {
+            scope,
+            entity,
+            state: CoroState::Running,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<Q: ReadOnlyWorldQuery + 'static> Future for HasAllFuture<'_, Q> {
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let entity = this.entity;
+        match this.state {
+            CoroState::Running => {
+                let Some(mut query) = this.scope.query_states().take::<Q>() else {
+                    // Building the state registers the components of the query
+                    this.state = CoroState::Halted;
+                    this.scope.yield_(CoroStatus::Exclusive);
+                    return Poll::Pending;
+                };
+
+                let world = this.scope.world_cell();
+                query.update_archetypes_unsafe_world_cell(world);
+                // SAFETY: No other coroutine runs meanwhile, and only the location of the entity
+                // is read, along with the components of `Q` whose items are dropped right away
+                let matches = query.get_manual(unsafe { world.world() }, entity).is_ok();
+                this.scope.query_states().insert(query);
+                Poll::Ready(matches)
+            }
+            // We assume the executor will only poll it while it holds the world exclusively
+            CoroState::Halted => {
+                // SAFETY: The coroutine yielded `CoroStatus::Exclusive`, and no item of its
+                // parameters is borrowed across the await point that led here
+                let world = unsafe { this.scope.exclusive_world() };
+                let mut query = Box::new(QueryState::<Q>::new(world));
+                let matches = query.get(world, entity).is_ok();
+                this.scope.query_states().insert(query);
+                Poll::Ready(matches)
+            }
+        }
+    }
+}
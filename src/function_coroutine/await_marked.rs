@@ -0,0 +1,93 @@
+use std::{
+    any::TypeId,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::{
+    prelude::Entity,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    executor::msg::{CoroStatus, SignalId},
+    id_alloc::Id,
+};
+
+use super::{scope::Scope, CoroState};
+
+/// The coroutines registered under a marker type on their owner, with [`Scope::register_as`],
+/// kept in the [`ScopeStorage`](crate::executor::ScopeStorage) of the executor. A registration
+/// is removed once its coroutine ends, which happens as well when its owner is despawned.
+#[derive(Default)]
+pub struct MarkedCoroutines {
+    by_marker: HashMap<(Entity, TypeId), Id>,
+    /// The registered coroutines which did not end yet, including the replaced ones
+    registered: HashSet<Id>,
+}
+
+impl MarkedCoroutines {
+    pub(crate) fn register(&mut self, owner: Entity, marker: TypeId, id: Id) {
+        self.by_marker.insert((owner, marker), id);
+        self.registered.insert(id);
+    }
+
+    /// Remove the registrations of the coroutine `id`, which ended. Returns true if it was
+    /// registered, in which case its completion signal must be emitted.
+    pub(crate) fn unregister(&mut self, id: Id) -> bool {
+        if !self.registered.remove(&id) {
+            return false;
+        }
+        self.by_marker.retain(|_, registered| *registered != id);
+        true
+    }
+
+    /// Returns the coroutine registered under `marker` on `owner`, if it did not end yet.
+    pub fn get(&self, owner: Entity, marker: TypeId) -> Option<Id> {
+        self.by_marker.get(&(owner, marker)).copied()
+    }
+}
+
+/// A future resolving once a coroutine registered under a marker finishes, created with
+/// [`Scope::await_marked`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct MarkedFuture<'a> {
+    scope: &'a mut Scope,
+    /// The awaited coroutine, if one was still running when the future was created
+    awaited: Option<Id>,
+    state: CoroState,
+}
+
+impl<'a> MarkedFuture<'a> {
+    pub(crate) fn new(scope: &'a mut Scope, owner: Entity, marker: TypeId) -> Self {
+        let awaited = scope
+            .marked_coroutines()
+            .get(owner, marker)
+            .filter(|id| scope.is_running(*id));
+        Self {
+            scope,
+            awaited,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl Future for MarkedFuture<'_> {
+    type Output = Option<Id>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match (this.state, this.awaited) {
+            (CoroState::Running, Some(id)) => {
+                this.state = CoroState::Halted;
+                // Emitted by the executor once the registered coroutine ends
+                this.scope
+                    .yield_(CoroStatus::Signal(SignalId::completed(id)));
+                Poll::Pending
+            }
+            // We assume the executor will only poll it once the coroutine ended
+            (_, awaited) => Poll::Ready(awaited),
+        }
+    }
+}
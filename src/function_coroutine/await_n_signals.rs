@@ -0,0 +1,67 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::executor::msg::{CoroStatus, SignalId};
+
+use super::{scope::Scope, CoroState};
+
+/// A future resolving once a signal has been emitted `n` times.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct NSignalsFuture<'a> {
+    scope: &'a mut Scope,
+    id: SignalId,
+    n: usize,
+    remaining: usize,
+    state: CoroState,
+}
+
+impl<'a> NSignalsFuture<'a> {
+    pub fn new(scope: &'a mut Scope, n: usize, id: SignalId) -> Self {
+        Self {
+            scope,
+            id,
+            n,
+            remaining: n,
+            state: CoroState::Running,
+        }
+    }
+
+    /// The number of emissions this future waits for.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+}
+
+impl<'a> Future for NSignalsFuture<'a> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.state {
+            // We assume the executor will only poll it once the signal is emitted
+            CoroState::Halted => {
+                self.remaining -= 1;
+                if self.remaining == 0 {
+                    self.state = CoroState::Running;
+                    return Poll::Ready(());
+                }
+
+                let id = self.id;
+                self.scope.yield_(CoroStatus::Signal(id));
+                Poll::Pending
+            }
+            CoroState::Running => {
+                if self.remaining == 0 {
+                    return Poll::Ready(());
+                }
+
+                self.state = CoroState::Halted;
+                let id = self.id;
+                self.scope.yield_(CoroStatus::Signal(id));
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::executor::{msg::CoroStatus, phase::Phase};
+
+use super::{scope::Scope, CoroState};
+
+/// A future resolving the next time the executor is ticked for a [`Phase`]. Created with
+/// [`Scope::after_set`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AfterPhase<'a> {
+    scope: &'a mut Scope,
+    phase: Phase,
+    state: CoroState,
+}
+
+impl<'a> AfterPhase<'a> {
+    pub(crate) fn new(scope: &'a mut Scope, phase: Phase) -> Self {
+        Self {
+            scope,
+            phase,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl Future for AfterPhase<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.state {
+            // We assume the executor will only poll it once ticked for this phase
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                Poll::Ready(())
+            }
+            CoroState::Running => {
+                self.state = CoroState::Halted;
+                let phase = self.phase.clone();
+                self.scope.yield_(CoroStatus::Phase(phase));
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::{
+    ecs::query::{FilteredAccess, ReadOnlyWorldQuery},
+    prelude::Entity,
+};
+
+use crate::{
+    executor::{
+        change_detection::{AnyChangeWait, ChangedEntities},
+        msg::CoroStatus,
+    },
+    SourceId,
+};
+
+use super::{
+    once_channel::{sync_once_channel, OnceRec},
+    scope::Scope,
+};
+
+enum QueryChangeState {
+    /// Not polled yet
+    Created,
+    /// Waiting to be resumed alone, to register the components of the query
+    Exclusive,
+    /// Waiting for the executor to detect a change
+    Waiting(OnceRec<ChangedEntities>),
+}
+
+/// A future resolving with an entity matching the query `Q` with the filter `F`, once the
+/// filter detects a change on it. Created with [`Scope::await_query_change`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct QueryChangeFuture<'a, Q: ReadOnlyWorldQuery, F: ReadOnlyWorldQuery> {
+    scope: &'a mut Scope,
+    state: QueryChangeState,
+    _phantom: PhantomData<fn() -> (Q, F)>,
+}
+
+impl<'a, Q: ReadOnlyWorldQuery, F: ReadOnlyWorldQuery> QueryChangeFuture<'a, Q, F> {
+    pub(crate) fn new(scope: &'a mut Scope) -> Self {
+        Self {
+            scope,
+            state: QueryChangeState::Created,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+// The receiver is never pinned
+impl<Q: ReadOnlyWorldQuery, F: ReadOnlyWorldQuery> Unpin for QueryChangeFuture<'_, Q, F> {}
+
+impl<Q, F> Future for QueryChangeFuture<'_, Q, F>
+where
+    Q: ReadOnlyWorldQuery + 'static,
+    F: ReadOnlyWorldQuery + 'static,
+{
+    type Output = Entity;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.state {
+            QueryChangeState::Created => {
+                // Registering the components of the query requires the world exclusively
+                self.state = QueryChangeState::Exclusive;
+                self.scope.yield_(CoroStatus::Exclusive);
+                Poll::Pending
+            }
+            QueryChangeState::Exclusive => {
+                let this = &mut *self;
+                // SAFETY: The coroutine yielded `CoroStatus::Exclusive`, the executor only resumes
+                // it while no other coroutine runs, and no item of its parameters is borrowed
+                // across the await point that led here.
+                let world = unsafe { this.scope.exclusive_world() };
+                let mut access = FilteredAccess::default();
+                Q::update_component_access(&Q::init_state(world), &mut access);
+                F::update_component_access(&F::init_state(world), &mut access);
+                // Like when a system runs, the changes made from now on get a newer tick
+                let since = world.increment_change_tick();
+
+                // SAFETY: The metadata are only set while the coroutine is polled, and are not
+                // borrowed by anything else meanwhile
+                let meta = unsafe { &mut *this.scope.meta_ptr() };
+                // The query only runs in the executor, in between two resumes, so a write of the
+                // coroutine itself on one of the entities is not an actual conflict
+                for component in access.access().reads() {
+                    meta.access.add_read(SourceId::AllEntities, component);
+                }
+
+                let (sender, receiver) = sync_once_channel();
+                this.state = QueryChangeState::Waiting(receiver);
+                this.scope
+                    .yield_(CoroStatus::AnyChange(AnyChangeWait::query::<Q, F>(
+                        since, sender,
+                    )));
+                Poll::Pending
+            }
+            // We assume the executor will only poll it once the changed entities are sent
+            QueryChangeState::Waiting(receiver) => Poll::Ready(
+                receiver
+                    .try_recv()
+                    .expect("The coroutine was resumed before any entity changed")[0],
+            ),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project::pin_project;
+use tinyset::SetU64;
+
+use super::{
+    handle::{CoroHandle, HandleTuple, Status},
+    CoroState, CoroStatus, Scope,
+};
+
+/// A future resolving once `k` out of the coroutines finished, created with [`Scope::quorum`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct AwaitQuorum<'a, T> {
+    scope: &'a mut Scope,
+    handles: Vec<CoroHandle<T>>,
+    k: usize,
+    state: CoroState,
+}
+
+impl<'a, T> AwaitQuorum<'a, T> {
+    pub(crate) fn new(scope: &'a mut Scope, k: usize, handles: Vec<CoroHandle<T>>) -> Self {
+        Self {
+            scope,
+            k: k.min(handles.len()),
+            handles,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> Future for AwaitQuorum<'_, T> {
+    type Output = Vec<T>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let resumed = matches!(this.state, CoroState::Halted);
+        *this.state = CoroState::Halted;
+
+        let mut done = 0;
+        let mut canceled = false;
+        let mut set = SetU64::new();
+        for h in this.handles.iter_mut() {
+            match h.update_status() {
+                Status::Done => done += 1,
+                Status::StillWaiting(id) => set.extend(id),
+                _ => canceled = true,
+            }
+        }
+
+        // Once the quorum is reached, the executor already cancelled the others
+        if done >= *this.k {
+            *this.state = CoroState::Running;
+            let results = this.handles.iter_mut().filter_map(|h| h.try_fetch());
+            return Poll::Ready(results.collect());
+        }
+
+        if canceled {
+            this.scope.yield_(CoroStatus::Cancel);
+            return Poll::Pending;
+        }
+
+        // We assume the executor will only resume it once enough coroutines finished
+        if resumed {
+            panic!("The executor resumed a coroutine at the wrong time, this is a bug");
+        }
+
+        this.scope.yield_(CoroStatus::Quorum {
+            need: *this.k - done,
+            set,
+        });
+        Poll::Pending
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::prelude::{Component, Entity};
+
+use crate::{
+    executor::{
+        change_detection::{AnyChangeWait, ChangedEntities},
+        msg::CoroStatus,
+    },
+    SourceId,
+};
+
+use super::{
+    once_channel::{sync_once_channel, OnceRec},
+    scope::Scope,
+};
+
+/// A future resolving once an entity no longer has the component `T`, either because it was
+/// removed or because the entity was despawned. Created with [`Scope::on_component_removed`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ComponentRemovedFuture<'a, T: Component> {
+    scope: &'a mut Scope,
+    entity: Entity,
+    /// Set while waiting for the executor to detect a removal
+    receiver: Option<OnceRec<ChangedEntities>>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: Component> ComponentRemovedFuture<'a, T> {
+    pub(crate) fn new(scope: &'a mut Scope, entity: Entity) -> Self {
+        Self {
+            scope,
+            entity,
+            receiver: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+// The receiver is never pinned
+impl<T: Component> Unpin for ComponentRemovedFuture<'_, T> {}
+
+impl<T: Component> Future for ComponentRemovedFuture<'_, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // The removals of `T` from other entities wake it as well, hence checking again
+        this.receiver = None;
+        let world = this.scope.world_cell();
+        let Some(component_id) = world.components().component_id::<T>() else {
+            // No entity ever had it
+            return Poll::Ready(());
+        };
+        // Only the archetype of the entity is read, not the component
+        let removed = world
+            .get_entity(this.entity)
+            .is_none_or(|entity| !entity.contains_id(component_id));
+        if removed {
+            return Poll::Ready(());
+        }
+
+        // SAFETY: The metadata are only set while the coroutine is polled, and are not borrowed
+        // by anything else meanwhile
+        let meta = unsafe { &mut *this.scope.meta_ptr() };
+        let source = SourceId::Entity(this.entity);
+        if !meta.access.can_read(source, component_id) {
+            meta.access.add_read(source, component_id);
+        }
+
+        // Like when a system runs, the removals made from now on are detected after this tick
+        let since = world.increment_change_tick();
+        let (sender, receiver) = sync_once_channel();
+        this.receiver = Some(receiver);
+        this.scope
+            .yield_(CoroStatus::AnyChange(AnyChangeWait::removed::<T>(
+                since, sender,
+            )));
+        Poll::Pending
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::ecs::{
+    component::{ComponentId, ComponentTicks, Tick},
+    system::Resource,
+};
+
+use crate::{executor::msg::CoroStatus, SourceId};
+
+use super::scope::Scope;
+
+/// A future resolving once the resource `R` has been mutated.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ResourceChangeFuture<'a, R: Resource> {
+    scope: &'a mut Scope,
+    component_id: ComponentId,
+    last_change_tick: Tick,
+    _phantom: PhantomData<fn() -> R>,
+}
+
+impl<'a, R: Resource> ResourceChangeFuture<'a, R> {
+    /// # Panics
+    /// If the resource `R` was never inserted.
+    pub fn new(scope: &'a mut Scope) -> Self {
+        let world = scope.world_cell();
+        let component_id = world
+            .components()
+            .resource_id::<R>()
+            .expect("Cannot wait on the change of a resource which does not exist");
+        // Once removed, its insertion is the next change
+        let last_change_tick = resource_ticks(scope, component_id).map_or_else(
+            || scope.world_cell().increment_change_tick(),
+            |ticks| ticks.last_changed_tick(),
+        );
+
+        Self {
+            scope,
+            component_id,
+            last_change_tick,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Returns the change ticks of the resource with the given id, if it exists.
+fn resource_ticks(scope: &Scope, component_id: ComponentId) -> Option<ComponentTicks> {
+    // SAFETY: We only read the ticks, while the coroutine is running
+    unsafe {
+        scope
+            .world_cell()
+            .storages()
+            .resources
+            .get(component_id)
+            .and_then(|data| data.get_ticks())
+    }
+}
+
+// Nothing is ever pinned
+impl<R: Resource> Unpin for ResourceChangeFuture<'_, R> {}
+
+impl<'a, R: Resource> Future for ResourceChangeFuture<'a, R> {
+    type Output = &'a R;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // SAFETY: The metadata are only set while the coroutine is polled, and are not borrowed
+        // by anything else meanwhile
+        let meta = unsafe { &mut *this.scope.meta_ptr() };
+        if !meta.access.can_read(SourceId::World, this.component_id) {
+            meta.access.add_read(SourceId::World, this.component_id);
+        }
+
+        let this_run = this.scope.world_cell().change_tick();
+        // Removed meanwhile, it keeps waiting until it is inserted again
+        if let Some(changed) =
+            resource_ticks(this.scope, this.component_id).map(|ticks| ticks.last_changed_tick())
+        {
+            if changed.is_newer_than(this.last_change_tick, this_run) {
+                // SAFETY: The returned reference borrows the scope for `'a`, meaning the
+                // coroutine cannot yield while holding it, and the resource cannot be mutated in
+                // the meantime.
+                let scope: &'a Scope = unsafe { &*(this.scope as *const Scope) };
+                let resource = unsafe { scope.world_cell().get_resource::<R>().unwrap() };
+                return Poll::Ready(resource);
+            }
+            this.last_change_tick = changed;
+        }
+
+        this.scope.yield_(CoroStatus::Tick);
+        Poll::Pending
+    }
+}
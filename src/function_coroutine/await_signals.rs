@@ -0,0 +1,89 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::executor::msg::{CoroStatus, SignalId};
+
+use super::{
+    once_channel::{sync_once_channel, OnceRec},
+    scope::Scope,
+};
+
+/// A future resolving once all of the signals are emitted, with the signals in the order they
+/// were received. Created with [`Scope::all_signals`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AllSignalsFuture<'a> {
+    scope: &'a mut Scope,
+    signals: Vec<SignalId>,
+    all: bool,
+    receiver: Option<OnceRec<Vec<SignalId>>>,
+}
+
+impl<'a> AllSignalsFuture<'a> {
+    pub fn new(scope: &'a mut Scope, signals: Vec<SignalId>) -> Self {
+        Self::with_mode(scope, signals, true)
+    }
+
+    fn with_mode(scope: &'a mut Scope, signals: Vec<SignalId>, all: bool) -> Self {
+        assert!(
+            signals.len() <= 64,
+            "At most 64 signals can be awaited at the same time"
+        );
+        Self {
+            scope,
+            signals,
+            all,
+            receiver: None,
+        }
+    }
+}
+
+impl<'a> Future for AllSignalsFuture<'a> {
+    type Output = Vec<SignalId>;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &self.receiver {
+            // We assume the executor will only poll it once the signals are received
+            Some(receiver) => Poll::Ready(
+                receiver
+                    .try_recv()
+                    .expect("The coroutine was resumed before the signals were emitted"),
+            ),
+            None if self.signals.is_empty() => Poll::Ready(Vec::new()),
+            None => {
+                let (sender, receiver) = sync_once_channel();
+                self.receiver = Some(receiver);
+                let signals = std::mem::take(&mut self.signals);
+                let all = self.all;
+                self.scope.yield_(CoroStatus::Signals {
+                    signals,
+                    all,
+                    sender,
+                });
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// A future resolving with the first of the signals to be emitted. Created with
+/// [`Scope::any_signal_in`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AnySignalInFuture<'a>(AllSignalsFuture<'a>);
+
+impl<'a> AnySignalInFuture<'a> {
+    pub fn new(scope: &'a mut Scope, signals: Vec<SignalId>) -> Self {
+        assert!(!signals.is_empty(), "At least one signal must be awaited");
+        Self(AllSignalsFuture::with_mode(scope, signals, false))
+    }
+}
+
+impl<'a> Future for AnySignalInFuture<'a> {
+    type Output = SignalId;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|received| received[0])
+    }
+}
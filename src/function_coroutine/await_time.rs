@@ -7,6 +7,8 @@ use std::task::Context;
 use std::task::Poll;
 use std::time::Duration;
 
+use crate::executor::TickCount;
+
 use super::CoroState;
 use super::CoroStatus;
 use super::Scope;
@@ -53,6 +55,53 @@ impl Future for NextTick<'_> {
     }
 }
 
+/// The state of the [`Time`] resource and of the executor, when it was read with
+/// [`Scope::time`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeSnapshot {
+    /// The duration of the last frame, the same as returned by [`Scope::next_tick`]
+    pub delta: Duration,
+    /// The total time elapsed
+    pub elapsed: Duration,
+    /// The number of times the executor was ticked, see [`TickCount`](crate::executor::TickCount)
+    pub tick_count: u64,
+}
+
+/// A future letting the other ready coroutines run, before resuming during the same tick.
+/// Created with [`Scope::yield_to_scheduler`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct YieldToScheduler<'a> {
+    scope: &'a mut Scope,
+    state: CoroState,
+}
+
+impl<'a> YieldToScheduler<'a> {
+    pub fn new(scope: &'a mut Scope) -> Self {
+        YieldToScheduler {
+            scope,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl Future for YieldToScheduler<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        match self.state {
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                Poll::Ready(())
+            }
+            CoroState::Running => {
+                self.state = CoroState::Halted;
+                self.scope.yield_(CoroStatus::Cooperative);
+                Poll::Pending
+            }
+        }
+    }
+}
+
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct DurationFuture<'a> {
     scope: &'a mut Scope,
@@ -70,15 +119,41 @@ impl<'a> DurationFuture<'a> {
     }
 }
 
+/// How late a [`DurationFuture`] resolved, because the last frame was longer than what was left
+/// of the duration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MissedInfo {
+    /// The time elapsed since the duration was over
+    pub overshoot: Duration,
+    /// The number of whole durations that fit in the overshoot, which is how many periods were
+    /// skipped when waiting on the same duration in a loop
+    pub missed_periods: u32,
+}
+
+impl MissedInfo {
+    fn new(overshoot: Duration, duration: Duration) -> Self {
+        let missed_periods = match duration.as_nanos() {
+            0 => 0,
+            period => (overshoot.as_nanos() / period)
+                .try_into()
+                .unwrap_or(u32::MAX),
+        };
+        MissedInfo {
+            overshoot,
+            missed_periods,
+        }
+    }
+}
+
 impl Future for DurationFuture<'_> {
-    type Output = ();
+    type Output = MissedInfo;
 
     fn poll(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
         match self.state {
             // We assume the executor will only poll it once the duration is over
             CoroState::Halted => {
                 self.state = CoroState::Running;
-                Poll::Ready(())
+                Poll::Ready(MissedInfo::new(self.scope.overshoot(), self.duration))
             }
             CoroState::Running => {
                 self.state = CoroState::Halted;
@@ -89,3 +164,49 @@ impl Future for DurationFuture<'_> {
         }
     }
 }
+
+/// A future resolving during the tick where the [`TickCount`] reaches a given number, created
+/// with [`Scope::wait_for_exact_tick`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ExactTickFuture<'a> {
+    scope: &'a mut Scope,
+    tick: u64,
+    state: CoroState,
+}
+
+impl<'a> ExactTickFuture<'a> {
+    pub fn new(scope: &'a mut Scope, tick: u64) -> Self {
+        ExactTickFuture {
+            scope,
+            tick,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl Future for ExactTickFuture<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        match self.state {
+            // We assume the executor will only poll it once the tick is reached
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                Poll::Ready(())
+            }
+            CoroState::Running => {
+                // SAFETY: Only the tick count is read, while the coroutine is running
+                let tick_count = unsafe { self.scope.world_cell().get_resource::<TickCount>() }
+                    .map_or(0, |ticks| ticks.0);
+                if tick_count >= self.tick {
+                    return Poll::Ready(());
+                }
+
+                self.state = CoroState::Halted;
+                let status = CoroStatus::ExactTick(self.tick);
+                self.scope.yield_(status);
+                Poll::Pending
+            }
+        }
+    }
+}
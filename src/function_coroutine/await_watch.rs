@@ -0,0 +1,47 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::executor::{msg::CoroStatus, watch::WatchToken};
+
+use super::{scope::Scope, CoroState};
+
+/// A future resolving with the new value of a [`WatchToken`], once it changes.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WatchFuture<'a, T> {
+    scope: &'a mut Scope,
+    token: WatchToken<T>,
+    state: CoroState,
+}
+
+impl<'a, T> WatchFuture<'a, T> {
+    pub(crate) fn new(scope: &'a mut Scope, token: WatchToken<T>) -> Self {
+        Self {
+            scope,
+            token,
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl<'a, T: Clone + PartialEq + Send + Sync + 'static> Future for WatchFuture<'a, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.state {
+            // We assume the executor will only poll it once the value changed
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                Poll::Ready(self.token.get().unwrap())
+            }
+            CoroState::Running => {
+                self.state = CoroState::Halted;
+                let id = self.token.signal_id();
+                self.scope.yield_(CoroStatus::Signal(id));
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::ecs::{change_detection::DetectChanges, component::Tick};
+use bevy::prelude::{Component, Entity};
+
+use crate::{executor::msg::CoroStatus, SourceId};
+
+use super::scope::Scope;
+
+/// A future resolving once the component `T` of an entity is changed, created with
+/// [`Scope::with_changed_component`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WithChangedFuture<'a, T: Component> {
+    scope: &'a mut Scope,
+    entity: Entity,
+    created_at: Tick,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: Component> WithChangedFuture<'a, T> {
+    pub fn new(scope: &'a mut Scope, entity: Entity) -> Self {
+        // Like when a system runs, the changes made from now on get a newer tick
+        let created_at = scope.world_cell().increment_change_tick();
+
+        Self {
+            scope,
+            entity,
+            created_at,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+// Nothing is ever pinned
+impl<T: Component> Unpin for WithChangedFuture<'_, T> {}
+
+impl<'a, T: Component> Future for WithChangedFuture<'a, T> {
+    type Output = Option<&'a T>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // SAFETY: The returned reference borrows the scope for `'a`, meaning the coroutine cannot
+        // yield while holding it, and the component cannot be mutated in the meantime.
+        let scope: &'a Scope = unsafe { &*(this.scope as *const Scope) };
+        let world = scope.world_cell();
+        let this_run = world.change_tick();
+
+        if let Some(component_id) = world.components().component_id::<T>() {
+            // SAFETY: The metadata are only set while the coroutine is polled, and are not
+            // borrowed by anything else meanwhile
+            let meta = unsafe { &mut *this.scope.meta_ptr() };
+            let source = SourceId::Entity(this.entity);
+            if !meta.access.can_read(source, component_id) {
+                meta.access.add_read(source, component_id);
+            }
+        }
+
+        // SAFETY: The component is only read, while the coroutine is running, and the read was
+        // added to its access
+        let component = world
+            .get_entity(this.entity)
+            .and_then(|entity| unsafe { entity.get_ref::<T>() });
+
+        match component {
+            None => Poll::Ready(None),
+            // Compared to the tick this future was created at rather than to the last run, so
+            // several changes since then are only seen once
+            Some(component)
+                if component
+                    .last_changed()
+                    .is_newer_than(this.created_at, this_run) =>
+            {
+                Poll::Ready(Some(component.into_inner()))
+            }
+            Some(_) => {
+                this.scope.yield_(CoroStatus::Tick);
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::prelude::World;
+
+use super::{scope::Scope, CoroState, CoroStatus};
+
+/// A future running a closure with exclusive access to the [`World`], once the executor
+/// resumed the coroutine alone. Created with [`Scope::with_world`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WithWorld<'a, F> {
+    scope: &'a mut Scope,
+    f: Option<F>,
+    state: CoroState,
+}
+
+impl<'a, F> WithWorld<'a, F> {
+    pub(crate) fn new(scope: &'a mut Scope, f: F) -> Self {
+        Self {
+            scope,
+            f: Some(f),
+            state: CoroState::Running,
+        }
+    }
+}
+
+// The closure is never pinned
+impl<F> Unpin for WithWorld<'_, F> {}
+
+impl<R, F: FnOnce(&mut World) -> R> Future for WithWorld<'_, F> {
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.state {
+            // We assume the executor will only poll it while it holds the world exclusively
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                let f = self.f.take().unwrap();
+                // SAFETY: No other coroutine runs while this one is resumed, and the world is
+                // not borrowed by this one in between two accesses of its parameters
+                let world = unsafe { self.scope.exclusive_world() };
+                Poll::Ready(f(world))
+            }
+            CoroState::Running => {
+                self.state = CoroState::Halted;
+                self.scope.yield_(CoroStatus::Exclusive);
+                Poll::Pending
+            }
+        }
+    }
+}
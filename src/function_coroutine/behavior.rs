@@ -0,0 +1,152 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use bevy::utils::synccell::SyncCell;
+
+use crate::{
+    executor::{
+        msg::{SignalId, Wakeups},
+        Executor,
+    },
+    id_alloc::Id,
+};
+
+use super::{once_channel::OnceRec, scope::Scope, CoroStatus};
+
+/// The queue of messages sent to a behavior.
+pub(crate) struct MessageQueue<M>(Mutex<QueueState<M>>);
+
+struct QueueState<M> {
+    messages: VecDeque<M>,
+    /// The behavior, while it waits on the next message
+    receiver: Option<(Id, Wakeups)>,
+}
+
+impl<M> Default for MessageQueue<M> {
+    fn default() -> Self {
+        Self(Mutex::new(QueueState {
+            messages: VecDeque::new(),
+            receiver: None,
+        }))
+    }
+}
+
+/// Value representing a long-lived coroutine, started with
+/// [`Scope::start_behavior`]. Contrary to a [`CoroHandle`](super::handle::CoroHandle), it cannot
+/// be awaited, but can be used to send messages to the coroutine, or to pause it. When the handle
+/// is dropped, the coroutine is automatically dropped as well.
+pub struct BehaviorHandle<M> {
+    id: Id,
+    queue: Arc<MessageQueue<M>>,
+    /// Only kept for the coroutine to be cancelled once dropped, never accessed
+    _receiver: SyncCell<OnceRec<()>>,
+}
+
+impl<M: Send + 'static> BehaviorHandle<M> {
+    pub(crate) fn new(id: Id, queue: Arc<MessageQueue<M>>, receiver: OnceRec<()>) -> Self {
+        Self {
+            id,
+            queue,
+            _receiver: SyncCell::new(receiver),
+        }
+    }
+
+    /// Returns the id of the underlying coroutine.
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    /// Returns true if the coroutine is still running.
+    pub fn is_alive(&self, executor: &Executor) -> bool {
+        executor.contains(self.id)
+    }
+
+    /// Send a message to the coroutine, received via [`Scope::mailbox`]. Messages are received in
+    /// the order they were sent.
+    pub fn send(&self, message: M) {
+        let mut state = self.queue.0.lock().unwrap();
+        state.messages.push_back(message);
+        if let Some((id, wakeups)) = state.receiver.take() {
+            wakeups.wake(id);
+        }
+    }
+
+    /// Cancel the coroutine. Messages which have not been received yet are dropped.
+    pub fn cancel(self) {}
+
+    /// Pause the coroutine, it won't be resumed until [`BehaviorHandle::resume`] is called.
+    pub fn pause(&self, executor: &mut Executor) {
+        executor.pause_coroutine(self.id);
+    }
+
+    /// Resume the coroutine, if it was paused.
+    pub fn resume(&self, executor: &mut Executor) {
+        executor.resume_coroutine(self.id);
+    }
+}
+
+impl<M> Drop for BehaviorHandle<M> {
+    fn drop(&mut self) {
+        // Woken if it waits on a message, for the executor to find out it was cancelled
+        if let Some((id, wakeups)) = self.queue.0.lock().unwrap().receiver.take() {
+            wakeups.wake(id);
+        }
+    }
+}
+
+/// The receiving end of the messages sent with a [`BehaviorHandle`].
+pub struct Mailbox<'a, M> {
+    scope: &'a mut Scope,
+    queue: Arc<MessageQueue<M>>,
+}
+
+impl<'a, M> Mailbox<'a, M> {
+    pub(crate) fn new(scope: &'a mut Scope, queue: Arc<MessageQueue<M>>) -> Self {
+        Self { scope, queue }
+    }
+
+    /// Returns a future that resolve with the next message. If no messages are available, the
+    /// coroutine is woken as soon as one is sent.
+    pub fn next(self) -> NextMessage<'a, M> {
+        NextMessage {
+            scope: self.scope,
+            queue: self.queue,
+        }
+    }
+
+    /// Returns the next message if there is one, without waiting.
+    pub fn try_next(&self) -> Option<M> {
+        self.queue.0.lock().unwrap().messages.pop_front()
+    }
+}
+
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct NextMessage<'a, M> {
+    scope: &'a mut Scope,
+    queue: Arc<MessageQueue<M>>,
+}
+
+impl<M> Future for NextMessage<'_, M> {
+    type Output = M;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut state = this.queue.0.lock().unwrap();
+        if let Some(message) = state.messages.pop_front() {
+            state.receiver = None;
+            return Poll::Ready(message);
+        }
+
+        // Set under the same lock as the queue, so that a message sent meanwhile wakes it
+        let id = this.scope.coroutine_id();
+        state.receiver = Some((id, this.scope.wakeups()));
+        drop(state);
+        this.scope.yield_(CoroStatus::Signal(SignalId::wake(id)));
+        Poll::Pending
+    }
+}
@@ -0,0 +1,39 @@
+use std::{
+    any::Any,
+    future::Future,
+    panic::{AssertUnwindSafe, UnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project::pin_project;
+
+/// A future catching the panics of the future it wraps. Created with [`Scope::catch_unwind`].
+///
+/// [`Scope::catch_unwind`]: super::scope::Scope::catch_unwind
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct CatchUnwindFuture<F> {
+    #[pin]
+    future: F,
+}
+
+impl<F: Future + UnwindSafe> CatchUnwindFuture<F> {
+    pub fn new(future: F) -> Self {
+        Self { future }
+    }
+}
+
+impl<F: Future + UnwindSafe> Future for CatchUnwindFuture<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.project().future;
+        // The future is required to be `UnwindSafe` when created
+        match std::panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(value)) => Poll::Ready(Ok(value)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    prelude::{Commands, Resource, World},
+    utils::HashMap,
+};
+
+use crate::{global_channel::CommandChannel, id_alloc::Id};
+
+pub(crate) type CleanupFn = Box<dyn FnOnce(&mut CleanupScope<'_>) + Send>;
+
+/// The closures registered with [`Scope::on_cleanup`](super::scope::Scope::on_cleanup), by
+/// coroutine. Shared between the executor and the coroutines.
+#[derive(Resource, Default, Clone)]
+pub(crate) struct CleanupRegistry(Arc<Mutex<HashMap<Id, Vec<CleanupFn>>>>);
+
+impl CleanupRegistry {
+    pub(crate) fn register(&self, id: Id, f: CleanupFn) {
+        self.0.lock().unwrap().entry(id).or_default().push(f);
+    }
+
+    /// Take the closures registered by the coroutine `id`, in the order they must run.
+    pub(crate) fn take(&self, id: Id) -> Vec<CleanupFn> {
+        let mut cleanups = self.0.lock().unwrap().remove(&id).unwrap_or_default();
+        cleanups.reverse();
+        cleanups
+    }
+}
+
+/// What the closures registered with
+/// [`Scope::on_cleanup`](super::scope::Scope::on_cleanup) have access to, once their coroutine
+/// ended.
+pub struct CleanupScope<'a> {
+    id: Id,
+    world: &'a World,
+    commands_channel: &'a CommandChannel,
+}
+
+impl<'a> CleanupScope<'a> {
+    pub(crate) fn new(id: Id, world: &'a World, commands_channel: &'a CommandChannel) -> Self {
+        Self {
+            id,
+            world,
+            commands_channel,
+        }
+    }
+
+    /// Returns the id of the coroutine which ended.
+    pub fn coroutine_id(&self) -> Id {
+        self.id
+    }
+
+    /// Returns [`Commands`] applied with the ones of the coroutines, at the end of the tick.
+    pub fn commands(&self) -> Commands<'_, '_> {
+        self.commands_channel.commands(self.world.entities())
+    }
+
+    /// Returns the world, which can only be read.
+    pub fn world(&self) -> &World {
+        self.world
+    }
+}
@@ -0,0 +1,44 @@
+use std::any::TypeId;
+
+use bevy::{
+    ecs::component::{ComponentId, Components},
+    prelude::Component,
+};
+
+/// The components an entity had when [`Scope::components_of`](super::scope::Scope::components_of)
+/// was called. This is a snapshot, it is not updated when components are inserted or removed.
+#[derive(Default, Clone, Debug)]
+pub struct ComponentSet {
+    components: Vec<(ComponentId, Option<TypeId>)>,
+}
+
+impl ComponentSet {
+    pub(crate) fn new(ids: impl Iterator<Item = ComponentId>, components: &Components) -> Self {
+        Self {
+            components: ids
+                .map(|id| (id, components.get_info(id).and_then(|info| info.type_id())))
+                .collect(),
+        }
+    }
+
+    /// Returns true if the entity had the component `T`.
+    pub fn contains<T: Component>(&self) -> bool {
+        let type_id = TypeId::of::<T>();
+        self.components.iter().any(|(_, t)| *t == Some(type_id))
+    }
+
+    /// Returns true if the entity had the component `id`.
+    pub fn contains_id(&self, id: ComponentId) -> bool {
+        self.components.iter().any(|(c, _)| *c == id)
+    }
+
+    /// Returns the number of components the entity had.
+    pub fn count(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Iterate over the ids of the components the entity had.
+    pub fn iter(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.components.iter().map(|(id, _)| *id)
+    }
+}
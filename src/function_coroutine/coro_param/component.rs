@@ -8,6 +8,9 @@ use bevy::{
     prelude::{Component, Entity, Mut},
 };
 
+#[cfg(feature = "debug")]
+use crate::function_coroutine::history::ComponentHistory;
+
 use super::{on_change::ChangeTracker, CoroParam};
 
 /// A readonly reference to a [`Component`] from the owning [`Entity`].
@@ -51,15 +54,14 @@ impl<T: Component> Rd<T> {
     /// Return the current value of the [`Component`]. The result ([`InGuard`]) cannot be held
     /// accros any await.
     pub fn get<'a>(&'a self, scope: &'a Scope) -> &'a T {
+        self.try_get(scope).unwrap()
+    }
+
+    /// Like [`Rd::get`], but return [`None`] if the owner or its component is gone.
+    pub(crate) fn try_get<'a>(&'a self, scope: &'a Scope) -> Option<&'a T> {
         scope.check_ownership(self.scope_id);
-        unsafe {
-            scope
-                .world_cell()
-                .get_entity(self.owner)
-                .unwrap()
-                .get::<T>()
-                .unwrap()
-        }
+        // SAFETY: The read was declared when the parameter was initialized
+        unsafe { scope.get_component::<T>(self.owner) }
     }
 }
 
@@ -104,34 +106,40 @@ impl<T: Component> CoroParam for Wr<T> {
 
 impl<T: Component> Wr<T> {
     pub fn get<'a>(&'a mut self, scope: &'a Scope) -> &'a T {
-        scope.check_ownership(self.scope_id);
-        let value = unsafe {
-            scope
-                .world_cell()
-                .get_entity(self.owner)
-                .unwrap()
-                .get::<T>()
-                .unwrap()
-        };
-
-        value
+        self.try_get(scope).unwrap()
     }
 
     pub fn get_mut<'a>(&'a mut self, scope: &'a Scope) -> Mut<'a, T> {
-        scope.check_ownership(self.scope_id);
+        self.try_get_mut(scope).unwrap()
+    }
 
-        unsafe {
-            let cell = scope.world_cell();
-            let entity = cell.get_entity(self.owner).unwrap();
+    /// Like [`Wr::get`], but return [`None`] if the owner or its component is gone.
+    pub(crate) fn try_get<'a>(&'a mut self, scope: &'a Scope) -> Option<&'a T> {
+        scope.check_ownership(self.scope_id);
+        // SAFETY: The write was declared when the parameter was initialized, which covers reads
+        unsafe { scope.get_component::<T>(self.owner) }
+    }
 
-            if entity.contains::<ChangeTracker<T>>() {
-                scope.emit_signal(SignalId {
-                    signal_type: self.id,
-                    owner: Some(self.owner),
-                });
-            }
+    /// Like [`Wr::get_mut`], but return [`None`] if the owner or its component is gone.
+    pub(crate) fn try_get_mut<'a>(&'a mut self, scope: &'a Scope) -> Option<Mut<'a, T>> {
+        scope.check_ownership(self.scope_id);
+        // SAFETY: The write was declared when the parameter was initialized
+        let value = unsafe { scope.get_component_mut::<T>(self.owner)? };
+
+        let entity = scope.world_cell().get_entity(self.owner)?;
+        #[cfg(feature = "debug")]
+        // SAFETY: The history belongs to the component, and is only written along with it
+        if let Some(mut history) = unsafe { entity.get_mut::<ComponentHistory<T>>() } {
+            history.record(&value);
+        }
 
-            cell.get_entity(self.owner).unwrap().get_mut::<T>().unwrap()
+        let signal_id = SignalId::component(self.id, Some(self.owner));
+        // Not gated on `Scope::has_waiters` for tracked components, the coroutines observing
+        // the component later during this tick must still see this change
+        if entity.contains::<ChangeTracker<T>>() || scope.has_waiters(signal_id) {
+            scope.emit_signal(signal_id);
         }
+
+        Some(value)
     }
 }
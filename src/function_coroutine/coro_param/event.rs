@@ -0,0 +1,103 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::ecs::{
+    event::{Event, Events, ManualEventReader},
+    world::unsafe_world_cell::UnsafeWorldCell,
+};
+
+use crate::{
+    executor::msg::CoroStatus, function_coroutine::scope::Scope, id_alloc::Id, CoroMeta, SourceId,
+};
+
+use super::CoroParam;
+
+/// Reads the events `E` sent after the coroutine was started. Each [`Evt`] has its own cursor,
+/// so every coroutine reading `E` sees each event once, whatever the other coroutines and the
+/// systems reading them do.
+///
+/// Like with an [`EventReader`](bevy::prelude::EventReader), events are only kept for two
+/// updates of [`Events<E>`], which happen in the [`First`](bevy::prelude::First) schedule with
+/// `App::add_event`. An event sent before the executor ticks can be read during this frame and
+/// the next one, a coroutine not reading them for longer misses them.
+///
+/// Note that a Coroutine with such parameter will be canceled if the resource [`Events<E>`] is
+/// removed.
+pub struct Evt<E: Event> {
+    scope_id: Id,
+    reader: ManualEventReader<E>,
+}
+
+impl<E: Event> CoroParam for Evt<E> {
+    fn init(world: UnsafeWorldCell<'_>, coro_meta: &mut CoroMeta) -> Option<Self> {
+        let id = world.components().resource_id::<Events<E>>()?;
+        if !coro_meta.access.add_read(SourceId::World, id) {
+            return None;
+        }
+
+        // SAFETY: Only the number of events sent so far is read
+        let events = unsafe { world.get_resource::<Events<E>>()? };
+        Some(Self {
+            scope_id: coro_meta.id,
+            reader: events.get_reader_current(),
+        })
+    }
+
+    fn is_valid(world: UnsafeWorldCell<'_>, _coro_meta: &CoroMeta) -> bool {
+        // SAFETY: The resource is only checked for existence, while the executor validates the
+        // coroutines, before any of them is resumed
+        unsafe { world.get_resource::<Events<E>>() }.is_some()
+    }
+}
+
+impl<E: Event> Evt<E> {
+    /// Returns a future that resolve with the next event not read yet by this coroutine. It
+    /// checks again on each tick, until one is sent.
+    pub fn next<'a>(&'a mut self, scope: &'a mut Scope) -> NextEvent<'a, E> {
+        NextEvent { evt: self, scope }
+    }
+
+    /// Returns all the events not read yet by this coroutine, without waiting.
+    pub fn drain_pending<'a>(&'a mut self, scope: &'a Scope) -> impl Iterator<Item = &'a E> {
+        self.reader.iter(self.events(scope))
+    }
+
+    fn events<'a>(&self, scope: &'a Scope) -> &'a Events<E> {
+        scope.check_ownership(self.scope_id);
+        // SAFETY: The read was declared when the parameter was initialized, and the events are
+        // only read while the coroutine is running
+        unsafe { scope.get_resource::<Events<E>>().unwrap() }
+    }
+}
+
+/// A future resolving with the next event of an [`Evt`], created with [`Evt::next`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct NextEvent<'a, E: Event> {
+    evt: &'a mut Evt<E>,
+    scope: &'a mut Scope,
+}
+
+impl<'a, E: Event> Future for NextEvent<'a, E> {
+    type Output = &'a E;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // SAFETY: The returned reference borrows the scope for `'a`, meaning the coroutine
+        // cannot yield while holding it, and the events cannot be updated in the meantime.
+        let scope: &'a Scope = unsafe { &*(this.scope as *const Scope) };
+        let events = this.evt.events(scope);
+
+        // Only one event is read, the others stay pending for the next call
+        if let Some(event) = this.evt.reader.iter(events).next() {
+            // SAFETY: The event lives in the resource, borrowed for `'a` as well
+            let event: &'a E = unsafe { &*(event as *const E) };
+            return Poll::Ready(event);
+        }
+
+        this.scope.yield_(CoroStatus::Tick);
+        Poll::Pending
+    }
+}
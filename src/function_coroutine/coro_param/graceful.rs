@@ -0,0 +1,44 @@
+use std::ops::{Deref, DerefMut};
+
+use bevy::ecs::world::unsafe_world_cell::UnsafeWorldCell;
+
+use crate::CoroMeta;
+
+use super::CoroParam;
+
+/// Wraps a [`CoroParam`] such that once it becomes invalid (for instance because the owner lost
+/// the component of a [`Rd`](super::component::Rd)), the coroutine is not cancelled right away.
+/// Instead, it is resumed one last time, with the reason available via
+/// [`Scope::invalidation`](crate::function_coroutine::scope::Scope::invalidation). Awaiting
+/// anything from there cancels it for real.
+///
+/// Note that the wrapped parameter must not be accessed once invalidated, since the data it
+/// refers to might be gone.
+pub struct Graceful<P: CoroParam>(P);
+
+impl<P: CoroParam> CoroParam for Graceful<P> {
+    fn init(world: UnsafeWorldCell<'_>, coro_meta: &mut CoroMeta) -> Option<Self> {
+        let param = P::init(world, coro_meta)?;
+        coro_meta.graceful = true;
+
+        Some(Self(param))
+    }
+
+    fn is_valid(world: UnsafeWorldCell<'_>, coro_meta: &CoroMeta) -> bool {
+        P::is_valid(world, coro_meta)
+    }
+}
+
+impl<P: CoroParam> Deref for Graceful<P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<P: CoroParam> DerefMut for Graceful<P> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
@@ -3,14 +3,26 @@ use bevy::{ecs::world::unsafe_world_cell::UnsafeWorldCell, utils::all_tuples};
 use super::CoroMeta;
 
 pub mod component;
+pub mod event;
+pub mod graceful;
 pub mod on_change;
+pub mod opt;
 
 pub mod prelude {
     #[doc(hidden)]
     pub use super::component::{Rd, Wr};
 
+    #[doc(hidden)]
+    pub use super::event::Evt;
+
+    #[doc(hidden)]
+    pub use super::graceful::Graceful;
+
     #[doc(hidden)]
     pub use super::on_change::{ChangeTracker, OnChange};
+
+    #[doc(hidden)]
+    pub use super::opt::Opt;
 }
 
 /// A function taking a scope and 0 or many [`CoroParam`]
@@ -1,20 +1,45 @@
 use std::marker::PhantomData;
 
-use bevy::{ecs::world::unsafe_world_cell::UnsafeWorldCell, prelude::Component};
+use bevy::{
+    ecs::world::unsafe_world_cell::UnsafeWorldCell,
+    prelude::{AppTypeRegistry, Commands, Component, Entity, ReflectComponent, World},
+    reflect::{utility::GenericTypePathCell, Reflect, TypePath},
+    utils::{get_short_name, synccell::SyncCell},
+};
 
 use crate::{
     executor::msg::SignalId,
-    function_coroutine::{await_change::AwaitChange, scope::Scope},
+    function_coroutine::{
+        await_change::{AwaitChange, AwaitChangeWhen},
+        scope::Scope,
+    },
     CoroMeta,
 };
 
 use super::CoroParam;
 
-#[derive(Component)]
+/// Inserted on the entities whose component `T` is observed by a coroutine, for the changes of
+/// `T` to be signaled. It is registered in the [`AppTypeRegistry`] when first inserted by a
+/// coroutine, without requiring `T` to be reflected.
+#[derive(Component, Reflect)]
+#[reflect(Component, type_path = false)]
 pub struct ChangeTracker<T: Component> {
+    #[reflect(ignore)]
     _phantom: PhantomData<T>,
 }
 
+// Written by hand, so that `T` does not need to implement `TypePath`
+impl<T: Component> TypePath for ChangeTracker<T> {
+    fn type_path() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn short_type_path() -> &'static str {
+        static CELL: GenericTypePathCell = GenericTypePathCell::new();
+        CELL.get_or_insert::<Self, _>(|| get_short_name(Self::type_path()))
+    }
+}
+
 impl<T: Component> Default for ChangeTracker<T> {
     fn default() -> Self {
         Self::new()
@@ -27,6 +52,21 @@ impl<T: Component> ChangeTracker<T> {
             _phantom: PhantomData,
         }
     }
+
+    /// Queue the insertion of a [`ChangeTracker<T>`] on `entity`, unless it already has one once
+    /// the `commands` are applied. The tracker is registered in the [`AppTypeRegistry`] if any.
+    pub fn ensure_present(entity: Entity, commands: &mut Commands) {
+        commands.add(move |world: &mut World| {
+            if let Some(registry) = world.get_resource::<AppTypeRegistry>() {
+                registry.write().register::<ChangeTracker<T>>();
+            }
+            if let Some(mut entity) = world.get_entity_mut(entity) {
+                if !entity.contains::<ChangeTracker<T>>() {
+                    entity.insert(ChangeTracker::<T>::new());
+                }
+            }
+        });
+    }
 }
 
 pub struct OnChange<T: Component> {
@@ -38,6 +78,18 @@ impl<T: Component> OnChange<T> {
     pub fn observe<'a>(&self, scope: &'a mut Scope) -> AwaitChange<'a> {
         AwaitChange::new(scope, self.id)
     }
+
+    /// Same as [`OnChange::observe`], but only resolves once the `predicate` holds for the new
+    /// value of the component. The predicate is evaluated by the executor each time the component
+    /// changes, the coroutine is not resumed in the meantime.
+    pub fn observe_when<'a, F>(&self, scope: &'a mut Scope, predicate: F) -> AwaitChangeWhen<'a>
+    where
+        F: Fn(&T) -> bool + Send + 'static,
+    {
+        let owner = self.id.owner.unwrap();
+        let predicate = move |world: &World| world.get::<T>(owner).is_some_and(&predicate);
+        AwaitChangeWhen::new(scope, self.id, SyncCell::new(Box::new(predicate)))
+    }
 }
 
 impl<T: Component> CoroParam for OnChange<T> {
@@ -45,11 +97,17 @@ impl<T: Component> CoroParam for OnChange<T> {
         let id = world.components().component_id::<T>()?;
         let owner = coro_meta.owner?;
 
+        // The tracker is inserted once the commands are applied, changes made before that are
+        // not observed
+        let tracked = world
+            .get_entity(owner)
+            .is_some_and(|entity| entity.contains::<ChangeTracker<T>>());
+        if !tracked {
+            coro_meta.setup.push(ChangeTracker::<T>::ensure_present);
+        }
+
         Some(Self {
-            id: SignalId {
-                signal_type: id,
-                owner: Some(owner),
-            },
+            id: SignalId::component(id, Some(owner)),
             _phantom: PhantomData,
         })
     }
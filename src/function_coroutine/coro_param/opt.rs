@@ -0,0 +1,51 @@
+use bevy::{
+    ecs::world::unsafe_world_cell::UnsafeWorldCell,
+    prelude::{Component, Mut},
+};
+
+use crate::{function_coroutine::scope::Scope, CoroMeta};
+
+use super::{
+    component::{Rd, Wr},
+    CoroParam,
+};
+
+/// Wraps a [`Rd`] or a [`Wr`] such that the coroutine is not cancelled when the owner loses the
+/// component, the access then returns [`None`] instead.
+///
+/// Note that a Coroutine with such parameter is still canceled once the owning
+/// [`Entity`](bevy::prelude::Entity) does not exist anymore.
+pub struct Opt<P: CoroParam>(P);
+
+impl<P: CoroParam> CoroParam for Opt<P> {
+    fn init(world: UnsafeWorldCell<'_>, coro_meta: &mut CoroMeta) -> Option<Self> {
+        P::init(world, coro_meta).map(Self)
+    }
+
+    fn is_valid(world: UnsafeWorldCell<'_>, coro_meta: &CoroMeta) -> bool {
+        match coro_meta.owner {
+            Some(owner) => world.get_entity(owner).is_some(),
+            None => true,
+        }
+    }
+}
+
+impl<T: Component> Opt<Rd<T>> {
+    /// Return the current value of the [`Component`], or [`None`] if the owner does not have it.
+    pub fn get<'a>(&'a self, scope: &'a Scope) -> Option<&'a T> {
+        self.0.try_get(scope)
+    }
+}
+
+impl<T: Component> Opt<Wr<T>> {
+    /// Return the current value of the [`Component`], or [`None`] if the owner does not have it.
+    pub fn get<'a>(&'a mut self, scope: &'a Scope) -> Option<&'a T> {
+        self.0.try_get(scope)
+    }
+
+    /// Return a mutable reference to the [`Component`], or [`None`] if the owner does not have
+    /// it.
+    pub fn get_mut<'a>(&'a mut self, scope: &'a Scope) -> Option<Mut<'a, T>> {
+        self.0.try_get_mut(scope)
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    ecs::system::CommandQueue,
+    prelude::{Commands, Resource, World},
+};
+
+use super::scope::Scope;
+
+type DeferredFn = Box<dyn FnOnce(&mut Commands) + Send>;
+
+/// The closures of the dropped [`DeferGuard`], applied by the executor at the end of each tick.
+#[derive(Resource, Default, Clone)]
+pub(crate) struct DeferredCommands(Arc<Mutex<Vec<DeferredFn>>>);
+
+impl DeferredCommands {
+    /// Run the deferred closures, and apply the commands they queued.
+    pub(crate) fn apply(world: &mut World) {
+        let Some(deferred) = world.get_resource::<DeferredCommands>() else {
+            return;
+        };
+        let deferred = std::mem::take(&mut *deferred.0.lock().unwrap());
+        if deferred.is_empty() {
+            return;
+        }
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        for f in deferred {
+            f(&mut commands);
+        }
+        queue.apply(world);
+    }
+}
+
+/// Runs a closure with [`Commands`] once dropped, whether the coroutine completed or got
+/// cancelled. Created with [`Scope::defer`].
+#[must_use = "the closure runs as soon as the guard is dropped"]
+pub struct DeferGuard {
+    f: Option<DeferredFn>,
+    // Owned, unlike the channels of the scope which are only valid while the coroutine runs
+    sink: DeferredCommands,
+}
+
+impl DeferGuard {
+    pub(crate) fn new(scope: &Scope, f: impl FnOnce(&mut Commands) + Send + 'static) -> Self {
+        // SAFETY: Only the sink is read, which is shared behind a mutex
+        let sink = unsafe { scope.world_cell().get_resource::<DeferredCommands>() }
+            .expect("The deferred commands are inserted by the executor")
+            .clone();
+
+        Self {
+            f: Some(Box::new(f)),
+            sink,
+        }
+    }
+
+    /// Drop the guard without running its closure.
+    pub fn disarm(mut self) {
+        self.f = None;
+    }
+
+    /// Run the closure right away with `commands`, instead of once dropped.
+    pub(crate) fn run(mut self, commands: &mut Commands) {
+        if let Some(f) = self.f.take() {
+            f(commands);
+        }
+    }
+}
+
+impl Drop for DeferGuard {
+    fn drop(&mut self) {
+        if let Some(f) = self.f.take() {
+            self.sink.0.lock().unwrap().push(f);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::{future::Future, pin::Pin};
+
+use super::scope::Scope;
+
+/// A trigger created by [`Every`] for each iteration, borrowing the [`Scope`] until it resolves.
+pub type EveryFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A future running a body until it breaks, created with [`Scope::repeat`]. It resolves with
+/// the value it broke with, or with [`None`] once the iterations are exhausted.
+pub type RepeatFuture<'a, R> = EveryFuture<'a, Option<R>>;
+
+/// Resolves each time a fresh trigger does, created with [`Scope::every`]. It never ends on its
+/// own, the iterations stop once it is no longer awaited.
+pub struct Every<'a, F> {
+    scope: &'a mut Scope,
+    factory: F,
+}
+
+impl<'a, T, F> Every<'a, F>
+where
+    F: FnMut(&mut Scope) -> EveryFuture<'_, T>,
+{
+    pub(crate) fn new(scope: &'a mut Scope, factory: F) -> Self {
+        Self { scope, factory }
+    }
+
+    /// Returns a future that resolve with the output of a new trigger, once it completes.
+    // Not an `Iterator`, each item is a future borrowing the scope
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> EveryFuture<'_, T> {
+        (self.factory)(self.scope)
+    }
+
+    /// The [`Scope`] borrowed by the triggers, usable between two iterations.
+    pub fn scope(&mut self) -> &mut Scope {
+        self.scope
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::time::Time;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use super::CoroState;
+use super::CoroStatus;
+use super::Scope;
+
+/// A future resolving at a fixed rate, whatever the actual frame rate is. Created with
+/// [`Scope::await_frame_sync`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct FrameSyncFuture<'a> {
+    scope: &'a mut Scope,
+    period: Duration,
+    state: CoroState,
+}
+
+impl<'a> FrameSyncFuture<'a> {
+    pub fn new(scope: &'a mut Scope, target_fps: f32) -> Self {
+        assert!(
+            target_fps > 0.0,
+            "The target frame rate must be strictly positive"
+        );
+        FrameSyncFuture {
+            scope,
+            period: Duration::from_secs_f32(1.0 / target_fps),
+            state: CoroState::Running,
+        }
+    }
+
+    /// Consume one period from the accumulated time, if enough time was accumulated.
+    fn try_consume(&mut self) -> bool {
+        let acc = self.scope.frame_sync_mut();
+        if *acc >= self.period {
+            *acc -= self.period;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Future for FrameSyncFuture<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        if self.state == CoroState::Halted {
+            // SAFETY: See [`Executor`]
+            let dt = unsafe {
+                (self.scope.world_cell())
+                    .get_resource::<Time>()
+                    .unwrap()
+                    .delta()
+            };
+            *self.scope.frame_sync_mut() += dt;
+        }
+
+        // The overshoot of a previous wait might already be enough
+        if self.try_consume() {
+            self.state = CoroState::Running;
+            Poll::Ready(())
+        } else {
+            self.state = CoroState::Halted;
+            self.scope.yield_(CoroStatus::Tick);
+            Poll::Pending
+        }
+    }
+}
@@ -8,6 +8,14 @@ use super::once_channel::OnceRec;
 
 /// Value representing an ongoing coroutine. Can be used to await it's result, or cancel the
 /// underlying coroutine by dropping it.
+///
+/// A handle can be moved to another thread, like the coroutine holding it, but cannot be shared
+/// between threads: its channel is not synchronized, and is only read by one coroutine at a time.
+///
+/// ```compile_fail
+/// fn is_sync<T: Sync>() {}
+/// is_sync::<corentin::prelude::CoroHandle<()>>();
+/// ```
 pub enum CoroHandle<T> {
     Waiting { id: Id, receiver: OnceRec<T> },
     Done(T),
@@ -15,9 +23,121 @@ pub enum CoroHandle<T> {
     Finish,
 }
 
+impl<T> CoroHandle<T> {
+    /// Returns the id of the coroutine, if it was still running when this handle was last
+    /// updated, see [`Scope::coroutine_id`](super::scope::Scope::coroutine_id).
+    pub fn id(&self) -> Option<Id> {
+        match self {
+            CoroHandle::Waiting { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Take the result of the coroutine, if it finished.
+    pub fn try_take(&mut self) -> Option<T> {
+        match self.update_status() {
+            Status::Done => self.try_fetch(),
+            _ => None,
+        }
+    }
+
+    /// Turn this handle into a [`WeakCoroHandle`], which does not keep the coroutine alive. The
+    /// coroutine keeps running, and its result is discarded unless the handle is upgraded back
+    /// in the meantime.
+    pub fn downgrade(self) -> WeakCoroHandle<T> {
+        match self {
+            CoroHandle::Waiting { id, receiver } => {
+                receiver.detach();
+                WeakCoroHandle {
+                    inner: Some((id, receiver)),
+                }
+            }
+            _ => WeakCoroHandle { inner: None },
+        }
+    }
+}
+
+/// A handle to a coroutine which does not keep it alive, see [`CoroHandle::downgrade`].
+pub struct WeakCoroHandle<T> {
+    inner: Option<(Id, OnceRec<T>)>,
+}
+
+impl<T> WeakCoroHandle<T> {
+    /// Returns the id of the coroutine, if it did not finish when this handle was created.
+    pub fn id(&self) -> Option<Id> {
+        self.inner.as_ref().map(|(id, _)| *id)
+    }
+
+    /// Returns true if the coroutine has not finished nor been cancelled yet.
+    pub fn is_running(&self) -> bool {
+        self.inner
+            .as_ref()
+            .is_some_and(|(_, receiver)| receiver.is_pending())
+    }
+
+    /// Turn this handle back into a [`CoroHandle`], keeping the coroutine alive again. Returns
+    /// [`None`] if the coroutine already finished or was cancelled.
+    pub fn upgrade(self) -> Option<CoroHandle<T>> {
+        let (id, receiver) = self.inner?;
+
+        if !receiver.is_pending() {
+            return None;
+        }
+
+        receiver.attach();
+        Some(CoroHandle::Waiting { id, receiver })
+    }
+}
+
+/// Handle to a coroutine started with [`Scope::fork`](super::scope::Scope::fork). Like a
+/// [`CoroHandle`], dropping it cancels the coroutine, unless it was detached first.
+pub struct ForkHandle<T> {
+    handle: CoroHandle<T>,
+}
+
+impl<T> ForkHandle<T> {
+    pub(crate) fn new(handle: CoroHandle<T>) -> Self {
+        Self { handle }
+    }
+
+    /// Returns the id of the coroutine, if it did not finish when it was forked.
+    pub fn id(&self) -> Option<Id> {
+        self.handle.id()
+    }
+
+    /// Returns true if the coroutine has not finished nor been cancelled yet.
+    pub fn is_running(&self) -> bool {
+        match &self.handle {
+            CoroHandle::Waiting { receiver, .. } => receiver.is_pending(),
+            _ => false,
+        }
+    }
+
+    /// Let the coroutine run independently, it is not cancelled anymore when this handle (or
+    /// the scope which forked it) is dropped. It can still be joined later on.
+    pub fn detach(&mut self) {
+        if let CoroHandle::Waiting { receiver, .. } = &self.handle {
+            receiver.detach();
+        }
+    }
+
+    /// Turn this handle back into a regular [`CoroHandle`], which can be awaited and cancels the
+    /// coroutine when dropped, even if it was detached. The result is kept if the coroutine
+    /// already finished.
+    pub fn join(self) -> CoroHandle<T> {
+        if let CoroHandle::Waiting { receiver, .. } = &self.handle {
+            receiver.attach();
+        }
+        self.handle
+    }
+}
+
 /// Trait so that we can have function generic over a tuple of handles, like await all.
 pub trait HandleTuple {
     type Output;
+    /// The output when only some of the coroutines finished, see
+    /// [`Scope::all_within`](super::scope::Scope::all_within).
+    type Partial;
 
     /// Update the status of each handles,
     fn update_status(&mut self) -> Status;
@@ -25,6 +145,9 @@ pub trait HandleTuple {
     // Try to fetch the result from the coroutines in this tuple. If any of the handle is not in
     // the [`CoroHandle::Done`] state, this returns [`None`].
     fn try_fetch(&mut self) -> Option<Self::Output>;
+
+    /// Take the result of each finished coroutine of this tuple.
+    fn take_partial(&mut self) -> Self::Partial;
 }
 
 pub enum Status {
@@ -53,6 +176,7 @@ impl Status {
 
 impl<T> HandleTuple for CoroHandle<T> {
     type Output = T;
+    type Partial = Option<T>;
 
     fn update_status(&mut self) -> Status {
         match self {
@@ -93,6 +217,29 @@ impl<T> HandleTuple for CoroHandle<T> {
             _ => None,
         }
     }
+
+    fn take_partial(&mut self) -> Self::Partial {
+        self.try_take()
+    }
+}
+
+impl<H: HandleTuple> HandleTuple for Vec<H> {
+    type Output = Vec<H::Output>;
+    type Partial = Vec<H::Partial>;
+
+    fn update_status(&mut self) -> Status {
+        self.iter_mut().fold(Status::Done, |status, h| {
+            status.combine(|| h.update_status())
+        })
+    }
+
+    fn try_fetch(&mut self) -> Option<Self::Output> {
+        self.iter_mut().map(|h| h.try_fetch()).collect()
+    }
+
+    fn take_partial(&mut self) -> Self::Partial {
+        self.iter_mut().map(|h| h.take_partial()).collect()
+    }
 }
 
 macro_rules! impl_handler_tuple {
@@ -100,6 +247,7 @@ macro_rules! impl_handler_tuple {
         #[allow(non_snake_case)]
         impl<$first: HandleTuple, $($param: HandleTuple),*> HandleTuple for ($first, $($param,)*) {
             type Output = ($first::Output, $($param::Output,)*);
+            type Partial = ($first::Partial, $($param::Partial,)*);
 
 
             /// Update the status of each handles,
@@ -115,6 +263,11 @@ macro_rules! impl_handler_tuple {
                 Some((first.try_fetch()?, $($param.try_fetch()?,)*))
 
             }
+
+            fn take_partial(&mut self) -> Self::Partial {
+                let (first, $($param,)*) = self;
+                (first.take_partial(), $($param.take_partial(),)*)
+            }
         }
     };
 }
@@ -0,0 +1,68 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::Component;
+
+/// Keeps the last values of the component `T` of an entity, recorded each time it is mutated
+/// through a [`Wr`](super::coro_param::component::Wr). Enabled with
+/// [`Scope::enable_history`](super::scope::Scope::enable_history).
+#[derive(Component)]
+pub struct ComponentHistory<T: Component> {
+    values: VecDeque<T>,
+    capacity: usize,
+    snapshot: fn(&T) -> T,
+}
+
+impl<T: Component + Clone> ComponentHistory<T> {
+    /// Creates an empty history, keeping at most `capacity` values.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+            snapshot: T::clone,
+        }
+    }
+}
+
+impl<T: Component> ComponentHistory<T> {
+    /// Record the value the component had right before being mutated.
+    pub(crate) fn record(&mut self, value: &T) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back((self.snapshot)(value));
+    }
+
+    /// Returns a copy of the last `n` recorded values.
+    pub(crate) fn last(&self, n: usize) -> Self {
+        let skip = self.values.len().saturating_sub(n);
+
+        Self {
+            values: self.values.iter().skip(skip).map(self.snapshot).collect(),
+            capacity: n.min(self.capacity),
+            snapshot: self.snapshot,
+        }
+    }
+
+    /// Iterate over the recorded values, from the oldest to the most recent.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.values.iter()
+    }
+
+    /// The number of recorded values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The maximum number of values kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
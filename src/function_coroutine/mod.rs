@@ -1,10 +1,15 @@
 use bevy::ecs::world::World;
+use bevy::log::error;
+use bevy::prelude::{Commands, Component, Entity};
+use bevy::time::Time;
 
 use bevy::ecs::world::unsafe_world_cell::UnsafeWorldCell;
 use bevy::utils::all_tuples;
+use std::fmt::{self, Display};
 use std::future::Future;
 
 use std::pin::Pin;
+use std::time::Duration;
 
 use std::ptr::null;
 use std::ptr::null_mut;
@@ -13,9 +18,12 @@ use std::task::Poll;
 
 use pin_project::pin_project;
 
+use crate::executor::msg::CancelMsg;
 use crate::executor::msg::EmitMsg;
+use crate::executor::msg::LocalEntityMsg;
 use crate::executor::msg::NewCoroutine;
 use crate::executor::msg::YieldMsg;
+use crate::executor::{ScopeStorage, SignalWaiters};
 use crate::global_channel::Channel;
 use crate::global_channel::CommandChannel;
 
@@ -26,30 +34,96 @@ use self::scope::Scope;
 
 use super::CoroAccess;
 use super::CoroMeta;
+use super::SourceId;
 
-use super::executor::msg::CoroStatus;
+use super::executor::msg::{CancelReason, CoroStatus};
 
 use super::id_alloc::Id;
 use super::id_alloc::Ids;
 use super::Coroutine;
 
 pub mod await_all;
+pub mod await_all_within;
+pub mod await_any_change;
+pub mod await_any_signal;
+pub mod await_asset;
 pub mod await_change;
+pub mod await_changed_by_other;
+pub mod await_event;
 pub mod await_first;
+pub mod await_has_all;
+pub mod await_marked;
+pub mod await_n_signals;
+pub mod await_phase;
+pub mod await_query;
+pub mod await_quorum;
+pub mod await_removed;
+pub mod await_resource;
 pub mod await_signal;
+pub mod await_signals;
 pub mod await_time;
+pub mod await_watch;
+pub mod await_with_changed;
+pub mod await_world;
+pub mod behavior;
+pub mod catch_unwind;
+pub mod cleanup;
+pub mod component_set;
 pub mod coro_param;
+pub mod defer;
+pub mod every;
+pub mod frame_sync;
 pub mod handle;
+#[cfg(feature = "debug")]
+pub mod history;
+pub mod mutex;
 pub mod once_channel;
+pub mod rate_limit;
+pub mod resource_lock;
 pub mod resume;
+#[cfg(feature = "rand")]
+pub mod rng;
+pub mod rollback;
 pub mod scope;
+pub mod scope_resource;
+pub mod state_machine;
+pub mod stopwatch;
+pub mod thread;
+pub mod throttle;
+pub mod unique;
 
 pub mod prelude {
     #[doc(hidden)]
     pub use super::scope::Scope;
 
     #[doc(hidden)]
-    pub use super::handle::CoroHandle;
+    pub use super::handle::{CoroHandle, ForkHandle, WeakCoroHandle};
+
+    #[doc(hidden)]
+    pub use super::behavior::BehaviorHandle;
+
+    #[doc(hidden)]
+    pub use super::component_set::ComponentSet;
+
+    #[doc(hidden)]
+    pub use super::stopwatch::CoroStopwatch;
+
+    #[doc(hidden)]
+    pub use super::throttle::Throttle;
+
+    #[doc(hidden)]
+    pub use super::state_machine::{CoroutineStateMachine, CoroutineStatus, EntityCoroutine};
+
+    #[doc(hidden)]
+    pub use super::mutex::{CoroMutex, CoroSemaphore};
+
+    #[doc(hidden)]
+    #[cfg(feature = "debug")]
+    pub use super::history::ComponentHistory;
+
+    #[doc(hidden)]
+    #[cfg(feature = "rand")]
+    pub use super::rng::{CoroRng, CoroSeed};
 
     #[doc(hidden)]
     pub use super::coro_param::prelude::*;
@@ -65,7 +139,94 @@ where
     id: Id,
     resume_param: Resume<ResumeParam>,
     meta: CoroMeta,
-    result_sender: Option<OnceSender<T>>,
+    result_sender: Option<ResultSender<T>>,
+    invalidation: Option<CancelReason>,
+    overshoot: Duration,
+}
+
+/// Where the result of a [`FunctionCoroutine`] goes once it finishes.
+pub(crate) enum ResultSender<T> {
+    /// Sent to the [`CoroHandle`](handle::CoroHandle) of the coroutine.
+    Handle(OnceSender<T>),
+    /// Inserted as a component on the given entity, with the given function.
+    Insert(Entity, fn(&mut Commands, Entity, T)),
+}
+
+impl<T> ResultSender<T> {
+    fn send(self, value: T, commands: &CommandChannel, world: &World) {
+        match self {
+            ResultSender::Handle(sender) => sender.send(value),
+            ResultSender::Insert(entity, insert) => {
+                insert(&mut commands.commands(world.entities()), entity, value)
+            }
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            ResultSender::Handle(sender) => sender.is_alive(),
+            ResultSender::Insert(_, _) => true,
+        }
+    }
+}
+
+/// Insert `value` on `entity`, if it still exists once the command is applied.
+pub(crate) fn insert_result<T: Component>(commands: &mut Commands, entity: Entity, value: T) {
+    commands.add(move |world: &mut World| {
+        if let Some(mut entity) = world.get_entity_mut(entity) {
+            entity.insert(value);
+        }
+    });
+}
+
+/// Why the parameters of a coroutine could not be initialized, see [`FunctionCoroutine::new`].
+#[derive(Debug)]
+pub(crate) enum InvalidParams {
+    /// They access the component with this name more than once, with at least one write
+    Conflict(String),
+    /// They are not available, because the owner or one of its components is missing for
+    /// instance. Holds the type name of the parameters.
+    Unavailable(&'static str),
+}
+
+impl InvalidParams {
+    /// Panics because the coroutine `C` cannot be started, for the paths where this is a bug.
+    pub(crate) fn panic<C>(self) -> ! {
+        panic!(
+            "Cannot start the coroutine `{}`, {self}. Use `try_start` or `start_or_cancel` to \
+            handle this case.",
+            std::any::type_name::<C>(),
+        )
+    }
+
+    /// Panics in debug builds if the parameters of the coroutine `C` conflict. Used by the
+    /// commands, which cannot report that the coroutine was not started, while such a signature
+    /// would never run.
+    pub(crate) fn debug_assert_no_conflict<C>(&self) {
+        #[cfg(debug_assertions)]
+        if let Self::Conflict(component) = self {
+            panic!(
+                "The coroutine `{}` has conflicting parameters, accessing `{component}` more \
+                than once with at least one write",
+                std::any::type_name::<C>(),
+            );
+        }
+    }
+}
+
+impl Display for InvalidParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conflict(component) => write!(
+                f,
+                "its parameters access `{component}` more than once with at least one write"
+            ),
+            Self::Unavailable(params) => write!(
+                f,
+                "its parameters `{params}` are unavailable (missing owner or component)"
+            ),
+        }
+    }
 }
 
 pub trait CoroutineParamFunction<Marker, T>: Send + 'static {
@@ -81,15 +242,35 @@ unsafe impl<Marker, F, T> Send for FunctionCoroutine<Marker, F, T> where
 {
 }
 
-const ERR_WRONGAWAIT: &str = "A coroutine yielded without notifying the executor
-the reason. That is most likely because it awaits a
-future which is not part of this library.";
+/// Log why the coroutine is cancelled when it yielded without notifying the executor the reason,
+/// which is most likely because it awaited a future which is not part of this library. Only this
+/// coroutine is cancelled, instead of crashing the whole app.
+fn wrong_await(meta: &CoroMeta) -> CoroStatus {
+    error!("{}", wrong_await_message(meta.name, meta.owner));
+    CoroStatus::WrongAwait
+}
+
+/// The error logged by [`wrong_await`]. With the `debug` feature, it contains the backtrace of
+/// where the coroutine was resumed from, since the future it awaited already returned.
+pub(crate) fn wrong_await_message(name: &str, owner: Option<Entity>) -> String {
+    let owner = owner.map_or(String::new(), |owner| format!(" (owned by {owner:?})"));
+    #[allow(unused_mut)]
+    let mut message = format!(
+        "The coroutine `{name}`{owner} yielded without notifying the executor the reason, it is \
+        cancelled. That is most likely because it awaits a future which is not part of this \
+        library."
+    );
+    #[cfg(feature = "debug")]
+    message.push_str(&format!("\n{}", std::backtrace::Backtrace::force_capture()));
+    message
+}
 
 impl<Marker: 'static, F, T> Coroutine for FunctionCoroutine<Marker, F, T>
 where
     T: Send + Sync + 'static,
     F: CoroutineParamFunction<Marker, T>,
 {
+    #[allow(clippy::too_many_arguments)]
     fn resume(
         self: Pin<&mut Self>,
         world: &mut World,
@@ -97,7 +278,11 @@ where
         curr_node: usize,
         emit_channel: &Channel<EmitMsg>,
         new_coro_channel: &Channel<NewCoroutine>,
+        local_entity_channel: &Channel<LocalEntityMsg>,
+        cancel_channel: &Channel<CancelMsg>,
         commands_channel: &CommandChannel,
+        waiters: &SignalWaiters,
+        storage: &mut ScopeStorage,
     ) -> CoroStatus {
         // TODO remove copy paste
         let waker = waker::create();
@@ -111,7 +296,11 @@ where
         let ids = ids as *const _;
         let emit_channel = emit_channel as *const _;
         let new_coro_channel = new_coro_channel as *const _;
+        let local_entity_channel = local_entity_channel as *const _;
+        let cancel_channel = cancel_channel as *const _;
         let commands_channel = commands_channel as *const _;
+        let waiters = waiters as *const _;
+        let storage = storage as *mut _;
 
         // Safety: The only unsafe operations are swapping the resume arguments back and forth
         // All the pointers are valid since we get them from references, and we are never doing
@@ -124,7 +313,14 @@ where
                 yield_sender: None,
                 emit_channel,
                 new_coro_channel,
+                local_entity_channel,
+                cancel_channel,
                 commands_channel,
+                waiters,
+                storage,
+                invalidation: *this.invalidation,
+                overshoot: std::mem::take(this.overshoot),
+                meta: this.meta as *mut _,
             });
 
             let res = this.future.poll(&mut cx);
@@ -134,7 +330,7 @@ where
                     assert!(this.resume_param.scope_droped());
 
                     if let Some(sender) = this.result_sender.take() {
-                        sender.send(t);
+                        sender.send(t, &*commands_channel, &*world);
                     }
                     CoroStatus::Done
                 }
@@ -144,7 +340,7 @@ where
                         .get_mut()
                         .yield_sender
                         .take()
-                        .expect(ERR_WRONGAWAIT);
+                        .unwrap_or_else(|| wrong_await(this.meta));
                     this.resume_param.set(ResumeParam::new());
                     status
                 }
@@ -159,7 +355,11 @@ where
         curr_node: usize,
         emit_channel: &Channel<EmitMsg>,
         new_coro_channel: &Channel<NewCoroutine>,
+        local_entity_channel: &Channel<LocalEntityMsg>,
+        cancel_channel: &Channel<CancelMsg>,
         commands_channel: &CommandChannel,
+        waiters: &SignalWaiters,
+        storage: &mut ScopeStorage,
         yield_channel: &Channel<YieldMsg>,
     ) {
         let waker = waker::create();
@@ -173,7 +373,11 @@ where
         let ids = ids as *const _;
         let emit_channel = emit_channel as *const _;
         let new_coro_channel = new_coro_channel as *const _;
+        let local_entity_channel = local_entity_channel as *const _;
+        let cancel_channel = cancel_channel as *const _;
         let commands_channel = commands_channel as *const _;
+        let waiters = waiters as *const _;
+        let storage = storage as *mut _;
 
         // Safety: The only unsafe operations are swapping the resume arguments back and forth
         // All the pointers are valid since we get them from references, and we are never doing
@@ -186,7 +390,14 @@ where
                 yield_sender: None,
                 emit_channel,
                 new_coro_channel,
+                local_entity_channel,
+                cancel_channel,
                 commands_channel,
+                waiters,
+                storage,
+                invalidation: *this.invalidation,
+                overshoot: std::mem::take(this.overshoot),
+                meta: this.meta as *mut _,
             });
 
             let res = this.future.poll(&mut cx);
@@ -194,7 +405,7 @@ where
             match res {
                 Poll::Ready(t) => {
                     if let Some(sender) = this.result_sender.take() {
-                        sender.send(t);
+                        sender.send(t, &*commands_channel, &*world);
                     }
                     yield_channel.send(YieldMsg {
                         id: *this.id,
@@ -208,7 +419,7 @@ where
                         .get_mut()
                         .yield_sender
                         .take()
-                        .expect(ERR_WRONGAWAIT);
+                        .unwrap_or_else(|| wrong_await(this.meta));
                     this.resume_param.set(ResumeParam::new());
                     yield_channel.send(YieldMsg {
                         id: *this.id,
@@ -221,18 +432,31 @@ where
     }
 
     fn is_valid(&self, world: &World) -> bool {
+        // The parameters refer to the components and entities of another world
+        if self.meta.world_id != Some(world.id()) {
+            return false;
+        }
+
         if let Some(sender) = &self.result_sender {
             if !sender.is_alive() {
                 return false;
             }
         }
 
-        return F::Params::is_valid(world.as_unsafe_world_cell_readonly(), &self.meta);
+        F::Params::is_valid(world.as_unsafe_world_cell_readonly(), &self.meta)
     }
 
     fn meta(&self) -> &CoroMeta {
         &self.meta
     }
+
+    fn invalidate(self: Pin<&mut Self>, reason: CancelReason) {
+        *self.project().invalidation = Some(reason);
+    }
+
+    fn set_overshoot(self: Pin<&mut Self>, overshoot: Duration) {
+        *self.project().overshoot = overshoot;
+    }
 }
 
 mod waker {
@@ -261,26 +485,56 @@ where
     pub(crate) fn new(
         scope: Scope,
         world_cell: UnsafeWorldCell,
+        mut commands: Commands,
         resume_param: Resume<ResumeParam>,
         id: Id,
-        result_sender: Option<OnceSender<T>>,
+        result_sender: Option<ResultSender<T>>,
         f: F,
-    ) -> Option<Self> {
+    ) -> Result<Self, InvalidParams> {
         let mut meta = CoroMeta {
             owner: scope.owner(),
             access: CoroAccess::default(),
             id,
+            name: std::any::type_name::<F>(),
+            graceful: false,
+            // SAFETY: Only the time is read, while the world is not mutated
+            spawned_at: unsafe { world_cell.get_resource::<Time>() }
+                .map_or(Duration::ZERO, |time| time.elapsed()),
+            setup: Vec::new(),
+            world_id: Some(world_cell.id()),
         };
 
-        let params = F::Params::init(world_cell, &mut meta)?;
+        // Every coroutine may read the time, see `Scope::time`
+        if let Some(time_id) = world_cell.components().resource_id::<Time>() {
+            meta.access.add_read(SourceId::World, time_id);
+        }
+
+        // The callers decide whether an invalid coroutine is a bug, or can be handled
+        let Some(params) = F::Params::init(world_cell, &mut meta) else {
+            return Err(match meta.access.conflict {
+                Some(component) => {
+                    let components = world_cell.components();
+                    let name = components.get_info(component).map_or("?", |c| c.name());
+                    InvalidParams::Conflict(name.to_owned())
+                }
+                None => InvalidParams::Unavailable(std::any::type_name::<F::Params>()),
+            });
+        };
+        if let Some(owner) = meta.owner {
+            for setup in meta.setup.drain(..) {
+                setup(owner, &mut commands);
+            }
+        }
         let future = f.init(scope, params);
 
-        Some(Self {
+        Ok(Self {
             future,
             resume_param,
             meta,
             id,
             result_sender,
+            invalidation: None,
+            overshoot: Duration::ZERO,
         })
     }
 }
@@ -320,7 +574,14 @@ pub(crate) struct ResumeParam {
     yield_sender: Option<CoroStatus>,
     emit_channel: *const Channel<EmitMsg>,
     new_coro_channel: *const Channel<NewCoroutine>,
+    local_entity_channel: *const Channel<LocalEntityMsg>,
+    cancel_channel: *const Channel<CancelMsg>,
     commands_channel: *const CommandChannel,
+    waiters: *const SignalWaiters,
+    storage: *mut ScopeStorage,
+    invalidation: Option<CancelReason>,
+    overshoot: Duration,
+    meta: *mut CoroMeta,
 }
 
 impl Default for ResumeParam {
@@ -342,7 +603,14 @@ impl ResumeParam {
             yield_sender: None,
             emit_channel: null(),
             new_coro_channel: null(),
+            local_entity_channel: null(),
+            cancel_channel: null(),
             commands_channel: null(),
+            waiters: null(),
+            storage: null_mut(),
+            invalidation: None,
+            overshoot: Duration::ZERO,
+            meta: null_mut(),
         }
     }
 }
@@ -0,0 +1,220 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use bevy::prelude::Resource;
+
+use crate::{
+    executor::msg::{SignalId, Wakeups},
+    id_alloc::Id,
+};
+
+use super::{scope::Scope, CoroStatus};
+
+/// A coroutine waiting for a permit.
+struct Waiter {
+    ticket: u64,
+    id: Id,
+    wakeups: Wakeups,
+}
+
+struct SemaphoreState {
+    permits: usize,
+    /// The waiting coroutines, in arrival order
+    queue: VecDeque<Waiter>,
+    next_ticket: u64,
+}
+
+impl SemaphoreState {
+    /// Wake the waiters which can acquire a permit, the ones already woken simply check again.
+    fn wake_first(&self) {
+        for waiter in self.queue.iter().take(self.permits) {
+            waiter.wakeups.wake(waiter.id);
+        }
+    }
+}
+
+/// A counting semaphore shared between coroutines, to limit how many of them run a section at
+/// the same time, across awaits. Cloning it gives another handle to the same semaphore. It can
+/// also be inserted as a [`Resource`].
+///
+/// Waiting coroutines acquire a permit in arrival order. They are woken by a signal as soon as a
+/// permit is released for them, and resumed during the same tick when it is released by
+/// another coroutine.
+#[derive(Resource, Clone)]
+pub struct CoroSemaphore {
+    state: Arc<Mutex<SemaphoreState>>,
+}
+
+impl CoroSemaphore {
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SemaphoreState {
+                permits,
+                queue: VecDeque::new(),
+                next_ticket: 0,
+            })),
+        }
+    }
+
+    /// Returns the number of permits which can be acquired right now.
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().permits
+    }
+
+    /// Returns a future resolving with a permit, once one is available and all the coroutines
+    /// which started waiting before acquired theirs. The permit is released when dropped. If
+    /// the coroutine is cancelled while waiting, it leaves the queue.
+    pub fn acquire<'a>(&self, scope: &'a mut Scope) -> Acquire<'a> {
+        Acquire {
+            scope,
+            semaphore: self.clone(),
+            ticket: None,
+        }
+    }
+
+    /// Returns a permit if one is available and no coroutine is waiting for it.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit> {
+        let mut state = self.state.lock().unwrap();
+        if state.permits == 0 || !state.queue.is_empty() {
+            return None;
+        }
+        state.permits -= 1;
+        Some(SemaphorePermit {
+            state: self.state.clone(),
+        })
+    }
+}
+
+/// A permit of a [`CoroSemaphore`], released when dropped.
+pub struct SemaphorePermit {
+    state: Arc<Mutex<SemaphoreState>>,
+}
+
+impl Drop for SemaphorePermit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.permits += 1;
+        state.wake_first();
+    }
+}
+
+/// A future resolving once a permit of a [`CoroSemaphore`] is acquired.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'a> {
+    scope: &'a mut Scope,
+    semaphore: CoroSemaphore,
+    ticket: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut state = this.semaphore.state.lock().unwrap();
+
+        let ticket = match this.ticket {
+            Some(ticket) => ticket,
+            None => {
+                let ticket = state.next_ticket;
+                state.next_ticket += 1;
+                state.queue.push_back(Waiter {
+                    ticket,
+                    id: this.scope.coroutine_id(),
+                    wakeups: this.scope.wakeups(),
+                });
+                this.ticket = Some(ticket);
+                ticket
+            }
+        };
+
+        // Only the first waiters get the available permits, so that they are served in order
+        let position = state.queue.iter().position(|w| w.ticket == ticket).unwrap();
+        if position < state.permits {
+            state.queue.remove(position);
+            state.permits -= 1;
+            this.ticket = None;
+            return Poll::Ready(SemaphorePermit {
+                state: this.semaphore.state.clone(),
+            });
+        }
+
+        drop(state);
+        let id = this.scope.coroutine_id();
+        this.scope.yield_(CoroStatus::Signal(SignalId::wake(id)));
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            let mut state = self.semaphore.state.lock().unwrap();
+            state.queue.retain(|w| w.ticket != ticket);
+            // The permit it was woken for, if any, goes to the next one
+            state.wake_first();
+        }
+    }
+}
+
+/// A mutex shared between coroutines, to make sure a single one of them runs a section at a
+/// time, across awaits. This is a [`CoroSemaphore`] with a single permit.
+#[derive(Resource, Clone)]
+pub struct CoroMutex {
+    semaphore: CoroSemaphore,
+}
+
+impl Default for CoroMutex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CoroMutex {
+    pub fn new() -> Self {
+        Self {
+            semaphore: CoroSemaphore::new(1),
+        }
+    }
+
+    /// Returns true if a coroutine holds the lock.
+    pub fn is_locked(&self) -> bool {
+        self.semaphore.available_permits() == 0
+    }
+
+    /// Returns a future resolving once the lock is acquired, see [`CoroSemaphore::acquire`].
+    pub fn lock<'a>(&self, scope: &'a mut Scope) -> Lock<'a> {
+        Lock(self.semaphore.acquire(scope))
+    }
+
+    /// Returns the lock if it is free and no coroutine is waiting for it.
+    pub fn try_lock(&self) -> Option<CoroMutexGuard> {
+        self.semaphore
+            .try_acquire()
+            .map(|permit| CoroMutexGuard { _permit: permit })
+    }
+}
+
+/// Releases the lock of a [`CoroMutex`] when dropped.
+pub struct CoroMutexGuard {
+    _permit: SemaphorePermit,
+}
+
+/// A future resolving once the lock of a [`CoroMutex`] is acquired.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Lock<'a>(Acquire<'a>);
+
+impl<'a> Future for Lock<'a> {
+    type Output = CoroMutexGuard;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0)
+            .poll(cx)
+            .map(|permit| CoroMutexGuard { _permit: permit })
+    }
+}
@@ -2,11 +2,11 @@ use std::{mem::MaybeUninit, ptr::NonNull};
 
 use oneshot::TryRecvError;
 use sync_states::*;
-/// This modules contains the definition of one-shot channels, which can be used either
-/// concurrently or not.
-///
-/// The idea is that coroutines should be aware if they are currently running on 1 or multiple
-/// thread, and therefore choose the appropriate way to communicate with the rest of the world
+// This modules contains the definition of one-shot channels, which can be used either
+// concurrently or not.
+//
+// The idea is that coroutines should be aware if they are currently running on 1 or multiple
+// thread, and therefore choose the appropriate way to communicate with the rest of the world
 
 pub fn sync_once_channel<T>() -> (OnceSender<T>, OnceRec<T>) {
     let channel_ptr = Box::into_raw(Box::new(SyncChannel::new()));
@@ -49,14 +49,15 @@ impl<T> OnceSender<T> {
         }
     }
 
-    /// Returns true iff the sender is still listening
+    /// Returns true iff the sender is still listening, or if the receiver was detached (see
+    /// [`OnceRec::detach`]).
     pub fn is_alive(&self) -> bool {
         // SAFETY: The channel exists on the heap for the entire duration of this method and we
         // only ever acquire shared references to it. Note that if the receiver disconnects it
         // does not free the channel.
         let channel = unsafe { self.channel_ptr.as_ref() };
 
-        channel.state != DROP_REC
+        channel.state != DROP_REC || channel.detached
     }
 }
 
@@ -74,6 +75,9 @@ impl<T> OnceRec<T> {
             DROP_SND => Err(TryRecvError::Disconnected),
             DONE => {
                 let res = std::mem::replace(&mut channel.message, MaybeUninit::uninit());
+                // Once taken, the message must neither be read nor dropped again, as if the
+                // sender was dropped without sending anything
+                channel.state = DROP_SND;
                 // Safety: We are in the done state, the message must be initialized
                 unsafe { Ok(res.assume_init()) }
             }
@@ -82,6 +86,35 @@ impl<T> OnceRec<T> {
     }
 }
 
+impl<T> OnceRec<T> {
+    /// Returns true iff nothing was sent yet, and the sender is still alive.
+    pub fn is_pending(&self) -> bool {
+        // SAFETY: The channel exists on the heap for the entire duration of this method and we
+        // only ever acquire shared references to it.
+        let channel = unsafe { self.channel_ptr.as_ref() };
+
+        channel.state == INIT
+    }
+
+    /// Once detached, the sender is considered alive even after the receiver is dropped.
+    pub fn detach(&self) {
+        self.set_detached(true);
+    }
+
+    /// Undo [`OnceRec::detach`].
+    pub fn attach(&self) {
+        self.set_detached(false);
+    }
+
+    fn set_detached(&self, detached: bool) {
+        let mut channel_ptr = self.channel_ptr;
+        // SAFETY: The channel exists on the heap for the entire duration of this method, and is
+        // only accessed from one thread at a time.
+        let channel = unsafe { channel_ptr.as_mut() };
+        channel.detached = detached;
+    }
+}
+
 impl<T> Drop for OnceRec<T> {
     fn drop(&mut self) {
         let mut channel_ptr = self.channel_ptr;
@@ -90,11 +123,19 @@ impl<T> Drop for OnceRec<T> {
         // does not free the channel.
         let channel = unsafe { channel_ptr.as_mut() };
         match channel.state {
-            DROP_SND | DONE => {
+            DROP_SND => {
                 // SAFETY: The receiver has been dropped, we can therefore safely drop
                 // the channel.
                 unsafe { drop(Box::from_raw(channel_ptr.as_ptr())) }
             }
+            DONE => {
+                // SAFETY: The message was sent but never received, it is initialized and
+                // dropped only here. The sender is gone, the channel can be dropped as well.
+                unsafe {
+                    channel.message.assume_init_drop();
+                    drop(Box::from_raw(channel_ptr.as_ptr()));
+                }
+            }
             INIT => {
                 channel.state = DROP_REC;
             }
@@ -129,6 +170,8 @@ unsafe impl<T: Send> Send for OnceRec<T> {}
 
 struct SyncChannel<T> {
     state: u8,
+    /// If true, the receiver does not keep the sender alive
+    detached: bool,
     message: MaybeUninit<T>,
 }
 
@@ -136,6 +179,7 @@ impl<T> SyncChannel<T> {
     fn new() -> Self {
         Self {
             state: INIT,
+            detached: false,
             message: MaybeUninit::uninit(),
         }
     }
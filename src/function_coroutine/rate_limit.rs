@@ -0,0 +1,70 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use crate::executor::msg::{CoroStatus, SignalId};
+
+use super::{scope::Scope, CoroState};
+
+/// The emissions of a signal received by a [`RateLimitedSignalFuture`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignalBatch {
+    /// The number of emissions to handle now, at most `max_per_tick`
+    pub count: usize,
+    /// The emissions above `max_per_tick`, left to the coroutine to handle or drop
+    pub excess: usize,
+}
+
+/// A future resolving once a signal is emitted, with the number of times it was emitted until
+/// the coroutine was resumed. Created with [`Scope::rate_limit`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RateLimitedSignalFuture<'a> {
+    scope: &'a mut Scope,
+    id: SignalId,
+    max_per_tick: usize,
+    counter: Arc<AtomicUsize>,
+    state: CoroState,
+}
+
+impl<'a> RateLimitedSignalFuture<'a> {
+    pub fn new(scope: &'a mut Scope, max_per_tick: usize, id: SignalId) -> Self {
+        Self {
+            scope,
+            id,
+            max_per_tick,
+            counter: Arc::new(AtomicUsize::new(0)),
+            state: CoroState::Running,
+        }
+    }
+}
+
+impl Future for RateLimitedSignalFuture<'_> {
+    type Output = SignalBatch;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.state {
+            // We assume the executor will only poll it once the signal is emitted
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                let total = self.counter.swap(0, Ordering::Relaxed);
+                let count = total.min(self.max_per_tick);
+                Poll::Ready(SignalBatch {
+                    count,
+                    excess: total - count,
+                })
+            }
+            CoroState::Running => {
+                self.state = CoroState::Halted;
+                let status = CoroStatus::SignalCount(self.id, Arc::clone(&self.counter));
+                self.scope.yield_(status);
+                Poll::Pending
+            }
+        }
+    }
+}
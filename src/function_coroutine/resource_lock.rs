@@ -0,0 +1,119 @@
+use std::{
+    any::{type_name, TypeId},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{ecs::world::unsafe_world_cell::UnsafeWorldCell, prelude::Resource, utils::HashMap};
+
+use super::{resume::WeakResume, scope::Scope, ResumeParam};
+
+/// Which resources are locked by a coroutine, see [`Scope::lock_resource`]. Inserted by the
+/// executor.
+#[derive(Resource, Default, Clone)]
+pub(crate) struct ResourceLocks(Arc<Mutex<HashMap<TypeId, bool>>>);
+
+/// Exclusive access to the resource `R`, released when dropped. Created with
+/// [`Scope::lock_resource`]. Only the lock is held across awaits, the resource is fetched from
+/// the world on each access.
+pub struct ResourceLockGuard<R: Resource> {
+    resume_param: WeakResume<ResumeParam>,
+    locks: ResourceLocks,
+    _phantom: PhantomData<fn() -> R>,
+}
+
+impl<R: Resource> ResourceLockGuard<R> {
+    /// # Panics
+    /// If the resource `R` does not exist, or is already locked.
+    pub(crate) fn new(scope: &Scope) -> Self {
+        let world = scope.world_cell();
+        // SAFETY: Only the lock table is read, which is shared behind a mutex
+        let locks = unsafe { world.get_resource::<ResourceLocks>() }
+            .expect("The resource locks are inserted by the executor")
+            .clone();
+
+        // SAFETY: Only checks that the resource exists
+        if !unsafe { world.world() }.contains_resource::<R>() {
+            panic!(
+                "Cannot lock the resource `{}`, which does not exist",
+                type_name::<R>()
+            );
+        }
+
+        let locked = locks.0.lock().unwrap().insert(TypeId::of::<R>(), true);
+        if locked == Some(true) {
+            panic!(
+                "The resource `{}` is already locked by another coroutine",
+                type_name::<R>()
+            );
+        }
+
+        Self {
+            resume_param: scope.resume_param(),
+            locks,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the world the coroutine holding the lock is resumed with.
+    ///
+    /// # Panics
+    /// If the coroutine is not being resumed.
+    fn world(&self) -> UnsafeWorldCell<'_> {
+        // SAFETY: Only the pointer is read, it is set only while the coroutine is running
+        let world = unsafe { self.resume_param.with(|param| param.world) };
+        match world {
+            // SAFETY: The world is set while the coroutine is running, in which case the guard
+            // is only accessed from it
+            Some(world) if !world.is_null() => unsafe { (*world).as_unsafe_world_cell() },
+            _ => panic!(
+                "The lock on the resource `{}` can only be accessed while its coroutine is running",
+                type_name::<R>()
+            ),
+        }
+    }
+}
+
+impl<R: Resource> Deref for ResourceLockGuard<R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: Coroutines run one at a time, and the lock excludes the other coroutines
+        // locking it. The reference borrows the guard, so it cannot be kept across an await.
+        unsafe { self.world().get_resource::<R>() }.unwrap_or_else(|| {
+            panic!(
+                "The locked resource `{}` was removed from the world",
+                type_name::<R>()
+            )
+        })
+    }
+}
+
+impl<R: Resource> DerefMut for ResourceLockGuard<R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: See `Deref`, the resource is marked as changed
+        unsafe { self.world().get_resource_mut::<R>() }
+            .unwrap_or_else(|| {
+                panic!(
+                    "The locked resource `{}` was removed from the world",
+                    type_name::<R>()
+                )
+            })
+            .into_inner()
+    }
+}
+
+impl<R: Resource> Drop for ResourceLockGuard<R> {
+    fn drop(&mut self) {
+        self.locks
+            .0
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<R>(), false);
+    }
+}
+
+// SAFETY: Only the lock and a handle to the resume parameters are held, the world is only
+// accessed through it while the coroutine holding the guard is resumed
+unsafe impl<R: Resource> Send for ResourceLockGuard<R> {}
@@ -1,4 +1,7 @@
-use std::{cell::UnsafeCell, sync::Arc};
+use std::{
+    cell::UnsafeCell,
+    sync::{Arc, Weak},
+};
 
 /// A value which is shared on each resumed with a [`Future`].
 /// Used to emulate resume arguments to a [`Coroutine`].
@@ -45,4 +48,25 @@ impl<T> Resume<T> {
     pub fn scope_droped(&self) -> bool {
         Arc::strong_count(&self.value) == 1
     }
+
+    /// Returns a handle to the value which does not keep it alive.
+    pub fn downgrade(&self) -> WeakResume<T> {
+        WeakResume {
+            value: Arc::downgrade(&self.value),
+        }
+    }
+}
+
+/// A handle to a [`Resume`] value, which does not count as a reference to the scope.
+pub struct WeakResume<T> {
+    value: Weak<UnsafeCell<T>>,
+}
+
+impl<T> WeakResume<T> {
+    /// Calls `f` with the value, if it was not dropped.
+    /// # Safety
+    /// This must not be called while the [`Future`] is using this value.
+    pub unsafe fn with<U>(&self, f: impl FnOnce(&T) -> U) -> Option<U> {
+        self.value.upgrade().map(|value| f(&*value.as_ref().get()))
+    }
 }
@@ -0,0 +1,44 @@
+use bevy::{ecs::world::unsafe_world_cell::UnsafeWorldCell, prelude::Resource};
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+
+use crate::id_alloc::Id;
+
+/// The random number generator of a coroutine, see [`Scope::rng`](super::scope::Scope::rng).
+pub type CoroRng = SmallRng;
+
+/// The global seed from which the random number generators of the coroutines are derived. If
+/// this resource does not exist, the seed is 0.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CoroSeed(pub u64);
+
+/// A [`CoroRng`] whose seed is chosen when the coroutine is spawned, but only built once used.
+pub(crate) struct LazyRng {
+    seed: u64,
+    rng: Option<CoroRng>,
+}
+
+impl LazyRng {
+    /// The generator of a coroutine added to the executor, seeded from the [`CoroSeed`] and its id.
+    pub(crate) fn root(world: UnsafeWorldCell<'_>, id: Id) -> Self {
+        // SAFETY: The seed is only read while the coroutine is spawned
+        let seed = unsafe { world.get_resource::<CoroSeed>() }.map_or(0, |seed| seed.0);
+        Self {
+            seed: seed ^ id.to_bits(),
+            rng: None,
+        }
+    }
+
+    /// The generator of a child coroutine, seeded from this one, which keeps the whole tree
+    /// reproducible whatever the order in which the coroutines are resumed.
+    pub(crate) fn child(&mut self) -> Self {
+        Self {
+            seed: self.get().next_u64(),
+            rng: None,
+        }
+    }
+
+    pub(crate) fn get(&mut self) -> &mut CoroRng {
+        let seed = self.seed;
+        self.rng.get_or_insert_with(|| CoroRng::seed_from_u64(seed))
+    }
+}
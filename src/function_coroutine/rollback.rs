@@ -0,0 +1,92 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::prelude::{Commands, Entity};
+
+use super::{
+    defer::DeferGuard,
+    handle::{CoroHandle, HandleTuple, Status},
+    CoroState, CoroStatus, Scope,
+};
+
+/// A future driving a fallible coroutine, and restoring a snapshot of the owner entity state if
+/// it fails or gets cancelled. Created with [`Scope::with_rollback`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RollbackFuture<'a, R> {
+    scope: &'a mut Scope,
+    handle: CoroHandle<Result<R, ()>>,
+    /// Restores the snapshot if dropped before the coroutine succeeded
+    rollback: Option<DeferGuard>,
+    state: CoroState,
+}
+
+impl<'a, R> RollbackFuture<'a, R> {
+    pub(crate) fn new<T, F>(
+        scope: &'a mut Scope,
+        owner: Entity,
+        handle: CoroHandle<Result<R, ()>>,
+        snapshot: T,
+        restore: F,
+    ) -> Self
+    where
+        T: Send + 'static,
+        F: FnOnce(Entity, T, &mut Commands) + Send + 'static,
+    {
+        let rollback = DeferGuard::new(scope, move |commands| {
+            // Nothing is left to restore once the owner is despawned
+            if commands.get_entity(owner).is_some() {
+                restore(owner, snapshot, commands);
+            }
+        });
+
+        Self {
+            scope,
+            handle,
+            rollback: Some(rollback),
+            state: CoroState::Running,
+        }
+    }
+}
+
+// Nothing is ever pinned
+impl<R> Unpin for RollbackFuture<'_, R> {}
+
+impl<R> Future for RollbackFuture<'_, R>
+where
+    R: Send + Sync + 'static,
+{
+    type Output = Result<R, ()>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.handle.update_status() {
+            Status::Done => {
+                this.state = CoroState::Running;
+                let result = this.handle.try_fetch().unwrap();
+                let rollback = this.rollback.take().unwrap();
+                if result.is_err() {
+                    // Applied along with the commands of this coroutine
+                    rollback.run(&mut this.scope.commands());
+                } else {
+                    rollback.disarm();
+                }
+                Poll::Ready(result)
+            }
+            Status::StillWaiting(ids) => {
+                // We assume the executor will only poll it once the coroutine has finished
+                debug_assert!(this.state == CoroState::Running);
+                this.state = CoroState::Halted;
+                this.scope.yield_(CoroStatus::First(ids));
+                Poll::Pending
+            }
+            // The rollback is dropped along with this coroutine once cancelled
+            _ => {
+                this.scope.yield_(CoroStatus::Cancel);
+                Poll::Pending
+            }
+        }
+    }
+}
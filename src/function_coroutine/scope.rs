@@ -1,41 +1,124 @@
-use std::time::Duration;
+use std::{
+    any::{Any, TypeId},
+    future::Future,
+    ops::ControlFlow,
+    panic::UnwindSafe,
+    sync::Arc,
+    time::Duration,
+};
 
 use bevy::{
-    ecs::world::unsafe_world_cell::UnsafeWorldCell,
-    prelude::{Commands, Entity},
+    asset::{Asset, Handle},
+    ecs::{
+        archetype::ArchetypeId, component::ComponentId, event::Event, query::ReadOnlyWorldQuery,
+        schedule::SystemSet, system::EntityCommands, world::unsafe_world_cell::UnsafeWorldCell,
+    },
+    hierarchy::DespawnRecursiveExt,
+    prelude::{Bundle, Commands, Component, Entity, Mut, Resource, World},
+    time::Time,
     utils::synccell::SyncCell,
 };
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    executor::msg::{EmitMsg, NewCoroutine, SignalId},
+    executor::{
+        msg::{
+            CancelMsg, CancelReason, EmitMsg, Latch, LocalEntityMsg, NewCoroutine, SignalId,
+            Wakeups,
+        },
+        phase::Phase,
+        quota::Limits,
+        watch::WatchToken,
+        CoroutineSpawned, ScopeStorage, TickCount,
+    },
     id_alloc::Id,
+    persistent::PersistentScripts,
+    CoroMeta, SourceId,
 };
 
 use super::{
-    await_all::AwaitAll,
-    await_first::AwaitFirst,
-    await_time::{DurationFuture, NextTick},
-    handle::{CoroHandle, HandleTuple},
-    once_channel::{sync_once_channel, OnceSender},
-    resume::Resume,
-    CoroStatus, CoroutineParamFunction, FunctionCoroutine, ResumeParam,
+    await_all::{AwaitAll, ParallelMapFuture},
+    await_all_within::AwaitAllWithin,
+    await_any_change::AnyChangeFuture,
+    await_any_signal::AnySignalFuture,
+    await_asset::{AssetLoadedFuture, AssetOrTimeoutFuture},
+    await_changed_by_other::ChangedByOtherFuture,
+    await_event::ReactToEventFuture,
+    await_first::{AwaitFirst, FirstOrCancelFuture, ScopedHandleFuture},
+    await_has_all::{HasAllFuture, QueryStates},
+    await_marked::{MarkedCoroutines, MarkedFuture},
+    await_n_signals::NSignalsFuture,
+    await_phase::AfterPhase,
+    await_query::QueryChangeFuture,
+    await_quorum::AwaitQuorum,
+    await_removed::ComponentRemovedFuture,
+    await_resource::ResourceChangeFuture,
+    await_signals::{AllSignalsFuture, AnySignalInFuture},
+    await_time::{DurationFuture, ExactTickFuture, NextTick, TimeSnapshot, YieldToScheduler},
+    await_watch::WatchFuture,
+    await_with_changed::WithChangedFuture,
+    await_world::WithWorld,
+    behavior::{BehaviorHandle, Mailbox, MessageQueue},
+    catch_unwind::CatchUnwindFuture,
+    cleanup::{CleanupRegistry, CleanupScope},
+    component_set::ComponentSet,
+    defer::DeferGuard,
+    every::{Every, EveryFuture, RepeatFuture},
+    frame_sync::FrameSyncFuture,
+    handle::{CoroHandle, ForkHandle, HandleTuple},
+    insert_result,
+    mutex::CoroMutex,
+    once_channel::sync_once_channel,
+    rate_limit::RateLimitedSignalFuture,
+    resource_lock::ResourceLockGuard,
+    resume::{Resume, WeakResume},
+    rollback::RollbackFuture,
+    stopwatch::CoroStopwatch,
+    thread::ThreadFuture,
+    throttle::Throttle,
+    unique::UniqueMut,
+    CoroStatus, CoroutineParamFunction, FunctionCoroutine, InvalidParams, ResultSender,
+    ResumeParam,
 };
 
+#[cfg(feature = "debug")]
+use super::history::ComponentHistory;
+
+#[cfg(feature = "rand")]
+use super::rng::{CoroRng, LazyRng};
+
 /// The first parameter of any [`Coroutine`] It is used to spawn sub-coroutines, yield back to the
 /// scheduler, queue commands and so on. It is the most unsafe part of this library, but once
 /// proper coroutines are implemented in Rust, this would not be the case for the most part.
 pub struct Scope {
     id: Id,
     owner: Option<Entity>,
+    /// The coroutine which started this one, if any
+    started_by: Option<Id>,
     resume_param: Resume<ResumeParam>,
+    mailbox: Option<Arc<dyn Any + Send + Sync>>,
+    frame_sync: Duration,
+    #[cfg(feature = "rand")]
+    rng: std::cell::RefCell<LazyRng>,
 }
 
 impl Scope {
-    pub(crate) fn new(id: Id, owner: Option<Entity>, resume_param: Resume<ResumeParam>) -> Self {
+    pub(crate) fn new(
+        id: Id,
+        owner: Option<Entity>,
+        resume_param: Resume<ResumeParam>,
+        mailbox: Option<Arc<dyn Any + Send + Sync>>,
+        #[cfg(feature = "rand")] rng: LazyRng,
+    ) -> Self {
         Self {
             id,
             owner,
+            started_by: None,
             resume_param,
+            mailbox,
+            frame_sync: Duration::ZERO,
+            #[cfg(feature = "rand")]
+            rng: std::cell::RefCell::new(rng),
         }
     }
 
@@ -44,9 +127,41 @@ impl Scope {
         AwaitAll::new(self, handles)
     }
 
+    /// Start a coroutine with `f` for each item of `iter`, and returns a future that resolve
+    /// with their results in the same order, once they all finished. They are all started
+    /// before waiting on any of them, so that they run in parallel. `f` starts them from the
+    /// scope it is given, with [`Scope::start`] for instance.
+    pub fn parallel_map<I, R, F>(&mut self, iter: I, mut f: F) -> ParallelMapFuture<'_, R>
+    where
+        I: IntoIterator,
+        F: FnMut(&mut Scope, I::Item) -> CoroHandle<R>,
+    {
+        let handles = iter.into_iter().map(|item| f(self, item)).collect();
+        self.all(handles)
+    }
+
+    /// Returns a future that resolve once all of the underlying coroutine finishes, or once the
+    /// `duration` elapsed. In which case the coroutines still running are cancelled. It returns
+    /// `Some` result for each finished coroutine, and `None` for the cancelled ones, in the same
+    /// order as the handles (which can be a tuple or a `Vec`).
+    ///
+    /// Coroutines finishing during the tick in which the duration elapses still count, the
+    /// deadline is only checked once all the other coroutines of that tick ran.
+    pub fn all_within<H: HandleTuple>(
+        &mut self,
+        handles: H,
+        duration: Duration,
+    ) -> AwaitAllWithin<'_, H> {
+        AwaitAllWithin::new(self, handles, duration)
+    }
+
     /// Returns a future that resolve once any of the underlying coroutine finishes. Note that
     /// once this is done, all the others are dropped. The coroutines are resumed from top to
     /// bottom, in case multiple of them are ready to make progress at the same time.
+    ///
+    /// If some coroutines already finished, the first of them in `handles` wins right away,
+    /// without yielding, even if others were cancelled. Since handles cannot be cloned, each
+    /// coroutine appears at most once.
     pub fn first<const N: usize, T>(&mut self, handles: [CoroHandle<T>; N]) -> AwaitFirst<'_, N, T>
     where
         T: Send + Sync + 'static,
@@ -54,6 +169,30 @@ impl Scope {
         AwaitFirst::new(self, handles)
     }
 
+    /// Same as [`Scope::first`], but a cancelled coroutine is only removed from the race, instead
+    /// of cancelling this coroutine as well. This one is only cancelled if all of them are. It
+    /// resolves with the index of the winner in `handles`, along with its result.
+    pub fn first_or_cancel<const N: usize, T>(
+        &mut self,
+        handles: [CoroHandle<T>; N],
+    ) -> FirstOrCancelFuture<'_, N, T>
+    where
+        T: Send + Sync + 'static,
+    {
+        FirstOrCancelFuture::new(self, handles)
+    }
+
+    /// Returns a future that resolve once `k` of the underlying coroutines finished, with their
+    /// results in the order of `handles`. Like with [`Scope::first`], the others are cancelled.
+    /// If `k` is 0, it resolves right away, and if it is greater than the number of handles, it
+    /// waits on all of them.
+    pub fn quorum<T>(&mut self, k: usize, handles: Vec<CoroHandle<T>>) -> AwaitQuorum<'_, T>
+    where
+        T: Send + Sync + 'static,
+    {
+        AwaitQuorum::new(self, k, handles)
+    }
+
     /// Return a future that resolve once the underlying coroutine finishes.
     pub fn on<T>(&mut self, handle: CoroHandle<T>) -> AwaitFirst<'_, 1, T>
     where
@@ -62,6 +201,15 @@ impl Scope {
         AwaitFirst::new(self, [handle])
     }
 
+    /// Turn `handle` into a [`Future`] resolving with the result of the coroutine, same as
+    /// [`Scope::on`], for the functions expecting any `Future<Output = T>`.
+    pub fn into_future<T>(&mut self, handle: CoroHandle<T>) -> ScopedHandleFuture<'_, T>
+    where
+        T: Send + Sync + 'static,
+    {
+        ScopedHandleFuture::new(self, handle)
+    }
+
     /// Returns a future that resolve the next time the [`Executor`] is ticked (via
     /// [`run`][crate::executor::Executor::run] for instance). It returns the duration of the
     /// last frame (delta time).
@@ -71,65 +219,873 @@ impl Scope {
         NextTick::new(self)
     }
 
+    /// Returns a future that resolve the next time the [`Executor`] is ticked right after the
+    /// system set `set`, within the same frame. For instance to read the
+    /// [`GlobalTransform`](bevy::prelude::GlobalTransform) of an entity once the transforms
+    /// are propagated. The phase must be declared with [`add_coroutine_phase`], otherwise the
+    /// coroutine is never resumed.
+    ///
+    /// [`Executor`]: crate::executor::Executor
+    /// [`add_coroutine_phase`]: crate::plugin::CoroutineAppExt::add_coroutine_phase
+    pub fn after_set(&mut self, set: impl SystemSet) -> AfterPhase<'_> {
+        AfterPhase::new(self, Phase::after(set))
+    }
+
+    /// Returns a [`Throttle`] letting a section of this coroutine run at most once every
+    /// `period`, see [`Throttle::wait`]. It should be created once, outside of the loop it
+    /// throttles.
+    pub fn throttle(&self, period: Duration) -> Throttle {
+        Throttle::new(period)
+    }
+
+    /// Returns a [`CoroStopwatch`] measuring the time elapsed from now on, as seen by the
+    /// [`Time`](bevy::time::Time) resource, whichever way this coroutine is resumed.
+    pub fn stopwatch(&self) -> CoroStopwatch {
+        CoroStopwatch::new(self.elapsed_time())
+    }
+
+    /// Returns the time elapsed since this coroutine was created, as seen by the
+    /// [`Time`](bevy::time::Time) resource.
+    pub fn coroutine_age(&self) -> Duration {
+        self.elapsed_time().saturating_sub(self.meta().spawned_at)
+    }
+
+    /// Returns a future running `f` with exclusive access to the [`World`], and resolving with
+    /// its result. The coroutine yields, and is resumed by the executor once it can run alone,
+    /// right away when ticked serially. This is the escape hatch for whatever the coroutine
+    /// parameters cannot express.
+    pub fn with_world<R, F>(&mut self, f: F) -> WithWorld<'_, F>
+    where
+        F: FnOnce(&mut World) -> R,
+    {
+        WithWorld::new(self, f)
+    }
+
+    /// Returns the delta and elapsed time of the [`Time`] resource, and the current tick of the
+    /// executor, without awaiting. Every coroutine declares a read of [`Time`] for this.
+    ///
+    /// # Panics
+    /// If called while this coroutine is not being resumed, from another thread for instance.
+    pub fn time(&self) -> TimeSnapshot {
+        self.assert_running("time");
+        // SAFETY: Only the time and the tick count are read, while the coroutine is running
+        unsafe {
+            let world = self.world_cell();
+            let time = world.get_resource::<Time>().unwrap();
+            TimeSnapshot {
+                delta: time.delta(),
+                elapsed: time.elapsed(),
+                tick_count: world.get_resource::<TickCount>().map_or(0, |ticks| ticks.0),
+            }
+        }
+    }
+
+    /// The total time elapsed, according to the [`Time`](bevy::time::Time) resource.
+    pub(crate) fn elapsed_time(&self) -> Duration {
+        // SAFETY: Only the time is read, while the coroutine is running
+        unsafe { self.world_cell().get_resource::<Time>() }
+            .map_or(Duration::ZERO, |time| time.elapsed())
+    }
+
+    /// Returns a new [`CoroMutex`], to be shared with the coroutines started from this one.
+    pub fn mutex(&self) -> CoroMutex {
+        CoroMutex::new()
+    }
+
+    /// Returns a future that resolve during the same tick, once all the other coroutines ready
+    /// to run were resumed. Long computations can be split this way, without stalling the
+    /// other coroutines, nor waiting for the next tick. A coroutine which never stops yielding
+    /// like this prevents the tick from ending.
+    pub fn yield_to_scheduler(&mut self) -> YieldToScheduler<'_> {
+        YieldToScheduler::new(self)
+    }
+
     /// Returns a future that resolve after a certain [`Duration`]. Note that if the duration
-    /// is smaller than the time between two tick of the [`Executor`] it won't be compensated.
+    /// is smaller than the time between two tick of the [`Executor`] it won't be compensated,
+    /// but the returned [`MissedInfo`](super::await_time::MissedInfo) tells by how much it was overshot.
     ///
     /// [`Executor`]: crate::executor::Executor
     pub fn duration(&mut self, duration: Duration) -> DurationFuture<'_> {
         DurationFuture::new(self, duration)
     }
 
+    /// Returns a future that resolve during the tick where the
+    /// [`TickCount`](crate::executor::TickCount) reaches `tick`, or right away if it already did.
+    /// Unlike durations, this does not depend on the frame rate, which suits scripted sequences.
+    pub fn wait_for_exact_tick(&mut self, tick: u64) -> ExactTickFuture<'_> {
+        ExactTickFuture::new(self, tick)
+    }
+
+    /// Returns a future that resolve at a fixed rate of `target_fps` times per second, whatever
+    /// the actual frame rate is. The time elapsed during the ticks is accumulated, and any
+    /// overshoot is carried over to the next wait, so this can resolve right away when the
+    /// executor runs slower than the target.
+    ///
+    /// # Panics
+    ///
+    /// If `target_fps` is not strictly positive.
+    pub fn await_frame_sync(&mut self, target_fps: f32) -> FrameSyncFuture<'_> {
+        FrameSyncFuture::new(self, target_fps)
+    }
+
+    /// Runs `body` at a fixed rate of `target_fps` times per second, with
+    /// [`Scope::await_frame_sync`], until it returns `false`.
+    pub async fn every_second(
+        &mut self,
+        target_fps: f32,
+        mut body: impl FnMut(&mut Scope) -> bool,
+    ) {
+        loop {
+            self.await_frame_sync(target_fps).await;
+            if !body(self) {
+                return;
+            }
+        }
+    }
+
+    /// Returns an [`Every`] resolving each time a trigger built by `factory` does, such as
+    /// [`Scope::next_tick`] or [`OnChange::observe`]. A new trigger is built for each call to
+    /// [`Every::next`], from the scope borrowed by the returned value. This is the general
+    /// version of [`Scope::every_second`], waiting on any condition rather than on a fixed rate.
+    ///
+    /// [`OnChange::observe`]: super::coro_param::on_change::OnChange::observe
+    pub fn every<T, F>(&mut self, factory: F) -> Every<'_, F>
+    where
+        F: FnMut(&mut Scope) -> EveryFuture<'_, T>,
+    {
+        Every::new(self, factory)
+    }
+
+    /// Returns a future running the future built by `body` until it returns
+    /// [`ControlFlow::Break`], at most `n` times, or forever if `n` is [`None`]. A new future is
+    /// built for each iteration, from the scope borrowed by the returned value. It resolves with
+    /// the value `body` broke with, or with [`None`] if it ran `n` times without breaking.
+    pub fn repeat<'a, R, F>(&'a mut self, n: Option<usize>, mut body: F) -> RepeatFuture<'a, R>
+    where
+        R: Send + 'a,
+        F: FnMut(&mut Scope) -> EveryFuture<'_, ControlFlow<R>> + Send + 'a,
+    {
+        Box::pin(async move {
+            let mut remaining = n;
+            while remaining != Some(0) {
+                remaining = remaining.map(|n| n - 1);
+                if let ControlFlow::Break(value) = body(self).await {
+                    return Some(value);
+                }
+            }
+            None
+        })
+    }
+
+    pub(crate) fn frame_sync_mut(&mut self) -> &mut Duration {
+        &mut self.frame_sync
+    }
+
+    /// Returns a future that resolve once the signal `signal_id` has been emitted `n` times,
+    /// starting from the first time the future is awaited.
+    pub fn await_n_signals(&mut self, n: usize, signal_id: SignalId) -> NSignalsFuture<'_> {
+        NSignalsFuture::new(self, n, signal_id)
+    }
+
+    /// Returns a future that resolve once the signal `S` is emitted on `owner`, or globally if
+    /// `owner` is [`None`], with [`Scope::emit`].
+    pub fn signal<S: 'static>(&mut self, owner: Option<Entity>) -> NSignalsFuture<'_> {
+        NSignalsFuture::new(self, 1, SignalId::custom::<S>(owner))
+    }
+
+    /// Same as [`Scope::signal`], for a signal identified by its `name`, emitted with
+    /// [`Scope::emit_named`].
+    pub fn signal_named(
+        &mut self,
+        name: &'static str,
+        owner: Option<Entity>,
+    ) -> NSignalsFuture<'_> {
+        NSignalsFuture::new(self, 1, SignalId::named(name, owner))
+    }
+
+    /// Returns a future resolving once `signal_id` is emitted, with the number of emissions
+    /// until this coroutine is resumed, such as the ones emitted in a burst during the same
+    /// tick. At most `max_per_tick` are reported in [`SignalBatch::count`], the others in
+    /// [`SignalBatch::excess`].
+    ///
+    /// [`SignalBatch::count`]: super::rate_limit::SignalBatch::count
+    /// [`SignalBatch::excess`]: super::rate_limit::SignalBatch::excess
+    pub fn rate_limit(
+        &mut self,
+        max_per_tick: usize,
+        signal_id: SignalId,
+    ) -> RateLimitedSignalFuture<'_> {
+        RateLimitedSignalFuture::new(self, max_per_tick, signal_id)
+    }
+
+    /// Emit the signal `S` on `owner`, or globally if `owner` is [`None`]. The coroutines
+    /// waiting on it are resumed during this tick, after this one yields.
+    ///
+    /// It is sent even if [`Scope::has_waiters`] returns false, since the coroutines which only
+    /// start waiting on it later during this tick still receive it.
+    pub fn emit<S: 'static>(&self, owner: Option<Entity>) {
+        self.emit_signal(SignalId::custom::<S>(owner));
+    }
+
+    /// Same as [`Scope::emit`], for a signal identified by its `name`.
+    pub fn emit_named(&self, name: &'static str, owner: Option<Entity>) {
+        self.emit_signal(SignalId::named(name, owner));
+    }
+
+    /// Emit the signal `signal_id`, like [`Scope::emit`], but the executor also remembers it for
+    /// the coroutines awaiting it later on, even during another tick, according to `latch`. The
+    /// latches of a signal owned by an entity are cleared once the entity is despawned, the
+    /// other ones with [`Executor::clear_latch`](crate::executor::Executor::clear_latch).
+    pub fn emit_latched(&self, signal_id: SignalId, latch: Latch) {
+        self.send_emit(signal_id, Some(latch));
+    }
+
+    /// Returns true if a coroutine currently waits on `signal_id`, including the ones which
+    /// started waiting earlier during this tick, or on any signal. Emitting a signal nobody waits
+    /// on can be skipped in hot paths, if the coroutines which only start waiting later on do not
+    /// need to see it. Latched signals should be emitted regardless.
+    pub fn has_waiters(&self, signal_id: SignalId) -> bool {
+        // SAFETY: The waiters are only mutated by the executor, once this coroutine yields
+        unsafe {
+            let waiters = self.resume_param.get().waiters.as_ref().unwrap();
+            waiters.has_waiters(signal_id)
+        }
+    }
+
+    /// Returns a future resolving with the id of the next signal emitted, whichever it is. Signals
+    /// emitted while the coroutine is not waiting on this future are not observed, it must
+    /// therefore be awaited again right away to track all of them.
+    pub fn any_signal(&mut self) -> AnySignalFuture<'_> {
+        AnySignalFuture::new(self)
+    }
+
+    /// Returns a future resolving with the first of `signals` to be emitted, like
+    /// [`Scope::any_signal`] but only for these signals.
+    ///
+    /// # Panics
+    ///
+    /// If `signals` is empty, or has more than 64 signals.
+    pub fn any_signal_in(
+        &mut self,
+        signals: impl IntoIterator<Item = SignalId>,
+    ) -> AnySignalInFuture<'_> {
+        AnySignalInFuture::new(self, signals.into_iter().collect())
+    }
+
+    /// Returns a future resolving once each of `signals` was emitted, with the signals in the
+    /// order they were first received. They can be emitted during different ticks, the ones
+    /// already received are remembered meanwhile.
+    ///
+    /// # Panics
+    ///
+    /// If there are more than 64 signals.
+    pub fn all_signals(
+        &mut self,
+        signals: impl IntoIterator<Item = SignalId>,
+    ) -> AllSignalsFuture<'_> {
+        AllSignalsFuture::new(self, signals.into_iter().collect())
+    }
+
+    /// Returns a future resolving with `true` once the component `component_id` of `entity` is
+    /// changed by another coroutine or by a system, but not by this coroutine. It resolves with
+    /// `false` if the entity or the component is removed. Changes are checked once per tick.
+    pub fn await_changed_by_other(
+        &mut self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> ChangedByOtherFuture<'_> {
+        ChangedByOtherFuture::new(self, entity, component_id)
+    }
+
+    /// Returns a future resolving with the component `T` of `entity`, once it is changed after
+    /// this function is called. However many times it changed by then, it only resolves once.
+    /// It resolves with [`None`] if the entity or the component is removed. Changes are checked
+    /// once per tick.
+    ///
+    /// A read of `T` on `entity` is added to the access of this coroutine.
+    pub fn with_changed_component<T: Component>(
+        &mut self,
+        entity: Entity,
+    ) -> WithChangedFuture<'_, T> {
+        WithChangedFuture::new(self, entity)
+    }
+
+    /// Returns the components of `entity`, or an empty set if it does not exist. This is a
+    /// snapshot, later insertions and removals are not reflected.
+    ///
+    /// A read of each of these components on `entity` is added to the access of this coroutine.
+    pub fn components_of(&self, entity: Entity) -> ComponentSet {
+        let world = self.world_cell();
+        let Some(cell) = world.get_entity(entity) else {
+            return ComponentSet::default();
+        };
+
+        // SAFETY: The metadata are only set while the coroutine is polled, and are not borrowed
+        // by anything else meanwhile
+        let meta = unsafe { &mut *self.meta_ptr() };
+        let source = SourceId::Entity(entity);
+        for component_id in cell.archetype().components() {
+            if !meta.access.can_read(source, component_id) {
+                meta.access.add_read(source, component_id);
+            }
+        }
+
+        ComponentSet::new(cell.archetype().components(), world.components())
+    }
+
+    /// Returns the archetype of `entity`, which identifies the set of components it has, or
+    /// [`None`] if it does not exist.
+    ///
+    /// Only the location of the entity is read, not its components, so nothing is added to the
+    /// access of this coroutine.
+    pub fn get_archetype(&self, entity: Entity) -> Option<ArchetypeId> {
+        self.world_cell()
+            .get_entity(entity)
+            .map(|entity| entity.archetype().id())
+    }
+
+    /// Returns true if `a` and `b` both exist and have exactly the same components, which is
+    /// cheaper than checking each of them.
+    ///
+    /// Their archetypes are compared with [`Scope::get_archetype`], without adding anything to
+    /// the access of this coroutine.
+    pub fn same_archetype(&self, a: Entity, b: Entity) -> bool {
+        match (self.get_archetype(a), self.get_archetype(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Returns a future resolving with true if `entity` exists and matches the query `Q`, for
+    /// instance if it has all the components of `(&A, &B)`. The first time a query is used, the
+    /// coroutine waits to be resumed alone to register its components.
+    ///
+    /// The items of `Q` are only fetched to tell whether `entity` matches, and dropped right
+    /// away, so no read of its components is added to the access of this coroutine.
+    pub fn entity_has_all<Q: ReadOnlyWorldQuery + 'static>(
+        &mut self,
+        entity: Entity,
+    ) -> HasAllFuture<'_, Q> {
+        HasAllFuture::new(self, entity)
+    }
+
+    /// Returns a future that resolve once `entity` no longer has the component `T`, or once it
+    /// is despawned. It resolves right away if it is already the case, otherwise the removal is
+    /// detected with [`RemovedComponents`](bevy::prelude::RemovedComponents) at the start of the
+    /// next ticks. A read of `T` on `entity` is added to the access of this coroutine.
+    pub fn on_component_removed<T: Component>(
+        &mut self,
+        entity: Entity,
+    ) -> ComponentRemovedFuture<'_, T> {
+        ComponentRemovedFuture::new(self, entity)
+    }
+
+    /// Returns a future that resolve once the asset of `handle` is loaded, with a reference to
+    /// it. It resolves right away if it is already loaded, and checks again on each tick
+    /// otherwise. The handle is kept until then, so the asset is not unloaded meanwhile. A read
+    /// of the [`Assets<A>`](bevy::asset::Assets) resource is added to the access of this
+    /// coroutine.
+    pub fn await_asset_loaded<A: Asset>(&mut self, handle: Handle<A>) -> AssetLoadedFuture<'_, A> {
+        AssetLoadedFuture::new(self, handle)
+    }
+
+    /// Same as [`Scope::await_asset_loaded`], but resolves with [`None`] if the asset is still
+    /// not loaded once the `duration` elapsed. A read of the [`Assets<A>`](bevy::asset::Assets)
+    /// resource is added to the access of this coroutine.
+    pub fn await_asset_or_timeout<A: Asset>(
+        &mut self,
+        handle: Handle<A>,
+        duration: Duration,
+    ) -> AssetOrTimeoutFuture<'_, A> {
+        AssetOrTimeoutFuture::new(self, handle, duration)
+    }
+
+    /// Returns a future that resolve with an entity matching the query `Q` and the filter `F`,
+    /// once the filter detects a change, for instance with `F` being `Changed<Health>`. Only
+    /// the changes made after the future is first polled are taken into account. They are
+    /// detected at the beginning of each tick, by a single query shared by all the coroutines
+    /// waiting on the same `Q` and `F`.
+    ///
+    /// A read access to the components of `Q` and `F` on all entities is declared once the
+    /// future is polled, which requires the coroutine to be resumed alone first.
+    pub fn await_query_change<Q: ReadOnlyWorldQuery + 'static, F: ReadOnlyWorldQuery + 'static>(
+        &mut self,
+    ) -> QueryChangeFuture<'_, Q, F> {
+        QueryChangeFuture::new(self)
+    }
+
+    /// Returns a future that resolve once the resource `R` is mutated, with a reference to its
+    /// new value. Only mutations performed after this function is called are taken into
+    /// account. If the resource is removed meanwhile, it keeps waiting until it is inserted
+    /// again, which counts as a mutation.
+    ///
+    /// A read of `R` is added to the access of this coroutine.
+    ///
+    /// # Panics
+    /// If the resource `R` was never inserted.
+    pub fn await_resource_change<R: Resource>(&mut self) -> ResourceChangeFuture<'_, R> {
+        ResourceChangeFuture::new(self)
+    }
+
+    /// Run `f` on the [`AsyncComputeTaskPool`](bevy::tasks::AsyncComputeTaskPool), which is
+    /// initialized if needed, and returns a future resolving with its result. The coroutine
+    /// checks whether it finished once per tick, meanwhile the other coroutines keep running.
+    /// Since it runs on another thread, `f` cannot access the world.
+    pub fn run_in_thread<T: Send + 'static>(
+        &mut self,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> ThreadFuture<'_, T> {
+        ThreadFuture::new(self, f)
+    }
+
+    /// Returns a guard running `f` once dropped, at the end of the tick, even if this coroutine
+    /// is cancelled in the meantime. This is the place for cleanups which must happen once the
+    /// coroutine ends, in any way. Use [`DeferGuard::disarm`] to drop it without running `f`.
+    pub fn defer(&self, f: impl FnOnce(&mut Commands) + Send + 'static) -> DeferGuard {
+        DeferGuard::new(self, f)
+    }
+
+    /// Register `f` to run once this coroutine ends, whether it completed or got cancelled. The
+    /// closures run at the end of the tick, in the reverse order of their registration. Unlike
+    /// [`Scope::defer`], they cannot be disarmed, and only see the world through a
+    /// [`CleanupScope`].
+    pub fn on_cleanup(&self, f: impl FnOnce(&mut CleanupScope<'_>) + Send + 'static) {
+        // SAFETY: Only the registry is read, which is shared behind a mutex
+        unsafe { self.world_cell().get_resource::<CleanupRegistry>() }
+            .expect("The cleanup registry is inserted by the executor")
+            .register(self.id, Box::new(f));
+    }
+
+    /// Returns a mutable reference to the component `T` of `entity`, declaring the write access
+    /// for as long as the guard is held only, instead of the whole coroutine like a
+    /// [`Wr`](super::coro_param::component::Wr) parameter. The guard cannot be held across any
+    /// await. Returns [`None`] if the entity does not have the component, or if this coroutine
+    /// already accesses it.
+    pub fn get_mut_unique<T: Component>(&self, entity: Entity) -> Option<UniqueMut<'_, T>> {
+        UniqueMut::new(self, entity)
+    }
+
+    /// Lock the resource `R`, without declaring it as a parameter, and returns a guard giving
+    /// exclusive access to it until dropped. The guard can be held across awaits, but the
+    /// resource can only be accessed through it while the coroutine runs, and is marked as
+    /// changed when accessed mutably. This lets coroutines share a resource they only need one
+    /// after the other. Accesses through parameters are not checked against it.
+    ///
+    /// # Panics
+    /// If the resource `R` does not exist, or is already locked by another coroutine.
+    pub fn lock_resource<R: Resource>(&self) -> ResourceLockGuard<R> {
+        ResourceLockGuard::new(self)
+    }
+
+    /// Returns a future calling `f` on each event `E` sent after this function is called, tick
+    /// after tick, and resolving once it returns `true`. Unlike waiting on a single event, every
+    /// pending event is processed.
+    ///
+    /// A read of the [`Events<E>`](bevy::ecs::event::Events) resource is added to the access of
+    /// this coroutine.
+    ///
+    /// # Panics
+    /// If the resource [`Events<E>`](bevy::ecs::event::Events) does not exist.
+    pub fn react_to_event<'a, E: Event>(
+        &'a mut self,
+        f: impl FnMut(&E) -> bool + Send + 'a,
+    ) -> ReactToEventFuture<'a, E> {
+        ReactToEventFuture::new(self, f)
+    }
+
+    /// Returns a future resolving with all the entities having the component `T` and matching
+    /// the filter `F` whose component changed, once at least one of them did. Entities spawned
+    /// with the component count as changed. Changes are detected at the beginning of each tick,
+    /// with a single query shared by all the coroutines waiting on the same `T` and `F`.
+    ///
+    /// A read of `T` on all entities is added to the access of this coroutine.
+    pub fn on_any_change<T: Component, F: ReadOnlyWorldQuery + 'static>(
+        &mut self,
+    ) -> AnyChangeFuture<'_> {
+        AnyChangeFuture::new::<T, F>(self)
+    }
+
+    /// Start the fallible `coroutine` bound to the owner of this scope, and returns a future
+    /// resolving with its result. If it fails, `restore` is called with the owner, the `snapshot`
+    /// taken before starting the coroutine and [`Commands`], to roll back the changes made to the
+    /// owner. Only the state of the owner is meant to be rolled back, not resources.
+    ///
+    /// The snapshot is also restored if the future is dropped before the coroutine succeeded,
+    /// for instance when this coroutine is cancelled, at the end of the tick as with
+    /// [`Scope::defer`]. It is skipped if the owner was despawned by then.
+    ///
+    /// # Panics
+    /// If this scope has no owner, or if the coroutine is invalid (with conflicting parameters
+    /// for instance).
+    pub fn with_rollback<Marker: 'static, T, R, C, F>(
+        &mut self,
+        snapshot: T,
+        restore: F,
+        coroutine: C,
+    ) -> RollbackFuture<'_, R>
+    where
+        C: CoroutineParamFunction<Marker, Result<R, ()>>,
+        R: Send + Sync + 'static,
+        T: Send + 'static,
+        F: FnOnce(Entity, T, &mut Commands) + Send + 'static,
+    {
+        let owner = self
+            .owner
+            .expect("Cannot rollback the state of a coroutine without owner");
+        let handle = self.start(coroutine);
+        RollbackFuture::new(self, owner, handle, snapshot, restore)
+    }
+
+    /// Returns a future resolving with the output of `future`, or with the payload of the panic
+    /// if it panicked, instead of crashing the executor. The `future` must be [`UnwindSafe`],
+    /// which a future using the scope is not: wrap it in
+    /// [`AssertUnwindSafe`](std::panic::AssertUnwindSafe) to acknowledge the risk of observing
+    /// a broken state after the panic. The panic hook is still called, and prints the panic
+    /// message by default.
+    ///
+    /// This is an associated function, so that `future` can borrow the scope:
+    /// `Scope::catch_unwind(AssertUnwindSafe(async { s.next_tick().await }))`.
+    pub fn catch_unwind<F>(future: F) -> CatchUnwindFuture<F>
+    where
+        F: Future + UnwindSafe,
+    {
+        CatchUnwindFuture::new(future)
+    }
+
     /// Start the `coroutine` when reaching the next `await`. When the scope is dropped, the
     /// `coroutine` is automatically dropped as well.
     ///
-    /// Note: If the coroutine is invalid (with conflicting parameters for instance), this function
-    /// has no effects.
+    /// Nothing is started if its parameters are invalid, because they conflict or their owner
+    /// lacks a component for instance.
     pub fn start_local<Marker: 'static, T, C>(&mut self, coroutine: C)
     where
         C: CoroutineParamFunction<Marker, T>,
         T: Sync + Send + 'static,
     {
-        self.build_coroutine(self.owner, true, Some(self.id), None, coroutine);
+        self.build_coroutine(self.owner, true, Some(self.id), None, None, None, coroutine)
+            .ok();
     }
 
     /// Start the `coroutine` when reaching the next `await`, and returns a [`CoroHandle`] to it.
     /// When the handle is dropped, the `coroutine` is automatically dropped as well.
     ///
-    /// Note: If the coroutine is invalid (with conflicting parameters for instance), this function
-    /// panics.
+    /// # Panics
+    /// If the parameters of the `coroutine` are invalid, because they conflict or their owner
+    /// lacks a component for instance, which helps catching bugs during development. Use
+    /// [`Scope::start_or_cancel`] or [`Scope::start_or_default`] to handle this case instead.
     pub fn start<Marker: 'static, T, C>(&mut self, coroutine: C) -> CoroHandle<T>
     where
         C: CoroutineParamFunction<Marker, T>,
         T: Sync + Send + 'static,
     {
-        self.try_start(coroutine).unwrap()
+        self.start_handle(coroutine)
+            .unwrap_or_else(|invalid| invalid.panic::<C>())
+    }
+
+    /// Same as [`Scope::start`], but returns [`None`] instead of panicking if the parameters of
+    /// the `coroutine` are invalid.
+    pub fn start_or_cancel<Marker: 'static, T, C>(&mut self, coroutine: C) -> Option<CoroHandle<T>>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.try_start(coroutine)
+    }
+
+    /// Same as [`Scope::start`], but if the parameters of the `coroutine` are invalid, the returned
+    /// handle immediately resolves with `default` instead of panicking.
+    pub fn start_or_default<Marker: 'static, T, C>(
+        &mut self,
+        coroutine: C,
+        default: T,
+    ) -> CoroHandle<T>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.try_start(coroutine)
+            .unwrap_or(CoroHandle::Done(default))
     }
 
     /// Start the `coroutine` when reaching the next `await`, and returns a [`CoroHandle`] to it.
     /// When the handle is dropped, the `coroutine` is automatically dropped as well.
-    /// If the coroutine is invalid (with conflicting parameters for instance), this function
-    /// returns None.
+    /// Returns [`None`] if its parameters are invalid, because they conflict or their owner
+    /// lacks a component for instance.
     pub fn try_start<Marker: 'static, T, C>(&mut self, coroutine: C) -> Option<CoroHandle<T>>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        self.start_handle(coroutine).ok()
+    }
+
+    fn start_handle<Marker: 'static, T, C>(
+        &mut self,
+        coroutine: C,
+    ) -> Result<CoroHandle<T>, InvalidParams>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        let (result_sender, receiver) = sync_once_channel();
+        let id = self.build_coroutine(
+            self.owner,
+            true,
+            None,
+            Some(ResultSender::Handle(result_sender)),
+            None,
+            None,
+            coroutine,
+        )?;
+        Ok(CoroHandle::Waiting { id, receiver })
+    }
+
+    /// Same as [`Scope::start`], but the `coroutine` is cancelled once it crosses one of its
+    /// `limits`, with [`CancelReason::QuotaExceeded`]. The coroutines it starts share what remains
+    /// of its budget. If this coroutine has limits itself, the strictest ones apply.
+    ///
+    /// # Panics
+    /// If the parameters of the `coroutine` are invalid, like [`Scope::start`].
+    pub fn start_with<Marker: 'static, T, C>(
+        &mut self,
+        limits: Limits,
+        coroutine: C,
+    ) -> CoroHandle<T>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        let (result_sender, receiver) = sync_once_channel();
+        let id = self
+            .build_coroutine(
+                self.owner,
+                true,
+                None,
+                Some(ResultSender::Handle(result_sender)),
+                None,
+                Some(limits),
+                coroutine,
+            )
+            .unwrap_or_else(|invalid| invalid.panic::<C>());
+        CoroHandle::Waiting { id, receiver }
+    }
+
+    /// Same as [`Scope::start`], but the `coroutine` only starts on the next tick, once the
+    /// commands queued during this one are applied, whereas [`Scope::start`] runs it within the
+    /// current tick. This lets it operate on entities or components created by those commands,
+    /// which do not exist yet when it is started.
+    ///
+    /// # Panics
+    /// If the parameters of the `coroutine` are invalid when it is created, like
+    /// [`Scope::start`].
+    pub fn spawn_on_next_tick<Marker: 'static, T, C>(&mut self, coroutine: C) -> CoroHandle<T>
     where
         C: CoroutineParamFunction<Marker, T>,
         T: Sync + Send + 'static,
     {
         let (result_sender, receiver) = sync_once_channel();
-        let id = self.build_coroutine(self.owner, true, None, Some(result_sender), coroutine)?;
-        Some(CoroHandle::Waiting { id, receiver })
+        let id = self
+            .build_coroutine(
+                self.owner,
+                false,
+                None,
+                Some(ResultSender::Handle(result_sender)),
+                None,
+                None,
+                coroutine,
+            )
+            .unwrap_or_else(|invalid| invalid.panic::<C>());
+        CoroHandle::Waiting { id, receiver }
+    }
+
+    /// Start the `coroutine` bound to `entity` when reaching the next `await`. Once it
+    /// finishes, its result is inserted as a component on `entity`. If the entity no longer
+    /// exists by then, the result is dropped. When the scope is dropped, the `coroutine` is
+    /// automatically dropped as well.
+    ///
+    /// Nothing is started if its parameters are invalid, for instance when `entity` lacks a
+    /// component they read.
+    pub fn start_into<Marker: 'static, T, C>(&mut self, entity: Entity, coroutine: C)
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Component,
+    {
+        self.build_coroutine(
+            Some(entity),
+            true,
+            Some(self.id),
+            Some(ResultSender::Insert(entity, insert_result::<T>)),
+            None,
+            None,
+            coroutine,
+        )
+        .ok();
     }
 
     /// Start the `coroutine` when reaching the next `await`. The coroutine cannot be dropped, and
     /// will be run until completion. This is unstructured and must be used with caution.
     ///
-    /// Note: If the coroutine is invalid (with conflicting parameters for instance), this function
-    /// has no effects.
-    pub fn start_forget<Marker: 'static, T, C>(&mut self, coroutine: C)
+    /// Returns the id of the coroutine, so it can still be cancelled later with
+    /// [`Scope::try_cancel`] or [`Executor::cancel_coroutine`], and is cancelled as well by
+    /// [`Executor::cancel_all`]. Like any other coroutine, it is also cancelled when its
+    /// parameters become invalid, for instance when the entity it reads is despawned.
+    ///
+    /// Returns [`None`] without starting anything if its parameters are invalid, because they
+    /// conflict for instance, since it has no owner.
+    ///
+    /// [`Executor::cancel_coroutine`]: crate::executor::Executor::cancel_coroutine
+    /// [`Executor::cancel_all`]: crate::executor::Executor::cancel_all
+    pub fn start_forget<Marker: 'static, T, C>(&mut self, coroutine: C) -> Option<Id>
     where
         C: CoroutineParamFunction<Marker, T>,
         T: Sync + Send + 'static,
     {
-        self.build_coroutine(None, true, None, None, coroutine);
+        self.build_coroutine(None, true, None, None, None, None, coroutine)
+            .ok()
+    }
+
+    /// Start the `coroutine` when reaching the next `await`, and returns a [`ForkHandle`] to it.
+    /// This is a middle ground between [`Scope::start`] and [`Scope::start_forget`]: the
+    /// coroutine is cancelled when the handle is dropped, unless [`ForkHandle::detach`] is
+    /// called, in which case it keeps running after this coroutine finished. It can still be
+    /// joined with [`ForkHandle::join`] in both cases.
+    ///
+    /// # Panics
+    /// If the parameters of the `coroutine` are invalid, like [`Scope::start`].
+    pub fn fork<Marker: 'static, T, C>(&mut self, coroutine: C) -> ForkHandle<T>
+    where
+        C: CoroutineParamFunction<Marker, T>,
+        T: Sync + Send + 'static,
+    {
+        ForkHandle::new(self.start(coroutine))
+    }
+
+    /// Start the `coroutine` when reaching the next `await`, and returns a [`BehaviorHandle`] to
+    /// it. Behaviors are meant to run forever, the handle can be used to send them messages of
+    /// type `M`, which they receive with [`Scope::mailbox`]. When the handle or the scope is
+    /// dropped, the `coroutine` is automatically dropped as well.
+    ///
+    /// # Panics
+    /// If the parameters of the behavior are invalid, since nothing could receive the messages.
+    pub fn start_behavior<Marker: 'static, M, C>(&mut self, coroutine: C) -> BehaviorHandle<M>
+    where
+        C: CoroutineParamFunction<Marker, ()>,
+        M: Send + 'static,
+    {
+        let (sender, receiver) = sync_once_channel();
+        let queue = Arc::new(MessageQueue::<M>::default());
+        let id = self
+            .build_coroutine(
+                self.owner,
+                true,
+                Some(self.id),
+                Some(ResultSender::Handle(sender)),
+                Some(queue.clone()),
+                None,
+                coroutine,
+            )
+            .unwrap_or_else(|invalid| invalid.panic::<C>());
+        BehaviorHandle::new(id, queue, receiver)
+    }
+
+    /// Returns the mailbox receiving the messages sent to this coroutine via its
+    /// [`BehaviorHandle`].
+    ///
+    /// # Panics
+    /// If this coroutine was not started with [`Scope::start_behavior`], with messages of type `M`.
+    pub fn mailbox<M: Send + 'static>(&mut self) -> Mailbox<'_, M> {
+        let queue = self
+            .mailbox
+            .clone()
+            .and_then(|mailbox| mailbox.downcast::<MessageQueue<M>>().ok())
+            .expect("This coroutine has no mailbox for this type of messages");
+        Mailbox::new(self, queue)
+    }
+
+    /// Returns true if the coroutine `id` has not finished nor been cancelled yet.
+    pub(crate) fn is_running(&self, id: Id) -> bool {
+        // SAFETY: The ids are only read while the coroutine is running
+        unsafe { self.resume_param.get().ids.as_ref().unwrap().contains(id) }
+    }
+
+    /// Register this coroutine under the marker type `M` on its owner, replacing the one
+    /// registered before, so that other coroutines can wait for it to finish with
+    /// [`Scope::await_marked`] without its handle.
+    ///
+    /// # Panics
+    /// If this coroutine has no owner.
+    pub fn register_as<M: 'static>(&mut self) {
+        let owner = self
+            .owner
+            .expect("Only coroutines with an owner can be registered under a marker");
+        let id = self.id;
+        self.storage().marked.register(owner, TypeId::of::<M>(), id);
+    }
+
+    /// Returns the query states cached by [`Scope::entity_has_all`].
+    pub(crate) fn query_states(&mut self) -> &mut QueryStates {
+        &mut self.storage().queries
+    }
+
+    /// Returns the coroutines to wake once what they wait on may be ready, see [`Wakeups`].
+    pub(crate) fn wakeups(&mut self) -> Wakeups {
+        self.storage().wakeups.clone()
+    }
+
+    /// Returns the coroutines registered under a marker, see [`Scope::register_as`].
+    pub(crate) fn marked_coroutines(&mut self) -> &MarkedCoroutines {
+        &self.storage().marked
+    }
+
+    /// Returns the value of type `T` stored for this coroutine, inserting its default value first
+    /// if there is none yet. Unlike local variables, it can be reached by the coroutines started
+    /// from this one with [`Scope::parent_resource`]. It is dropped once this coroutine ends.
+    pub fn scope_resource<T: Default + Send + Sync + 'static>(&mut self) -> &mut T {
+        let id = self.id;
+        self.storage().resources.get_or_default(id)
+    }
+
+    /// Returns the value of type `T` stored by the coroutine which started this one, with
+    /// [`Scope::scope_resource`]. Returns [`None`] if there is none, or if this coroutine was not
+    /// started from another one.
+    pub fn parent_resource<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        let parent = self.started_by?;
+        self.storage().resources.get_mut(parent)
+    }
+
+    /// Returns a future that resolve once the coroutine registered under the marker type `M` on
+    /// `entity` finishes, with its id, see [`Scope::register_as`]. It resolves right away with
+    /// [`None`] if no coroutine is registered, or if it already finished.
+    pub fn await_marked<M: 'static>(&mut self, entity: Entity) -> MarkedFuture<'_> {
+        MarkedFuture::new(self, entity, TypeId::of::<M>())
+    }
+
+    /// Cancel the coroutine `id` once this one yields, like dropping its handle would. Returns
+    /// false if it already finished or was cancelled. This is useful when its handle is no longer
+    /// reachable, since coroutines are not cancelled when their id is dropped.
+    pub fn try_cancel(&self, id: Id) -> bool {
+        // SAFETY: The ids and the channel are only read while the coroutine is running
+        unsafe {
+            let resume_param = self.resume_param.get();
+            if !resume_param.ids.as_ref().unwrap().contains(id) {
+                return false;
+            }
+            resume_param
+                .cancel_channel
+                .as_ref()
+                .unwrap()
+                .send(CancelMsg { id });
+        }
+        true
+    }
+
+    /// Same as [`Scope::try_cancel`], with the id of the coroutine of `handle`. Returns false if
+    /// it already finished or was cancelled.
+    pub fn try_cancel_handle<T>(&self, handle: &CoroHandle<T>) -> bool {
+        match handle {
+            CoroHandle::Waiting { id, .. } => self.try_cancel(*id),
+            _ => false,
+        }
     }
 
     /// Returns the [`Entity`] owning this [`Coroutine`], if it exists.
@@ -137,6 +1093,110 @@ impl Scope {
         self.owner
     }
 
+    /// Same as [`Scope::owner`], named after [`Scope::coroutine_id`].
+    pub fn owner_entity(&self) -> Option<Entity> {
+        self.owner
+    }
+
+    /// Returns the id of this coroutine, the same as the one of its handle, with which it can
+    /// be cancelled or awaited by marker for instance. It stays the same until it finishes.
+    pub fn coroutine_id(&self) -> Id {
+        self.id
+    }
+
+    /// Spawn an entity with the given `bundle` once the commands of this coroutine are applied,
+    /// which is despawned once this coroutine ends, either because it finished or because it
+    /// was cancelled. The entity is despawned at the end of the executor tick, after the
+    /// commands have been applied.
+    pub fn spawn_local<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.commands().spawn(bundle).id();
+        self.send_local_entity(entity);
+        entity
+    }
+
+    /// Same as [`Scope::spawn_local`], but the entity only has a [`CoroutineSpawned`] component,
+    /// referring to this coroutine.
+    pub fn spawn_empty_scoped(&mut self) -> Entity {
+        let by = self.id;
+        self.spawn_local(CoroutineSpawned { by })
+    }
+
+    /// Start recording the last `ticks` values of the component `T` of `entity`, each time it is
+    /// mutated through a [`Wr`](super::coro_param::component::Wr). Replaces any previous history.
+    ///
+    /// # Panics
+    /// If the entity does not exist.
+    #[cfg(feature = "debug")]
+    pub fn enable_history<T: Component + Clone>(&mut self, entity: Entity, ticks: u32) {
+        // SAFETY: The executor resumes coroutines one after the other, and borrowing the scope
+        // mutably ensures no item of the parameters of this one is alive, so nothing points into
+        // the archetype the entity is moved out of.
+        unsafe {
+            self.world_cell()
+                .world_mut()
+                .entity_mut(entity)
+                .insert(ComponentHistory::<T>::new(ticks as usize));
+        }
+    }
+
+    /// Returns the last `ticks` values the component `T` of `entity` had before being mutated,
+    /// from the oldest to the most recent. The history is empty if it was never enabled with
+    /// [`Scope::enable_history`].
+    #[cfg(feature = "debug")]
+    pub fn read_component_history<T: Component + Clone>(
+        &self,
+        entity: Entity,
+        ticks: u32,
+    ) -> ComponentHistory<T> {
+        // SAFETY: The history is only written to while mutating the component, which cannot
+        // happen while this coroutine is running.
+        unsafe {
+            self.world_cell()
+                .get_entity(entity)
+                .and_then(|e| e.get::<ComponentHistory<T>>())
+                .map(|history| history.last(ticks as usize))
+                .unwrap_or_else(|| ComponentHistory::new(0))
+        }
+    }
+
+    /// Returns why this coroutine is no longer valid, if it is being resumed one last time
+    /// because of a [`Graceful`](super::coro_param::graceful::Graceful) parameter. Awaiting
+    /// anything from there cancels the coroutine.
+    pub fn invalidation(&self) -> Option<CancelReason> {
+        unsafe { self.resume_param.get().invalidation }
+    }
+
+    /// How long ago the timer this coroutine was resumed by finished, zero otherwise.
+    pub(crate) fn overshoot(&self) -> Duration {
+        unsafe { self.resume_param.get().overshoot }
+    }
+
+    /// Add `delta` to the counter `name`, which can be read with
+    /// [`Executor::metrics_snapshot`](crate::executor::Executor::metrics_snapshot) once the
+    /// commands of this tick are applied. Counters persist across ticks.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_counter(&self, name: &'static str, delta: i64) {
+        use crate::executor::metrics::PendingMetrics;
+
+        self.commands()
+            .add(move |world: &mut bevy::prelude::World| {
+                world
+                    .get_resource_or_insert_with(PendingMetrics::default)
+                    .0
+                    .push((name, delta));
+            });
+    }
+
+    /// Returns the random number generator of this coroutine, which persists across awaits. It
+    /// is seeded when the coroutine is spawned: from the [`CoroSeed`](super::rng::CoroSeed)
+    /// and the id of the coroutine for the ones added to the executor, or from the generator of
+    /// their parent otherwise. The sequences are therefore reproducible, as long as the
+    /// coroutines are spawned in the same order.
+    #[cfg(feature = "rand")]
+    pub fn rng(&mut self) -> &mut CoroRng {
+        self.rng.get_mut().get()
+    }
+
     pub fn commands(&self) -> Commands<'_, '_> {
         unsafe {
             let entities = self.world_cell().entities();
@@ -149,6 +1209,89 @@ impl Scope {
         }
     }
 
+    /// Run `f` with the [`EntityCommands`] of the owner of this coroutine. Like with
+    /// [`Scope::commands`], they are deferred and applied at the next flush point (see
+    /// [`CommandFlushPoint`](crate::executor::CommandFlushPoint)), so no access needs to be
+    /// declared for them.
+    ///
+    /// # Panics
+    /// If this coroutine has no owner, see [`Scope::try_owner_commands`].
+    pub fn owner_commands<R>(&self, f: impl FnOnce(&mut EntityCommands<'_, '_, '_>) -> R) -> R {
+        self.try_owner_commands(f)
+            .expect("Owner commands were used in a coroutine without owner")
+    }
+
+    /// Same as [`Scope::owner_commands`], but returns [`None`] without running `f` if this
+    /// coroutine has no owner.
+    pub fn try_owner_commands<R>(
+        &self,
+        f: impl FnOnce(&mut EntityCommands<'_, '_, '_>) -> R,
+    ) -> Option<R> {
+        let owner = self.owner?;
+        let mut commands = self.commands();
+        Some(f(&mut commands.entity(owner)))
+    }
+
+    /// Run `f` with the [`EntityCommands`] of `entity`, to chain several commands on it, and
+    /// returns this scope for further calls. The commands are deferred, like with
+    /// [`Scope::owner_commands`].
+    pub fn with_entity(
+        &mut self,
+        entity: Entity,
+        f: impl FnOnce(&mut EntityCommands<'_, '_, '_>),
+    ) -> &mut Self {
+        f(&mut self.commands().entity(entity));
+        self
+    }
+
+    /// Insert `bundle` on the owner of this coroutine, once the commands are applied, see
+    /// [`Scope::owner_commands`].
+    ///
+    /// # Panics
+    /// If this coroutine has no owner.
+    pub fn insert(&self, bundle: impl Bundle) {
+        self.owner_commands(|owner| {
+            owner.insert(bundle);
+        });
+    }
+
+    /// Remove the bundle `B` from the owner of this coroutine, once the commands are applied,
+    /// see [`Scope::owner_commands`].
+    ///
+    /// # Panics
+    /// If this coroutine has no owner.
+    pub fn remove<B: Bundle>(&self) {
+        self.owner_commands(|owner| {
+            owner.remove::<B>();
+        });
+    }
+
+    /// Despawn all the descendants of the owner of this coroutine, once the commands are
+    /// applied, see [`Scope::owner_commands`].
+    ///
+    /// # Panics
+    /// If this coroutine has no owner.
+    pub fn despawn_descendants(&self) {
+        self.owner_commands(|owner| {
+            owner.despawn_descendants();
+        });
+    }
+
+    /// Save `state` as the last checkpoint of the persistent coroutine `script_id`, which it is
+    /// restarted from after a reload, see [`PersistentScripts`]. It is recorded once the commands
+    /// of this coroutine are applied.
+    ///
+    /// # Panics
+    /// If `state` fails to serialize, as a map with non-string keys does for instance.
+    pub fn checkpoint<S: Serialize + DeserializeOwned>(&self, script_id: &'static str, state: &S) {
+        let state = serde_json::to_value(state).expect("The checkpoint could not be serialized");
+        self.commands().add(move |world: &mut World| {
+            world
+                .get_resource_or_insert_with(PersistentScripts::default)
+                .save(script_id, state);
+        });
+    }
+
     pub fn bind_coroutine<Marker: 'static, T, C>(&self, to: Entity, coroutine: C) -> CoroHandle<T>
     where
         C: CoroutineParamFunction<Marker, T>,
@@ -156,11 +1299,93 @@ impl Scope {
     {
         let (sender, receiver) = sync_once_channel();
         let id = self
-            .build_coroutine(Some(to), false, Some(self.id), Some(sender), coroutine)
-            .unwrap();
+            .build_coroutine(
+                Some(to),
+                false,
+                Some(self.id),
+                Some(ResultSender::Handle(sender)),
+                None,
+                None,
+                coroutine,
+            )
+            .unwrap_or_else(|invalid| invalid.panic::<C>());
         CoroHandle::Waiting { id, receiver }
     }
 
+    /// Panics with a clear message if the coroutine is not being resumed, in which case the
+    /// world cannot be accessed.
+    fn assert_running(&self, method: &str) {
+        // SAFETY: Only the pointer is read, it is set only while the coroutine is running
+        let running = unsafe { !self.resume_param.get().world.is_null() };
+        assert!(
+            running,
+            "`Scope::{method}` can only be called while the coroutine is running, not from \
+            another thread or once it yielded"
+        );
+    }
+
+    /// Calls `f` with the world the coroutine runs in, for what the coroutine parameters cannot
+    /// express. All the operations of the cell are unsafe: accessing anything which is not
+    /// declared by the parameters of this coroutine, or keeping a reference across an `await`,
+    /// is undefined behavior if another coroutine accesses it meanwhile.
+    ///
+    /// # Panics
+    /// If called while this coroutine is not being resumed, from another thread for instance.
+    /// Or if the access declared by this coroutine conflicts with the one of another coroutine
+    /// which did not finish, as both would then be free to alias the same component.
+    pub fn map_world<T>(&self, f: impl FnOnce(UnsafeWorldCell<'_>) -> T) -> T {
+        self.assert_running("map_world");
+        let world = self.world_cell();
+        let access = &self.meta().access;
+        // SAFETY: The executor lends its storage for the duration of the resume, and nothing
+        // else borrows it while this coroutine only reads the accesses of the other ones
+        let storage = unsafe { self.resume_param.get().storage.as_ref().unwrap() };
+        for (other, (name, other_access)) in &storage.accesses {
+            if *other == self.id {
+                continue;
+            }
+            if let Some((source, component)) = access.conflict_with(other_access) {
+                let component = world
+                    .components()
+                    .get_info(component)
+                    .map_or("unknown component", |info| info.name());
+                panic!(
+                    "`Scope::map_world` called by the coroutine {:?} ({}), whose access to \
+                    `{component}` on {source:?} conflicts with the one of the coroutine {other:?} \
+                    ({name})",
+                    self.id,
+                    self.meta().name,
+                );
+            }
+        }
+        f(world)
+    }
+
+    /// Same as [`Scope::map_world`], with a cell only allowing to read the world.
+    ///
+    /// # Panics
+    /// If called while this coroutine is not being resumed, from another thread for instance.
+    pub fn map_world_readonly<T>(&self, f: impl FnOnce(UnsafeWorldCell<'_>) -> T) -> T {
+        self.assert_running("map_world_readonly");
+        // SAFETY: The world is set while the coroutine is running, and only shared with `f`
+        let world = unsafe { self.resume_param.get().world.as_ref().unwrap() };
+        f(world.as_unsafe_world_cell_readonly())
+    }
+
+    /// Returns the world the coroutine is resumed with.
+    ///
+    /// # Safety
+    /// The executor must hold the world exclusively, with no other coroutine running, which is
+    /// the case once it yielded [`CoroStatus::Exclusive`].
+    pub(crate) unsafe fn exclusive_world(&mut self) -> &mut World {
+        self.resume_param.get().world.as_mut().unwrap()
+    }
+
+    /// Returns a handle to the resume parameters of this coroutine, which can outlive a resume.
+    pub(crate) fn resume_param(&self) -> WeakResume<ResumeParam> {
+        self.resume_param.downgrade()
+    }
+
     pub(crate) fn world_cell(&self) -> UnsafeWorldCell<'_> {
         unsafe {
             self.resume_param
@@ -172,13 +1397,118 @@ impl Scope {
         }
     }
 
+    /// Returns a future resolving with the new value of the watch `token`, once the executor
+    /// notices it changed, at the start of a tick. Changes happening while the coroutine is not
+    /// waiting on this future are not observed.
+    pub fn await_watch<T>(&mut self, token: &WatchToken<T>) -> WatchFuture<'_, T>
+    where
+        T: Clone + PartialEq + Send + Sync + 'static,
+    {
+        WatchFuture::new(self, token.clone())
+    }
+
+    /// Panics if this coroutine did not declare a read access to `component` of `source`. Must
+    /// be called by [`CoroParam`](super::coro_param::CoroParam) implementations before reading
+    /// the world. Only checked in debug builds with the `validate-access` feature, it does
+    /// nothing otherwise.
+    #[allow(unused_variables)]
+    pub fn validate_read(&self, source: SourceId, component: ComponentId) {
+        #[cfg(all(feature = "validate-access", debug_assertions))]
+        {
+            let meta = self.meta();
+            if !meta.access.can_read(source, component) {
+                panic!(
+                    "The coroutine `{}` read ({:?}, {:?}) without declaring it",
+                    meta.name, source, component
+                );
+            }
+        }
+    }
+
+    /// Same as [`Scope::validate_read`], but for a write access.
+    #[allow(unused_variables)]
+    pub fn validate_write(&self, source: SourceId, component: ComponentId) {
+        #[cfg(all(feature = "validate-access", debug_assertions))]
+        {
+            let meta = self.meta();
+            if !meta.access.can_write(source, component) {
+                panic!(
+                    "The coroutine `{}` wrote ({:?}, {:?}) without declaring it",
+                    meta.name, source, component
+                );
+            }
+        }
+    }
+
+    /// Returns the component `T` of `entity`, once checked with [`Scope::validate_read`].
+    /// [`CoroParam`](super::coro_param::CoroParam) implementations read components through it,
+    /// so that undeclared reads are caught with the `validate-access` feature.
+    ///
+    /// # Safety
+    /// This coroutine must have declared reading `T` on `entity`, and the reference must not be
+    /// kept across an await.
+    pub unsafe fn get_component<T: Component>(&self, entity: Entity) -> Option<&T> {
+        let world = self.world_cell();
+        let id = world.components().component_id::<T>()?;
+        self.validate_read(SourceId::Entity(entity), id);
+        world.get_entity(entity)?.get::<T>()
+    }
+
+    /// Same as [`Scope::get_component`], but for a write access, checked with
+    /// [`Scope::validate_write`].
+    ///
+    /// # Safety
+    /// This coroutine must have declared writing `T` on `entity`, and the reference must not be
+    /// kept across an await.
+    pub unsafe fn get_component_mut<T: Component>(&self, entity: Entity) -> Option<Mut<'_, T>> {
+        let world = self.world_cell();
+        let id = world.components().component_id::<T>()?;
+        self.validate_write(SourceId::Entity(entity), id);
+        world.get_entity(entity)?.get_mut::<T>()
+    }
+
+    /// Returns the resource `R`, once checked with [`Scope::validate_read`].
+    ///
+    /// # Safety
+    /// This coroutine must have declared reading `R`, and the reference must not be kept across
+    /// an await.
+    pub unsafe fn get_resource<R: Resource>(&self) -> Option<&R> {
+        let world = self.world_cell();
+        let id = world.components().resource_id::<R>()?;
+        self.validate_read(SourceId::World, id);
+        world.get_resource::<R>()
+    }
+
+    /// Returns the values kept by the executor for the coroutines.
+    fn storage(&mut self) -> &mut ScopeStorage {
+        // SAFETY: The executor lends its storage for the duration of the resume, and only one
+        // coroutine is resumed at a time. The returned reference borrows this scope mutably, so
+        // it cannot be kept across an await point, nor alias another one taken from this scope.
+        unsafe { self.resume_param.get().storage.as_mut().unwrap() }
+    }
+
+    fn meta(&self) -> &CoroMeta {
+        // SAFETY: The metadata outlive the coroutine, and are only set while it is polled
+        unsafe { self.resume_param.get().meta.as_ref().unwrap() }
+    }
+
+    /// The metadata of this coroutine, only valid while it is polled.
+    pub(crate) fn meta_ptr(&self) -> *mut CoroMeta {
+        // SAFETY: Only the pointer is copied
+        unsafe { self.resume_param.get().meta }
+    }
+
     /// Emit the given signal
     pub(crate) fn emit_signal(&self, id: SignalId) {
+        self.send_emit(id, None);
+    }
+
+    fn send_emit(&self, id: SignalId, latch: Option<Latch>) {
         // Safety: None, fuck it
         unsafe {
             let by = self.resume_param.get().curr_node;
             let sender = self.resume_param.get().emit_channel.as_ref().unwrap();
-            sender.send(EmitMsg { id, by });
+            sender.send(EmitMsg { id, by, latch });
         };
     }
 
@@ -190,6 +1520,21 @@ impl Scope {
         }
     }
 
+    /// Notify the executor that `entity` must be despawned once this coroutine ends
+    fn send_local_entity(&self, entity: Entity) {
+        unsafe {
+            self.resume_param
+                .get()
+                .local_entity_channel
+                .as_ref()
+                .unwrap()
+                .send(LocalEntityMsg {
+                    owner: self.id,
+                    entity,
+                });
+        }
+    }
+
     /// Send a new coroutine to the executor
     fn send_new_coro(&self, new_coro: NewCoroutine) {
         unsafe {
@@ -212,14 +1557,17 @@ impl Scope {
     }
 
     /// Build a new coroutine with various parameter
+    #[allow(clippy::too_many_arguments)]
     fn build_coroutine<Marker: 'static, T, C>(
         &self,
         owner: Option<Entity>,
         start_now: bool,
         parent_scope: Option<Id>,
-        result_sender: Option<OnceSender<T>>,
+        result_sender: Option<ResultSender<T>>,
+        mailbox: Option<Arc<dyn Any + Send + Sync>>,
+        limits: Option<Limits>,
         coroutine: C,
-    ) -> Option<Id>
+    ) -> Result<Id, InvalidParams>
     where
         C: CoroutineParamFunction<Marker, T>,
         T: Sync + Send + 'static,
@@ -228,7 +1576,12 @@ impl Scope {
         let new_scope = Self {
             id: self.alloc_id(),
             owner,
+            started_by: Some(self.id),
             resume_param: resume_param.clone(),
+            mailbox,
+            frame_sync: Duration::ZERO,
+            #[cfg(feature = "rand")]
+            rng: std::cell::RefCell::new(self.rng.borrow_mut().child()),
         };
 
         let new_id = new_scope.id;
@@ -236,6 +1589,7 @@ impl Scope {
         let coroutine = FunctionCoroutine::new(
             new_scope,
             self.world_cell(),
+            self.commands(),
             resume_param,
             new_id,
             result_sender,
@@ -247,10 +1601,12 @@ impl Scope {
             ran_after: self.curr_node(),
             coroutine: SyncCell::new(Box::pin(coroutine)),
             is_owned_by: parent_scope,
+            started_by: self.id,
+            limits,
             should_start_now: start_now,
         });
 
-        Some(new_id)
+        Ok(new_id)
     }
 
     fn curr_node(&self) -> usize {
@@ -0,0 +1,38 @@
+use std::any::{Any, TypeId};
+
+use bevy::utils::HashMap;
+
+use crate::id_alloc::Id;
+
+/// The values stored by each coroutine with
+/// [`Scope::scope_resource`](super::scope::Scope::scope_resource), by type, kept in the
+/// [`ScopeStorage`](crate::executor::ScopeStorage) of the executor. They are dropped at the end
+/// of the tick where their coroutine ends.
+#[derive(Default)]
+pub(crate) struct ScopeResources(HashMap<Id, HashMap<TypeId, Box<dyn Any + Send + Sync>>>);
+
+impl ScopeResources {
+    /// Returns the value of type `T` stored by `id`, inserting its default value if needed.
+    pub fn get_or_default<T: Default + Send + Sync + 'static>(&mut self, id: Id) -> &mut T {
+        self.0
+            .entry(id)
+            .or_default()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Returns the value of type `T` stored by `id`, if any.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self, id: Id) -> Option<&mut T> {
+        self.0
+            .get_mut(&id)?
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut()
+    }
+
+    /// Drop the values of the coroutines for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(Id) -> bool) {
+        self.0.retain(|id, _| keep(*id));
+    }
+}
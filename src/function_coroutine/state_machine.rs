@@ -0,0 +1,103 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bevy::time::{Timer, TimerMode};
+use pin_project::pin_project;
+
+pub use corentin_macros::EntityCoroutine;
+
+use crate::executor::msg::CoroStatus;
+
+use super::{coro_param::CoroParam, scope::Scope};
+
+/// The parameters of a [`CoroutineStateMachine`], implemented with `#[derive(EntityCoroutine)]`
+/// from the `#[param(...)]` attributes of the struct, in order.
+pub trait StateMachineParams {
+    type Params: CoroParam;
+}
+
+/// A coroutine written as a state machine over its parameters, rather than as an `async`
+/// function. Deriving [`EntityCoroutine`] makes the struct usable wherever a coroutine function
+/// is, such as [`coroutine`](crate::commands::coroutine).
+///
+/// Unlike a [`CoroutineState`](crate::state::CoroutineState), it can declare parameters and use
+/// the [`Scope`], but cannot be saved.
+pub trait CoroutineStateMachine: StateMachineParams + Send + 'static {
+    /// Run when the coroutine is first resumed, [`CoroutineStateMachine::update`] is run from
+    /// the next tick on. Does nothing by default.
+    #[allow(unused_variables)]
+    fn init(&mut self, scope: &mut Scope, params: &mut Self::Params) {}
+
+    /// Run each time the coroutine is resumed after the first one, until it returns
+    /// [`CoroutineStatus::Done`].
+    fn update(&mut self, scope: &mut Scope, params: &mut Self::Params) -> CoroutineStatus;
+}
+
+/// What a [`CoroutineStateMachine`] waits on after an update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoroutineStatus {
+    NextTick,
+    Duration(Duration),
+    Done,
+}
+
+/// The marker of the coroutine functions implemented by deriving [`EntityCoroutine`].
+pub struct StateMachineMarker;
+
+/// Runs a [`CoroutineStateMachine`] as the future of a coroutine.
+#[doc(hidden)]
+#[pin_project]
+pub struct StateMachineFuture<S: StateMachineParams> {
+    machine: S,
+    /// Dropped once the machine is done, as the coroutine must not outlive its scope
+    scope: Option<Scope>,
+    params: S::Params,
+    started: bool,
+}
+
+impl<S: CoroutineStateMachine> StateMachineFuture<S> {
+    pub fn new(machine: S, scope: Scope, params: S::Params) -> Self {
+        Self {
+            machine,
+            scope: Some(scope),
+            params,
+            started: false,
+        }
+    }
+}
+
+impl<S: CoroutineStateMachine> Future for StateMachineFuture<S> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let scope = this
+            .scope
+            .as_mut()
+            .expect("Polled a finished state machine");
+
+        let status = if *this.started {
+            this.machine.update(scope, this.params)
+        } else {
+            *this.started = true;
+            this.machine.init(scope, this.params);
+            CoroutineStatus::NextTick
+        };
+
+        match status {
+            CoroutineStatus::NextTick => scope.yield_(CoroStatus::Tick),
+            CoroutineStatus::Duration(duration) => {
+                scope.yield_(CoroStatus::Duration(Timer::new(duration, TimerMode::Once)))
+            }
+            CoroutineStatus::Done => {
+                *this.scope = None;
+                return Poll::Ready(());
+            }
+        }
+        Poll::Pending
+    }
+}
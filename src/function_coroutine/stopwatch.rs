@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use super::scope::Scope;
+
+/// Measures the time elapsed across the awaits of a coroutine, according to the
+/// [`Time`](bevy::time::Time) resource rather than the wall-clock. Created with
+/// [`Scope::stopwatch`].
+#[derive(Clone, Copy, Debug)]
+pub struct CoroStopwatch {
+    /// When it was started, or last reset
+    start: Duration,
+    /// When it was paused, if it is
+    paused_at: Option<Duration>,
+    /// The total time spent paused since the start
+    paused: Duration,
+}
+
+impl CoroStopwatch {
+    pub(crate) fn new(now: Duration) -> Self {
+        Self {
+            start: now,
+            paused_at: None,
+            paused: Duration::ZERO,
+        }
+    }
+
+    /// Returns the time elapsed since this stopwatch was created or reset, excluding the time
+    /// spent paused.
+    pub fn elapsed(&self, scope: &Scope) -> Duration {
+        let now = self.paused_at.unwrap_or_else(|| scope.elapsed_time());
+        now.saturating_sub(self.start).saturating_sub(self.paused)
+    }
+
+    /// Start measuring from zero again. Does not change whether it is paused.
+    pub fn reset(&mut self, scope: &Scope) {
+        let now = scope.elapsed_time();
+        self.start = now;
+        self.paused = Duration::ZERO;
+        if self.paused_at.is_some() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    /// Stop measuring time, until [`CoroStopwatch::unpause`] is called.
+    pub fn pause(&mut self, scope: &Scope) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(scope.elapsed_time());
+        }
+    }
+
+    /// Measure time again after a call to [`CoroStopwatch::pause`].
+    pub fn unpause(&mut self, scope: &Scope) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused += scope.elapsed_time().saturating_sub(paused_at);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+}
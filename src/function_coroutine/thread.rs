@@ -0,0 +1,43 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::tasks::{AsyncComputeTaskPool, Task, TaskPool};
+
+use crate::executor::msg::CoroStatus;
+
+use super::scope::Scope;
+
+/// A future resolving with the result of a closure run on the [`AsyncComputeTaskPool`],
+/// checked once per tick. Created with [`Scope::run_in_thread`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ThreadFuture<'a, T> {
+    scope: &'a mut Scope,
+    task: Task<T>,
+}
+
+impl<'a, T: Send + 'static> ThreadFuture<'a, T> {
+    pub fn new(scope: &'a mut Scope, f: impl FnOnce() -> T + Send + 'static) -> Self {
+        let pool = AsyncComputeTaskPool::init(TaskPool::default);
+        Self {
+            scope,
+            task: pool.spawn(async move { f() }),
+        }
+    }
+}
+
+impl<T> Future for ThreadFuture<'_, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.task).poll(cx) {
+            Poll::Ready(value) => Poll::Ready(value),
+            Poll::Pending => {
+                self.scope.yield_(CoroStatus::Tick);
+                Poll::Pending
+            }
+        }
+    }
+}
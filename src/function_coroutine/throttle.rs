@@ -0,0 +1,83 @@
+use bevy::time::{Timer, TimerMode};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use super::CoroState;
+use super::CoroStatus;
+use super::Scope;
+
+/// Limits how often a section of a coroutine runs, to at most once per period of the
+/// [`Time`](bevy::time::Time) resource. Created once with [`Scope::throttle`], outside of the
+/// loop it throttles.
+#[derive(Clone, Copy, Debug)]
+pub struct Throttle {
+    period: Duration,
+    /// When it last resolved, if it did
+    last: Option<Duration>,
+}
+
+impl Throttle {
+    pub(crate) fn new(period: Duration) -> Self {
+        Self { period, last: None }
+    }
+
+    /// Returns a future that resolve right away the first time, or if at least the period
+    /// elapsed since it last resolved. Otherwise, it waits for the remainder, which does not
+    /// elapse while the coroutine is paused. The period starts over once it resolves, so a
+    /// long stall is followed by a single resolution, not by a burst of them.
+    pub fn wait<'a>(&'a mut self, scope: &'a mut Scope) -> ThrottleFuture<'a> {
+        ThrottleFuture {
+            scope,
+            throttle: self,
+            state: CoroState::Running,
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+/// A future resolving once the period of a [`Throttle`] elapsed, created with
+/// [`Throttle::wait`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ThrottleFuture<'a> {
+    scope: &'a mut Scope,
+    throttle: &'a mut Throttle,
+    state: CoroState,
+}
+
+impl Future for ThrottleFuture<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        let now = self.scope.elapsed_time();
+        match self.state {
+            // We assume the executor will only poll it once the remainder is over
+            CoroState::Halted => {
+                self.state = CoroState::Running;
+                self.throttle.last = Some(now);
+                Poll::Ready(())
+            }
+            CoroState::Running => {
+                let remainder = self.throttle.last.map_or(Duration::ZERO, |last| {
+                    self.throttle
+                        .period
+                        .saturating_sub(now.saturating_sub(last))
+                });
+                if remainder.is_zero() {
+                    self.throttle.last = Some(now);
+                    return Poll::Ready(());
+                }
+
+                self.state = CoroState::Halted;
+                let status = CoroStatus::Duration(Timer::new(remainder, TimerMode::Once));
+                self.scope.yield_(status);
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::{Deref, DerefMut};
+
+use bevy::{
+    ecs::component::ComponentId,
+    prelude::{Component, Entity, Mut},
+};
+
+use crate::{executor::msg::SignalId, CoroMeta, SourceId};
+
+#[cfg(feature = "debug")]
+use super::history::ComponentHistory;
+use super::{coro_param::on_change::ChangeTracker, scope::Scope};
+
+/// Exclusive access to the component `T` of an entity, whose write access is declared for as long
+/// as it is held, and released when dropped. Created with [`Scope::get_mut_unique`]. It cannot be
+/// held across any await.
+pub struct UniqueMut<'a, T: Component> {
+    value: Mut<'a, T>,
+    meta: *mut CoroMeta,
+    source: SourceId,
+    id: ComponentId,
+}
+
+impl<'a, T: Component> UniqueMut<'a, T> {
+    pub(crate) fn new(scope: &'a Scope, entity: Entity) -> Option<Self> {
+        let world = scope.world_cell();
+        let id = world.components().component_id::<T>()?;
+        let source = SourceId::Entity(entity);
+        let meta = scope.meta_ptr();
+
+        // SAFETY: The metadata are only set while the coroutine is polled, and are not borrowed
+        // by anything else meanwhile
+        unsafe {
+            let cell = world.get_entity(entity)?;
+            if !cell.contains::<T>() || !(*meta).access.add_write(source, id) {
+                return None;
+            }
+
+            #[cfg(feature = "debug")]
+            if let Some(mut history) = cell.get_mut::<ComponentHistory<T>>() {
+                history.record(cell.get::<T>().unwrap());
+            }
+
+            let signal_id = SignalId::component(id, Some(entity));
+            // Also received by the coroutines waiting on a change made by another one
+            if cell.contains::<ChangeTracker<T>>() || scope.has_waiters(signal_id) {
+                scope.emit_signal(signal_id);
+            }
+
+            Some(Self {
+                value: cell.get_mut::<T>().unwrap(),
+                meta,
+                source,
+                id,
+            })
+        }
+    }
+}
+
+impl<T: Component> Deref for UniqueMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: Component> DerefMut for UniqueMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<T: Component> Drop for UniqueMut<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: See `UniqueMut::new`, the guard does not outlive the poll it was created in
+        unsafe { (*self.meta).access.remove_write(self.source, self.id) };
+    }
+}
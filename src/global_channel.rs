@@ -1,4 +1,7 @@
-use std::cell::UnsafeCell;
+use std::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use bevy::{
     ecs::{
@@ -28,6 +31,11 @@ impl<T: Send> Channel<T> {
         unsafe { cell.get().as_mut().unwrap() }.push(value);
     }
 
+    /// Returns true if no message was sent since the last [`Channel::receive`].
+    pub fn is_empty(&mut self) -> bool {
+        self.chan.iter_mut().all(|q| q.get_mut().is_empty())
+    }
+
     pub fn receive(&mut self) -> impl Iterator<Item = T> + '_ {
         self.chan.iter_mut().flat_map(|q| q.get_mut().drain(..))
     }
@@ -36,12 +44,15 @@ impl<T: Send> Channel<T> {
 #[derive(Default)]
 pub struct CommandChannel {
     storage: ThreadLocal<UnsafeCell<CommandQueue>>,
+    /// The number of times [`CommandChannel::commands`] was called
+    issued: AtomicUsize,
 }
 
 impl CommandChannel {
     pub fn add(&self, _c: impl Command) {}
 
-    pub fn commands<'a>(&'a self, entities: &'a Entities) -> Commands<'_, '_> {
+    pub fn commands<'a>(&'a self, entities: &'a Entities) -> Commands<'a, 'a> {
+        self.issued.fetch_add(1, Ordering::Relaxed);
         let queue = unsafe { self.storage.get_or_default().get().as_mut().unwrap() };
 
         Commands::new_from_entities(queue, entities)
@@ -52,4 +63,14 @@ impl CommandChannel {
             queue.get_mut().apply(world);
         }
     }
+
+    /// Returns the number of times commands were queued since the channel was created.
+    pub fn issued(&self) -> usize {
+        self.issued.load(Ordering::Relaxed)
+    }
+
+    /// Take the queued commands without applying them, to apply them later on.
+    pub fn take(&mut self) -> impl Iterator<Item = CommandQueue> + '_ {
+        self.storage.iter_mut().map(|q| std::mem::take(q.get_mut()))
+    }
 }
@@ -1,6 +1,8 @@
 /// Taken from Bevy (will put a better notice later on)
 use std::{convert::TryFrom, fmt, sync::atomic::Ordering};
 
+use bevy::reflect::Reflect;
+
 #[cfg(target_has_atomic = "64")]
 use std::sync::atomic::AtomicI64 as AtomicIdCursor;
 #[cfg(target_has_atomic = "64")]
@@ -15,7 +17,8 @@ use std::sync::atomic::AtomicIsize as AtomicIdCursor;
 type IdCursor = isize;
 
 /// A unique, reusable identifier.
-#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, Reflect)]
+#[reflect_value(Hash, PartialEq)]
 pub struct Id {
     generation: u32,
     index: u32,
@@ -201,7 +204,9 @@ impl Ids {
     /// Reserve entity IDs concurrently.
     ///
     /// Storage for entity generation and location is lazily allocated by calling [`flush`](Entities::flush).
-    pub fn allocate_ids(&self, count: u32) -> ReserveEntitiesIterator {
+    // The conversions are only fallible where `IdCursor` is an `isize`
+    #[allow(clippy::unnecessary_fallible_conversions)]
+    pub fn allocate_ids(&self, count: u32) -> ReserveEntitiesIterator<'_> {
         // Use one atomic subtract to grab a range of new IDs. The range might be
         // entirely nonnegative, meaning all IDs come from the freelist, or entirely
         // negative, meaning they are all new IDs to allocate, or a mix of both.
@@ -317,6 +322,8 @@ impl Ids {
     }
 
     /// Ensure at least `n` allocations can succeed without reallocating.
+    // The conversion is only fallible where `IdCursor` is an `isize`
+    #[allow(clippy::unnecessary_fallible_conversions)]
     pub fn reserve(&mut self, additional: u32) {
         self.flush_if_needed();
 
@@ -334,7 +341,7 @@ impl Ids {
     // not reallocated since the generation is incremented in `free`
     pub fn contains(&self, id: Id) -> bool {
         self.resolve_from_id(id.index())
-            .map_or(false, |e| e.generation() == id.generation)
+            .is_some_and(|e| e.generation() == id.generation)
     }
 
     /// Clears all [`Entity`] from the World.
@@ -1,20 +1,27 @@
 use std::pin::Pin;
+use std::time::Duration;
 
 use bevy::ecs::component::ComponentId;
 
 use bevy::ecs::world::unsafe_world_cell::UnsafeWorldCell;
+use bevy::ecs::world::WorldId;
+use bevy::prelude::Commands;
 use bevy::prelude::Entity;
 use bevy::prelude::World;
 use bevy::utils::synccell::SyncCell;
 use bevy::utils::HashMap;
+use executor::msg::CancelMsg;
+use executor::msg::CancelReason;
 use executor::msg::CoroStatus;
 use executor::msg::YieldMsg;
+use executor::{ScopeStorage, SignalWaiters};
 use global_channel::Channel;
 use global_channel::CommandChannel;
 use id_alloc::Id;
 use tinyset::SetUsize;
 
 use self::executor::msg::EmitMsg;
+use self::executor::msg::LocalEntityMsg;
 use self::executor::msg::NewCoroutine;
 
 use self::id_alloc::Ids;
@@ -24,7 +31,15 @@ pub mod executor;
 pub mod function_coroutine;
 pub mod global_channel;
 pub mod id_alloc;
+mod macros;
+pub mod persistent;
 pub mod plugin;
+pub mod state;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+// Allows the derive macros to refer to this crate by name, from within this crate
+extern crate self as corentin;
 
 pub mod prelude {
     #[doc(hidden)]
@@ -35,10 +50,12 @@ pub mod prelude {
 
     #[doc(hidden)]
     pub use crate::plugin::*;
+
+    #[doc(hidden)]
+    pub use crate::{co, co_owned};
 }
 
 // THINGS MISSING:
-// Dropping a scope should drop the local entities
 // SIGNALS !!!
 
 /// A coroutine is a form of state machine. It can get resumed, and returns on which condition it
@@ -46,6 +63,7 @@ pub mod prelude {
 pub trait Coroutine: Send + 'static {
     /// Resume execution of this coroutine and returns it's new status.
     /// All other side effects are communicated back via channels.
+    #[allow(clippy::too_many_arguments)]
     fn resume(
         self: Pin<&mut Self>,
         world: &mut World,
@@ -53,7 +71,11 @@ pub trait Coroutine: Send + 'static {
         curr_node: usize,
         emit_channel: &Channel<EmitMsg>,
         new_coro_channel: &Channel<NewCoroutine>,
+        local_entity_channel: &Channel<LocalEntityMsg>,
+        cancel_channel: &Channel<CancelMsg>,
         commands_channel: &CommandChannel,
+        waiters: &SignalWaiters,
+        storage: &mut ScopeStorage,
     ) -> CoroStatus;
 
     /// Resume this coroutine, but with an [`UnsafeWorldCell`] to access the [`World`].
@@ -71,7 +93,11 @@ pub trait Coroutine: Send + 'static {
         curr_node: usize,
         emit_channel: &Channel<EmitMsg>,
         new_coro_channel: &Channel<NewCoroutine>,
+        local_entity_channel: &Channel<LocalEntityMsg>,
+        cancel_channel: &Channel<CancelMsg>,
         commands_channel: &CommandChannel,
+        waiters: &SignalWaiters,
+        storage: &mut ScopeStorage,
         yield_channel: &Channel<YieldMsg>,
     );
 
@@ -81,21 +107,49 @@ pub trait Coroutine: Send + 'static {
 
     /// Returns this coroutine metadata
     fn meta(&self) -> &CoroMeta;
+
+    /// Notify this coroutine that it is no longer valid, and will be resumed one last time
+    /// before being cancelled. Only called for coroutines whose metadata are `graceful`.
+    fn invalidate(self: Pin<&mut Self>, reason: CancelReason);
+
+    /// Notify this coroutine of how long ago the timer it waits on finished, right before it
+    /// is resumed because of it.
+    fn set_overshoot(self: Pin<&mut Self>, _overshoot: Duration) {}
+
+    /// Returns the current state of this coroutine serialized, if it can be saved (see
+    /// [`state::CoroutineState`]).
+    fn save(&self) -> Option<serde_json::Value> {
+        None
+    }
 }
 
 pub struct CoroMeta {
     id: Id,
     owner: Option<Entity>,
     access: CoroAccess,
+    /// The name of the coroutine, for debugging purposes
+    name: &'static str,
+    /// If true, the coroutine is resumed one last time once invalid, instead of being cancelled
+    /// right away
+    graceful: bool,
+    /// The elapsed [`Time`](bevy::time::Time) when the coroutine was created
+    spawned_at: Duration,
+    /// Commands queued for the owner while initializing the parameters, since the world cannot
+    /// be mutated at this point
+    setup: Vec<fn(Entity, &mut Commands)>,
+    /// The world in which the parameters were initialized, if any
+    world_id: Option<WorldId>,
 }
 
 #[derive(Default, Clone)]
 pub struct CoroAccess {
     reads: HashMap<SourceId, SetUsize>,
     writes: HashMap<SourceId, SetUsize>,
+    /// The last component whose access was rejected, to report which one conflicts
+    conflict: Option<ComponentId>,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub enum SourceId {
     Entity(Entity),
     AllEntities,
@@ -106,28 +160,136 @@ impl CoroAccess {
     /// Add a write access. Returns false if there is a conflict.
     /// The access is updated only when no conflicts are found.
     pub fn add_write(&mut self, to: SourceId, component: ComponentId) -> bool {
-        if let Some(reads) = self.reads.get(&to) {
-            if reads.contains(component.index()) {
-                return false;
+        if Self::overlaps(&self.reads, to, component) || Self::overlaps(&self.writes, to, component)
+        {
+            self.conflict = Some(component);
+            return false;
+        }
+
+        self.writes.entry(to).or_default().insert(component.index());
+
+        true
+    }
+
+    /// Returns true if reading `component` from `source` was declared, either as a read or a
+    /// write, on this specific source or on all entities.
+    pub fn can_read(&self, source: SourceId, component: ComponentId) -> bool {
+        self.declared(&self.reads, source, component) || self.can_write(source, component)
+    }
+
+    /// Remove a write access previously added with [`CoroAccess::add_write`].
+    pub fn remove_write(&mut self, to: SourceId, component: ComponentId) {
+        if let Some(components) = self.writes.get_mut(&to) {
+            components.remove(component.index());
+            if components.is_empty() {
+                self.writes.remove(&to);
             }
         }
+    }
+
+    /// Returns true if writing `component` to `source` was declared, on this specific source or
+    /// on all entities.
+    pub fn can_write(&self, source: SourceId, component: ComponentId) -> bool {
+        self.declared(&self.writes, source, component)
+    }
+
+    fn declared(
+        &self,
+        accesses: &HashMap<SourceId, SetUsize>,
+        source: SourceId,
+        component: ComponentId,
+    ) -> bool {
+        let contains = |source| {
+            accesses
+                .get(&source)
+                .is_some_and(|c| c.contains(component.index()))
+        };
 
-        self.writes.entry(to).or_default().insert(component.index())
+        contains(source)
+            || (matches!(source, SourceId::Entity(_)) && contains(SourceId::AllEntities))
     }
 
     /// Add a read access. Returns false if there is a conflict.
     /// The access is updated only when no conflicts are found.
     pub fn add_read(&mut self, to: SourceId, component: ComponentId) -> bool {
-        if let Some(reads) = self.writes.get(&to) {
-            if reads.contains(component.index()) {
-                return false;
-            }
+        if Self::overlaps(&self.writes, to, component) {
+            self.conflict = Some(component);
+            return false;
         }
 
         self.reads.entry(to).or_default().insert(component.index());
 
         true
     }
+
+    /// Returns true if `accesses` contains `component` for a source overlapping `to`: the same
+    /// one, all the entities if `to` is an entity, or any entity if `to` is all of them.
+    fn overlaps(
+        accesses: &HashMap<SourceId, SetUsize>,
+        to: SourceId,
+        component: ComponentId,
+    ) -> bool {
+        accesses.iter().any(|(source, components)| {
+            let overlapping = match (*source, to) {
+                (SourceId::Entity(_), SourceId::AllEntities)
+                | (SourceId::AllEntities, SourceId::Entity(_)) => true,
+                (source, to) => source == to,
+            };
+            overlapping && components.contains(component.index())
+        })
+    }
+
+    /// Returns a component written through one of the accesses, and read or written through the
+    /// other, from overlapping sources. Returns [`None`] if they do not conflict.
+    pub fn conflict_with(&self, other: &CoroAccess) -> Option<(SourceId, ComponentId)> {
+        let find = |a: &HashMap<SourceId, SetUsize>, b: &HashMap<SourceId, SetUsize>| {
+            b.iter().find_map(|(source, components)| {
+                components
+                    .iter()
+                    .map(ComponentId::new)
+                    .find(|component| Self::overlaps(a, *source, *component))
+                    .map(|component| (*source, component))
+            })
+        };
+
+        find(&self.writes, &other.writes)
+            .or_else(|| find(&self.writes, &other.reads))
+            .or_else(|| find(&self.reads, &other.writes))
+    }
+
+    /// Merge `other` into this access, as if both were declared by the same coroutine. Returns
+    /// false if there is a conflict, when a component of a source is written by one and read or
+    /// written by the other. The access is updated only when no conflicts are found.
+    pub fn union_with(&mut self, other: &CoroAccess) -> bool {
+        let overlap = |a: &HashMap<SourceId, SetUsize>, b: &HashMap<SourceId, SetUsize>| {
+            b.iter().any(|(source, components)| {
+                a.get(source)
+                    .is_some_and(|c| components.iter().any(|i| c.contains(i)))
+            })
+        };
+
+        if overlap(&self.writes, &other.writes)
+            || overlap(&self.writes, &other.reads)
+            || overlap(&self.reads, &other.writes)
+        {
+            return false;
+        }
+
+        for (source, components) in &other.reads {
+            self.reads
+                .entry(*source)
+                .or_default()
+                .extend(components.iter());
+        }
+        for (source, components) in &other.writes {
+            self.writes
+                .entry(*source)
+                .or_default()
+                .extend(components.iter());
+        }
+
+        true
+    }
 }
 
 /// A heap allocated [`Coroutine`]
@@ -138,20 +300,66 @@ type HeapCoro = SyncCell<Pin<Box<dyn Coroutine>>>;
 #[cfg(test)]
 mod test {
     use std::{
+        any::TypeId,
+        ops::ControlFlow,
+        panic::AssertUnwindSafe,
         sync::{Arc, Mutex},
         thread,
         time::{Duration, Instant},
     };
 
     use bevy::{
-        ecs::system::{Command, EntityCommand},
-        prelude::{Component, Mut, World},
+        app::{AppLabel, SubApp},
+        asset::{AddAsset, AssetPlugin, Assets, Handle, HandleId},
+        core::TaskPoolPlugin,
+        ecs::{
+            event::{Event, Events},
+            system::{Command, EntityCommand},
+            world::unsafe_world_cell::UnsafeWorldCell,
+        },
+        hierarchy::{BuildChildren, BuildWorldChildren, DespawnRecursiveExt},
+        prelude::{
+            App, AppTypeRegistry, Changed, Commands, Component, Entity, EventReader,
+            GlobalTransform, IntoSystemConfigs, Mut, PostUpdate, ReflectComponent, Resource,
+            Schedule, Startup, Transform, TransformBundle, Update, With, World,
+        },
+        reflect::{TypePath, TypeUuid},
         time::Time,
+        transform::{TransformPlugin, TransformSystem},
     };
 
     use super::prelude::*;
 
-    use super::executor::Executor;
+    use super::state::{CoroutineState, StateWait};
+
+    use super::{function_coroutine::coro_param::CoroParam, id_alloc::Id};
+
+    use super::{CoroAccess, CoroMeta, SourceId};
+
+    use super::testing::TestExecutor;
+
+    use super::function_coroutine::wrong_await_message;
+
+    use super::function_coroutine::await_time::MissedInfo;
+
+    use super::plugin::{despawn_orphaned_entities, run_coroutines};
+
+    use super::persistent::{reload_persistent_coroutines, PersistentScripts};
+
+    use serde::{Deserialize, Serialize};
+
+    #[cfg(feature = "debug-ui")]
+    use super::executor::debug_ui::CoroutineDebugInfo;
+
+    use super::function_coroutine::CoroutineParamFunction;
+
+    use super::executor::{
+        msg::{CancelReason, CleanupReason, CoroStatus, CoroStatusKind, Latch, SignalId},
+        quota::{CoroutineCancelled, Limits},
+        CommandFlushPoint, CoroutineSpawned, DeserializeError, Executor, ExecutorHooks,
+        HasCoroutines, LocalExecutor, PollOutcome, TickCount, TickOrder, TickSummary, WaitCounts,
+        WaitReason,
+    };
 
     #[derive(Component)]
     struct ExampleComponent(u32);
@@ -186,30 +394,59 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
-    fn await_external_future_panic() {
+    fn await_external_future_cancels() {
         async fn external_future() {
             std::future::pending::<()>().await;
         }
         let mut world = World::new();
         world.init_resource::<Executor>();
         world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn_empty().id();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let ev = Arc::clone(&events);
+        world
+            .resource_mut::<Executor>()
+            .register_cleanup_hook(move |_, reason| ev.lock().unwrap().push(reason));
 
-        root_coroutine(|_: Scope| async move {
+        coroutine(|_: Scope| async move {
             external_future().await;
         })
+        .apply(e, &mut world);
+
+        let ticks = Arc::new(Mutex::new(0));
+        let t = Arc::clone(&ticks);
+        root_coroutine(move |mut s: Scope| async move {
+            loop {
+                *t.lock().unwrap() += 1;
+                s.next_tick().await;
+            }
+        })
         .apply(&mut world);
 
-        world.resource_scope(|w, mut executor: Mut<Executor>| {
+        let name = world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let name = executor.coroutines_of(e).next().unwrap().name;
+            executor.tick(w);
+            assert_eq!(executor.coroutines_of(e).count(), 0);
             executor.tick(w);
+            name
         });
+
+        assert_eq!(*ticks.lock().unwrap(), 2);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![CleanupReason::Cancelled(CancelReason::WrongAwait)]
+        );
+
+        assert!(name.contains("await_external_future_cancels"));
+        let message = wrong_await_message(name, Some(e));
+        assert!(message.contains(name));
+        assert!(message.contains(&format!("{e:?}")));
     }
 
     #[test]
     fn waiting_on_first() {
-        let mut world = World::new();
-        world.init_resource::<Executor>();
-        world.insert_resource(Time::new(Instant::now()));
+        let mut executor = TestExecutor::new();
 
         let a = Arc::new(Mutex::new(0));
         let b = Arc::clone(&a);
@@ -230,21 +467,42 @@ mod test {
 
             fib.first([first, second]).await;
         })
-        .apply(&mut world);
+        .apply(executor.world());
 
-        world.resource_scope(|w, mut executor: Mut<Executor>| {
-            for i in 0..5 {
-                executor.tick(w);
-                if i == 4 {
-                    let val = *a.lock().unwrap();
-                    // On the last tick, it is not defined if the second or first coroutine will
-                    // resume first, meaning the value may or may not be incremented at the end
-                    assert!(val == 4 || val == 3);
-                } else {
-                    assert_eq!(*a.lock().unwrap(), i);
-                }
-            }
-        });
+        let yields = executor.step();
+        let root = yields[0].0;
+        let kinds: Vec<_> = yields.iter().map(|(_, kind)| *kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                CoroStatusKind::First,
+                CoroStatusKind::Tick,
+                CoroStatusKind::Tick
+            ]
+        );
+        assert_eq!(*a.lock().unwrap(), 0);
+
+        for i in 1..4 {
+            let yields = executor.step();
+            assert_eq!(yields.len(), 2);
+            assert!(yields.iter().all(|(_, kind)| *kind == CoroStatusKind::Tick));
+            assert_eq!(*a.lock().unwrap(), i);
+        }
+
+        // The second coroutine wins, the root is resumed right after it, and the first one is
+        // cancelled. It is not defined if the first one is resumed before that
+        let yields = executor.step();
+        let done: Vec<_> = yields
+            .iter()
+            .filter(|(_, kind)| *kind == CoroStatusKind::Done)
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(done.len(), 2);
+        assert_eq!(done[1], root);
+        let val = *a.lock().unwrap();
+        assert!(val == 4 || val == 3);
+        assert_eq!(yields.len(), val as usize - 1);
+        assert_eq!(executor.executor().len(), 0);
     }
 
     #[test]
@@ -315,6 +573,39 @@ mod test {
         });
     }
 
+    #[test]
+    fn handle_into_future() {
+        async fn doubled(future: impl std::future::Future<Output = u32>) -> u32 {
+            future.await * 2
+        }
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let mut handle = executor.start(w, |mut s: Scope| async move {
+                let h1 = s.start(|mut s: Scope| async move {
+                    s.next_tick().await;
+                    1
+                });
+                let h2 = s.start(|mut s: Scope| async move {
+                    s.next_tick().await;
+                    s.next_tick().await;
+                    2
+                });
+
+                let a = doubled(s.into_future(h1)).await;
+                let b = doubled(s.into_future(h2)).await;
+                (a, b)
+            });
+
+            assert_eq!(executor.block_on(&mut handle, w, 2), None);
+            assert_eq!(executor.block_on(&mut handle, w, 10), Some((2, 4)));
+            assert!(executor.is_empty());
+        });
+    }
+
     #[test]
     fn waiting_on_all_result() {
         let mut world = World::new();
@@ -456,4 +747,4909 @@ mod test {
             executor.tick(world);
         });
     }
+
+    #[test]
+    fn entity_coroutines_start_on_first_update() {
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.insert_resource(Time::new(Instant::now()));
+
+        let a = Arc::new(Mutex::new(0));
+        let b = Arc::clone(&a);
+        let c = Arc::clone(&a);
+
+        app.add_systems(Startup, move |mut commands: Commands| {
+            let b = Arc::clone(&b);
+            let c = Arc::clone(&c);
+            let e = commands
+                .spawn((
+                    ExampleComponent(0),
+                    EntityCoroutines::new().with(
+                        move |s: Scope, ex: Rd<ExampleComponent>| async move {
+                            *b.lock().unwrap() += 1 + ex.get(&s).0;
+                        },
+                    ),
+                ))
+                .id();
+            commands.add(entity_coroutine(e, move |_: Scope| async move {
+                *c.lock().unwrap() += 1;
+            }));
+        });
+
+        assert_eq!(*a.lock().unwrap(), 0);
+        app.update();
+        assert_eq!(*a.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn entity_coroutines_queued_later_are_registered() {
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.insert_resource(Time::new(Instant::now()));
+
+        let a = Arc::new(Mutex::new(0));
+        let (b, c) = (Arc::clone(&a), Arc::clone(&a));
+        let e = app
+            .world
+            .spawn(EntityCoroutines::new().with(move |_: Scope| async move {
+                *b.lock().unwrap() += 1;
+            }))
+            .id();
+
+        app.update();
+        assert_eq!(*a.lock().unwrap(), 1);
+
+        // Queued in the component already on the entity
+        entity_coroutine(e, move |_: Scope| async move {
+            *c.lock().unwrap() += 10;
+        })
+        .apply(&mut app.world);
+        app.update();
+        app.update();
+        assert_eq!(*a.lock().unwrap(), 11);
+    }
+
+    #[test]
+    fn command_flush_points() {
+        for (point, expected) in [
+            (CommandFlushPoint::InsideTick, Some(7)),
+            (CommandFlushPoint::NextApplyDeferred, None),
+        ] {
+            let mut app = App::new();
+            app.add_plugins((CorentinPlugin, CommandFlushPlugin(point)));
+            app.insert_resource(Time::new(Instant::now()));
+
+            // Queues a command before the executor runs, applied at the next sync point
+            let e = app.world.spawn(ExampleComponent(0)).id();
+            let reinsert = move |mut commands: Commands| {
+                commands.entity(e).insert(ExampleComponent(7));
+            };
+            app.add_systems(Update, reinsert.before(run_coroutines));
+
+            root_coroutine(move |s: Scope| async move {
+                s.commands().entity(e).remove::<ExampleComponent>();
+            })
+            .apply(&mut app.world);
+
+            app.update();
+            let value = app.world.get::<ExampleComponent>(e).map(|c| c.0);
+            assert_eq!(value, expected);
+        }
+    }
+
+    #[test]
+    fn entity_coroutine_follows_component() {
+        #[derive(Component)]
+        struct Brain;
+
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.insert_resource(Time::new(Instant::now()));
+        app.add_entity_coroutine::<Brain, _, _, _>(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                let mut resumed = 0;
+                loop {
+                    resumed += 1;
+                    example.get_mut(&s).0 = resumed;
+                    s.next_tick().await;
+                }
+            },
+        );
+
+        let a = app.world.spawn((Brain, ExampleComponent(0))).id();
+        let b = app.world.spawn((Brain, ExampleComponent(0))).id();
+        let c = app.world.spawn(ExampleComponent(0)).id();
+
+        let values = |app: &App| [a, b, c].map(|e| app.world.get::<ExampleComponent>(e).unwrap().0);
+        let running = |app: &App| app.world.resource::<Executor>().len();
+
+        app.update();
+        assert_eq!(values(&app), [1, 1, 0]);
+        assert_eq!(running(&app), 2);
+
+        // Removed from `a`, removed and inserted back right away on `b`, inserted on `c`
+        app.world.entity_mut(a).remove::<Brain>();
+        app.world.entity_mut(b).remove::<Brain>();
+        app.world.entity_mut(b).insert(Brain);
+        app.world.entity_mut(c).insert(Brain);
+        app.update();
+        assert_eq!(values(&app), [1, 1, 1]);
+        assert_eq!(running(&app), 2);
+
+        app.update();
+        assert_eq!(values(&app), [1, 2, 2]);
+
+        // Inserted back on `a`, which starts afresh
+        app.world.entity_mut(a).insert(Brain);
+        app.world.despawn(b);
+        app.update();
+        assert_eq!(app.world.get::<ExampleComponent>(a).unwrap().0, 1);
+        assert_eq!(app.world.get::<ExampleComponent>(c).unwrap().0, 3);
+        assert_eq!(running(&app), 2);
+    }
+
+    #[test]
+    fn coroutines_run_in_sub_app() {
+        #[derive(AppLabel, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+        struct CoroutineSubApp;
+
+        let mut sub_app = App::new();
+        sub_app.insert_resource(Time::new(Instant::now()));
+
+        let mut app = App::new();
+        app.insert_sub_app(CoroutineSubApp, SubApp::new(sub_app, |_, _| {}));
+        app.add_plugins(CorentinPlugin::for_sub_app(CoroutineSubApp));
+
+        let a = Arc::new(Mutex::new(0));
+        let b = Arc::clone(&a);
+
+        let sub_world = &mut app.sub_app_mut(CoroutineSubApp).world;
+        root_coroutine(|mut s: Scope| async move {
+            loop {
+                *b.lock().unwrap() += 1;
+                s.next_tick().await;
+            }
+        })
+        .apply(sub_world);
+
+        assert!(!app.world.contains_resource::<Executor>());
+        app.update();
+        assert_eq!(*a.lock().unwrap(), 1);
+        app.update();
+        assert_eq!(*a.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn start_into_inserts_result() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        #[derive(Component, PartialEq, Debug)]
+        struct Computed(u32);
+
+        let e = world.spawn(ExampleComponent(3)).id();
+
+        root_coroutine(move |mut s: Scope| async move {
+            s.start_into(e, |mut s: Scope, ex: Rd<ExampleComponent>| async move {
+                s.next_tick().await;
+                Computed(ex.get(&s).0 * 2)
+            });
+            loop {
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|world, mut executor: Mut<Executor>| {
+            executor.tick(world);
+            assert!(world.get::<Computed>(e).is_none());
+            executor.tick(world);
+            assert_eq!(world.get::<Computed>(e), Some(&Computed(6)));
+        });
+    }
+
+    #[test]
+    fn start_into_despawned_entity() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e1 = world.spawn(ExampleComponent(0)).id();
+        let e2 = world.spawn_empty().id();
+
+        root_coroutine(move |mut s: Scope| async move {
+            s.start_into(e1, |mut s: Scope, _: Rd<ExampleComponent>| async move {
+                s.next_tick().await;
+                ExampleComponent(1)
+            });
+            s.start_into(e2, |mut s: Scope| async move {
+                s.next_tick().await;
+                ExampleComponent(2)
+            });
+            loop {
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|world, mut executor: Mut<Executor>| {
+            executor.tick(world);
+            world.despawn(e1);
+            world.despawn(e2);
+            executor.tick(world);
+            executor.tick(world);
+        });
+
+        let mut state = world.query::<&ExampleComponent>();
+        assert_eq!(state.iter(&world).len(), 0);
+    }
+
+    fn n_signals_world(emissions: usize, n: usize) -> (World, Arc<Mutex<bool>>) {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world
+            .spawn((
+                ExampleComponent(0),
+                ChangeTracker::new() as ChangeTracker<ExampleComponent>,
+            ))
+            .id();
+        let signal =
+            SignalId::component(world.component_id::<ExampleComponent>().unwrap(), Some(e));
+
+        let done = Arc::new(Mutex::new(false));
+        let d = Arc::clone(&done);
+
+        coroutine(move |mut s: Scope| async move {
+            s.await_n_signals(n, signal).await;
+            *d.lock().unwrap() = true;
+        })
+        .apply(e, &mut world);
+
+        coroutine(
+            move |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                for _ in 0..emissions {
+                    s.next_tick().await;
+                    example.get_mut(&s).0 += 1;
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        (world, done)
+    }
+
+    #[test]
+    fn await_n_signals_resolves_after_n() {
+        let (mut world, done) = n_signals_world(3, 3);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            // First tick only starts both coroutines, each following one emits once
+            for _ in 0..3 {
+                executor.tick(w);
+                assert!(!*done.lock().unwrap());
+            }
+            executor.tick(w);
+            assert!(*done.lock().unwrap());
+        });
+    }
+
+    #[test]
+    fn await_n_signals_not_enough_emissions() {
+        let (mut world, done) = n_signals_world(2, 3);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for _ in 0..6 {
+                executor.tick(w);
+            }
+            assert!(!*done.lock().unwrap());
+        });
+    }
+
+    #[test]
+    fn cleanup_hooks_report_reasons() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+
+        {
+            let mut executor = world.resource_mut::<Executor>();
+            let f = Arc::clone(&first);
+            executor.register_cleanup_hook(move |id, reason| f.lock().unwrap().push((id, reason)));
+            let s = Arc::clone(&second);
+            executor.register_cleanup_hook(move |id, reason| s.lock().unwrap().push((id, reason)));
+        }
+
+        root_coroutine(|mut s: Scope| async move {
+            s.start_local(|mut s: Scope| async move {
+                loop {
+                    s.next_tick().await;
+                }
+            });
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        for events in [first, second] {
+            let events = events.lock().unwrap();
+            assert_eq!(events.len(), 2);
+            assert_eq!(events[0].1, CleanupReason::Completed);
+            assert_eq!(
+                events[1].1,
+                CleanupReason::Cancelled(CancelReason::ParentCancelled)
+            );
+            assert_ne!(events[0].0, events[1].0);
+        }
+    }
+
+    #[test]
+    fn behavior_receives_messages_in_order() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&received);
+
+        root_coroutine(|mut s: Scope| async move {
+            let handle = s.start_behavior(move |mut s: Scope| async move {
+                loop {
+                    let msg = s.mailbox::<u32>().next().await;
+                    r.lock().unwrap().push(msg);
+                }
+            });
+            for i in 0..3u32 {
+                handle.send(i);
+                s.next_tick().await;
+            }
+            handle.send(3);
+            handle.cancel();
+            loop {
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for _ in 0..6 {
+                executor.tick(w);
+            }
+        });
+
+        assert_eq!(*received.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn behavior_woken_by_message_sent_outside() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&received);
+        let handle = Arc::new(Mutex::new(None));
+        let h = Arc::clone(&handle);
+
+        root_coroutine(|mut s: Scope| async move {
+            *h.lock().unwrap() = Some(s.start_behavior(move |mut s: Scope| async move {
+                loop {
+                    let msg = s.mailbox::<u32>().next().await;
+                    r.lock().unwrap().push(msg);
+                }
+            }));
+            loop {
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            // Only the root is resumed while the mailbox is empty
+            executor.tick(w);
+            assert_eq!(executor.debug_last_yields().len(), 1);
+
+            handle.lock().unwrap().as_ref().unwrap().send(7u32);
+            executor.tick(w);
+            assert_eq!(*received.lock().unwrap(), vec![7]);
+
+            let id = handle.lock().unwrap().take().unwrap().id();
+            executor.tick(w);
+            assert!(!executor.contains(id));
+        });
+    }
+
+    #[test]
+    fn behavior_started_with_command() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn_empty().id();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&received);
+        start_behavior::<_, u32, _>(move |mut s: Scope| async move {
+            loop {
+                let msg = s.mailbox::<u32>().next().await;
+                r.lock().unwrap().push(msg);
+            }
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            for i in 0..3u32 {
+                w.get::<Behavior<u32>>(e).unwrap().handle().send(i);
+                executor.tick(w);
+            }
+            assert_eq!(*received.lock().unwrap(), vec![0, 1, 2]);
+
+            let id = w.get::<Behavior<u32>>(e).unwrap().handle().id();
+            w.entity_mut(e).remove::<Behavior<u32>>();
+            executor.tick(w);
+            assert!(!executor.contains(id));
+        });
+    }
+
+    #[test]
+    fn behavior_can_be_paused() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&received);
+        let handle = Arc::new(Mutex::new(None));
+        let h = Arc::clone(&handle);
+
+        root_coroutine(|mut s: Scope| async move {
+            *h.lock().unwrap() = Some(s.start_behavior(move |mut s: Scope| async move {
+                loop {
+                    let msg = s.mailbox::<u32>().next().await;
+                    r.lock().unwrap().push(msg);
+                }
+            }));
+            loop {
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            let id = {
+                let handle = handle.lock().unwrap();
+                let handle = handle.as_ref().unwrap();
+                assert!(handle.is_alive(&executor));
+                handle.pause(&mut executor);
+                handle.send(1u32);
+                handle.id()
+            };
+
+            executor.tick(w);
+            executor.tick(w);
+            assert!(received.lock().unwrap().is_empty());
+
+            handle
+                .lock()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .resume(&mut executor);
+            executor.tick(w);
+            assert_eq!(*received.lock().unwrap(), vec![1]);
+
+            handle.lock().unwrap().take().unwrap().cancel();
+            executor.tick(w);
+            assert!(!executor.contains(id));
+        });
+    }
+
+    #[test]
+    fn await_resource_change() {
+        #[derive(Resource)]
+        struct Counter(u32);
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.insert_resource(Counter(0));
+
+        let a = Arc::new(Mutex::new(0));
+        let b = Arc::clone(&a);
+
+        root_coroutine(|mut s: Scope| async move {
+            let counter = s.await_resource_change::<Counter>().await;
+            *b.lock().unwrap() = counter.0;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 1..=4 {
+                // Done by the schedule in an app
+                w.increment_change_tick();
+                if tick == 3 {
+                    w.resource_mut::<Counter>().0 = 3;
+                }
+                executor.tick(w);
+                assert_eq!(*a.lock().unwrap(), if tick >= 3 { 3 } else { 0 });
+            }
+        });
+    }
+
+    #[test]
+    fn await_resource_change_waits_for_reinsertion() {
+        #[derive(Resource)]
+        struct Counter(u32);
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.insert_resource(Counter(0));
+
+        let a = Arc::new(Mutex::new(None));
+        let b = Arc::clone(&a);
+
+        root_coroutine(|mut s: Scope| async move {
+            let counter = s.await_resource_change::<Counter>().await;
+            *b.lock().unwrap() = Some(counter.0);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 1..=4 {
+                // Done by the schedule in an app
+                w.increment_change_tick();
+                if tick == 2 {
+                    w.remove_resource::<Counter>();
+                }
+                if tick == 3 {
+                    w.insert_resource(Counter(3));
+                }
+                executor.tick(w);
+                assert_eq!(*a.lock().unwrap(), (tick >= 3).then_some(3));
+            }
+        });
+    }
+
+    #[test]
+    fn on_any_change_reports_changed_entities() {
+        #[derive(Component)]
+        struct Enemy;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let enemies: Vec<Entity> = (0..3)
+            .map(|_| world.spawn((Enemy, ExampleComponent(10))).id())
+            .collect();
+        let ally = world.spawn(ExampleComponent(10)).id();
+
+        let wakes = Arc::new(Mutex::new(Vec::new()));
+        for _ in 0..2 {
+            let w = Arc::clone(&wakes);
+            root_coroutine(move |mut s: Scope| async move {
+                loop {
+                    let changed = s.on_any_change::<ExampleComponent, With<Enemy>>().await;
+                    w.lock().unwrap().push(changed.to_vec());
+                }
+            })
+            .apply(&mut world);
+        }
+
+        let mut spawned = None;
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 1..=4 {
+                // Done by the schedule in an app
+                w.increment_change_tick();
+                if tick == 2 {
+                    w.get_mut::<ExampleComponent>(enemies[1]).unwrap().0 = 5;
+                    w.get_mut::<ExampleComponent>(ally).unwrap().0 = 5;
+                }
+                if tick == 3 {
+                    spawned = Some(w.spawn((Enemy, ExampleComponent(10))).id());
+                }
+                executor.tick(w);
+            }
+        });
+
+        let spawned = spawned.unwrap();
+        assert_eq!(
+            *wakes.lock().unwrap(),
+            vec![
+                vec![enemies[1]],
+                vec![enemies[1]],
+                vec![spawned],
+                vec![spawned]
+            ]
+        );
+    }
+
+    #[test]
+    fn on_any_change_detects_writes_later_in_the_tick() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let wakes = Arc::new(Mutex::new(Vec::new()));
+        let w = Arc::clone(&wakes);
+        root_coroutine(move |mut s: Scope| async move {
+            loop {
+                let changed = s.on_any_change::<ExampleComponent, ()>().await;
+                w.lock().unwrap().push(changed.to_vec());
+            }
+        })
+        .apply(&mut world);
+
+        // Resumed after the waiter suspended, within the same tick
+        coroutine(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                example.get_mut(&s).0 = 5;
+                s.next_tick().await;
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert!(wakes.lock().unwrap().is_empty());
+            executor.tick(w);
+            executor.tick(w);
+        });
+
+        assert_eq!(*wakes.lock().unwrap(), vec![vec![e]]);
+    }
+
+    #[test]
+    fn spawn_local_despawned_when_done() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let spawned = Arc::new(Mutex::new(Vec::new()));
+        let s1 = Arc::clone(&spawned);
+        let s2 = Arc::clone(&spawned);
+
+        root_coroutine(move |mut s: Scope| async move {
+            s1.lock().unwrap().push(s.spawn_local(ExampleComponent(0)));
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        root_coroutine(move |mut s: Scope| async move {
+            // Cancelled once its parent finishes
+            s.start_local(|mut s: Scope| async move {
+                s2.lock().unwrap().push(s.spawn_local(ExampleComponent(1)));
+                s.duration(Duration::from_secs(100)).await;
+            });
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            let entities = spawned.lock().unwrap().clone();
+            assert_eq!(entities.len(), 2);
+            for e in &entities {
+                assert!(w.get_entity(*e).is_some());
+            }
+
+            executor.tick(w);
+            for e in &entities {
+                assert!(w.get_entity(*e).is_none());
+            }
+        });
+    }
+
+    #[test]
+    fn graceful_param_resumed_once_when_invalid() {
+        #[derive(Component)]
+        struct Corpse;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&reasons);
+        world
+            .resource_mut::<Executor>()
+            .register_cleanup_hook(move |_, reason| r.lock().unwrap().push(reason));
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = Arc::clone(&log);
+        coroutine(
+            move |mut s: Scope, _health: Graceful<Rd<ExampleComponent>>| async move {
+                loop {
+                    if let Some(reason) = s.invalidation() {
+                        l.lock().unwrap().push(reason);
+                        s.commands().spawn(Corpse);
+                    }
+                    s.next_tick().await;
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert!(log.lock().unwrap().is_empty());
+
+            // Would be done by a system
+            w.entity_mut(e).remove::<ExampleComponent>();
+            executor.tick(w);
+            executor.tick(w);
+        });
+
+        assert_eq!(*log.lock().unwrap(), vec![CancelReason::InvalidParams]);
+        assert_eq!(
+            *reasons.lock().unwrap(),
+            vec![CleanupReason::Cancelled(CancelReason::InvalidParams)]
+        );
+        assert_eq!(world.query::<&Corpse>().iter(&world).count(), 1);
+    }
+
+    #[test]
+    fn graceful_param_resumed_once_while_waiting() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let signaled = world.spawn(ExampleComponent(0)).id();
+        let sleeping = world.spawn(ExampleComponent(0)).id();
+
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&reasons);
+        world
+            .resource_mut::<Executor>()
+            .register_cleanup_hook(move |_, reason| r.lock().unwrap().push(reason));
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = Arc::clone(&log);
+        coroutine(
+            move |mut s: Scope, _health: Graceful<Rd<ExampleComponent>>| async move {
+                loop {
+                    // Never emitted
+                    s.signal_named("revived", None).await;
+                    l.lock().unwrap().push(s.invalidation());
+                }
+            },
+        )
+        .apply(signaled, &mut world);
+
+        let l = Arc::clone(&log);
+        coroutine(
+            move |mut s: Scope, _health: Graceful<Rd<ExampleComponent>>| async move {
+                loop {
+                    s.duration(Duration::from_secs(3600)).await;
+                    l.lock().unwrap().push(s.invalidation());
+                }
+            },
+        )
+        .apply(sleeping, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert!(log.lock().unwrap().is_empty());
+
+            // Would be done by a system
+            w.entity_mut(signaled).remove::<ExampleComponent>();
+            w.despawn(sleeping);
+            executor.tick(w);
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+
+        let mut log = log.lock().unwrap().clone();
+        log.sort_by_key(|reason| format!("{reason:?}"));
+        assert_eq!(
+            log,
+            vec![
+                Some(CancelReason::InvalidParams),
+                Some(CancelReason::OwnerDespawned)
+            ]
+        );
+        assert_eq!(reasons.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "debug")]
+    fn component_history_keeps_last_values() {
+        #[derive(Component, Clone)]
+        struct Health(u32);
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(Health(0)).id();
+
+        let history = Arc::new(Mutex::new(Vec::new()));
+        let h = Arc::clone(&history);
+        coroutine(move |mut s: Scope, mut health: Wr<Health>| async move {
+            s.enable_history::<Health>(e, 3);
+            for i in 1..=5 {
+                health.get_mut(&s).0 = i;
+            }
+            let recorded = s.read_component_history::<Health>(e, 3);
+            *h.lock().unwrap() = recorded.iter().map(|h| h.0).collect();
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+
+        assert_eq!(*history.lock().unwrap(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn executor_counts_and_idle() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        assert!(world.resource_mut::<Executor>().is_idle());
+
+        root_coroutine(|mut s: Scope| async move {
+            let slow = s.start(|mut s: Scope| async move {
+                s.duration(Duration::from_secs(100)).await;
+            });
+            let fast = s.start(|mut s: Scope| async move {
+                s.next_tick().await;
+            });
+            s.first([slow, fast]).await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            assert_eq!(executor.len(), 1);
+            assert!(!executor.is_idle());
+
+            executor.tick(w);
+            assert_eq!(executor.len(), 3);
+            assert_eq!(
+                executor.counts(),
+                WaitCounts {
+                    on_tick: 1,
+                    on_time: 1,
+                    on_first: 1,
+                    awaited: 2,
+                    ..Default::default()
+                }
+            );
+
+            executor.tick(w);
+            assert_eq!(executor.len(), 0);
+            assert_eq!(executor.counts(), WaitCounts::default());
+            assert!(executor.is_idle());
+        });
+    }
+
+    #[test]
+    fn any_signal_sees_all_signals_in_order() {
+        #[derive(Component)]
+        struct Mana(u32);
+
+        #[derive(Component)]
+        struct Stamina(u32);
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world
+            .spawn((
+                ExampleComponent(0),
+                Mana(0),
+                Stamina(0),
+                ChangeTracker::new() as ChangeTracker<ExampleComponent>,
+                ChangeTracker::new() as ChangeTracker<Mana>,
+                ChangeTracker::new() as ChangeTracker<Stamina>,
+            ))
+            .id();
+        let signal = |signal_type| SignalId::component(signal_type, Some(e));
+        let expected = vec![
+            signal(world.component_id::<Mana>().unwrap()),
+            signal(world.component_id::<ExampleComponent>().unwrap()),
+            signal(world.component_id::<Stamina>().unwrap()),
+        ];
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let s1 = Arc::clone(&seen);
+        root_coroutine(move |mut s: Scope| async move {
+            loop {
+                let id = s.any_signal().await;
+                s1.lock().unwrap().push(id);
+            }
+        })
+        .apply(&mut world);
+
+        coroutine(
+            |mut s: Scope,
+             mut example: Wr<ExampleComponent>,
+             mut mana: Wr<Mana>,
+             mut stamina: Wr<Stamina>| async move {
+                s.next_tick().await;
+                mana.get_mut(&s).0 += 1;
+                s.next_tick().await;
+                example.get_mut(&s).0 += 1;
+                s.next_tick().await;
+                stamina.get_mut(&s).0 += 1;
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for _ in 0..4 {
+                executor.tick(w);
+            }
+        });
+
+        assert_eq!(*seen.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn weak_handle_does_not_keep_alive() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let done = Arc::new(Mutex::new(false));
+        let d = Arc::clone(&done);
+        let upgrades = Arc::new(Mutex::new(Vec::new()));
+        let u = Arc::clone(&upgrades);
+
+        root_coroutine(move |mut s: Scope| async move {
+            // Dropping the weak handle does not cancel the coroutine
+            let weak = s
+                .start(move |mut s: Scope| async move {
+                    s.next_tick().await;
+                    *d.lock().unwrap() = true;
+                })
+                .downgrade();
+            drop(weak);
+
+            let weak = s
+                .start(|mut s: Scope| async move {
+                    s.next_tick().await;
+                    1
+                })
+                .downgrade();
+            let strong = weak.upgrade().unwrap();
+            let weak = strong.downgrade();
+            s.next_tick().await;
+            s.next_tick().await;
+            u.lock().unwrap().push(weak.is_running());
+            u.lock().unwrap().push(weak.upgrade().is_some());
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert!(*done.lock().unwrap());
+        assert_eq!(*upgrades.lock().unwrap(), vec![false, false]);
+    }
+
+    #[test]
+    fn coroutines_of_entity_match_markers() {
+        let mut app = App::new();
+        app.insert_resource(Time::new(Instant::now()))
+            .add_plugins((CorentinPlugin, CoroutineMarkersPlugin));
+
+        let e = app.world.spawn(ExampleComponent(0)).id();
+        coroutine(|mut s: Scope| async move {
+            s.next_tick().await;
+        })
+        .apply(e, &mut app.world);
+        coroutine(|mut s: Scope| async move {
+            s.duration(Duration::from_secs(100)).await;
+        })
+        .apply(e, &mut app.world);
+
+        let check = |app: &mut App, expected: usize| {
+            let mut marked = app.world.get::<HasCoroutines>(e).unwrap().0.to_vec();
+            let executor = app.world.resource::<Executor>();
+            let mut listed: Vec<_> = executor.coroutines_of(e).map(|info| info.id).collect();
+            marked.sort();
+            listed.sort();
+            assert_eq!(marked.len(), expected);
+            assert!(marked == listed);
+        };
+
+        app.update();
+        check(&mut app, 2);
+
+        app.update();
+        check(&mut app, 1);
+        let info = app
+            .world
+            .resource::<Executor>()
+            .coroutines_of(e)
+            .next()
+            .unwrap();
+        assert_eq!(info.wait, WaitReason::Time);
+    }
+
+    #[test]
+    fn start_invalid_coroutine_gracefully() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&results);
+
+        // Without owner, the `Rd` parameter cannot be initialized
+        root_coroutine(move |mut s: Scope| async move {
+            let cancelled = s.start_or_cancel(|_: Scope, _: Rd<ExampleComponent>| async {});
+            r.lock().unwrap().push(cancelled.is_none() as u32);
+
+            let default = s.start_or_default(|_: Scope, _: Rd<ExampleComponent>| async { 1 }, 2);
+            let value = s.on(default).await;
+            r.lock().unwrap().push(value);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+
+        assert_eq!(*results.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn start_conflicting_coroutine_gracefully() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&results);
+
+        // Even in debug builds, the variants handling invalid coroutines do not panic
+        let e = world.spawn(ExampleComponent(0)).id();
+        coroutine(move |mut s: Scope| async move {
+            let conflicting = |_: Scope, _: Wr<ExampleComponent>, _: Wr<ExampleComponent>| async {};
+            let cancelled = s.start_or_cancel(conflicting);
+            r.lock().unwrap().push(cancelled.is_none() as u32);
+            r.lock()
+                .unwrap()
+                .push(s.try_start(conflicting).is_none() as u32);
+            r.lock()
+                .unwrap()
+                .push(s.start_forget(conflicting).is_none() as u32);
+            s.start_local(conflicting);
+
+            let default = s.start_or_default(
+                |_: Scope, _: Wr<ExampleComponent>, _: Rd<ExampleComponent>| async { 1 },
+                2,
+            );
+            let value = s.on(default).await;
+            r.lock().unwrap().push(value);
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+
+        assert_eq!(*results.lock().unwrap(), vec![1, 1, 1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "access `corentin::test::ExampleComponent` more than once")]
+    fn start_conflicting_coroutine_panics() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        coroutine(|mut s: Scope| async move {
+            s.start(|_: Scope, _: Wr<ExampleComponent>, _: Wr<ExampleComponent>| async {});
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Rd<corentin::test::ExampleComponent>")]
+    fn start_invalid_coroutine_panics() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        root_coroutine(|mut s: Scope| async move {
+            s.start(|_: Scope, _: Rd<ExampleComponent>| async {});
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+    }
+
+    #[test]
+    fn changed_by_other_ignores_own_changes() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        let component_id = world.component_id::<ExampleComponent>().unwrap();
+
+        let wakes = Arc::new(Mutex::new(Vec::new()));
+        let w = Arc::clone(&wakes);
+        coroutine(
+            move |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                loop {
+                    example.get_mut(&s).0 += 1;
+                    s.await_changed_by_other(e, component_id).await;
+                    w.lock().unwrap().push(example.get(&s).0);
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 0..5 {
+                w.increment_change_tick();
+                if tick == 3 {
+                    // Would be done by a system
+                    w.get_mut::<ExampleComponent>(e).unwrap().0 = 10;
+                }
+                executor.tick(w);
+            }
+        });
+
+        assert_eq!(*wakes.lock().unwrap(), vec![10]);
+    }
+
+    #[test]
+    fn changed_by_other_wakes_on_other_coroutine() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        let component_id = world.component_id::<ExampleComponent>().unwrap();
+
+        let woken = Arc::new(Mutex::new(None));
+        let w = Arc::clone(&woken);
+        root_coroutine(move |mut s: Scope| async move {
+            let exists = s.await_changed_by_other(e, component_id).await;
+            *w.lock().unwrap() = Some(exists);
+        })
+        .apply(&mut world);
+        coroutine(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                s.next_tick().await;
+                example.get_mut(&s).0 += 1;
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert_eq!(*woken.lock().unwrap(), None);
+            // Written during the same tick, by a coroutine which did not run before the waiter
+            executor.tick(w);
+        });
+
+        assert_eq!(*woken.lock().unwrap(), Some(true));
+    }
+
+    #[test]
+    fn all_within_returns_partial_results() {
+        let start = Instant::now();
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(start));
+
+        let results = Arc::new(Mutex::new(None));
+        let r = Arc::clone(&results);
+
+        let wait = |millis, value| {
+            move |mut s: Scope| async move {
+                s.duration(Duration::from_millis(millis)).await;
+                value
+            }
+        };
+
+        root_coroutine(move |mut s: Scope| async move {
+            let handles = vec![
+                s.start(|mut s: Scope| async move {
+                    s.next_tick().await;
+                    1
+                }),
+                s.start(wait(500, 2)),
+                // Finishes in the same tick as the deadline, which still counts
+                s.start(wait(1000, 3)),
+                s.start(wait(5000, 4)),
+            ];
+            let partial = s.all_within(handles, Duration::from_secs(1)).await;
+
+            let (a, b) = (s.start(wait(0, 5)), s.start(wait(5000, 6)));
+            let tuple = s.all_within((a, b), Duration::from_millis(500)).await;
+
+            *r.lock().unwrap() = Some((partial, tuple));
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 0..5 {
+                w.resource_mut::<Time>()
+                    .update_with_instant(start + Duration::from_millis(500 * tick));
+                executor.tick(w);
+            }
+
+            assert_eq!(
+                results.lock().unwrap().take(),
+                Some((vec![Some(1), Some(2), Some(3), None], (Some(5), None)))
+            );
+            assert_eq!(executor.len(), 0);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn metrics_counters_persist_across_ticks() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        root_coroutine(|mut s: Scope| async move {
+            for _ in 0..3 {
+                s.metrics_counter("damage_dealt", 10);
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for _ in 0..3 {
+                executor.tick(w);
+            }
+            assert_eq!(executor.metrics_snapshot().get("damage_dealt"), 30);
+            assert!(executor.metrics_snapshot().get("corentin.resumed") > 0);
+
+            executor.reset_metric("damage_dealt");
+            assert_eq!(executor.metrics_snapshot().get("damage_dealt"), 0);
+        });
+    }
+
+    /// A parameter declaring a read of `ExampleComponent` on its owner if `DECLARED`, and
+    /// accessing it through the checked accessors of the scope.
+    #[cfg(all(feature = "validate-access", debug_assertions))]
+    struct Sneaky<const DECLARED: bool> {
+        owner: Entity,
+    }
+
+    #[cfg(all(feature = "validate-access", debug_assertions))]
+    impl<const DECLARED: bool> CoroParam for Sneaky<DECLARED> {
+        fn init(world: UnsafeWorldCell<'_>, coro_meta: &mut CoroMeta) -> Option<Self> {
+            let owner = coro_meta.owner?;
+            let id = world.components().component_id::<ExampleComponent>()?;
+            if DECLARED && !coro_meta.access.add_read(SourceId::Entity(owner), id) {
+                return None;
+            }
+            Some(Self { owner })
+        }
+
+        fn is_valid(_world: UnsafeWorldCell<'_>, _coro_meta: &CoroMeta) -> bool {
+            true
+        }
+    }
+
+    /// Tick once a coroutine owned by an entity with an `ExampleComponent`, in a world with a
+    /// `Score`.
+    #[cfg(all(feature = "validate-access", debug_assertions))]
+    fn tick_with_owned<Marker: 'static + Send, C>(coroutine: C)
+    where
+        C: CoroutineParamFunction<Marker, ()>,
+    {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.init_resource::<Score>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.add_function_coroutine(Some(e), w, coroutine);
+            executor.tick(w);
+        });
+    }
+
+    #[test]
+    #[cfg(all(feature = "validate-access", debug_assertions))]
+    #[should_panic(expected = "read (Entity(")]
+    fn undeclared_component_read_panics() {
+        tick_with_owned(|s: Scope, sneaky: Sneaky<false>| async move {
+            // SAFETY: Not declared, which is what is being checked
+            unsafe { s.get_component::<ExampleComponent>(sneaky.owner) };
+        });
+    }
+
+    #[test]
+    #[cfg(all(feature = "validate-access", debug_assertions))]
+    #[should_panic(expected = "wrote (Entity(")]
+    fn write_declared_as_read_panics() {
+        tick_with_owned(|s: Scope, sneaky: Sneaky<true>| async move {
+            // SAFETY: Only the read is declared, which is what is being checked
+            unsafe { s.get_component::<ExampleComponent>(sneaky.owner) };
+            unsafe { s.get_component_mut::<ExampleComponent>(sneaky.owner) };
+        });
+    }
+
+    #[test]
+    #[cfg(all(feature = "validate-access", debug_assertions))]
+    #[should_panic(expected = "read (World,")]
+    fn undeclared_resource_read_panics() {
+        tick_with_owned(|s: Scope| async move {
+            // SAFETY: Not declared, which is what is being checked
+            unsafe { s.get_resource::<Score>() };
+        });
+    }
+
+    #[test]
+    fn declared_accesses_pass_validation() {
+        #[derive(Event)]
+        struct Ping;
+
+        #[derive(Component)]
+        struct Mana(u32);
+
+        #[derive(Component)]
+        struct Stamina;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.init_resource::<Events<Ping>>();
+        world.init_component::<Stamina>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn((ExampleComponent(0), Mana(0))).id();
+        coroutine(
+            |s: Scope,
+             example: Rd<ExampleComponent>,
+             mut mana: Wr<Mana>,
+             missing: Opt<Rd<Stamina>>,
+             mut pings: Evt<Ping>| async move {
+                mana.get_mut(&s).0 += example.get(&s).0 + 1;
+                assert!(missing.get(&s).is_none());
+                assert_eq!(pings.drain_pending(&s).count(), 0);
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+        assert_eq!(world.get::<Mana>(e).unwrap().0, 1);
+    }
+
+    #[test]
+    fn spawn_on_next_tick_sees_commands() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.init_component::<ExampleComponent>();
+        let e = world.spawn_empty().id();
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let mut handle = executor.start_on(e, w, move |mut s: Scope| async move {
+                s.commands().entity(e).insert(ExampleComponent(3));
+                let read = |s: Scope, c: Rd<ExampleComponent>| async move { c.get(&s).0 };
+                let child = s.spawn_on_next_tick(read);
+                s.on(child).await
+            });
+
+            executor.tick(w);
+            assert_eq!(handle.try_take(), None);
+            executor.tick(w);
+            assert!(executor.is_empty());
+            assert_eq!(handle.try_take(), Some(3));
+        });
+    }
+
+    #[test]
+    fn bound_children_cancelled_once() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn_empty().id();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let ev = Arc::clone(&events);
+        world
+            .resource_mut::<Executor>()
+            .register_cleanup_hook(move |id, reason| ev.lock().unwrap().push((id, reason)));
+
+        root_coroutine(move |mut s: Scope| async move {
+            // Finishes while its bound child is still running
+            s.start_local(move |s: Scope| async move {
+                s.bind_coroutine(e, |mut s: Scope| async move {
+                    loop {
+                        s.next_tick().await;
+                    }
+                });
+            });
+            // Awaits its bound child when it gets cancelled
+            s.start_local(move |mut s: Scope| async move {
+                let child = s.bind_coroutine(e, |mut s: Scope| async move {
+                    loop {
+                        s.next_tick().await;
+                    }
+                });
+                s.first([child]).await;
+            });
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+            assert!(executor.is_empty());
+        });
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 5);
+        for (id, _) in events.iter() {
+            assert_eq!(events.iter().filter(|(other, _)| other == id).count(), 1);
+        }
+        let cancelled = events
+            .iter()
+            .filter(|(_, r)| *r == CleanupReason::Cancelled(CancelReason::ParentCancelled))
+            .count();
+        assert_eq!(cancelled, 3);
+    }
+
+    #[test]
+    fn detached_fork_outlives_parent() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let ticks = Arc::new(Mutex::new(0));
+        let t = Arc::clone(&ticks);
+        let joined = Arc::new(Mutex::new(None));
+        let j = Arc::clone(&joined);
+
+        root_coroutine(move |mut s: Scope| async move {
+            s.start_local(move |mut s: Scope| async move {
+                let mut fork = s.fork(move |mut s: Scope| async move {
+                    for _ in 0..4 {
+                        s.next_tick().await;
+                        *t.lock().unwrap() += 1;
+                    }
+                });
+                fork.detach();
+            });
+
+            let mut fork = s.fork(|mut s: Scope| async move {
+                s.next_tick().await;
+                1
+            });
+            fork.detach();
+            s.next_tick().await;
+            s.next_tick().await;
+            let handle = fork.join();
+            *j.lock().unwrap() = Some(s.on(handle).await);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            // The parent finishes right away, the fork keeps running
+            executor.tick(w);
+            assert_eq!(*ticks.lock().unwrap(), 0);
+            for i in 1..=3 {
+                executor.tick(w);
+                assert_eq!(*ticks.lock().unwrap(), i);
+            }
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(*ticks.lock().unwrap(), 4);
+        assert_eq!(*joined.lock().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn components_of_entity() {
+        #[derive(Component)]
+        struct Health;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let healthy = world.spawn((Health, ExampleComponent(0))).id();
+        let other = world.spawn(ExampleComponent(0)).id();
+        let gone = world.spawn(Health).id();
+        world.despawn(gone);
+
+        let result = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&result);
+
+        let health_id = world.init_component::<Health>();
+        let declared = Arc::new(Mutex::new(Vec::new()));
+        let d = Arc::clone(&declared);
+
+        root_coroutine(move |s: Scope| async move {
+            for e in [healthy, other, gone] {
+                let components = s.components_of(e);
+                r.lock()
+                    .unwrap()
+                    .push((components.contains::<Health>(), components.count()));
+                // SAFETY: Only read while the coroutine is polled
+                let access = unsafe { &(*s.meta_ptr()).access };
+                d.lock()
+                    .unwrap()
+                    .push(access.can_read(SourceId::Entity(e), health_id));
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(
+            *result.lock().unwrap(),
+            vec![(true, 2), (false, 1), (false, 0)]
+        );
+        assert_eq!(*declared.lock().unwrap(), vec![true, false, false]);
+    }
+
+    #[test]
+    fn entities_with_same_archetype() {
+        #[derive(Component)]
+        struct Health;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let hero = world.spawn((Health, ExampleComponent(0))).id();
+        let enemy = world.spawn((Health, ExampleComponent(1))).id();
+        let neutral = world.spawn(ExampleComponent(0)).id();
+
+        let result = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&result);
+
+        root_coroutine(move |mut s: Scope| async move {
+            let seen = [
+                s.same_archetype(hero, enemy),
+                s.same_archetype(hero, neutral),
+                s.get_archetype(hero) == s.get_archetype(neutral),
+                s.entity_has_all::<(&Health, &ExampleComponent)>(enemy)
+                    .await,
+                s.entity_has_all::<(&Health, &ExampleComponent)>(neutral)
+                    .await,
+            ];
+            r.lock().unwrap().extend(seen);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(
+            *result.lock().unwrap(),
+            vec![true, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn hooks_trace_a_tick() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let (t1, t2, t3, t4) = (
+            Arc::clone(&trace),
+            Arc::clone(&trace),
+            Arc::clone(&trace),
+            Arc::clone(&trace),
+        );
+        world.resource_mut::<Executor>().set_hooks(ExecutorHooks {
+            on_tick_start: Some(Box::new(move |_| {
+                t1.lock().unwrap().push(("start", None, None));
+            })),
+            on_tick_end: Some(Box::new(move |_, summary| {
+                let TickSummary {
+                    resumed, completed, ..
+                } = summary;
+                t2.lock()
+                    .unwrap()
+                    .push(("end", None, Some(resumed * 10 + completed)));
+            })),
+            before_resume: Some(Box::new(move |info| {
+                t3.lock().unwrap().push(("before", Some(info.id), None));
+            })),
+            after_resume: Some(Box::new(move |info, status| {
+                let done = matches!(status, CoroStatus::Done) as usize;
+                t4.lock()
+                    .unwrap()
+                    .push(("after", Some(info.id), Some(done)));
+            })),
+        });
+
+        root_coroutine(|_: Scope| async move {}).apply(&mut world);
+        root_coroutine(|mut s: Scope| async move {
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+
+        let trace = trace.lock().unwrap();
+        assert_eq!(trace.len(), 6);
+        assert_eq!(trace[0], ("start", None, None));
+        let (a, b) = (trace[1].1, trace[3].1);
+        assert_ne!(a, b);
+        assert_eq!(trace[1].0, "before");
+        assert_eq!(trace[2].0, "after");
+        assert_eq!(trace[2].1, a);
+        assert_eq!(trace[3].0, "before");
+        assert_eq!(trace[4].0, "after");
+        assert_eq!(trace[4].1, b);
+        // One of them finished, the other one waits for the next tick
+        assert_eq!(trace[2].2.unwrap() + trace[4].2.unwrap(), 1);
+        assert_eq!(trace[5], ("end", None, Some(21)));
+    }
+
+    #[test]
+    fn catch_unwind_recovers_from_panics() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let result = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&result);
+
+        root_coroutine(move |mut s: Scope| async move {
+            let panicked = Scope::catch_unwind(AssertUnwindSafe(async {
+                s.next_tick().await;
+                panic!("test");
+            }))
+            .await;
+            let message = panicked.unwrap_err().downcast::<&str>().unwrap();
+            r.lock().unwrap().push(message.to_string());
+
+            let value = Scope::catch_unwind(AssertUnwindSafe(async {
+                s.next_tick().await;
+                1
+            }))
+            .await;
+            r.lock().unwrap().push(value.unwrap().to_string());
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(*result.lock().unwrap(), vec!["test", "1"]);
+    }
+
+    #[test]
+    fn cooperative_yield_lets_others_run() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let (t1, t2) = (Arc::clone(&trace), Arc::clone(&trace));
+
+        root_coroutine(move |mut s: Scope| async move {
+            for i in 0..10 {
+                t1.lock().unwrap().push(i);
+                s.yield_to_scheduler().await;
+            }
+            t1.lock().unwrap().push(10);
+        })
+        .apply(&mut world);
+        root_coroutine(move |_: Scope| async move {
+            t2.lock().unwrap().push(-1);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+
+        // The fast coroutine runs at the latest once the slow one yielded for the first time
+        let trace = trace.lock().unwrap();
+        assert_eq!(trace.len(), 12);
+        assert!(trace.iter().position(|i| *i == -1).unwrap() <= 1);
+    }
+
+    #[test]
+    fn stopwatch_measures_virtual_time() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        let start = Instant::now();
+        world.insert_resource(Time::new(start));
+
+        let e = world
+            .spawn((
+                ExampleComponent(0),
+                ChangeTracker::new() as ChangeTracker<ExampleComponent>,
+            ))
+            .id();
+
+        let measures = Arc::new(Mutex::new(Vec::new()));
+        let (m1, m2) = (Arc::clone(&measures), Arc::clone(&measures));
+
+        root_coroutine(move |mut s: Scope| async move {
+            let total = s.stopwatch();
+            let mut paused = s.stopwatch();
+            s.next_tick().await;
+            paused.pause(&s);
+            s.next_tick().await;
+            paused.unpause(&s);
+            s.next_tick().await;
+            m1.lock().unwrap().push(total.elapsed(&s));
+            m1.lock().unwrap().push(paused.elapsed(&s));
+            m1.lock().unwrap().push(s.coroutine_age());
+        })
+        .apply(&mut world);
+
+        // Woken up by a signal instead
+        root_coroutine(move |mut s: Scope| async move {
+            let total = s.stopwatch();
+            s.any_signal().await;
+            m2.lock().unwrap().push(total.elapsed(&s));
+        })
+        .apply(&mut world);
+
+        coroutine(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                for _ in 0..3 {
+                    s.next_tick().await;
+                }
+                example.get_mut(&s).0 += 1;
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 0..4 {
+                w.resource_mut::<Time>()
+                    .update_with_instant(start + Duration::from_millis(16 * tick));
+                executor.tick(w);
+            }
+        });
+
+        let ms = Duration::from_millis;
+        let mut measures = measures.lock().unwrap().clone();
+        measures.sort();
+        assert_eq!(measures, vec![ms(32), ms(48), ms(48), ms(48)]);
+    }
+
+    #[test]
+    fn on_change_inserts_missing_tracker() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let observed = Arc::new(Mutex::new(0));
+        let o = Arc::clone(&observed);
+
+        coroutine(
+            move |mut s: Scope, on_change: OnChange<ExampleComponent>| async move {
+                on_change.observe(&mut s).await;
+                *o.lock().unwrap() += 1;
+            },
+        )
+        .apply(e, &mut world);
+
+        coroutine(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                s.next_tick().await;
+                example.get_mut(&s).0 += 1;
+            },
+        )
+        .apply(e, &mut world);
+
+        assert!(!world
+            .entity(e)
+            .contains::<ChangeTracker<ExampleComponent>>());
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert!(w.entity(e).contains::<ChangeTracker<ExampleComponent>>());
+            executor.tick(w);
+        });
+
+        assert_eq!(*observed.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn signal_kinds_wake_their_own_waiters() {
+        struct Alarm;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world
+            .spawn((
+                ExampleComponent(0),
+                ChangeTracker::new() as ChangeTracker<ExampleComponent>,
+            ))
+            .id();
+        let other = world.spawn_empty().id();
+
+        let woken = Arc::new(Mutex::new(Vec::new()));
+        let push = |event: &'static str| {
+            let w = Arc::clone(&woken);
+            move || w.lock().unwrap().push(event)
+        };
+
+        let (opened, closed, alarm) = (push("opened"), push("closed"), push("alarm"));
+        root_coroutine(move |mut s: Scope| async move {
+            s.signal_named("door_opened", None).await;
+            opened();
+        })
+        .apply(&mut world);
+        root_coroutine(move |mut s: Scope| async move {
+            s.signal_named("door_closed", None).await;
+            closed();
+        })
+        .apply(&mut world);
+        root_coroutine(move |mut s: Scope| async move {
+            s.signal::<Alarm>(Some(e)).await;
+            alarm();
+        })
+        .apply(&mut world);
+
+        let changed = push("changed");
+        coroutine(
+            move |mut s: Scope, on_change: OnChange<ExampleComponent>| async move {
+                on_change.observe(&mut s).await;
+                changed();
+            },
+        )
+        .apply(e, &mut world);
+
+        coroutine(
+            move |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                s.next_tick().await;
+                s.emit_named("door_opened", None);
+                s.emit::<Alarm>(Some(other));
+                s.next_tick().await;
+                s.emit::<Alarm>(Some(e));
+                s.next_tick().await;
+                s.emit_named("door_closed", None);
+                example.get_mut(&s).0 += 1;
+            },
+        )
+        .apply(e, &mut world);
+
+        let expected: [Vec<&str>; 4] = [
+            vec![],
+            vec!["opened"],
+            vec!["alarm", "opened"],
+            vec!["alarm", "changed", "closed", "opened"],
+        ];
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for expected in expected {
+                executor.tick(w);
+                let mut woken = woken.lock().unwrap().clone();
+                woken.sort();
+                assert_eq!(woken, expected);
+            }
+            assert!(executor.is_empty());
+        });
+    }
+
+    #[test]
+    fn latched_signal_received_later() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn_empty().id();
+
+        root_coroutine(move |s: Scope| async move {
+            s.emit_latched(SignalId::named("level_ready", None), Latch::Sticky);
+            s.emit_latched(SignalId::named("level_ready", Some(e)), Latch::Sticky);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+        world.despawn(e);
+
+        let received = Arc::new(Mutex::new(0));
+        let r = Arc::clone(&received);
+        root_coroutine(move |mut s: Scope| async move {
+            s.signal_named("level_ready", None).await;
+            *r.lock().unwrap() += 1;
+            s.signal_named("level_ready", Some(e)).await;
+            *r.lock().unwrap() += 1;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert_eq!(*received.lock().unwrap(), 1);
+            assert_eq!(executor.len(), 1);
+        });
+    }
+
+    #[test]
+    fn sticky_and_oneshot_latches() {
+        for (latch, expected) in [(Latch::Sticky, 2), (Latch::Oneshot, 1)] {
+            let mut world = World::new();
+            world.init_resource::<Executor>();
+            world.insert_resource(Time::new(Instant::now()));
+
+            root_coroutine(move |s: Scope| async move {
+                s.emit_latched(SignalId::named("door", None), latch);
+            })
+            .apply(&mut world);
+
+            let received = Arc::new(Mutex::new(0));
+            for _ in 0..2 {
+                let r = Arc::clone(&received);
+                world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick(w));
+                root_coroutine(move |mut s: Scope| async move {
+                    s.signal_named("door", None).await;
+                    *r.lock().unwrap() += 1;
+                })
+                .apply(&mut world);
+            }
+
+            world.resource_scope(|w, mut executor: Mut<Executor>| {
+                executor.tick(w);
+                assert_eq!(*received.lock().unwrap(), expected);
+                assert_eq!(executor.len(), 2 - expected);
+            });
+        }
+    }
+
+    #[test]
+    fn watch_wakes_when_value_changes() {
+        #[derive(Component)]
+        struct Enemy;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let token = world
+            .resource_mut::<Executor>()
+            .add_watch(|w: &World| w.iter_entities().filter(|e| e.contains::<Enemy>()).count());
+
+        let tick = Arc::new(Mutex::new(0));
+        let woken = Arc::new(Mutex::new(None));
+        let (t, wo) = (Arc::clone(&tick), Arc::clone(&woken));
+        root_coroutine(move |mut s: Scope| async move {
+            let enemies = s.await_watch(&token).await;
+            *wo.lock().unwrap() = Some((*t.lock().unwrap(), enemies));
+        })
+        .apply(&mut world);
+
+        for i in 1..=6 {
+            if i == 5 {
+                world.spawn(Enemy);
+            }
+            *tick.lock().unwrap() = i;
+            world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick(w));
+        }
+
+        assert_eq!(*woken.lock().unwrap(), Some((5, 1)));
+    }
+
+    #[test]
+    fn limits_cancel_scripts() {
+        fn run_script<Marker: 'static, C>(
+            limits: Limits,
+            script: C,
+        ) -> (Vec<CoroutineCancelled>, usize)
+        where
+            C: CoroutineParamFunction<Marker, ()>,
+        {
+            let mut world = World::new();
+            world.init_resource::<Executor>();
+            world.insert_resource(Time::new(Instant::now()));
+            world.init_resource::<Events<CoroutineCancelled>>();
+
+            root_coroutine(move |mut s: Scope| async move {
+                let _script = s.start_with(limits, script);
+                loop {
+                    s.next_tick().await;
+                }
+            })
+            .apply(&mut world);
+
+            world.resource_scope(|w, mut executor: Mut<Executor>| {
+                for _ in 0..5 {
+                    executor.tick(w);
+                }
+            });
+
+            let events = world.resource::<Events<CoroutineCancelled>>();
+            let cancelled = events.get_reader().iter(events).copied().collect();
+            (cancelled, world.resource::<Executor>().len())
+        }
+
+        let children = Limits {
+            max_children: 2,
+            ..Default::default()
+        };
+        let (cancelled, running) = run_script(children, |mut s: Scope| async move {
+            for _ in 0..3 {
+                s.start_local(|mut s: Scope| async move {
+                    loop {
+                        s.next_tick().await;
+                    }
+                });
+                s.next_tick().await;
+            }
+            s.next_tick().await;
+        });
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].reason, CancelReason::QuotaExceeded);
+        assert_eq!(running, 1);
+
+        let commands = Limits {
+            max_commands_per_tick: 2,
+            ..Default::default()
+        };
+        let (cancelled, running) = run_script(commands, |mut s: Scope| async move {
+            for n in [2, 2, 3] {
+                for _ in 0..n {
+                    s.commands().spawn_empty();
+                }
+                s.next_tick().await;
+            }
+        });
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(running, 1);
+
+        let local_entities = Limits {
+            max_local_entities: 2,
+            ..Default::default()
+        };
+        let (cancelled, running) = run_script(local_entities, |mut s: Scope| async move {
+            for _ in 0..3 {
+                s.spawn_local(());
+                s.next_tick().await;
+            }
+        });
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(running, 1);
+
+        // Within its limits, the script completes
+        let (cancelled, running) = run_script(local_entities, |mut s: Scope| async move {
+            s.spawn_local(());
+            s.next_tick().await;
+        });
+        assert!(cancelled.is_empty());
+        assert_eq!(running, 1);
+    }
+
+    #[test]
+    fn mutex_sections_never_interleave() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let t = Arc::clone(&trace);
+
+        root_coroutine(move |mut s: Scope| async move {
+            let mutex = s.mutex();
+            let section = |name: &'static str| {
+                let (mutex, t) = (mutex.clone(), Arc::clone(&t));
+                move |mut s: Scope| async move {
+                    let _guard = mutex.lock(&mut s).await;
+                    t.lock().unwrap().push(name);
+                    for _ in 0..5 {
+                        s.next_tick().await;
+                    }
+                    t.lock().unwrap().push(name);
+                }
+            };
+
+            // `a` holds the lock while `b` then `c` queue up
+            let a = s.start(section("a"));
+            s.next_tick().await;
+            let b = s.start(section("b"));
+            s.next_tick().await;
+            let c = s.start(section("c"));
+            s.next_tick().await;
+            assert!(mutex.is_locked());
+
+            // Cancelling the first waiter does not stall the next one
+            drop(b);
+            s.all((a, c)).await;
+            assert!(!mutex.is_locked());
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(*trace.lock().unwrap(), vec!["a", "a", "c", "c"]);
+    }
+
+    #[test]
+    fn mutex_waiter_woken_on_release() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let acquired = Arc::new(Mutex::new(false));
+        let a = Arc::clone(&acquired);
+        root_coroutine(move |mut s: Scope| async move {
+            let mutex = s.mutex();
+            let guard = mutex.lock(&mut s).await;
+            let m = mutex.clone();
+            let _waiter = s.start(move |mut s: Scope| async move {
+                let _guard = m.lock(&mut s).await;
+                *a.lock().unwrap() = true;
+            });
+            for _ in 0..3 {
+                s.next_tick().await;
+            }
+            drop(guard);
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            // The waiter is not resumed while the lock is held
+            for _ in 0..2 {
+                executor.tick(w);
+                assert_eq!(executor.debug_last_yields().len(), 1);
+            }
+            // It acquires the lock during the tick it is released
+            executor.tick(w);
+            assert!(*acquired.lock().unwrap());
+        });
+    }
+
+    #[test]
+    fn semaphore_limits_concurrency() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let running = Arc::new(Mutex::new((0, 0)));
+        let semaphore = CoroSemaphore::new(2);
+
+        for _ in 0..5 {
+            let (semaphore, r) = (semaphore.clone(), Arc::clone(&running));
+            root_coroutine(move |mut s: Scope| async move {
+                let _permit = semaphore.acquire(&mut s).await;
+                {
+                    let (current, max) = &mut *r.lock().unwrap();
+                    *current += 1;
+                    *max = (*max).max(*current);
+                }
+                s.next_tick().await;
+                r.lock().unwrap().0 -= 1;
+            })
+            .apply(&mut world);
+        }
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(*running.lock().unwrap(), (0, 2));
+        assert_eq!(semaphore.available_permits(), 2);
+    }
+
+    #[derive(Resource)]
+    struct Steps(Vec<u32>);
+
+    #[derive(CoroutineState)]
+    struct Counter {
+        count: u32,
+    }
+
+    impl CoroutineState for Counter {
+        fn step(&mut self, world: &mut World) -> StateWait {
+            self.count += 1;
+            world.resource_mut::<Steps>().0.push(self.count);
+            if self.count == 5 {
+                StateWait::Done
+            } else {
+                StateWait::NextTick
+            }
+        }
+    }
+
+    #[test]
+    fn restore_saved_state_coroutine() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.insert_resource(Steps(Vec::new()));
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.add_state_coroutine(None, Counter { count: 0 });
+            for _ in 0..3 {
+                executor.tick(w);
+            }
+        });
+        assert_eq!(world.resource::<Steps>().0, vec![1, 2, 3]);
+        let saved = world.resource::<Executor>().serialize();
+
+        let mut loaded = World::new();
+        loaded.init_resource::<Executor>();
+        loaded.insert_resource(Time::new(Instant::now()));
+        loaded.insert_resource(Steps(Vec::new()));
+
+        loaded.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.register_state::<Counter>();
+            executor.deserialize(&saved, w).unwrap();
+            executor.tick_until_empty(w);
+        });
+        assert_eq!(loaded.resource::<Steps>().0, vec![4, 5]);
+
+        let mut executor = Executor::default();
+        assert!(matches!(
+            executor.deserialize(&saved, &mut loaded),
+            Err(DeserializeError::UnknownState(_))
+        ));
+        assert_eq!(
+            executor.deserialize(&saved[..saved.len() - 1], &mut loaded),
+            Err(DeserializeError::Invalid)
+        );
+
+        // A state which does not match its state machine is rejected as a whole
+        executor.register_state::<Counter>();
+        let corrupted = String::from_utf8(saved)
+            .unwrap()
+            .replace(r#"{"count":3}"#, r#"{"count":"3"}"#);
+        assert_eq!(
+            executor.deserialize(corrupted.as_bytes(), &mut loaded),
+            Err(DeserializeError::Invalid)
+        );
+        assert_eq!(executor.len(), 0);
+    }
+
+    #[derive(Event)]
+    struct Ping(u32);
+
+    #[test]
+    fn react_to_events_until_predicate() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.init_resource::<Events<Ping>>();
+        let events_id = world.components().resource_id::<Events<Ping>>().unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let s = Arc::clone(&seen);
+        root_coroutine(move |mut scope: Scope| async move {
+            scope
+                .react_to_event(|ping: &Ping| {
+                    s.lock().unwrap().push(ping.0);
+                    ping.0 == 3
+                })
+                .await;
+            // SAFETY: Only read while the coroutine is polled
+            let declared = unsafe {
+                (*scope.meta_ptr())
+                    .access
+                    .can_read(SourceId::World, events_id)
+            };
+            assert!(declared);
+            s.lock().unwrap().push(0);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            w.send_event(Ping(1));
+            w.send_event(Ping(2));
+            executor.tick(w);
+            assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+            w.send_event(Ping(3));
+            w.send_event(Ping(4));
+            executor.tick(w);
+        });
+
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn first_with_already_finished_handle() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        root_coroutine(|mut s: Scope| async move {
+            let pending = s.start(|mut s: Scope| async move {
+                s.duration(Duration::from_secs(10)).await;
+                1
+            });
+            let done = s.start(|_: Scope| async move { 2 });
+            s.next_tick().await;
+
+            // Resolves without yielding, the pending one is cancelled once its handle is dropped
+            let tick = s.coroutine_age();
+            assert_eq!(s.first([pending, done]).await, 2);
+            assert_eq!(s.coroutine_age(), tick);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert_eq!(executor.counts().on_first, 0);
+            assert_eq!(executor.counts().awaited, 0);
+        });
+    }
+
+    #[test]
+    fn first_clears_waiting_entries() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        root_coroutine(|mut s: Scope| async move {
+            let a = s.start(|mut s: Scope| async move {
+                s.next_tick().await;
+                1
+            });
+            let b = s.start(|mut s: Scope| async move {
+                s.next_tick().await;
+                2
+            });
+            // Both finish during the same tick, the first one resumed wins
+            let winner = s.first([a, b]).await;
+            assert!(winner == 1 || winner == 2);
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert_eq!(executor.counts().on_first, 1);
+            assert_eq!(executor.counts().awaited, 2);
+
+            executor.tick(w);
+            assert_eq!(executor.counts().on_first, 0);
+            assert_eq!(executor.counts().awaited, 0);
+            assert_eq!(executor.len(), 1);
+        });
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn rng_is_deterministic() {
+        use rand::Rng;
+
+        fn run(seed: u64, delay_first: bool) -> Vec<(&'static str, u32)> {
+            let mut world = World::new();
+            world.init_resource::<Executor>();
+            world.insert_resource(Time::new(Instant::now()));
+            world.insert_resource(CoroSeed(seed));
+            let drawn = Arc::new(Mutex::new(Vec::new()));
+
+            let d = Arc::clone(&drawn);
+            root_coroutine(move |mut s: Scope| async move {
+                let (d1, d2) = (Arc::clone(&d), Arc::clone(&d));
+                let a = s.start(move |mut s: Scope| async move {
+                    if delay_first {
+                        s.next_tick().await;
+                    }
+                    for _ in 0..3 {
+                        let value = s.rng().gen();
+                        d1.lock().unwrap().push(("a", value));
+                        s.next_tick().await;
+                    }
+                });
+                let b = s.start(move |mut s: Scope| async move {
+                    for _ in 0..3 {
+                        let value = s.rng().gen();
+                        d2.lock().unwrap().push(("b", value));
+                        s.next_tick().await;
+                    }
+                });
+                let value = s.rng().gen();
+                d.lock().unwrap().push(("root", value));
+                s.all((a, b)).await;
+            })
+            .apply(&mut world);
+
+            world.resource_scope(|w, mut executor: Mut<Executor>| {
+                executor.tick_until_empty(w);
+            });
+
+            // Only the sequence of each coroutine is deterministic, not their interleaving
+            let mut drawn = drawn.lock().unwrap().clone();
+            drawn.sort_by_key(|(name, _)| *name);
+            drawn
+        }
+
+        let first = run(42, false);
+        assert_eq!(first.len(), 7);
+        assert_eq!(first, run(42, true));
+        assert_ne!(first, run(7, false));
+    }
+
+    #[test]
+    fn rate_limit_batches_excess_emissions() {
+        struct Hit;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let batches = Arc::new(Mutex::new(Vec::new()));
+
+        let b = Arc::clone(&batches);
+        root_coroutine(move |mut s: Scope| async move {
+            let consumer = s.start(move |mut s: Scope| async move {
+                let batch = s.rate_limit(3, SignalId::custom::<Hit>(None)).await;
+                b.lock().unwrap().push((batch.count, batch.excess));
+            });
+            s.next_tick().await;
+            for _ in 0..10 {
+                s.emit::<Hit>(None);
+            }
+            s.on(consumer).await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(*batches.lock().unwrap(), vec![(3, 7)]);
+    }
+
+    #[test]
+    fn pause_and_resume_coroutine() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        let mut time = Time::new(Instant::now());
+        let start = time.startup();
+        world.insert_resource(time.clone());
+
+        let current_tick = Arc::new(Mutex::new(0));
+        let runs = Arc::new(Mutex::new(Vec::new()));
+        let timer_done = Arc::new(Mutex::new(0));
+
+        let (c, r) = (Arc::clone(&current_tick), Arc::clone(&runs));
+        let (c2, t) = (Arc::clone(&current_tick), Arc::clone(&timer_done));
+        let (looping, timed) = world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let looping =
+                executor.add_function_coroutine(None, w, move |mut s: Scope| async move {
+                    loop {
+                        r.lock().unwrap().push(*c.lock().unwrap());
+                        s.next_tick().await;
+                    }
+                });
+            let timed = executor.add_function_coroutine(None, w, move |mut s: Scope| async move {
+                s.duration(Duration::from_secs(2)).await;
+                *t.lock().unwrap() = *c2.lock().unwrap();
+            });
+            (looping.unwrap(), timed.unwrap())
+        });
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 1..=5 {
+                if tick == 2 {
+                    executor.pause_coroutine(looping);
+                    executor.pause_coroutine(timed);
+                }
+                if tick == 4 {
+                    executor.resume_coroutine(timed);
+                }
+                if tick == 5 {
+                    executor.resume_coroutine(looping);
+                }
+                *current_tick.lock().unwrap() = tick;
+                time.update_with_instant(start + Duration::from_secs(tick));
+                w.insert_resource(time.clone());
+                executor.tick(w);
+            }
+        });
+
+        assert_eq!(*runs.lock().unwrap(), vec![1, 5]);
+        // Its timer started at tick 1, and only advanced during ticks 4 and 5
+        assert_eq!(*timer_done.lock().unwrap(), 5);
+    }
+
+    #[test]
+    fn quorum_cancels_stragglers() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        let r = Arc::clone(&results);
+        root_coroutine(move |mut s: Scope| async move {
+            // The member `i` reaches the rally point after `i` ticks
+            let members = (1..=5)
+                .rev()
+                .map(|i| {
+                    s.start(move |mut s: Scope| async move {
+                        for _ in 0..i {
+                            s.next_tick().await;
+                        }
+                        i
+                    })
+                })
+                .collect();
+            let arrived = s.quorum(3, members).await;
+            r.lock().unwrap().push(arrived);
+
+            assert!(s.quorum(0, Vec::<CoroHandle<u32>>::new()).await.is_empty());
+            let all = vec![
+                s.start(|_: Scope| async { 1 }),
+                s.start(|_: Scope| async { 2 }),
+            ];
+            let all = s.quorum(5, all).await;
+            r.lock().unwrap().push(all);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for _ in 0..3 {
+                executor.tick(w);
+            }
+            assert_eq!(executor.counts().on_all, 1);
+            assert_eq!(executor.len(), 4);
+
+            executor.tick(w);
+            assert_eq!(executor.counts().awaited, 0);
+            assert_eq!(executor.len(), 0);
+        });
+
+        assert_eq!(*results.lock().unwrap(), vec![vec![3, 2, 1], vec![1, 2]]);
+    }
+
+    #[test]
+    fn run_in_thread_does_not_block() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let result = Arc::new(Mutex::new(None));
+        let ticks = Arc::new(Mutex::new(0));
+
+        let r = Arc::clone(&result);
+        root_coroutine(move |mut s: Scope| async move {
+            let value = s
+                .run_in_thread(|| {
+                    thread::sleep(Duration::from_millis(100));
+                    42
+                })
+                .await;
+            *r.lock().unwrap() = Some(value);
+        })
+        .apply(&mut world);
+
+        let t = Arc::clone(&ticks);
+        root_coroutine(move |mut s: Scope| async move {
+            loop {
+                *t.lock().unwrap() += 1;
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        let start = Instant::now();
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            while result.lock().unwrap().is_none() {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                executor.tick(w);
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        assert_eq!(*result.lock().unwrap(), Some(42));
+        assert!(*ticks.lock().unwrap() > 5);
+    }
+
+    #[derive(Resource, Default)]
+    struct Score(u32);
+
+    #[test]
+    fn lock_resource_sequentially() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.init_resource::<Score>();
+
+        for _ in 0..2 {
+            root_coroutine(|mut s: Scope| async move {
+                {
+                    let mut score = s.lock_resource::<Score>();
+                    score.0 += 1;
+                }
+                s.next_tick().await;
+                s.lock_resource::<Score>().0 += 1;
+            })
+            .apply(&mut world);
+        }
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick_until_empty(w);
+        });
+
+        assert_eq!(world.resource::<Score>().0, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "already locked")]
+    fn lock_resource_held_across_await() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.init_resource::<Score>();
+
+        for _ in 0..2 {
+            root_coroutine(|mut s: Scope| async move {
+                let mut score = s.lock_resource::<Score>();
+                s.next_tick().await;
+                score.0 += 1;
+            })
+            .apply(&mut world);
+        }
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+    }
+
+    #[derive(Resource, Default)]
+    struct Cleanups(u32);
+
+    fn count_cleanup(commands: &mut Commands) {
+        commands.add(|w: &mut World| w.resource_mut::<Cleanups>().0 += 1);
+    }
+
+    #[test]
+    fn defer_runs_once_on_completion() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.init_resource::<Cleanups>();
+
+        root_coroutine(|mut s: Scope| async move {
+            let _guard = s.defer(count_cleanup);
+            s.defer(count_cleanup).disarm();
+            s.next_tick().await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert_eq!(w.resource::<Cleanups>().0, 0);
+            executor.tick_until_empty(w);
+            executor.tick(w);
+        });
+
+        assert_eq!(world.resource::<Cleanups>().0, 1);
+    }
+
+    #[test]
+    fn defer_runs_once_on_cancel() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.init_resource::<Cleanups>();
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        root_coroutine(|mut s: Scope| async move {
+            let loser = s.start(|mut s: Scope| async move {
+                let _guard = s.defer(count_cleanup);
+                s.duration(Duration::from_secs(100)).await;
+            });
+            let winner = s.start(|mut s: Scope| async move {
+                s.next_tick().await;
+            });
+            s.first([loser, winner]).await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let bound = |mut s: Scope, _: Rd<ExampleComponent>| async move {
+                let _guard = s.defer(count_cleanup);
+                loop {
+                    s.next_tick().await;
+                }
+            };
+            executor.add_function_coroutine(Some(e), w, bound);
+
+            executor.tick(w);
+            assert_eq!(w.resource::<Cleanups>().0, 0);
+
+            // The loser of `first` is cancelled
+            executor.tick(w);
+            assert_eq!(w.resource::<Cleanups>().0, 1);
+
+            // The owner is despawned
+            w.despawn(e);
+            executor.tick(w);
+            executor.tick(w);
+            assert_eq!(executor.len(), 0);
+        });
+
+        assert_eq!(world.resource::<Cleanups>().0, 2);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "another world")]
+    fn reject_mismatched_world() {
+        let mut world_a = World::new();
+        world_a.insert_resource(Time::new(Instant::now()));
+        let mut world_b = World::new();
+        world_b.insert_resource(Time::new(Instant::now()));
+
+        let mut executor = Executor::default();
+        executor.add_function_coroutine(None, &world_a, |mut s: Scope| async move {
+            s.next_tick().await;
+        });
+        executor.tick(&mut world_a);
+        executor.tick(&mut world_b);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn mismatched_world_cancels_coroutines() {
+        let mut world_a = World::new();
+        world_a.insert_resource(Time::new(Instant::now()));
+        let mut world_b = World::new();
+        world_b.insert_resource(Time::new(Instant::now()));
+
+        let mut executor = Executor::default();
+        executor.add_function_coroutine(None, &world_a, |mut s: Scope| async move {
+            loop {
+                s.on_any_change::<ExampleComponent, ()>().await;
+            }
+        });
+        executor.tick(&mut world_a);
+        executor.tick(&mut world_a);
+
+        executor.tick(&mut world_b);
+        assert_eq!(executor.len(), 0);
+    }
+
+    #[test]
+    fn migrate_world_cancels_coroutines() {
+        let mut world_a = World::new();
+        world_a.insert_resource(Time::new(Instant::now()));
+        world_a.spawn(ExampleComponent(0));
+        let mut world_b = World::new();
+        world_b.insert_resource(Time::new(Instant::now()));
+        let e = world_b.spawn(ExampleComponent(0)).id();
+
+        let mut executor = Executor::default();
+        executor.add_function_coroutine(None, &world_a, |mut s: Scope| async move {
+            loop {
+                s.next_tick().await;
+            }
+        });
+        executor.tick(&mut world_a);
+
+        executor.migrate_world();
+        assert_eq!(executor.len(), 0);
+
+        executor.add_function_coroutine(
+            Some(e),
+            &world_b,
+            |s: Scope, mut c: Wr<ExampleComponent>| async move {
+                c.get_mut(&s).0 += 1;
+            },
+        );
+        executor.tick(&mut world_b);
+        assert_eq!(world_b.get::<ExampleComponent>(e).unwrap().0, 1);
+    }
+
+    #[test]
+    fn frame_sync_at_lower_rate() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        let start = Instant::now();
+        world.insert_resource(Time::new(start));
+        let ticks = Arc::new(Mutex::new(Vec::new()));
+        let current = Arc::new(Mutex::new(0));
+
+        let (t, c) = (Arc::clone(&ticks), Arc::clone(&current));
+        root_coroutine(move |mut s: Scope| async move {
+            s.every_second(10.0, |_| {
+                t.lock().unwrap().push(*c.lock().unwrap());
+                true
+            })
+            .await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 0..=60 {
+                *current.lock().unwrap() = tick;
+                w.resource_mut::<Time>()
+                    .update_with_instant(start + Duration::from_secs_f64(tick as f64 / 60.0));
+                executor.tick(w);
+            }
+        });
+
+        let ticks = ticks.lock().unwrap().clone();
+        assert!((9..=10).contains(&ticks.len()), "{ticks:?}");
+        for pair in ticks.windows(2) {
+            assert!((5..=7).contains(&(pair[1] - pair[0])), "{ticks:?}");
+        }
+    }
+
+    #[test]
+    fn no_stale_entries_after_cancel_while_sleeping() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let id = executor
+                .add_function_coroutine(None, w, |mut s: Scope| async move {
+                    let sleeper = s.start(|mut s: Scope| async move {
+                        s.duration(Duration::from_secs(10)).await;
+                    });
+                    let listener = s.start(|mut s: Scope| async move {
+                        s.signal_named("never", None).await;
+                    });
+                    s.all_within((sleeper, listener), Duration::from_secs(5))
+                        .await;
+                })
+                .unwrap();
+
+            executor.tick(w);
+            assert_eq!(executor.len(), 3);
+            assert_eq!(executor.indexed_len(), 3);
+
+            assert!(executor.cancel_coroutine(id));
+            assert!(executor.is_empty());
+            assert_eq!(executor.indexed_len(), 0);
+            assert_eq!(executor.counts(), WaitCounts::default());
+        });
+    }
+
+    #[test]
+    fn no_stale_entries_after_first_cancels_sleepers() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        root_coroutine(|mut s: Scope| async move {
+            let winner = s.start(|_: Scope| async move {});
+            let sleeper = s.start(|mut s: Scope| async move {
+                s.duration(Duration::from_secs(10)).await;
+            });
+            s.first([winner, sleeper]).await;
+            s.signal_named("never", None).await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert_eq!(executor.len(), 1);
+            // Only the signal waited on by the root is left
+            assert_eq!(executor.indexed_len(), 1);
+            assert_eq!(
+                executor.counts(),
+                WaitCounts {
+                    on_signal: 1,
+                    ..Default::default()
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn all_signals_across_ticks() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let received = Arc::new(Mutex::new(None));
+        let (left, right) = (
+            SignalId::named("left_plate", None),
+            SignalId::named("right_plate", None),
+        );
+
+        let r = Arc::clone(&received);
+        root_coroutine(move |mut s: Scope| async move {
+            let door = s.start(move |mut s: Scope| async move {
+                *r.lock().unwrap() = Some(s.all_signals([left, right]).await);
+            });
+            s.next_tick().await;
+            s.emit_named("right_plate", None);
+            s.emit_named("right_plate", None);
+            s.next_tick().await;
+            s.emit_named("left_plate", None);
+            s.on(door).await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert_eq!(*received.lock().unwrap(), None);
+            assert_eq!(executor.counts().on_signal, 1);
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+
+        assert_eq!(*received.lock().unwrap(), Some(vec![right, left]));
+    }
+
+    #[test]
+    fn multiple_signals_within_same_tick() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (noise, sight) = (
+            SignalId::named("noise", None),
+            SignalId::named("sight", None),
+        );
+
+        let (r1, r2) = (Arc::clone(&received), Arc::clone(&received));
+        root_coroutine(move |mut s: Scope| async move {
+            let all = s.start(move |mut s: Scope| async move {
+                let signals = s.all_signals([noise, sight]).await;
+                r1.lock().unwrap().push(signals);
+            });
+            let any = s.start(move |mut s: Scope| async move {
+                let signal = s.any_signal_in([noise, sight]).await;
+                r2.lock().unwrap().push(vec![signal]);
+            });
+            s.next_tick().await;
+            s.emit_named("ignored", None);
+            s.emit_named("sight", None);
+            s.emit_named("noise", None);
+            s.all((all, any)).await;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+
+        let mut received = received.lock().unwrap().clone();
+        received.sort_by_key(|r| r.len());
+        assert_eq!(received, vec![vec![sight], vec![sight, noise]]);
+    }
+
+    #[test]
+    fn union_of_conflicting_accesses() {
+        #[derive(Component)]
+        struct Health;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let health = world.init_component::<Health>();
+        let (e, other) = (world.spawn(Health).id(), world.spawn(Health).id());
+
+        let mut parent = CoroAccess::default();
+        assert!(parent.add_write(SourceId::Entity(e), health));
+        let mut child = CoroAccess::default();
+        assert!(child.add_read(SourceId::Entity(e), health));
+        assert!(!parent.clone().union_with(&child));
+
+        let mut elsewhere = CoroAccess::default();
+        assert!(elsewhere.add_read(SourceId::Entity(other), health));
+        assert!(parent.union_with(&elsewhere));
+        assert!(parent.can_read(SourceId::Entity(other), health));
+        assert!(!parent.union_with(&child));
+
+        coroutine(|mut s: Scope, _: Wr<Health>| async move {
+            let child = s.start(|mut s: Scope, _: Rd<Health>| async move {
+                s.next_tick().await;
+            });
+            s.on(child).await;
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            let conflicts: Vec<bool> = executor
+                .coroutines_of(e)
+                .map(|info| executor.conflicts_with_scope(info.id))
+                .collect();
+            assert_eq!(conflicts.len(), 2);
+            assert_eq!(conflicts.iter().filter(|c| **c).count(), 1);
+        });
+    }
+
+    #[test]
+    fn observe_when_predicate_holds() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world
+            .spawn((
+                ExampleComponent(100),
+                ChangeTracker::new() as ChangeTracker<ExampleComponent>,
+            ))
+            .id();
+
+        let wakes = Arc::new(Mutex::new(Vec::new()));
+        let w = Arc::clone(&wakes);
+
+        coroutine(
+            move |mut s: Scope,
+                  health: Rd<ExampleComponent>,
+                  on_change: OnChange<ExampleComponent>| async move {
+                loop {
+                    on_change.observe_when(&mut s, |h| h.0 < 25).await;
+                    w.lock().unwrap().push(health.get(&s).0);
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        coroutine(
+            |mut s: Scope, mut health: Wr<ExampleComponent>| async move {
+                for value in [50, 30, 60, 40, 20, 10] {
+                    s.next_tick().await;
+                    health.get_mut(&s).0 = value;
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|world, mut executor: Mut<Executor>| {
+            for _ in 0..5 {
+                executor.tick(world);
+                assert!(wakes.lock().unwrap().is_empty());
+            }
+            executor.tick(world);
+            assert_eq!(*wakes.lock().unwrap(), vec![20]);
+            executor.tick(world);
+            assert_eq!(*wakes.lock().unwrap(), vec![20, 10]);
+        });
+    }
+
+    #[test]
+    fn rollback_on_failure() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(10)).id();
+
+        coroutine(|mut s: Scope, ex: Rd<ExampleComponent>| async move {
+            let snapshot = ex.get(&s).0;
+            let result = s
+                .with_rollback(
+                    snapshot,
+                    |owner, snapshot, commands: &mut Commands| {
+                        commands.entity(owner).insert(ExampleComponent(snapshot));
+                    },
+                    |mut s: Scope, mut ex: Wr<ExampleComponent>| async move {
+                        ex.get_mut(&s).0 += 5;
+                        s.next_tick().await;
+                        ex.get_mut(&s).0 += 5;
+                        s.next_tick().await;
+                        Err::<(), ()>(())
+                    },
+                )
+                .await;
+            assert!(result.is_err());
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|world, mut executor: Mut<Executor>| {
+            executor.tick(world);
+            assert_eq!(world.get::<ExampleComponent>(e).unwrap().0, 15);
+            executor.tick(world);
+            assert_eq!(world.get::<ExampleComponent>(e).unwrap().0, 20);
+            executor.tick(world);
+            assert_eq!(world.get::<ExampleComponent>(e).unwrap().0, 10);
+        });
+    }
+
+    #[test]
+    fn rollback_on_cancellation() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(10)).id();
+        let gone = world.spawn(ExampleComponent(10)).id();
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let mut ids = Vec::new();
+            for owner in [e, gone] {
+                let id = executor.add_function_coroutine(
+                    Some(owner),
+                    w,
+                    |mut s: Scope, ex: Rd<ExampleComponent>| async move {
+                        let snapshot = ex.get(&s).0;
+                        let _ = s
+                            .with_rollback(
+                                snapshot,
+                                |owner, snapshot, commands: &mut Commands| {
+                                    commands.entity(owner).insert(ExampleComponent(snapshot));
+                                },
+                                |mut s: Scope, mut ex: Wr<ExampleComponent>| async move {
+                                    for _ in 0..10 {
+                                        ex.get_mut(&s).0 += 5;
+                                        s.next_tick().await;
+                                    }
+                                    Ok::<(), ()>(())
+                                },
+                            )
+                            .await;
+                    },
+                );
+                ids.push(id.unwrap());
+            }
+
+            executor.tick(w);
+            executor.tick(w);
+            assert_eq!(w.get::<ExampleComponent>(e).unwrap().0, 20);
+
+            // Its parent is cancelled, or its owner despawned, while it still runs
+            executor.cancel_coroutine(ids[0]);
+            w.despawn(gone);
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+
+        assert_eq!(world.get::<ExampleComponent>(e).unwrap().0, 10);
+    }
+
+    #[test]
+    fn cancel_by_id() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let child_id = Arc::new(Mutex::new(None));
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let (c, r) = (Arc::clone(&child_id), Arc::clone(&results));
+        root_coroutine(move |mut s: Scope| async move {
+            let handle = s.start(|mut s: Scope| async move {
+                loop {
+                    s.next_tick().await;
+                }
+            });
+            let CoroHandle::Waiting { id, .. } = handle else {
+                unreachable!()
+            };
+            *c.lock().unwrap() = Some(id);
+            r.lock().unwrap().push(s.try_cancel(id));
+            s.next_tick().await;
+            r.lock().unwrap().push(s.try_cancel(id));
+            r.lock().unwrap().push(s.try_cancel_handle(&handle));
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            let id = child_id.lock().unwrap().unwrap();
+            assert!(!executor.contains(id));
+            executor.tick_until_empty(w);
+        });
+        assert_eq!(*results.lock().unwrap(), vec![true, false, false]);
+    }
+
+    #[test]
+    fn resume_after_transform_propagation() {
+        let mut app = App::new();
+        app.add_plugins((CorentinPlugin, TransformPlugin));
+        app.add_coroutine_phase(PostUpdate, TransformSystem::TransformPropagate);
+        app.insert_resource(Time::new(Instant::now()));
+
+        let e = app.world.spawn(TransformBundle::default()).id();
+        let result = Arc::new(Mutex::new(None));
+        let r = Arc::clone(&result);
+        let coroutine = move |mut s: Scope,
+                              mut transform: Wr<Transform>,
+                              global: Rd<GlobalTransform>| async move {
+            transform.get_mut(&s).translation.x = 5.0;
+            let before = global.get(&s).translation().x;
+            s.after_set(TransformSystem::TransformPropagate).await;
+            let after = global.get(&s).translation().x;
+            *r.lock().unwrap() = Some((before, after));
+        };
+        entity_coroutine(e, coroutine).apply(&mut app.world);
+
+        app.update();
+        assert_eq!(*result.lock().unwrap(), Some((0.0, 5.0)));
+    }
+
+    #[test]
+    fn every_change_of_component() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world
+            .spawn((
+                ExampleComponent(0),
+                ChangeTracker::new() as ChangeTracker<ExampleComponent>,
+            ))
+            .id();
+
+        let observed = Arc::new(Mutex::new(0));
+        let o = Arc::clone(&observed);
+        coroutine(
+            move |mut s: Scope, on_change: OnChange<ExampleComponent>| async move {
+                let mut changes = s.every(|s| Box::pin(on_change.observe(s)));
+                loop {
+                    changes.next().await;
+                    *o.lock().unwrap() += 1;
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        coroutine(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                for _ in 0..3 {
+                    s.next_tick().await;
+                    example.get_mut(&s).0 += 1;
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            for i in 1..=3 {
+                executor.tick(w);
+                assert_eq!(*observed.lock().unwrap(), i);
+            }
+
+            // Nothing changed since
+            executor.tick(w);
+            executor.tick(w);
+        });
+        assert_eq!(*observed.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn persistent_coroutine_resumes_from_checkpoint() {
+        let quest = |log: Arc<Mutex<Vec<u32>>>| {
+            move |mut s: Scope, restore: Option<u32>| {
+                let log = Arc::clone(&log);
+                async move {
+                    let mut step = restore.unwrap_or(0);
+                    loop {
+                        step += 1;
+                        log.lock().unwrap().push(step);
+                        s.checkpoint("quest_1", &step);
+                        s.next_tick().await;
+                    }
+                }
+            }
+        };
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.insert_resource(Time::new(Instant::now()));
+        app.register_persistent_coroutine("quest_1", quest(Arc::clone(&log)));
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(*log.lock().unwrap(), vec![1, 2, 3]);
+        let save = app.world.resource::<PersistentScripts>().export();
+
+        // Reloaded into a fresh world
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.insert_resource(Time::new(Instant::now()));
+        app.register_persistent_coroutine("quest_1", quest(Arc::clone(&log)));
+        app.world
+            .resource_mut::<PersistentScripts>()
+            .import(&save)
+            .unwrap();
+        app.update();
+        assert_eq!(*log.lock().unwrap(), vec![4]);
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct QuestProgress {
+        step: u32,
+    }
+
+    #[test]
+    fn reload_restarts_persistent_coroutines() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = Arc::clone(&log);
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.insert_resource(Time::new(Instant::now()));
+        app.register_persistent_coroutine(
+            "quest_2",
+            move |mut s: Scope, restore: Option<QuestProgress>| {
+                let log = Arc::clone(&l);
+                async move {
+                    let mut progress = restore.unwrap_or(QuestProgress { step: 0 });
+                    loop {
+                        progress.step += 1;
+                        log.lock().unwrap().push(progress.step);
+                        s.checkpoint("quest_2", &progress);
+                        s.next_tick().await;
+                    }
+                }
+            },
+        );
+
+        let ticks = Arc::new(Mutex::new(0));
+        let t = Arc::clone(&ticks);
+        root_coroutine(move |mut s: Scope| async move {
+            loop {
+                *t.lock().unwrap() += 1;
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut app.world);
+
+        for _ in 0..3 {
+            app.update();
+        }
+        let save = app.world.resource::<PersistentScripts>().export();
+        app.update();
+        assert_eq!(*log.lock().unwrap(), vec![1, 2, 3, 4]);
+
+        // Loading the save restarts the quest from the checkpoint of the third step, and cancels
+        // the coroutines which are not persistent
+        app.world
+            .resource_mut::<PersistentScripts>()
+            .import(&save)
+            .unwrap();
+        reload_persistent_coroutines(&mut app.world);
+        let ticks_before = *ticks.lock().unwrap();
+        app.update();
+        assert_eq!(*log.lock().unwrap(), vec![1, 2, 3, 4, 4]);
+        assert_eq!(*ticks.lock().unwrap(), ticks_before);
+    }
+
+    #[test]
+    fn parallel_map_in_input_order() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let result = Arc::new(Mutex::new(None));
+        let r = Arc::clone(&result);
+        root_coroutine(move |mut s: Scope| async move {
+            let doubled = s
+                .parallel_map([1, 2, 3], |s, item: u32| {
+                    s.start(move |mut s: Scope| async move {
+                        s.next_tick().await;
+                        item * 2
+                    })
+                })
+                .await;
+            *r.lock().unwrap() = Some(doubled);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert_eq!(*result.lock().unwrap(), None);
+            executor.tick(w);
+        });
+        assert_eq!(*result.lock().unwrap(), Some(vec![2, 4, 6]));
+    }
+
+    #[test]
+    fn time_snapshot_matches_next_tick() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        let start = Instant::now();
+        world.insert_resource(Time::new(start));
+
+        let snapshots = Arc::new(Mutex::new(Vec::new()));
+        let snaps = Arc::clone(&snapshots);
+        root_coroutine(move |mut s: Scope| async move {
+            for _ in 0..3 {
+                let delta = s.next_tick().await;
+                snaps.lock().unwrap().push((delta, s.time()));
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 0..4 {
+                w.resource_mut::<Time>()
+                    .update_with_instant(start + Duration::from_millis(16 * tick));
+                executor.tick(w);
+            }
+        });
+
+        let snapshots = snapshots.lock().unwrap();
+        let ticks: Vec<u64> = snapshots.iter().map(|(_, time)| time.tick_count).collect();
+        assert_eq!(ticks, vec![2, 3, 4]);
+        for (delta, time) in snapshots.iter() {
+            assert_eq!(*delta, time.delta);
+            assert_eq!(time.delta, Duration::from_millis(16));
+        }
+        assert!(snapshots[0].1.elapsed < snapshots[2].1.elapsed);
+    }
+
+    #[test]
+    fn with_world_runs_exclusively() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let a = world.spawn(ExampleComponent(1)).id();
+        let b = world.spawn(ExampleComponent(2)).id();
+
+        let result = Arc::new(Mutex::new(None));
+        let r = Arc::clone(&result);
+        root_coroutine(move |mut s: Scope| async move {
+            let sum: u32 = s
+                .with_world(|world| {
+                    let mut query = world.query::<&ExampleComponent>();
+                    query.iter(world).map(|c| c.0).sum()
+                })
+                .await;
+            s.with_world(move |world| world.despawn(b)).await;
+            *r.lock().unwrap() = Some(sum);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+        assert_eq!(*result.lock().unwrap(), Some(3));
+        assert!(world.get_entity(a).is_some());
+        assert!(world.get_entity(b).is_none());
+    }
+
+    #[test]
+    fn wake_on_component_removed() {
+        #[derive(Component)]
+        struct Stun;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let stunned = world.spawn(Stun).id();
+        let despawned = world.spawn(Stun).id();
+
+        let woken = Arc::new(Mutex::new(Vec::new()));
+        for (name, entity) in [("removed", stunned), ("despawned", despawned)] {
+            let w = Arc::clone(&woken);
+            root_coroutine(move |mut s: Scope| async move {
+                s.on_component_removed::<Stun>(entity).await;
+                w.lock().unwrap().push((name, s.time().tick_count));
+            })
+            .apply(&mut world);
+        }
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 1..=6 {
+                if tick == 4 {
+                    w.entity_mut(stunned).remove::<Stun>();
+                }
+                if tick == 6 {
+                    w.despawn(despawned);
+                }
+                executor.tick(w);
+                // Not resumed until a removal is detected
+                if tick == 3 {
+                    assert!(executor.debug_last_yields().is_empty());
+                }
+            }
+            assert!(executor.is_empty());
+        });
+        assert_eq!(
+            *woken.lock().unwrap(),
+            vec![("removed", 4), ("despawned", 6)]
+        );
+    }
+
+    #[test]
+    fn map_world_reads_undeclared_component() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(42)).id();
+
+        let result = Arc::new(Mutex::new(None));
+        let r = Arc::clone(&result);
+        root_coroutine(move |s: Scope| async move {
+            // SAFETY: Only read, while no other coroutine is running
+            let read = |w: UnsafeWorldCell<'_>| unsafe {
+                w.get_entity(e)
+                    .unwrap()
+                    .get::<ExampleComponent>()
+                    .unwrap()
+                    .0
+            };
+            *r.lock().unwrap() = Some((s.map_world(read), s.map_world_readonly(read)));
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+        assert_eq!(*result.lock().unwrap(), Some((42, 42)));
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicts with the one of the coroutine")]
+    fn map_world_panics_on_conflicting_access() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        coroutine(|mut s: Scope, _example: Wr<ExampleComponent>| async move {
+            s.next_tick().await;
+        })
+        .apply(e, &mut world);
+        coroutine(|s: Scope, _example: Rd<ExampleComponent>| async move {
+            s.map_world(|_| ());
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+        });
+    }
+
+    #[test]
+    fn event_readers_have_their_own_cursor() {
+        #[derive(Event, Clone, Copy)]
+        struct Ping(u32);
+
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.add_event::<Ping>();
+        app.insert_resource(Time::new(Instant::now()));
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = Arc::clone(&log);
+        app.add_systems(Update, move |mut pings: EventReader<Ping>| {
+            for ping in pings.iter() {
+                l.lock().unwrap().push(("system", ping.0));
+            }
+        });
+        for name in ["a", "b"] {
+            let l = Arc::clone(&log);
+            root_coroutine(move |mut s: Scope, mut pings: Evt<Ping>| async move {
+                loop {
+                    let ping = pings.next(&mut s).await.0;
+                    l.lock().unwrap().push((name, ping));
+                }
+            })
+            .apply(&mut app.world);
+        }
+
+        app.world.send_event(Ping(1));
+        app.update();
+        app.world.send_event(Ping(2));
+        app.world.send_event(Ping(3));
+        app.update();
+        app.update();
+
+        let log = log.lock().unwrap();
+        for reader in ["system", "a", "b"] {
+            let seen: Vec<u32> = log
+                .iter()
+                .filter(|(name, _)| *name == reader)
+                .map(|(_, ping)| *ping)
+                .collect();
+            assert_eq!(seen, vec![1, 2, 3], "{reader}");
+        }
+    }
+
+    #[test]
+    fn drain_pending_events() {
+        #[derive(Event)]
+        struct Ping(u32);
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.init_resource::<Events<Ping>>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let drained = Arc::new(Mutex::new(Vec::new()));
+        let d = Arc::clone(&drained);
+        root_coroutine(move |mut s: Scope, mut pings: Evt<Ping>| async move {
+            s.next_tick().await;
+            let pings: Vec<u32> = pings.drain_pending(&s).map(|ping| ping.0).collect();
+            *d.lock().unwrap() = pings;
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            w.send_event(Ping(1));
+            w.send_event(Ping(2));
+            executor.tick(w);
+        });
+        assert_eq!(*drained.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn cancel_forgotten_coroutine() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let forgotten = Arc::new(Mutex::new(None));
+        let f = Arc::clone(&forgotten);
+        root_coroutine(move |mut s: Scope| async move {
+            *f.lock().unwrap() = s.start_forget(|mut s: Scope| async move {
+                loop {
+                    s.next_tick().await;
+                }
+            });
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            let id = forgotten.lock().unwrap().unwrap();
+            assert!(executor.contains(id));
+            let len = executor.len();
+
+            assert!(executor.cancel_coroutine(id));
+            assert!(!executor.contains(id));
+            assert_eq!(executor.len(), len - 1);
+            assert!(!executor.cancel_coroutine(id));
+        });
+    }
+
+    #[test]
+    fn rotating_tick_order() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let resumed = Arc::new(Mutex::new(Vec::new()));
+        for i in 0..3 {
+            let r = Arc::clone(&resumed);
+            root_coroutine(move |mut s: Scope| async move {
+                loop {
+                    r.lock().unwrap().push(i);
+                    s.next_tick().await;
+                }
+            })
+            .apply(&mut world);
+        }
+
+        let mut first = Vec::new();
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.set_tick_order(TickOrder::Rotating);
+            for _ in 0..4 {
+                resumed.lock().unwrap().clear();
+                executor.tick(w);
+                first.push(resumed.lock().unwrap()[0]);
+            }
+        });
+        // The starting point moves by one coroutine each tick
+        assert_eq!(first, vec![0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn wake_on_asset_loaded() {
+        #[derive(TypeUuid, TypePath)]
+        #[uuid = "2f8c6e4a-93b1-4d0e-8a57-6c1d2e9f7b30"]
+        struct Level(u32);
+
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            CorentinPlugin,
+        ));
+        app.add_asset::<Level>();
+        app.insert_resource(Time::new(Instant::now()));
+
+        let id = HandleId::random::<Level>();
+        let mut handle = Handle::weak(id);
+        handle.make_strong(app.world.resource::<Assets<Level>>());
+        let loaded = Arc::new(Mutex::new(None));
+        let l = Arc::clone(&loaded);
+        root_coroutine(move |mut s: Scope| async move {
+            let level = s.await_asset_loaded(handle).await.0;
+            *l.lock().unwrap() = Some((level, s.time().tick_count));
+        })
+        .apply(&mut app.world);
+
+        // The handle waited on is the only one, it keeps the asset from being freed once loaded
+        app.update();
+        app.update();
+        app.update();
+        assert!(loaded.lock().unwrap().is_none());
+
+        // Loaded after three ticks, the coroutine wakes up on the next one
+        app.world
+            .resource_mut::<Assets<Level>>()
+            .set_untracked(id, Level(7));
+        app.update();
+        assert_eq!(*loaded.lock().unwrap(), Some((7, 4)));
+
+        // Freed a few ticks after the coroutine dropped the handle
+        app.update();
+        app.update();
+        app.update();
+        assert!(!app
+            .world
+            .resource::<Assets<Level>>()
+            .contains(&Handle::weak(id)));
+    }
+
+    #[test]
+    fn owner_commands_are_deferred() {
+        for (point, expected) in [
+            (CommandFlushPoint::InsideTick, Some(7)),
+            (CommandFlushPoint::NextApplyDeferred, None),
+        ] {
+            let mut app = App::new();
+            app.add_plugins((CorentinPlugin, CommandFlushPlugin(point)));
+            app.insert_resource(Time::new(Instant::now()));
+
+            // Same as with `Scope::commands`, see `command_flush_points`
+            let e = app.world.spawn(ExampleComponent(0)).id();
+            let reinsert = move |mut commands: Commands| {
+                commands.entity(e).insert(ExampleComponent(7));
+            };
+            app.add_systems(Update, reinsert.before(run_coroutines));
+
+            coroutine(move |s: Scope| async move {
+                s.remove::<ExampleComponent>();
+            })
+            .apply(e, &mut app.world);
+
+            app.update();
+            let value = app.world.get::<ExampleComponent>(e).map(|c| c.0);
+            assert_eq!(value, expected);
+        }
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let ran = Arc::new(Mutex::new(None));
+        let r = Arc::clone(&ran);
+        root_coroutine(move |s: Scope| async move {
+            *r.lock().unwrap() = Some(s.try_owner_commands(|_| ()).is_some());
+        })
+        .apply(&mut world);
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick(w));
+        assert_eq!(*ran.lock().unwrap(), Some(false));
+    }
+
+    #[test]
+    fn repeat_until_break() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let count = Arc::new(Mutex::new(0));
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let (c, r) = (Arc::clone(&count), Arc::clone(&results));
+        root_coroutine(move |mut s: Scope| async move {
+            let broke = s
+                .repeat(Some(10), |s| {
+                    let c = Arc::clone(&c);
+                    Box::pin(async move {
+                        s.next_tick().await;
+                        let mut count = c.lock().unwrap();
+                        *count += 1;
+                        if *count == 5 {
+                            ControlFlow::Break(*count)
+                        } else {
+                            ControlFlow::Continue(())
+                        }
+                    })
+                })
+                .await;
+            r.lock().unwrap().push(broke);
+
+            let exhausted = s
+                .repeat(Some(3), |s| {
+                    Box::pin(async move {
+                        s.next_tick().await;
+                        ControlFlow::<u32>::Continue(())
+                    })
+                })
+                .await;
+            r.lock().unwrap().push(exhausted);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick_until_empty(w));
+        assert_eq!(*count.lock().unwrap(), 5);
+        assert_eq!(*results.lock().unwrap(), vec![Some(5), None]);
+    }
+
+    #[test]
+    fn await_marked_coroutine() {
+        struct DeathAnimation;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let animation = |mut s: Scope| async move {
+            s.register_as::<DeathAnimation>();
+            s.next_tick().await;
+            s.next_tick().await;
+        };
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let waiter = |entity: Entity| {
+            let r = Arc::clone(&results);
+            root_coroutine(move |mut s: Scope| async move {
+                let finished = s.await_marked::<DeathAnimation>(entity).await;
+                r.lock()
+                    .unwrap()
+                    .push((finished.is_some(), s.time().tick_count));
+            })
+        };
+
+        // Registered before the waiter starts
+        let a = world.spawn_empty().id();
+        coroutine(animation).apply(a, &mut world);
+        waiter(a).apply(&mut world);
+
+        // Registered after the waiter starts
+        let b = world.spawn_empty().id();
+        waiter(b).apply(&mut world);
+        coroutine(animation).apply(b, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick_until_empty(w));
+        assert_eq!(*results.lock().unwrap(), vec![(false, 1), (true, 3)]);
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Guard {
+        Idle,
+        Alert,
+        Attack,
+    }
+
+    #[derive(EntityCoroutine)]
+    #[param(Rd<ExampleComponent>)]
+    struct GuardAi {
+        state: Guard,
+        log: Arc<Mutex<Vec<Guard>>>,
+    }
+
+    impl CoroutineStateMachine for GuardAi {
+        fn init(&mut self, _: &mut Scope, _: &mut (Rd<ExampleComponent>,)) {
+            self.log.lock().unwrap().push(self.state);
+        }
+
+        fn update(
+            &mut self,
+            s: &mut Scope,
+            (threat,): &mut (Rd<ExampleComponent>,),
+        ) -> CoroutineStatus {
+            self.state = match self.state {
+                Guard::Idle if threat.get(s).0 > 0 => Guard::Alert,
+                Guard::Idle => Guard::Idle,
+                Guard::Alert => Guard::Attack,
+                Guard::Attack => return CoroutineStatus::Done,
+            };
+            self.log.lock().unwrap().push(self.state);
+            CoroutineStatus::NextTick
+        }
+    }
+
+    #[test]
+    fn state_machine_transitions() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        coroutine(GuardAi {
+            state: Guard::Idle,
+            log: Arc::clone(&log),
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert_eq!(*log.lock().unwrap(), vec![Guard::Idle, Guard::Idle]);
+
+            w.get_mut::<ExampleComponent>(e).unwrap().0 = 1;
+            executor.tick(w);
+            executor.tick(w);
+            assert!(!executor.is_empty());
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+        let expected = vec![Guard::Idle, Guard::Idle, Guard::Alert, Guard::Attack];
+        assert_eq!(*log.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn wake_on_query_change() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        world.spawn(ExampleComponent(0));
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let woken = Arc::new(Mutex::new(None));
+        let w = Arc::clone(&woken);
+        root_coroutine(move |mut s: Scope| async move {
+            let entity = s
+                .await_query_change::<&ExampleComponent, Changed<ExampleComponent>>()
+                .await;
+            *w.lock().unwrap() = Some(entity);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert!(woken.lock().unwrap().is_none());
+
+            w.get_mut::<ExampleComponent>(e).unwrap().0 = 1;
+            executor.tick(w);
+        });
+        assert_eq!(*woken.lock().unwrap(), Some(e));
+    }
+
+    #[test]
+    fn reflected_components_are_registered() {
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+        assert!(registry.get(TypeId::of::<HasCoroutines>()).is_some());
+        assert!(registry.get(TypeId::of::<EntityCoroutines>()).is_some());
+        #[cfg(feature = "debug-ui")]
+        assert!(registry.get(TypeId::of::<CoroutineDebugInfo>()).is_some());
+    }
+
+    #[test]
+    fn change_trackers_are_registered_once_inserted() {
+        let mut app = App::new();
+        app.insert_resource(Time::new(Instant::now()))
+            .add_plugins(CorentinPlugin);
+
+        let e = app.world.spawn(ExampleComponent(0)).id();
+        coroutine(
+            |mut s: Scope, on_change: OnChange<ExampleComponent>| async move {
+                on_change.observe(&mut s).await;
+            },
+        )
+        .apply(e, &mut app.world);
+
+        let tracker = TypeId::of::<ChangeTracker<ExampleComponent>>();
+        assert!(app
+            .world
+            .resource::<AppTypeRegistry>()
+            .read()
+            .get(tracker)
+            .is_none());
+
+        app.update();
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+        let registration = registry.get(tracker).unwrap();
+        assert!(registration.data::<ReflectComponent>().is_some());
+        assert_eq!(
+            ChangeTracker::<ExampleComponent>::short_type_path(),
+            "ChangeTracker<ExampleComponent>"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "debug-ui")]
+    fn debug_info_mirrors_coroutines() {
+        let mut app = App::new();
+        app.insert_resource(Time::new(Instant::now()))
+            .add_plugins(CorentinPlugin);
+
+        let e = app.world.spawn(CoroutineDebugInfo::default()).id();
+        coroutine(|mut s: Scope| async move {
+            loop {
+                s.next_tick().await;
+            }
+        })
+        .apply(e, &mut app.world);
+        let other = app.world.spawn_empty().id();
+        coroutine(|mut s: Scope| async move { s.next_tick().await }).apply(other, &mut app.world);
+
+        app.update();
+        let info = &app.world.get::<CoroutineDebugInfo>(e).unwrap().0;
+        assert_eq!(info.len(), 1);
+        assert_eq!(info[0].wait, "Tick");
+        assert!(app.world.get::<CoroutineDebugInfo>(other).is_none());
+    }
+
+    /// Initialize the parameters `P` of a coroutine owned by `owner`, returning the access it
+    /// declared if they are accepted.
+    fn param_access<P: CoroParam>(world: &World, owner: Entity) -> Option<CoroAccess> {
+        let mut meta = CoroMeta {
+            id: Id::from_raw(0),
+            owner: Some(owner),
+            access: CoroAccess::default(),
+            name: "",
+            graceful: false,
+            spawned_at: Duration::ZERO,
+            setup: Vec::new(),
+            world_id: None,
+        };
+        P::init(world.as_unsafe_world_cell_readonly(), &mut meta)?;
+        Some(meta.access)
+    }
+
+    #[test]
+    fn access_registration_matrix() {
+        let mut world = World::new();
+        let e = world.spawn(ExampleComponent(0)).id();
+        let other = world.spawn_empty().id();
+        let health = world.component_id::<ExampleComponent>().unwrap();
+        let ours = SourceId::Entity(e);
+
+        let access = param_access::<(Rd<ExampleComponent>, Rd<ExampleComponent>)>(&world, e);
+        let access = access.unwrap();
+        assert!(access.can_read(ours, health) && !access.can_write(ours, health));
+        type RdWr = (Rd<ExampleComponent>, Wr<ExampleComponent>);
+        assert!(param_access::<RdWr>(&world, e).is_none());
+        type WrRd = (Wr<ExampleComponent>, Rd<ExampleComponent>);
+        assert!(param_access::<WrRd>(&world, e).is_none());
+        type WrWr = (Wr<ExampleComponent>, Wr<ExampleComponent>);
+        assert!(param_access::<WrWr>(&world, e).is_none());
+        let access = param_access::<(Wr<ExampleComponent>,)>(&world, e).unwrap();
+        assert!(access.can_write(ours, health));
+
+        // Resources are read from the world, there are no resource parameters yet
+        let time = world.init_resource::<Time>();
+        let mut access = CoroAccess::default();
+        assert!(access.add_read(SourceId::World, time));
+        assert!(access.add_read(SourceId::World, time));
+        assert!(!access.can_write(SourceId::World, time));
+        let mut access = CoroAccess::default();
+        assert!(access.add_write(SourceId::World, time));
+        assert!(!access.add_read(SourceId::World, time));
+        assert!(!access.add_write(SourceId::World, time));
+        assert_eq!(access.reads.get(&SourceId::World), None);
+
+        // A rejected access is not recorded, whichever source it overlaps
+        let mut access = CoroAccess::default();
+        assert!(access.add_write(SourceId::AllEntities, health));
+        assert!(!access.add_read(ours, health));
+        assert!(access.reads.is_empty());
+        let mut access = CoroAccess::default();
+        assert!(access.add_read(ours, health));
+        assert!(!access.add_write(SourceId::AllEntities, health));
+        assert!(access.add_write(SourceId::Entity(other), health));
+        assert!(access.writes.get(&SourceId::AllEntities).is_none());
+    }
+
+    #[test]
+    fn apply_deferred_after_each_coroutine() {
+        for after_each in [false, true] {
+            let mut world = World::new();
+            world.init_resource::<Executor>();
+            world.insert_resource(Time::new(Instant::now()));
+
+            let spawned = Arc::new(Mutex::new(None));
+            let seen = Arc::new(Mutex::new(None));
+            let sp = Arc::clone(&spawned);
+            root_coroutine(move |s: Scope| async move {
+                *sp.lock().unwrap() = Some(s.commands().spawn(ExampleComponent(0)).id());
+            })
+            .apply(&mut world);
+            let (sp, se) = (Arc::clone(&spawned), Arc::clone(&seen));
+            root_coroutine(move |mut s: Scope| async move {
+                // Resumed after the other one, whatever the order they were started in
+                s.yield_to_scheduler().await;
+                let e = sp.lock().unwrap().unwrap();
+                *se.lock().unwrap() = Some(s.entity_has_all::<&ExampleComponent>(e).await);
+            })
+            .apply(&mut world);
+
+            world.resource_scope(|w, mut executor: Mut<Executor>| {
+                executor.set_apply_deferred_after_each(after_each);
+                executor.tick(w);
+            });
+            assert_eq!(*seen.lock().unwrap(), Some(after_each));
+        }
+    }
+
+    #[test]
+    fn first_or_cancel_skips_cancelled() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let first_id = Arc::new(Mutex::new(None));
+        let result = Arc::new(Mutex::new(None));
+        let (f, r) = (Arc::clone(&first_id), Arc::clone(&result));
+        root_coroutine(move |mut s: Scope| async move {
+            let h0 = s.start(|mut s: Scope| async move {
+                s.duration(Duration::from_secs(100)).await;
+                0
+            });
+            let h1 = s.start(|mut s: Scope| async move {
+                s.next_tick().await;
+                s.next_tick().await;
+                42
+            });
+            let h2 = s.start(|mut s: Scope| async move {
+                s.duration(Duration::from_secs(100)).await;
+                0
+            });
+            let CoroHandle::Waiting { id, .. } = h0 else {
+                unreachable!()
+            };
+            *f.lock().unwrap() = Some(id);
+            *r.lock().unwrap() = Some(s.first_or_cancel([h0, h1, h2]).await);
+        })
+        .apply(&mut world);
+
+        // Cancels the first child on the next tick, its parent keeps waiting on the others
+        let f = Arc::clone(&first_id);
+        root_coroutine(move |mut s: Scope| async move {
+            s.next_tick().await;
+            assert!(s.try_cancel(f.lock().unwrap().unwrap()));
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+            assert!(result.lock().unwrap().is_none());
+            executor.tick_until_empty(w);
+        });
+        assert_eq!(*result.lock().unwrap(), Some((1, 42)));
+    }
+
+    #[test]
+    fn opt_param_survives_missing_component() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(3)).id();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let se = Arc::clone(&seen);
+        coroutine(
+            move |mut s: Scope, ex: Opt<Rd<ExampleComponent>>| async move {
+                loop {
+                    se.lock().unwrap().push(ex.get(&s).map(|ex| ex.0));
+                    s.next_tick().await;
+                }
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            w.entity_mut(e).remove::<ExampleComponent>();
+            executor.tick(w);
+            assert_eq!(executor.len(), 1);
+
+            // Only the owner despawning cancels the coroutine
+            w.despawn(e);
+            executor.tick(w);
+            assert_eq!(executor.len(), 0);
+        });
+        assert_eq!(*seen.lock().unwrap(), vec![Some(3), None]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "accessing `corentin::test::ExampleComponent` more than once")]
+    fn duplicate_write_params_panic() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        coroutine(|_: Scope, _: Wr<ExampleComponent>, _: Wr<ExampleComponent>| async move {})
+            .apply(e, &mut world);
+    }
+
+    #[test]
+    fn distinct_write_params_run() {
+        #[derive(Component)]
+        struct Health(u32);
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn((ExampleComponent(0), Health(0))).id();
+        coroutine(
+            |mut s: Scope, mut ex: Wr<ExampleComponent>, mut health: Wr<Health>| async move {
+                ex.get_mut(&s).0 += 1;
+                health.get_mut(&s).0 += 1;
+                s.next_tick().await;
+            },
+        )
+        .apply(e, &mut world);
+        assert_eq!(world.resource::<Executor>().len(), 1);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick_until_empty(w));
+        assert_eq!(world.get::<Health>(e).unwrap().0, 1);
+    }
+
+    #[test]
+    fn get_mut_unique_releases_access() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        let id = world.component_id::<ExampleComponent>().unwrap();
+        world.clear_trackers();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = Arc::clone(&log);
+        coroutine(move |s: Scope| async move {
+            // SAFETY: Only read while the coroutine is polled
+            let declared = || unsafe { (*s.meta_ptr()).access.can_write(SourceId::Entity(e), id) };
+            {
+                let mut ex = s.get_mut_unique::<ExampleComponent>(e).unwrap();
+                ex.0 = 5;
+                l.lock().unwrap().push(declared());
+                let again = s.get_mut_unique::<ExampleComponent>(e);
+                l.lock().unwrap().push(again.is_some());
+            }
+            l.lock().unwrap().push(declared());
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick_until_empty(w));
+        assert_eq!(*log.lock().unwrap(), vec![true, false, false]);
+        assert_eq!(world.get::<ExampleComponent>(e).unwrap().0, 5);
+        let mut changed = world.query_filtered::<(), Changed<ExampleComponent>>();
+        assert_eq!(changed.iter(&world).count(), 1);
+    }
+
+    #[test]
+    fn co_macros_build_coroutines() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let e = world.spawn(ExampleComponent(0)).id();
+        co_owned!(|s, mut ex: Wr<ExampleComponent>| {
+            s.next_tick().await;
+            ex.get_mut(&s).0 += 1;
+        })
+        .apply(e, &mut world);
+        coroutine(co!(|s, ex: Rd<ExampleComponent>| {
+            s.next_tick().await;
+            s.next_tick().await;
+            assert_eq!(ex.get(&s).0, 1);
+        }))
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick_until_empty(w));
+        assert_eq!(world.get::<ExampleComponent>(e).unwrap().0, 1);
+    }
+
+    #[test]
+    fn wake_on_exact_tick() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let woken = Arc::new(Mutex::new(None));
+        let w = Arc::clone(&woken);
+        root_coroutine(move |mut s: Scope| async move {
+            s.wait_for_exact_tick(100).await;
+            *w.lock().unwrap() = Some(s.time().tick_count);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for _ in 0..99 {
+                executor.tick(w);
+            }
+            assert_eq!(*woken.lock().unwrap(), None);
+            executor.tick(w);
+        });
+        assert_eq!(*woken.lock().unwrap(), Some(100));
+        assert!(world.resource::<Executor>().is_empty());
+    }
+
+    #[test]
+    fn children_share_scope_resource() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let result = Arc::new(Mutex::new(None));
+        let r = Arc::clone(&result);
+        root_coroutine(move |mut s: Scope| async move {
+            *s.scope_resource::<u32>() = 1;
+            let child = s.start(|mut s: Scope| async move {
+                s.next_tick().await;
+                *s.parent_resource::<u32>().unwrap() += 1;
+                assert!(s.parent_resource::<u64>().is_none());
+            });
+            s.on(child).await;
+            *r.lock().unwrap() = Some(*s.scope_resource::<u32>());
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick_until_empty(w));
+        assert_eq!(*result.lock().unwrap(), Some(2));
+    }
+
+    #[test]
+    fn despawn_recursive_cancels_subtree() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let root = world.spawn_empty().id();
+        let mid = world.spawn_empty().set_parent(root).id();
+        let leaf = world.spawn_empty().set_parent(mid).id();
+
+        let cleaned = Arc::new(Mutex::new(Vec::new()));
+        let c = Arc::clone(&cleaned);
+        world
+            .resource_mut::<Executor>()
+            .register_cleanup_hook(move |id, _| c.lock().unwrap().push(id));
+
+        for owner in [root, mid, leaf] {
+            coroutine(|mut s: Scope| async move {
+                // Despawned with the hierarchy, before the coroutine is cleaned up
+                let local = s.spawn_local(());
+                s.commands().entity(s.owner().unwrap()).add_child(local);
+                loop {
+                    s.next_tick().await;
+                }
+            })
+            .apply(owner, &mut world);
+        }
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            let ids: Vec<Id> = [leaf, mid, root]
+                .into_iter()
+                .map(|owner| executor.coroutines_of(owner).next().unwrap().id)
+                .collect();
+
+            w.entity_mut(root).despawn_recursive();
+            executor.tick(w);
+            assert!(executor.is_empty());
+            assert_eq!(*cleaned.lock().unwrap(), ids);
+        });
+        assert_eq!(world.entities().len(), 0);
+    }
+
+    #[test]
+    fn with_entity_chains_commands() {
+        #[derive(Component)]
+        struct Marker;
+
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let a = world.spawn(ExampleComponent(0)).id();
+        let b = world.spawn_empty().id();
+        root_coroutine(move |mut s: Scope| async move {
+            s.with_entity(a, |e| {
+                e.insert(Marker).remove::<ExampleComponent>();
+            })
+            .with_entity(b, |e| {
+                e.insert(ExampleComponent(3));
+            });
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick(w));
+        assert!(world.get::<ExampleComponent>(a).is_none());
+        assert!(world.get::<Marker>(a).is_some());
+        assert_eq!(world.get::<ExampleComponent>(b).map(|c| c.0), Some(3));
+    }
+
+    #[test]
+    fn poll_single_coroutine() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let steps = Arc::new(Mutex::new(Vec::new()));
+        let child_ran = Arc::new(Mutex::new(false));
+        let (st, cr) = (Arc::clone(&steps), Arc::clone(&child_ran));
+        let script = move |mut s: Scope| async move {
+            st.lock().unwrap().push(1);
+            s.next_tick().await;
+            st.lock().unwrap().push(2);
+            s.start_forget(move |_: Scope| async move {
+                *cr.lock().unwrap() = true;
+            });
+            s.next_tick().await;
+            st.lock().unwrap().push(3);
+            s.next_tick().await;
+            st.lock().unwrap().push(4);
+        };
+        let blocked = |mut s: Scope| async move {
+            let child = s.start(|mut s: Scope| async move {
+                s.next_tick().await;
+            });
+            s.on(child).await;
+        };
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let id = executor.add_function_coroutine(None, w, script).unwrap();
+            let waiting = PollOutcome::Waiting(WaitReason::Tick);
+            assert_eq!(executor.poll_coroutine(id, w), waiting);
+            assert_eq!(executor.poll_coroutine(id, w), waiting);
+            // The child is registered, but only runs on the next tick
+            assert_eq!(executor.len(), 2);
+            assert!(!*child_ran.lock().unwrap());
+
+            executor.tick(w);
+            assert!(*child_ran.lock().unwrap());
+            assert_eq!(*steps.lock().unwrap(), vec![1, 2, 3]);
+            assert_eq!(executor.poll_coroutine(id, w), PollOutcome::Finished);
+            assert_eq!(executor.poll_coroutine(id, w), PollOutcome::NotFound);
+
+            let id = executor.add_function_coroutine(None, w, blocked).unwrap();
+            executor.tick(w);
+            let outcome = executor.poll_coroutine(id, w);
+            assert_eq!(outcome, PollOutcome::Blocked(WaitReason::First));
+            executor.tick_until_empty(w);
+        });
+        assert_eq!(*steps.lock().unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn coroutine_id_matches_handle() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let ids = Arc::new(Mutex::new(None));
+        let i = Arc::clone(&ids);
+        let e = world.spawn_empty().id();
+        coroutine(move |mut s: Scope| async move {
+            let inner = Arc::new(Mutex::new(None));
+            let n = Arc::clone(&inner);
+            let handle = s.start(move |mut s: Scope| async move {
+                *n.lock().unwrap() = Some((s.coroutine_id(), s.owner_entity()));
+                s.next_tick().await;
+            });
+            let id = handle.id();
+            s.on(handle).await;
+            *i.lock().unwrap() = Some((id, *inner.lock().unwrap(), s.coroutine_id()));
+        })
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick_until_empty(w));
+        let (handle_id, inner, parent) = ids.lock().unwrap().unwrap();
+        let (child_id, owner) = inner.unwrap();
+        assert_eq!(handle_id, Some(child_id));
+        assert_ne!(child_id, parent);
+        assert_eq!(owner, Some(e));
+    }
+
+    #[test]
+    fn throttle_does_not_burst() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        let start = Instant::now();
+        world.insert_resource(Time::new(start));
+
+        let resolved = Arc::new(Mutex::new(Vec::new()));
+        let r = Arc::clone(&resolved);
+        root_coroutine(move |mut s: Scope| async move {
+            let mut throttle = s.throttle(Duration::from_millis(250));
+            loop {
+                throttle.wait(&mut s).await;
+                r.lock().unwrap().push(s.time().elapsed.as_millis());
+                s.next_tick().await;
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            // Ticks every 100ms, with a stall between 600ms and 2s
+            for millis in [0, 100, 200, 300, 400, 500, 600, 2000, 2100, 2200] {
+                w.resource_mut::<Time>()
+                    .update_with_instant(start + Duration::from_millis(millis));
+                executor.tick(w);
+            }
+        });
+        assert_eq!(*resolved.lock().unwrap(), vec![0, 300, 600, 2000]);
+    }
+
+    #[test]
+    fn cleanups_run_in_reverse_order() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let pooled = world.spawn_empty().id();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let o = Arc::clone(&order);
+        let cancelled = move |mut s: Scope| async move {
+            s.on_cleanup(move |c| {
+                c.commands().entity(pooled).despawn();
+            });
+            loop {
+                s.next_tick().await;
+            }
+        };
+        let completed = move |mut s: Scope| async move {
+            for i in 0..3 {
+                let o = Arc::clone(&o);
+                s.on_cleanup(move |_| o.lock().unwrap().push(i));
+            }
+            s.next_tick().await;
+        };
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let id = executor.add_function_coroutine(None, w, cancelled).unwrap();
+            executor.add_function_coroutine(None, w, completed);
+            executor.tick(w);
+            assert!(w.get_entity(pooled).is_some());
+
+            assert!(executor.cancel_coroutine(id));
+            executor.tick(w);
+        });
+        assert!(world.get_entity(pooled).is_none());
+        assert_eq!(*order.lock().unwrap(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn duration_reports_missed_periods() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        let mut time = Time::new(Instant::now());
+        let start = time.startup();
+        world.insert_resource(time.clone());
+
+        let missed = Arc::new(Mutex::new(Vec::new()));
+        let m = Arc::clone(&missed);
+        root_coroutine(move |mut s: Scope| async move {
+            loop {
+                let info = s.duration(Duration::from_secs(1)).await;
+                m.lock().unwrap().push(info);
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            // A hitch of 3.5 seconds, followed by a regular frame
+            for millis in [0, 3500, 4700] {
+                time.update_with_instant(start + Duration::from_millis(millis));
+                w.insert_resource(time.clone());
+                executor.tick(w);
+            }
+        });
+
+        assert_eq!(
+            *missed.lock().unwrap(),
+            vec![
+                MissedInfo {
+                    overshoot: Duration::from_millis(2500),
+                    missed_periods: 2,
+                },
+                MissedInfo {
+                    overshoot: Duration::from_millis(200),
+                    missed_periods: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn with_changed_component_resolves_once() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let v = Arc::clone(&seen);
+        root_coroutine(move |mut s: Scope| async move {
+            while let Some(example) = s.with_changed_component::<ExampleComponent>(e).await {
+                v.lock().unwrap().push(example.0);
+            }
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for tick in 0..5 {
+                w.increment_change_tick();
+                if tick == 2 {
+                    // Changed twice before the coroutine gets to see it
+                    w.get_mut::<ExampleComponent>(e).unwrap().0 = 1;
+                    w.increment_change_tick();
+                    w.get_mut::<ExampleComponent>(e).unwrap().0 = 2;
+                }
+                executor.tick(w);
+            }
+        });
+
+        assert_eq!(*seen.lock().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn with_changed_component_sees_writes_later_in_the_tick() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let v = Arc::clone(&seen);
+        root_coroutine(move |mut s: Scope| async move {
+            while let Some(example) = s.with_changed_component::<ExampleComponent>(e).await {
+                v.lock().unwrap().push(example.0);
+            }
+        })
+        .apply(&mut world);
+
+        // Resumed after the waiter suspended, within the same tick
+        coroutine(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                example.get_mut(&s).0 = 5;
+                s.next_tick().await;
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            for _ in 0..3 {
+                executor.tick(w);
+            }
+        });
+
+        assert_eq!(*seen.lock().unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn spawn_empty_scoped_marks_entity() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let spawned = Arc::new(Mutex::new(None));
+        let sp = Arc::clone(&spawned);
+        let id = world.resource_scope(|w, mut executor: Mut<Executor>| {
+            let id = executor.add_function_coroutine(None, w, move |mut s: Scope| async move {
+                *sp.lock().unwrap() = Some(s.spawn_empty_scoped());
+                s.next_tick().await;
+            });
+            executor.tick(w);
+            id.unwrap()
+        });
+
+        let e = spawned.lock().unwrap().unwrap();
+        assert_eq!(world.get::<CoroutineSpawned>(e).unwrap().by, id);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| executor.tick(w));
+        assert!(world.get_entity(e).is_none());
+
+        // Left behind by a coroutine which no longer exists
+        let orphan = world.spawn(CoroutineSpawned { by: id }).id();
+        let mut schedule = Schedule::new();
+        schedule.add_systems(despawn_orphaned_entities);
+        schedule.run(&mut world);
+        assert!(world.get_entity(orphan).is_none());
+    }
+
+    #[test]
+    fn local_executor_runs_within_system() {
+        let mut app = App::new();
+        app.add_plugins(CorentinPlugin);
+        app.insert_resource(Time::new(Instant::now()));
+
+        let result = Arc::new(Mutex::new(None));
+        let local_entity = Arc::new(Mutex::new(None));
+        let (r, l) = (Arc::clone(&result), Arc::clone(&local_entity));
+        app.add_systems(Update, move |world: &mut World| {
+            let mut local = LocalExecutor::new(world);
+            let mut handle = local.start(|mut s: Scope| async move {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..3 {
+                    elapsed += s.next_tick().await;
+                }
+                elapsed
+            });
+            let l = Arc::clone(&l);
+            let _unfinished = local.start(move |mut s: Scope| async move {
+                *l.lock().unwrap() = Some(s.spawn_local(ExampleComponent(0)));
+                loop {
+                    s.next_tick().await;
+                }
+            });
+
+            *r.lock().unwrap() = local.block_on(&mut handle, Duration::from_secs(1), 10);
+            assert!(!local.is_empty());
+        });
+        app.update();
+
+        assert_eq!(*result.lock().unwrap(), Some(Duration::from_secs(3)));
+        // Dropped along with the executor, at the end of the system
+        let e = local_entity.lock().unwrap().unwrap();
+        assert!(app.world.get_entity(e).is_none());
+        // Only the executor of the world ticked it
+        assert_eq!(*app.world.resource::<TickCount>(), TickCount(1));
+    }
+
+    #[test]
+    fn has_waiters_sees_earlier_waiters() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let ping = SignalId::named("ping", None);
+        let pong = SignalId::named("pong", None);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (a, b) = (Arc::clone(&seen), Arc::clone(&seen));
+        // The roots are resumed in order, the waiter starts waiting earlier during this tick
+        root_coroutine(move |mut s: Scope| async move {
+            s.signal_named("ping", None).await;
+            a.lock().unwrap().push("received");
+        })
+        .apply(&mut world);
+        root_coroutine(move |s: Scope| async move {
+            for (signal, name) in [(ping, "ping"), (pong, "pong")] {
+                let waited = if s.has_waiters(signal) { name } else { "none" };
+                b.lock().unwrap().push(waited);
+            }
+            s.emit_named("ping", None);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+        assert_eq!(*seen.lock().unwrap(), vec!["ping", "none", "received"]);
+    }
+
+    #[test]
+    fn emit_reaches_later_waiters_without_earlier_ones() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+
+        let ping = SignalId::named("ping", None);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (a, b) = (Arc::clone(&seen), Arc::clone(&seen));
+        // The roots are resumed in order, nobody waits yet when the signal is emitted
+        root_coroutine(move |s: Scope| async move {
+            a.lock().unwrap().push(s.has_waiters(ping));
+            s.emit_named("ping", None);
+        })
+        .apply(&mut world);
+        root_coroutine(move |mut s: Scope| async move {
+            s.signal_named("ping", None).await;
+            b.lock().unwrap().push(true);
+        })
+        .apply(&mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            assert!(executor.is_empty());
+        });
+        assert_eq!(*seen.lock().unwrap(), vec![false, true]);
+    }
+
+    #[test]
+    fn observer_sees_write_made_earlier_in_tick() {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        let e = world.spawn(ExampleComponent(0)).id();
+
+        let observed = Arc::new(Mutex::new(false));
+        let o = Arc::clone(&observed);
+        coroutine(
+            |mut s: Scope, mut example: Wr<ExampleComponent>| async move {
+                s.next_tick().await;
+                example.get_mut(&s).0 += 1;
+            },
+        )
+        .apply(e, &mut world);
+        coroutine(
+            move |mut s: Scope, on_change: OnChange<ExampleComponent>| async move {
+                s.next_tick().await;
+                // Nobody waits on the change yet when the write happens
+                s.yield_to_scheduler().await;
+                on_change.observe(&mut s).await;
+                *o.lock().unwrap() = true;
+            },
+        )
+        .apply(e, &mut world);
+
+        world.resource_scope(|w, mut executor: Mut<Executor>| {
+            executor.tick(w);
+            executor.tick(w);
+        });
+        assert!(*observed.lock().unwrap());
+    }
+
+    #[test]
+    fn once_channel_drops_unreceived_message() {
+        use super::function_coroutine::once_channel::sync_once_channel;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Counted(Arc<AtomicUsize>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        // Either end can be dropped first
+        let (sender, receiver) = sync_once_channel::<Counted>();
+        drop(receiver);
+        drop(sender);
+        let (sender, receiver) = sync_once_channel::<Counted>();
+        drop(sender);
+        assert!(receiver.try_recv().is_err());
+        drop(receiver);
+
+        // Sent but never received
+        let (sender, receiver) = sync_once_channel();
+        sender.send(Counted(Arc::clone(&drops)));
+        drop(receiver);
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+        // Received once only
+        let (sender, receiver) = sync_once_channel();
+        sender.send(Counted(Arc::clone(&drops)));
+        drop(receiver.try_recv().ok());
+        assert!(receiver.try_recv().is_err());
+        drop(receiver);
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+    }
 }
@@ -0,0 +1,84 @@
+/// Expands to a coroutine closure taking a mutable [`Scope`](crate::prelude::Scope) followed by
+/// the given parameters, whose body is an `async move` block. This avoids the confusing errors
+/// about [`CoroutineParamFunction`](crate::function_coroutine::CoroutineParamFunction) when the
+/// `mut` or the `move` is forgotten. The scope is named by the first identifier, and the
+/// parameters are written as usual, `mut` included if needed.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use corentin::prelude::*;
+/// # #[derive(Component)]
+/// # struct Health(u32);
+/// fn setup(mut commands: Commands) {
+///     commands.add(root_coroutine(co!(|s| {
+///         s.next_tick().await;
+///     })));
+///     commands.spawn(Health(10)).add(coroutine(co!(|s, mut hp: Wr<Health>| {
+///         s.next_tick().await;
+///         hp.get_mut(&s).0 += 1;
+///     })));
+/// }
+/// ```
+///
+/// Written by hand, forgetting the `mut` of the scope does not compile:
+///
+/// ```compile_fail
+/// # use bevy::prelude::*;
+/// # use corentin::prelude::*;
+/// fn setup(mut commands: Commands) {
+///     commands.add(root_coroutine(|s: Scope| async move {
+///         s.next_tick().await;
+///     }));
+/// }
+/// ```
+///
+/// Nor does forgetting the `move` of the async block:
+///
+/// ```compile_fail
+/// # use bevy::prelude::*;
+/// # use corentin::prelude::*;
+/// fn setup(mut commands: Commands) {
+///     commands.add(root_coroutine(|mut s: Scope| async {
+///         s.next_tick().await;
+///     }));
+/// }
+/// ```
+#[macro_export]
+macro_rules! co {
+    (|$scope:ident $(,)?| $body:block) => {
+        |#[allow(unused_mut)] mut $scope: $crate::prelude::Scope| async move $body
+    };
+    (|$scope:ident, $($rest:tt)*) => {
+        $crate::co!(@params $scope [] $($rest)*)
+    };
+    // The parameters are accumulated one token at a time up to the closing `|`, since a
+    // pattern can't be followed by the `:` of its type in a macro matcher
+    (@params $scope:ident [$($params:tt)*] | $body:block) => {
+        |#[allow(unused_mut)] mut $scope: $crate::prelude::Scope, $($params)*| async move $body
+    };
+    (@params $scope:ident [$($params:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::co!(@params $scope [$($params)* $next] $($rest)*)
+    };
+}
+
+/// Same as [`co!`], but expands to an [`EntityCommand`](bevy::ecs::system::EntityCommand)
+/// binding the coroutine to the entity, see [`coroutine`](crate::commands::coroutine).
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use corentin::prelude::*;
+/// # #[derive(Component)]
+/// # struct Health(u32);
+/// fn setup(mut commands: Commands) {
+///     commands.spawn(Health(10)).add(co_owned!(|s, hp: Rd<Health>| {
+///         s.next_tick().await;
+///         println!("{}", hp.get(&s).0);
+///     }));
+/// }
+/// ```
+#[macro_export]
+macro_rules! co_owned {
+    ($($closure:tt)*) => {
+        $crate::commands::coroutine($crate::co!($($closure)*))
+    };
+}
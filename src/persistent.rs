@@ -0,0 +1,107 @@
+//! Coroutines which survive a save and reload of the world, by restarting from the last state
+//! they saved with [`Scope::checkpoint`]. Unlike a [`CoroutineState`], they are written as
+//! `async` functions, but their progress in between two checkpoints is lost.
+//!
+//! [`CoroutineState`]: crate::state::CoroutineState
+
+use std::future::Future;
+
+use bevy::{
+    prelude::{Mut, Resource, World},
+    utils::HashMap,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    executor::{DeserializeError, Executor},
+    function_coroutine::scope::Scope,
+};
+
+/// Starts a persistent coroutine, from its last checkpoint if any.
+type ScriptStarter = Box<dyn Fn(&World, &mut Executor, Option<&Value>) + Send + Sync>;
+
+/// The persistent coroutines, with the last checkpoint of each, see
+/// [`register_persistent_coroutine`].
+///
+/// [`register_persistent_coroutine`]: crate::plugin::CoroutineAppExt::register_persistent_coroutine
+#[derive(Resource, Default)]
+pub struct PersistentScripts {
+    starters: HashMap<&'static str, ScriptStarter>,
+    checkpoints: HashMap<String, Value>,
+}
+
+impl PersistentScripts {
+    /// Register the persistent coroutine `script_id`, built by `factory` from its last
+    /// checkpoint, or from [`None`] if it never saved one. Replaces the previous one with the
+    /// same id.
+    pub fn register<S, F, Fut>(&mut self, script_id: &'static str, factory: F)
+    where
+        S: DeserializeOwned + Send + 'static,
+        F: Fn(Scope, Option<S>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let starter = move |world: &World, exec: &mut Executor, checkpoint: Option<&Value>| {
+            // A checkpoint which cannot be deserialized, by an older version of the script for
+            // instance, is ignored
+            let restore = checkpoint.and_then(|state| S::deserialize(state).ok());
+            let factory = factory.clone();
+            exec.add_function_coroutine(None, world, move |s: Scope| factory(s, restore));
+        };
+        self.starters.insert(script_id, Box::new(starter));
+    }
+
+    pub(crate) fn has_starters(&self) -> bool {
+        !self.starters.is_empty()
+    }
+
+    /// Returns the last checkpoint of `script_id`, serialized.
+    pub fn checkpoint(&self, script_id: &str) -> Option<&Value> {
+        self.checkpoints.get(script_id)
+    }
+
+    pub(crate) fn save(&mut self, script_id: &str, state: Value) {
+        self.checkpoints.insert(script_id.to_string(), state);
+    }
+
+    /// Serialize the last checkpoint of each persistent coroutine, as JSON, to be restored with
+    /// [`PersistentScripts::import`].
+    pub fn export(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.checkpoints).expect("JSON values can always be serialized")
+    }
+
+    /// Replace the checkpoints by the ones saved with [`PersistentScripts::export`]. They are
+    /// used the next time the persistent coroutines are started, see
+    /// [`reload_persistent_coroutines`]. Nothing is replaced if an error is returned.
+    pub fn import(&mut self, data: &[u8]) -> Result<(), DeserializeError> {
+        self.checkpoints = serde_json::from_slice(data).map_err(|_| DeserializeError::Invalid)?;
+        Ok(())
+    }
+
+    /// Start each persistent coroutine from its last checkpoint.
+    fn start_all(&self, world: &World, exec: &mut Executor) {
+        for (script_id, starter) in &self.starters {
+            starter(world, exec, self.checkpoint(script_id));
+        }
+    }
+}
+
+/// Start each persistent coroutine from its last checkpoint. Added to the
+/// [`Startup`](bevy::prelude::Startup) schedule with the first persistent coroutine.
+pub fn start_persistent_coroutines(world: &mut World) {
+    world.resource_scope(|w, scripts: Mut<PersistentScripts>| {
+        w.resource_scope(|w, mut exec: Mut<Executor>| scripts.start_all(w, &mut exec));
+    });
+}
+
+/// Cancel all the coroutines, persistent or not, and start each persistent coroutine again from
+/// its last checkpoint. To be run once the world is loaded, after importing the checkpoints with
+/// [`PersistentScripts::import`].
+pub fn reload_persistent_coroutines(world: &mut World) {
+    world.resource_scope(|w, scripts: Mut<PersistentScripts>| {
+        w.resource_scope(|w, mut exec: Mut<Executor>| {
+            exec.cancel_all();
+            scripts.start_all(w, &mut exec);
+        });
+    });
+}
@@ -1,18 +1,336 @@
-use bevy::prelude::{Mut, Plugin, Update, World};
+use std::{future::Future, marker::PhantomData};
 
-use crate::executor::Executor;
+use bevy::{
+    app::{AppLabel, AppLabelId},
+    ecs::schedule::{ScheduleLabel, SystemSet},
+    prelude::{
+        apply_deferred, Added, App, Changed, Component, Entity, IntoSystemConfigs, Local, Mut,
+        Plugin, QueryState, Resource, Startup, Update, World,
+    },
+    utils::HashMap,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    commands::EntityCoroutines,
+    executor::{
+        phase::Phase, quota::CoroutineCancelled, CommandFlushPoint, CoroutineSpawned, Executor,
+        HasCoroutines, PendingCommands, TickOrder,
+    },
+    function_coroutine::{scope::Scope, CoroutineParamFunction},
+    id_alloc::Id,
+    persistent::{start_persistent_coroutines, PersistentScripts},
+};
+
+#[cfg(feature = "debug-ui")]
+use crate::executor::debug_ui::CoroutineDebugInfo;
 
 pub struct CorentinPlugin;
 
 impl Plugin for CorentinPlugin {
-    fn build(&self, app: &mut bevy::prelude::App) {
+    fn build(&self, app: &mut App) {
         app.init_resource::<Executor>()
-            .add_systems(Update, run_coroutines);
+            .add_event::<CoroutineCancelled>()
+            .register_type::<HasCoroutines>()
+            .register_type::<EntityCoroutines>()
+            .add_systems(
+                Update,
+                (
+                    register_entity_coroutines,
+                    run_coroutines,
+                    despawn_orphaned_entities,
+                )
+                    .chain(),
+            );
+        #[cfg(feature = "debug-ui")]
+        app.register_type::<CoroutineDebugInfo>();
+    }
+}
+
+impl CorentinPlugin {
+    /// Returns a plugin running coroutines in the [`SubApp`](bevy::app::SubApp) with the given
+    /// `label`, instead of the main app. The sub-app must be inserted before adding this plugin,
+    /// and needs a [`Time`](bevy::time::Time) resource.
+    ///
+    /// The sub-app gets its own [`Executor`], ticked in its main schedule. Coroutines never
+    /// cross the boundary between two apps, the executor of the main app is not extracted.
+    pub fn for_sub_app(label: impl AppLabel) -> CorentinSubAppPlugin {
+        CorentinSubAppPlugin {
+            label: label.as_label(),
+        }
+    }
+}
+
+/// Keeps a [`HasCoroutines`](crate::executor::HasCoroutines) component on each entity with
+/// coroutines bound to it, so that they can be inspected from the ECS. Must be added after
+/// [`CorentinPlugin`].
+pub struct CoroutineMarkersPlugin;
+
+impl Plugin for CoroutineMarkersPlugin {
+    fn build(&self, app: &mut App) {
+        app.world
+            .resource_mut::<Executor>()
+            .enable_coroutine_markers();
+    }
+}
+
+/// Choose when the commands queued by coroutines are applied, see [`CommandFlushPoint`]. With
+/// [`CommandFlushPoint::NextApplyDeferred`], [`apply_coroutine_commands`] is added right after
+/// a sync point following the executor. Must be added after [`CorentinPlugin`].
+pub struct CommandFlushPlugin(pub CommandFlushPoint);
+
+impl Plugin for CommandFlushPlugin {
+    fn build(&self, app: &mut App) {
+        app.world
+            .resource_mut::<Executor>()
+            .set_command_flush_point(self.0);
+        if self.0 == CommandFlushPoint::NextApplyDeferred {
+            app.add_systems(
+                Update,
+                (apply_deferred, apply_coroutine_commands)
+                    .chain()
+                    .after(run_coroutines),
+            );
+        }
     }
 }
 
-fn run_coroutines(world: &mut World) {
+/// Choose the order in which the coroutines waiting on the next tick are resumed, see
+/// [`TickOrder`]. Must be added after [`CorentinPlugin`].
+pub struct TickOrderPlugin(pub TickOrder);
+
+impl Plugin for TickOrderPlugin {
+    fn build(&self, app: &mut App) {
+        app.world.resource_mut::<Executor>().set_tick_order(self.0);
+    }
+}
+
+/// Same as [`CorentinPlugin`], but for a [`SubApp`](bevy::app::SubApp). Built with
+/// [`CorentinPlugin::for_sub_app`].
+pub struct CorentinSubAppPlugin {
+    label: AppLabelId,
+}
+
+impl Plugin for CorentinSubAppPlugin {
+    fn build(&self, app: &mut App) {
+        let sub_app = app.sub_app_mut(self.label);
+        let schedule = sub_app.main_schedule_label.clone();
+        sub_app
+            .init_resource::<Executor>()
+            .add_event::<CoroutineCancelled>()
+            .register_type::<HasCoroutines>()
+            .register_type::<EntityCoroutines>()
+            .add_systems(
+                schedule,
+                (
+                    register_entity_coroutines,
+                    run_coroutines,
+                    despawn_orphaned_entities,
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub(crate) fn run_coroutines(world: &mut World) {
     world.resource_scope(|w, mut exec: Mut<Executor>| {
         exec.tick(w);
     })
 }
+
+/// Apply the commands queued by coroutines since the last time it ran, when they are not
+/// applied by the executor directly (see [`CommandFlushPoint::NextApplyDeferred`]).
+pub fn apply_coroutine_commands(world: &mut World) {
+    PendingCommands::apply(world);
+}
+
+/// Despawn the entities with a [`CoroutineSpawned`] component whose coroutine no longer exists.
+/// They are normally despawned along with it, this is a safety net for the ones left behind,
+/// when the executor is replaced for instance.
+pub fn despawn_orphaned_entities(
+    world: &mut World,
+    mut query: Local<QueryState<(Entity, &'static CoroutineSpawned)>>,
+) {
+    let Some(executor) = world.get_resource::<Executor>() else {
+        return;
+    };
+    let orphans: Vec<Entity> = query
+        .iter(world)
+        .filter(|(_, spawned)| !executor.contains(spawned.by))
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for entity in orphans {
+        world.despawn(entity);
+    }
+}
+
+/// Register all the coroutines queued in [`EntityCoroutines`] components.
+fn register_entity_coroutines(
+    world: &mut World,
+    mut query: Local<QueryState<(Entity, &mut EntityCoroutines), Changed<EntityCoroutines>>>,
+) {
+    // Kept across runs, so that only the components inserted or queued to since the last one
+    // are matched. Taking their pending coroutines does not match them again on the next run.
+    let pending: Vec<_> = query
+        .iter_mut(world)
+        .filter(|(_, coroutines)| !coroutines.is_empty())
+        .map(|(owner, mut coroutines)| (owner, coroutines.take_pending()))
+        .collect();
+
+    if pending.is_empty() {
+        return;
+    }
+
+    world.resource_scope(|w, mut exec: Mut<Executor>| {
+        for (owner, coroutines) in pending {
+            EntityCoroutines::register(coroutines, owner, w, &mut exec);
+        }
+    })
+}
+
+/// Extension trait for [`App`], to declare coroutines from the app directly.
+pub trait CoroutineAppExt {
+    /// Start `coroutine` on each entity gaining the component `T`, bound to it. The coroutine
+    /// is cancelled when the component is removed, or when the entity is despawned, and
+    /// started afresh when the component is inserted again. Must be called after adding
+    /// [`CorentinPlugin`].
+    fn add_entity_coroutine<T, Marker, R, C>(&mut self, coroutine: C) -> &mut Self
+    where
+        T: Component,
+        C: CoroutineParamFunction<Marker, R> + Clone + Sync,
+        R: Sync + Send + 'static,
+        Marker: 'static + Send + Sync;
+
+    /// Tick the executor for the [`Phase`] of `set` right after it, in `schedule`. The
+    /// coroutines awaiting [`Scope::after_set`](crate::prelude::Scope::after_set) with this set
+    /// are resumed there, the others wait for the next regular tick. Must be called after
+    /// adding [`CorentinPlugin`].
+    fn add_coroutine_phase(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        set: impl SystemSet,
+    ) -> &mut Self;
+
+    /// Register a coroutine surviving reloads, see [`PersistentScripts`]. It is started on
+    /// startup by `factory`, from the last state saved with
+    /// [`Scope::checkpoint`](crate::prelude::Scope::checkpoint) for `script_id` if any, and
+    /// started again from it by [`reload_persistent_coroutines`]. Must be called after adding
+    /// [`CorentinPlugin`].
+    ///
+    /// [`reload_persistent_coroutines`]: crate::persistent::reload_persistent_coroutines
+    fn register_persistent_coroutine<S, F, Fut>(
+        &mut self,
+        script_id: &'static str,
+        factory: F,
+    ) -> &mut Self
+    where
+        S: DeserializeOwned + Send + 'static,
+        F: Fn(Scope, Option<S>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static;
+}
+
+impl CoroutineAppExt for App {
+    fn add_entity_coroutine<T, Marker, R, C>(&mut self, coroutine: C) -> &mut Self
+    where
+        T: Component,
+        C: CoroutineParamFunction<Marker, R> + Clone + Sync,
+        R: Sync + Send + 'static,
+        Marker: 'static + Send + Sync,
+    {
+        self.insert_resource(EntityScript::<T, Marker, R, C> {
+            coroutine,
+            running: HashMap::default(),
+            _phantom: PhantomData,
+        })
+        .add_systems(
+            Update,
+            run_entity_script::<T, Marker, R, C>.before(run_coroutines),
+        )
+    }
+
+    fn add_coroutine_phase(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        set: impl SystemSet,
+    ) -> &mut Self {
+        let phase = Phase::from_boxed(set.dyn_clone());
+        let run_phase = move |world: &mut World| {
+            world.resource_scope(|w, mut exec: Mut<Executor>| {
+                exec.tick_phase(w, &phase);
+            })
+        };
+        self.add_systems(schedule, run_phase.after(set))
+    }
+
+    fn register_persistent_coroutine<S, F, Fut>(
+        &mut self,
+        script_id: &'static str,
+        factory: F,
+    ) -> &mut Self
+    where
+        S: DeserializeOwned + Send + 'static,
+        F: Fn(Scope, Option<S>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        // The coroutines are all started by the same system
+        self.init_resource::<PersistentScripts>();
+        if !self.world.resource::<PersistentScripts>().has_starters() {
+            self.add_systems(Startup, start_persistent_coroutines);
+        }
+        self.world
+            .resource_mut::<PersistentScripts>()
+            .register(script_id, factory);
+        self
+    }
+}
+
+/// The coroutine started on each entity with the component `T`, see
+/// [`CoroutineAppExt::add_entity_coroutine`].
+#[derive(Resource)]
+struct EntityScript<T, Marker, R, C> {
+    coroutine: C,
+    /// The coroutine started on each entity
+    running: HashMap<Entity, Id>,
+    _phantom: PhantomData<(T, Marker, R)>,
+}
+
+/// Cancel the scripts of the entities which lost the component `T`, and start the ones of the
+/// entities which gained it.
+fn run_entity_script<T, Marker, R, C>(
+    world: &mut World,
+    mut query: Local<QueryState<Entity, Added<T>>>,
+) where
+    T: Component,
+    C: CoroutineParamFunction<Marker, R> + Clone + Sync,
+    R: Sync + Send + 'static,
+    Marker: 'static + Send + Sync,
+{
+    // Kept across runs, so that only the components added since the last one are matched
+    let added: Vec<Entity> = query.iter(world).collect();
+
+    world.resource_scope(|w, mut script: Mut<EntityScript<T, Marker, R, C>>| {
+        w.resource_scope(|w, mut exec: Mut<Executor>| {
+            let script = &mut *script;
+            script.running.retain(|entity, id| {
+                // Removed then inserted again since the last run, the script is restarted
+                let restarted = added.contains(entity);
+                let removed = w.get::<T>(*entity).is_none();
+                if restarted || removed {
+                    exec.cancel_coroutine(*id);
+                    false
+                } else {
+                    true
+                }
+            });
+
+            for entity in added {
+                if let Some(id) =
+                    exec.add_function_coroutine(Some(entity), w, script.coroutine.clone())
+                {
+                    script.running.insert(entity, id);
+                }
+            }
+        })
+    });
+}
@@ -0,0 +1,141 @@
+//! Coroutines written as explicit state machines, which unlike `async` functions can be saved and
+//! restored, see [`Executor::serialize`](crate::executor::Executor::serialize).
+
+use std::{any::type_name, pin::Pin, time::Duration};
+
+use bevy::{ecs::world::unsafe_world_cell::UnsafeWorldCell, prelude::Entity, prelude::World};
+
+pub use corentin_macros::CoroutineState;
+#[doc(hidden)]
+pub use serde;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    executor::msg::{
+        CancelMsg, CancelReason, CoroStatus, EmitMsg, LocalEntityMsg, NewCoroutine, SignalId,
+        YieldMsg,
+    },
+    executor::{ScopeStorage, SignalWaiters},
+    global_channel::{Channel, CommandChannel},
+    id_alloc::{Id, Ids},
+    CoroAccess, CoroMeta, Coroutine,
+};
+
+/// A coroutine written as a state machine, advanced one step at a time by the executor. Started
+/// with [`Executor::add_state_coroutine`](crate::executor::Executor::add_state_coroutine).
+///
+/// Between two steps, the whole state is contained in the value, which can therefore be saved
+/// and restored. Only the wait points which remain meaningful after a reload are supported.
+/// `#[derive(CoroutineState)]` derives the serde traits it requires, entities are saved as is.
+pub trait CoroutineState: Serialize + DeserializeOwned + Send + Unpin + 'static {
+    /// The name identifying this state machine in saves. Defaults to the type name, which
+    /// changes if the type is renamed or moved.
+    fn name() -> &'static str {
+        type_name::<Self>()
+    }
+
+    /// Run until the next wait point.
+    fn step(&mut self, world: &mut World) -> StateWait;
+}
+
+/// What a [`CoroutineState`] waits on after a step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateWait {
+    NextTick,
+    Duration(Duration),
+    /// A named signal, see [`Scope::emit_named`](crate::function_coroutine::scope::Scope::emit_named)
+    Signal(&'static str, Option<Entity>),
+    Done,
+}
+
+/// Runs a [`CoroutineState`] in the executor.
+pub(crate) struct StateCoroutine<S> {
+    state: S,
+    meta: CoroMeta,
+}
+
+impl<S: CoroutineState> StateCoroutine<S> {
+    pub(crate) fn new(id: Id, owner: Option<Entity>, state: S) -> Self {
+        Self {
+            state,
+            meta: CoroMeta {
+                id,
+                owner,
+                access: CoroAccess::default(),
+                name: S::name(),
+                graceful: false,
+                spawned_at: Duration::ZERO,
+                setup: Vec::new(),
+                world_id: None,
+            },
+        }
+    }
+}
+
+impl<S: CoroutineState> Coroutine for StateCoroutine<S> {
+    fn resume(
+        mut self: Pin<&mut Self>,
+        world: &mut World,
+        _ids: &Ids,
+        _curr_node: usize,
+        _emit_channel: &Channel<EmitMsg>,
+        _new_coro_channel: &Channel<NewCoroutine>,
+        _local_entity_channel: &Channel<LocalEntityMsg>,
+        _cancel_channel: &Channel<CancelMsg>,
+        _commands_channel: &CommandChannel,
+        _waiters: &SignalWaiters,
+        _storage: &mut ScopeStorage,
+    ) -> CoroStatus {
+        match self.state.step(world) {
+            StateWait::NextTick => CoroStatus::Tick,
+            StateWait::Duration(duration) => CoroStatus::Duration(bevy::time::Timer::new(
+                duration,
+                bevy::time::TimerMode::Once,
+            )),
+            StateWait::Signal(name, owner) => CoroStatus::Signal(SignalId::named(name, owner)),
+            StateWait::Done => CoroStatus::Done,
+        }
+    }
+
+    unsafe fn resume_unsafe(
+        self: Pin<&mut Self>,
+        _world: UnsafeWorldCell<'_>,
+        _ids: &Ids,
+        curr_node: usize,
+        _emit_channel: &Channel<EmitMsg>,
+        _new_coro_channel: &Channel<NewCoroutine>,
+        _local_entity_channel: &Channel<LocalEntityMsg>,
+        _cancel_channel: &Channel<CancelMsg>,
+        _commands_channel: &CommandChannel,
+        _waiters: &SignalWaiters,
+        _storage: &mut ScopeStorage,
+        yield_channel: &Channel<YieldMsg>,
+    ) {
+        // A step may touch anything in the world, which no access can declare, so it is only
+        // taken once the executor resumes this coroutine alone
+        yield_channel.send(YieldMsg {
+            id: self.meta.id,
+            node: curr_node,
+            status: CoroStatus::Exclusive,
+        });
+    }
+
+    fn is_valid(&self, world: &World) -> bool {
+        match self.meta.owner {
+            Some(owner) => world.get_entity(owner).is_some(),
+            None => true,
+        }
+    }
+
+    fn meta(&self) -> &CoroMeta {
+        &self.meta
+    }
+
+    fn invalidate(self: Pin<&mut Self>, _reason: CancelReason) {}
+
+    fn save(&self) -> Option<Value> {
+        // Only fails for states which cannot be represented, such as maps with non-string keys
+        serde_json::to_value(&self.state).ok()
+    }
+}
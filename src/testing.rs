@@ -0,0 +1,51 @@
+//! Helpers to test coroutines, by inspecting what they yield at each tick.
+
+use bevy::{
+    prelude::{Mut, World},
+    time::Time,
+    utils::Instant,
+};
+
+use crate::{
+    executor::{msg::CoroStatusKind, Executor},
+    id_alloc::Id,
+};
+
+/// A world with an [`Executor`], ticked one step at a time.
+pub struct TestExecutor {
+    world: World,
+}
+
+impl Default for TestExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestExecutor {
+    pub fn new() -> Self {
+        let mut world = World::new();
+        world.init_resource::<Executor>();
+        world.insert_resource(Time::new(Instant::now()));
+        Self { world }
+    }
+
+    /// The world in which the coroutines run, to add them or inspect their effects.
+    pub fn world(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn executor(&self) -> &Executor {
+        self.world.resource::<Executor>()
+    }
+
+    /// Tick the executor once, and returns what each coroutine yielded, see
+    /// [`Executor::debug_last_yields`].
+    pub fn step(&mut self) -> Vec<(Id, CoroStatusKind)> {
+        self.world
+            .resource_scope(|world, mut executor: Mut<Executor>| {
+                executor.tick(world);
+                executor.debug_last_yields()
+            })
+    }
+}